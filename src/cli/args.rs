@@ -1,6 +1,8 @@
 //! CLI argument definitions using clap derive
 
-use clap::{Args, Parser, Subcommand};
+use clap::builder::{BoolishValueParser, RangedU64ValueParser};
+use clap::{ArgAction, ArgGroup, Args, Parser, Subcommand, ValueEnum};
+use std::ops::RangeInclusive;
 use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
@@ -33,30 +35,79 @@ pub enum Commands {
     Gcs(GcsArgs),
     /// TFTP enumeration mode
     Tftp(TftpArgs),
+    /// TCP port and banner grabbing mode
+    Tcp(TcpArgs),
 }
 
 /// Global options shared across all modes
 #[derive(Args, Debug, Clone)]
 pub struct GlobalOpts {
-    /// Path to wordlist file
-    #[arg(short, long, value_name = "FILE")]
-    pub wordlist: PathBuf,
+    /// Path to wordlist file (- reads stdin); repeat to merge several, dropping
+    /// entries already seen
+    #[arg(short, long, value_name = "FILE", required = true)]
+    pub wordlist: Vec<PathBuf>,
+
+    /// Scan repeated wordlist entries again instead of skipping them
+    #[arg(long, visible_alias = "no-dedupe")]
+    pub keep_duplicates: bool,
+
+    /// Treat wordlist lines starting with # as entries, not comments
+    #[arg(long)]
+    pub keep_comments: bool,
 
-    /// Number of concurrent threads
+    /// Text added before every wordlist entry, e.g. api/ (not in tcp mode)
+    #[arg(long, value_name = "TEXT")]
+    pub word_prefix: Option<String>,
+
+    /// Text added after every wordlist entry, e.g. .json (not in tcp mode)
+    #[arg(long, value_name = "TEXT")]
+    pub word_suffix: Option<String>,
+
+    /// Number of concurrent threads (the most --adaptive ramps up to)
     #[arg(short, long, default_value = "10", value_name = "N")]
     pub threads: usize,
 
+    /// Start at --min-threads and adjust concurrency to the error rate: one more
+    /// thread while errors stay rare, half as many when timeouts or resets spike
+    #[arg(long)]
+    pub adaptive: bool,
+
+    /// Fewest concurrent threads --adaptive backs off to
+    #[arg(long, default_value = "2", value_name = "N", requires = "adaptive")]
+    pub min_threads: usize,
+
     /// Output file for results
     #[arg(short, long, value_name = "FILE")]
     pub output: Option<PathBuf>,
 
+    /// Write sorted, minimal result lines to the output file for diffing
+    #[arg(long, requires = "output")]
+    pub canonical: bool,
+
+    /// Write one JSON object per line to the output file as results arrive
+    #[arg(long, requires = "output")]
+    pub ndjson: bool,
+
+    /// Write `IP hostname` lines to the output file for /etc/hosts (dns mode)
+    #[arg(long, requires = "output", conflicts_with_all = ["canonical", "ndjson"])]
+    pub hosts: bool,
+
+    /// Write one found URL per line to the output file, for Burp's "Add to site
+    /// map from URLs" or ZAP (dir mode)
+    #[arg(long, requires = "output", conflicts_with_all = ["canonical", "ndjson", "hosts"])]
+    pub urls: bool,
+
+    /// Write .json output as a bare array of results, without scan metadata
+    #[arg(long, requires = "output")]
+    pub json_array: bool,
+
     /// Suppress banner and non-essential output
     #[arg(short, long)]
     pub quiet: bool,
 
-    /// Verbose output (show errors)
-    #[arg(short, long)]
-    pub verbose: bool,
+    /// Verbose output: -v shows errors, -vv every response, -vvv also response headers
+    #[arg(short, long, action = ArgAction::Count)]
+    pub verbose: u8,
 
     /// Disable progress bar
     #[arg(short = 'z', long)]
@@ -66,13 +117,59 @@ pub struct GlobalOpts {
     #[arg(long, value_name = "MS")]
     pub delay: Option<u64>,
 
+    /// Random extra delay before each request, in milliseconds (added to --delay)
+    #[arg(long, value_name = "MIN-MAX", value_parser = parse_jitter)]
+    pub jitter: Option<(u64, u64)>,
+
+    /// Maximum requests per second across all threads
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub rate: Option<u32>,
+
+    /// Abort the scan after N consecutive request errors
+    #[arg(long, value_name = "N", value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    pub max_errors: Option<usize>,
+
     /// No color output
     #[arg(long)]
     pub no_color: bool,
+
+    /// Shell command to run for each result ({line}, {status}, {path}, ... are replaced)
+    #[arg(long, value_name = "CMD")]
+    pub on_found_cmd: Option<String>,
+
+    /// Webhook URL that receives each result as a JSON POST
+    #[arg(long, value_name = "URL")]
+    pub webhook: Option<String>,
+
+    /// Webhook body to send instead of the result JSON ({line}, {status}, {path}, ... are
+    /// replaced with JSON-escaped values), e.g. '{"text": "{line}"}' for Slack
+    #[arg(long, value_name = "TEMPLATE", requires = "webhook")]
+    pub webhook_template: Option<String>,
+
+    /// Minimum delay between hook invocations in milliseconds
+    #[arg(long, default_value = "250", value_name = "MS")]
+    pub hook_interval: u64,
+}
+
+/// Wordlist mutation options
+#[derive(Args, Debug, Clone)]
+pub struct MutateOpts {
+    /// Add lowercase, uppercase and capitalized variants of each word
+    #[arg(long)]
+    pub mutate_case: bool,
+
+    /// Append these to each word (comma-separated, ranges allowed, e.g., 0-9,2023,2024)
+    #[arg(long, value_name = "LIST")]
+    pub mutate_suffix: Option<String>,
+
+    /// Prepend these to each word (comma-separated, ranges allowed)
+    #[arg(long, value_name = "LIST")]
+    pub mutate_prefix: Option<String>,
 }
 
 /// HTTP options shared across HTTP-based modes
 #[derive(Args, Debug, Clone)]
+#[command(group(ArgGroup::new("proxies").multiple(true)))]
 pub struct HttpOpts {
     /// Custom headers (can be used multiple times)
     #[arg(short = 'H', long = "header", value_name = "HEADER")]
@@ -82,17 +179,93 @@ pub struct HttpOpts {
     #[arg(short, long, value_name = "COOKIE")]
     pub cookies: Option<String>,
 
-    /// Custom User-Agent
-    #[arg(short = 'a', long, default_value = "robuster/1.0", value_name = "UA")]
-    pub user_agent: String,
+    /// Custom User-Agent [default: robuster/1.0]
+    #[arg(short = 'a', long, value_name = "UA")]
+    pub user_agent: Option<String>,
+
+    /// Send a random browser User-Agent with each request (ignored with --user-agent)
+    #[arg(long)]
+    pub random_agent: bool,
+
+    /// User-Agents to pick from instead of the built-in browser list, one per line
+    /// (implies --random-agent)
+    #[arg(long, value_name = "FILE")]
+    pub user_agents_file: Option<PathBuf>,
 
     /// Skip TLS certificate verification
     #[arg(short = 'k', long)]
     pub insecure: bool,
 
-    /// Proxy URL (http://host:port or socks5://host:port)
-    #[arg(short, long, value_name = "URL")]
-    pub proxy: Option<String>,
+    /// Verify the certificate hostname (on/off); "off" still validates the
+    /// certificate chain, unlike --insecure
+    #[arg(
+        long,
+        default_value = "on",
+        value_name = "on|off",
+        action = ArgAction::Set,
+        value_parser = BoolishValueParser::new(),
+        hide_possible_values = true
+    )]
+    pub verify_tls_hostname: bool,
+
+    /// Trust the CA certificates in this PEM file in addition to the system
+    /// roots (repeatable)
+    #[arg(long, value_name = "FILE")]
+    pub cacert: Vec<PathBuf>,
+
+    /// Client certificate for mutual TLS (PEM; may also contain the key)
+    #[arg(long, value_name = "FILE")]
+    pub client_cert: Option<PathBuf>,
+
+    /// Private key for --client-cert (PEM)
+    #[arg(long, value_name = "FILE", requires = "client_cert")]
+    pub client_key: Option<PathBuf>,
+
+    /// Client certificate and key for mutual TLS as a PKCS#12 bundle (.p12/.pfx)
+    #[arg(long, value_name = "FILE", conflicts_with = "client_cert")]
+    pub client_pkcs12: Option<PathBuf>,
+
+    /// Password of the --client-pkcs12 bundle
+    #[arg(long, value_name = "PASSWORD", requires = "client_pkcs12")]
+    pub client_pkcs12_pass: Option<String>,
+
+    /// Only connect over IPv4
+    #[arg(short = '4', long, conflicts_with = "ipv6_only")]
+    pub ipv4_only: bool,
+
+    /// Only connect over IPv6
+    #[arg(short = '6', long)]
+    pub ipv6_only: bool,
+
+    /// HTTP version to use
+    #[arg(long, value_enum, default_value = "auto", value_name = "VERSION")]
+    pub http_version: HttpVersion,
+
+    /// Idle connections kept open per host for reuse (keep at or above --threads
+    /// for throughput; lower it to save file descriptors)
+    #[arg(long, default_value = "100", value_name = "N")]
+    pub pool_idle: usize,
+
+    /// Disable Nagle's algorithm so small requests go out immediately
+    #[arg(long, value_enum, default_value = "on", value_name = "SWITCH")]
+    pub tcp_nodelay: Switch,
+
+    /// Proxy URL (http://, socks5:// or socks5h:// to resolve hostnames on the proxy);
+    /// repeat to rotate requests through several
+    #[arg(short, long, value_name = "URL", group = "proxies")]
+    pub proxy: Vec<String>,
+
+    /// File with proxy URLs to rotate through, one per line
+    #[arg(long, value_name = "FILE", group = "proxies")]
+    pub proxy_file: Option<PathBuf>,
+
+    /// Proxy username (sent as Basic proxy authentication, to every proxy)
+    #[arg(long, value_name = "USER", requires = "proxies")]
+    pub proxy_user: Option<String>,
+
+    /// Proxy password
+    #[arg(long, value_name = "PASS", requires = "proxy_user")]
+    pub proxy_pass: Option<String>,
 
     /// HTTP Basic Auth username
     #[arg(short = 'U', long, value_name = "USER")]
@@ -110,9 +283,31 @@ pub struct HttpOpts {
     #[arg(short = 'r', long)]
     pub follow_redirect: bool,
 
+    /// Redirects to follow before reporting the last redirect response
+    /// (redirect loops are reported the same way)
+    #[arg(
+        long,
+        default_value = "10",
+        value_name = "N",
+        requires = "follow_redirect"
+    )]
+    pub max_redirects: usize,
+
     /// HTTP method to use
     #[arg(long, default_value = "GET", value_name = "METHOD")]
     pub method: String,
+
+    /// Retry connection errors and timeouts this many times
+    #[arg(long, default_value = "0", value_name = "N")]
+    pub retries: u32,
+
+    /// Base delay between retries in milliseconds (multiplied by the attempt)
+    #[arg(long, default_value = "500", value_name = "MS")]
+    pub retry_backoff: u64,
+
+    /// Don't pause and retry on 429, or 503 with Retry-After; report them as results
+    #[arg(long)]
+    pub ignore_retry_after: bool,
 }
 
 /// Directory enumeration mode arguments
@@ -124,24 +319,40 @@ pub struct DirArgs {
     #[command(flatten)]
     pub http: HttpOpts,
 
+    #[command(flatten)]
+    pub mutate: MutateOpts,
+
     /// Target URL
-    #[arg(short, long, value_name = "URL")]
-    pub url: String,
+    #[arg(short, long, value_name = "URL", required_unless_present = "url_file")]
+    pub url: Option<String>,
+
+    /// File with one target base URL per line, scanned with the same wordlist
+    #[arg(long, value_name = "FILE", conflicts_with = "url")]
+    pub url_file: Option<PathBuf>,
 
     /// File extensions to search (comma-separated, e.g., php,html,js)
     #[arg(short = 'x', long, value_name = "EXT")]
     pub extensions: Option<String>,
 
-    /// Positive status codes (comma-separated)
+    /// File with one extension per line, merged with --extensions
+    #[arg(long, value_name = "FILE")]
+    pub extensions_file: Option<PathBuf>,
+
+    /// Don't add extensions to words that already have one (no index.php.php)
+    #[arg(long)]
+    pub no_double_extension: bool,
+
+    /// Positive status codes (comma-separated; ranges like 200-299, classes like 2xx, or all).
+    /// Scope codes to an extension with EXT: (e.g. php:200,bak:200,403,*:200,301)
     #[arg(
         short = 's',
         long,
-        default_value = "200,204,301,302,307,308,401,403,405",
+        default_value = DEFAULT_STATUS_CODES,
         value_name = "CODES"
     )]
     pub status_codes: String,
 
-    /// Negative status codes to exclude (comma-separated)
+    /// Negative status codes to exclude (same syntax as --status-codes)
     #[arg(short = 'b', long, value_name = "CODES")]
     pub status_codes_blacklist: Option<String>,
 
@@ -157,17 +368,160 @@ pub struct DirArgs {
     #[arg(short = 'l', long)]
     pub show_length: bool,
 
-    /// Exclude responses with specified lengths (comma-separated)
+    /// Exclude responses with these lengths (comma-separated: 1024, 1024-2048, 1500±50)
     #[arg(long, value_name = "LENGTHS")]
     pub exclude_length: Option<String>,
 
+    /// Only show responses whose body matches this regex (reads full bodies)
+    #[arg(long, value_name = "PATTERN")]
+    pub match_regex: Option<String>,
+
+    /// Hide responses whose body matches this regex (reads full bodies)
+    #[arg(long, value_name = "PATTERN")]
+    pub filter_regex: Option<String>,
+
+    /// Only show responses with these content types (comma-separated prefixes, e.g. application/json,text/)
+    #[arg(long, value_name = "TYPES")]
+    pub match_content_type: Option<String>,
+
+    /// Hide responses with these content types (comma-separated prefixes, e.g. image/,font/)
+    #[arg(long, value_name = "TYPES")]
+    pub filter_content_type: Option<String>,
+
+    /// Only show responses with this header, and a value matching the regex if
+    /// given ("Name" or "Name: regex"; repeat to require all)
+    #[arg(long = "match-header", value_name = "HEADER")]
+    pub match_headers: Vec<String>,
+
+    /// Hide responses with this header, and a value matching the regex if given
+    /// ("Name" or "Name: regex"; repeat to hide only those matching all)
+    #[arg(long = "filter-header", value_name = "HEADER")]
+    pub filter_headers: Vec<String>,
+
     /// Search for backup files when a file is found
     #[arg(long)]
     pub discover_backup: bool,
 
+    /// Backup suffixes to try (comma-separated, e.g. .php~,.php.bak,.inc)
+    #[arg(long, value_name = "SUFFIXES", requires = "discover_backup")]
+    pub backup_extensions: Option<String>,
+
+    /// Whether --backup-extensions replaces or extends the built-in suffixes
+    #[arg(
+        long,
+        value_enum,
+        default_value = "append",
+        value_name = "MODE",
+        requires = "backup_extensions"
+    )]
+    pub backup_extensions_mode: BackupExtensionsMode,
+
+    /// Hide responses once too many paths return the same page (soft-404s)
+    #[arg(long)]
+    pub dedupe: bool,
+
+    /// Number of paths shown per identical page before --dedupe hides the rest
+    #[arg(long, default_value = "3", value_name = "N", requires = "dedupe")]
+    pub dedupe_threshold: usize,
+
+    /// Use HEAD requests (size from Content-Length, GET fallback on 405)
+    #[arg(long)]
+    pub head: bool,
+
+    /// Request body sent with every request (needs a method such as POST or PUT)
+    #[arg(short = 'd', long, value_name = "DATA", conflicts_with = "head")]
+    pub data: Option<String>,
+
+    /// Read the request body from a file
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["data", "head"])]
+    pub data_file: Option<PathBuf>,
+
+    /// Send this raw HTTP request file over TCP/TLS instead of building requests;
+    /// FUZZ is replaced by each path (no proxy, wildcard check, crawl or backups)
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["url_file", "head", "data", "data_file", "crawl", "discover_backup"]
+    )]
+    pub raw: Option<PathBuf>,
+
+    /// Content-Type of the request body
+    #[arg(
+        long,
+        default_value = "application/x-www-form-urlencoded",
+        value_name = "TYPE"
+    )]
+    pub content_type: String,
+
+    /// Show response time
+    #[arg(long)]
+    pub show_time: bool,
+
+    /// Report decompressed body sizes or the raw bytes sent by the server
+    #[arg(long, value_enum, default_value = "decoded", value_name = "MODE")]
+    pub size: SizeMode,
+
+    /// Only show responses slower than this many milliseconds
+    #[arg(long, value_name = "MS")]
+    pub slower_than: Option<u64>,
+
+    /// Only show responses faster than this many milliseconds
+    #[arg(long, value_name = "MS")]
+    pub faster_than: Option<u64>,
+
+    /// Checkpoint file for resuming an interrupted scan (created if missing)
+    #[arg(long, visible_alias = "resume", value_name = "FILE")]
+    pub checkpoint: Option<PathBuf>,
+
+    /// Crawl found pages for same-site links and check those too
+    #[arg(long)]
+    pub crawl: bool,
+
+    /// Maximum number of link hops to follow when crawling
+    #[arg(long, default_value = "2", value_name = "N", requires = "crawl")]
+    pub crawl_depth: usize,
+
     /// Force continued operation on wildcard responses
     #[arg(long)]
     pub wildcard: bool,
+
+    /// Probe random paths (with and without each extension) before scanning
+    /// and hide responses that look like theirs, instead of the wildcard check
+    #[arg(long, visible_alias = "ac", conflicts_with = "wildcard")]
+    pub autocalibrate: bool,
+
+    /// Show a live dashboard with per-target progress and recent finds
+    /// instead of the progress bar (needs a terminal)
+    #[arg(long)]
+    pub tui: bool,
+
+    /// Print results in wordlist order once the scan ends instead of as
+    /// they arrive, so repeated scans give identical output
+    #[arg(long)]
+    pub ordered: bool,
+
+    /// Send a foreign Origin (https://evil.example unless -H sets one) and
+    /// flag results that trust it with credentials
+    #[arg(long, conflicts_with = "raw")]
+    pub check_cors: bool,
+
+    /// Save the body of each shown response to a file in this directory
+    #[arg(long, value_name = "DIR")]
+    pub save_responses: Option<PathBuf>,
+
+    /// Write the status line and headers before saved bodies
+    #[arg(long, requires = "save_responses")]
+    pub save_headers: bool,
+
+    /// Longest body kept and saved per response in bytes; the rest is read
+    /// for its size only
+    #[arg(
+        long,
+        default_value = "1048576",
+        value_name = "BYTES",
+        requires = "save_responses"
+    )]
+    pub max_response_size: usize,
 }
 
 /// DNS subdomain enumeration mode arguments
@@ -176,6 +530,9 @@ pub struct DnsArgs {
     #[command(flatten)]
     pub global: GlobalOpts,
 
+    #[command(flatten)]
+    pub mutate: MutateOpts,
+
     /// Target domain
     #[arg(short, long, value_name = "DOMAIN")]
     pub domain: String,
@@ -184,6 +541,10 @@ pub struct DnsArgs {
     #[arg(short = 'r', long, value_name = "RESOLVER")]
     pub resolver: Option<String>,
 
+    /// Tunnel DNS queries over TCP through a SOCKS5 proxy (requires --resolver)
+    #[arg(long, value_name = "URL", requires = "resolver")]
+    pub dns_socks: Option<String>,
+
     /// Show resolved IP addresses
     #[arg(short = 'i', long)]
     pub show_ips: bool,
@@ -192,10 +553,66 @@ pub struct DnsArgs {
     #[arg(short = 'c', long)]
     pub show_cname: bool,
 
+    /// Record types to query (comma-separated)
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        default_value = "a,aaaa,cname",
+        value_name = "TYPES"
+    )]
+    pub record_types: Vec<DnsRecordType>,
+
+    /// Only report IPv4 addresses (A records)
+    #[arg(short = '4', long, conflicts_with = "ipv6_only")]
+    pub ipv4_only: bool,
+
+    /// Only report IPv6 addresses (AAAA records)
+    #[arg(short = '6', long)]
+    pub ipv6_only: bool,
+
+    /// Only report subdomains whose CNAME target doesn't resolve (takeover candidates)
+    #[arg(long)]
+    pub cname_only: bool,
+
+    /// Try a zone transfer (AXFR) from the domain's nameservers before brute forcing
+    #[arg(long)]
+    pub axfr: bool,
+
     /// Force continue on wildcard
     #[arg(long)]
     pub wildcard: bool,
 
+    /// Look up a random sibling name before reporting each hit and hide hits
+    /// answering like it (catches wildcards that rotate addresses)
+    #[arg(long, conflicts_with = "wildcard")]
+    pub wildcard_strict: bool,
+
+    /// Percentage of a hit's addresses that must be wildcard addresses for it to be hidden
+    #[arg(
+        long,
+        default_value = "100",
+        value_name = "PERCENT",
+        value_parser = clap::value_parser!(u8).range(1..=100)
+    )]
+    pub wildcard_overlap: u8,
+
+    /// Probe each resolved subdomain over HTTP(S) and report its status
+    #[arg(long)]
+    pub http_check: bool,
+
+    /// Queue permutations of each found subdomain (dev-api, api-dev, api2, ...)
+    #[arg(long)]
+    pub permute: bool,
+
+    /// Words to combine with found subdomains instead of the built-in list
+    #[arg(long, value_name = "FILE", requires = "permute")]
+    pub permutation_words: Option<PathBuf>,
+
+    /// Rounds of permutations, each built from the previous round's finds
+    #[arg(long, default_value = "1", value_name = "N", requires = "permute")]
+    pub permute_depth: usize,
+
     /// Request timeout in seconds
     #[arg(long, default_value = "5", value_name = "SECS")]
     pub timeout: u64,
@@ -222,8 +639,16 @@ pub struct VhostArgs {
     #[arg(long, value_name = "DOMAIN")]
     pub domain: Option<String>,
 
-    /// Exclude responses with specified length
-    #[arg(long, value_name = "LENGTH")]
+    /// How responses are compared against the baseline
+    #[arg(long, value_enum, default_value = "hash", value_name = "MODE")]
+    pub compare: VhostCompare,
+
+    /// Send each candidate host as TLS SNI too, not just in the Host header
+    #[arg(long, conflicts_with = "proxies")]
+    pub sni: bool,
+
+    /// Exclude responses with these lengths (comma-separated: 1024, 1024-2048, 1500±50)
+    #[arg(long, value_name = "LENGTHS")]
     pub exclude_length: Option<String>,
 }
 
@@ -236,25 +661,203 @@ pub struct FuzzArgs {
     #[command(flatten)]
     pub http: HttpOpts,
 
-    /// Target URL with FUZZ keyword
+    /// Target URL with FUZZ keyword (without one for --param-discovery)
     #[arg(short, long, value_name = "URL")]
     pub url: String,
 
+    /// Find query parameters: request the URL with ?WORD=<random value> per
+    /// wordlist entry and report parameters that change the response or
+    /// reflect the value (implies --autocalibrate)
+    #[arg(long, conflicts_with_all = ["raw", "wordlist2"])]
+    pub param_discovery: bool,
+
     /// POST data with FUZZ keyword
     #[arg(short = 'd', long, value_name = "DATA")]
     pub data: Option<String>,
 
+    /// Read the request body template from a file (JSON bodies are sent as application/json)
+    #[arg(long, value_name = "FILE", conflicts_with = "data")]
+    pub data_file: Option<PathBuf>,
+
+    /// Send this raw HTTP request file, with FUZZ keywords filled in, over
+    /// TCP/TLS to the host of --url instead of building requests (no proxy)
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["data", "data_file", "cache"])]
+    pub raw: Option<PathBuf>,
+
+    /// Second wordlist, substituted for the FUZZ2 keyword (FUZZ1 uses --wordlist)
+    #[arg(long, value_name = "FILE")]
+    pub wordlist2: Option<PathBuf>,
+
+    /// Percent-encode payloads substituted into the URL
+    #[arg(long)]
+    pub encode: bool,
+
+    /// Percent-encode payloads in headers, cookies and body too
+    #[arg(long)]
+    pub encode_all: bool,
+
+    /// How to combine the two wordlists
+    #[arg(long, value_enum, default_value = "product", value_name = "MODE")]
+    pub fuzz_mode: FuzzMode,
+
     /// Exclude responses with specified status codes
     #[arg(long, value_name = "CODES")]
     pub exclude_status: Option<String>,
 
-    /// Exclude responses with specified length
-    #[arg(long, value_name = "LENGTH")]
+    /// Exclude responses with these lengths (comma-separated: 1024, 1024-2048, 1500±50)
+    #[arg(long, value_name = "LENGTHS")]
     pub exclude_length: Option<String>,
 
-    /// Filter responses containing this string
+    /// Only show responses whose body contains this string
+    #[arg(long, value_name = "STRING")]
+    pub match_string: Option<String>,
+
+    /// Hide responses whose body contains this string
     #[arg(long, value_name = "STRING")]
     pub filter_string: Option<String>,
+
+    /// Only show responses with these content types (comma-separated prefixes, e.g. application/json,text/)
+    #[arg(long, value_name = "TYPES")]
+    pub match_content_type: Option<String>,
+
+    /// Hide responses with these content types (comma-separated prefixes, e.g. image/,font/)
+    #[arg(long, value_name = "TYPES")]
+    pub filter_content_type: Option<String>,
+
+    /// Only show responses with this header, and a value matching the regex if
+    /// given ("Name" or "Name: regex"; repeat to require all)
+    #[arg(long = "match-header", value_name = "HEADER")]
+    pub match_headers: Vec<String>,
+
+    /// Hide responses with this header, and a value matching the regex if given
+    /// ("Name" or "Name: regex"; repeat to hide only those matching all)
+    #[arg(long = "filter-header", value_name = "HEADER")]
+    pub filter_headers: Vec<String>,
+
+    /// Exclude responses with these word counts (comma-separated, ranges like 10-20)
+    #[arg(long, value_name = "COUNTS", value_parser = parse_ranges)]
+    pub exclude_words: Option<CountRanges>,
+
+    /// Only show responses with these word counts (comma-separated, ranges like 10-20)
+    #[arg(long, value_name = "COUNTS", value_parser = parse_ranges)]
+    pub match_words: Option<CountRanges>,
+
+    /// Exclude responses with these line counts (comma-separated, ranges like 10-20)
+    #[arg(long, value_name = "COUNTS", value_parser = parse_ranges)]
+    pub exclude_lines: Option<CountRanges>,
+
+    /// Only show responses with these line counts (comma-separated, ranges like 10-20)
+    #[arg(long, value_name = "COUNTS", value_parser = parse_ranges)]
+    pub match_lines: Option<CountRanges>,
+
+    /// Probe random payloads first and hide responses that look like theirs
+    #[arg(long, visible_alias = "ac")]
+    pub autocalibrate: bool,
+
+    /// Send a foreign Origin (https://evil.example unless -H sets one) and
+    /// flag results that trust it with credentials
+    #[arg(long, conflicts_with = "raw")]
+    pub check_cors: bool,
+
+    /// Save the body of each shown response to a file in this directory
+    #[arg(long, value_name = "DIR")]
+    pub save_responses: Option<PathBuf>,
+
+    /// Write the status line and headers before saved bodies
+    #[arg(long, requires = "save_responses")]
+    pub save_headers: bool,
+
+    /// Longest body kept and saved per response in bytes; the rest is read
+    /// for its size only
+    #[arg(
+        long,
+        default_value = "1048576",
+        value_name = "BYTES",
+        requires = "save_responses"
+    )]
+    pub max_response_size: usize,
+
+    /// Cache responses in this directory and replay them on later runs
+    #[arg(long, value_name = "DIR")]
+    pub cache: Option<PathBuf>,
+
+    /// Maximum age of cached responses in seconds
+    #[arg(long, value_name = "SECS", requires = "cache")]
+    pub cache_ttl: Option<u64>,
+
+    /// Ignore cached responses (fresh responses are still stored)
+    #[arg(long, requires = "cache")]
+    pub no_cache: bool,
+}
+
+/// Setting that can be turned on or off
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Switch {
+    On,
+    Off,
+}
+
+/// HTTP version selection
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HttpVersion {
+    /// Negotiate with the server
+    Auto,
+    /// HTTP/1.1 only
+    #[value(name = "1.1")]
+    Http1,
+    /// HTTP/2 only (prior knowledge, also over plain HTTP)
+    #[value(name = "2")]
+    Http2,
+}
+
+/// Which body size dir mode reports and filters on
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SizeMode {
+    /// Length of the decompressed body
+    Decoded,
+    /// Bytes on the wire (Content-Length when sent)
+    Raw,
+}
+
+/// How --backup-extensions combines with the built-in backup suffixes
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackupExtensionsMode {
+    /// Use only the given suffixes
+    Replace,
+    /// Try the given suffixes after the built-in ones
+    Append,
+}
+
+/// How vhost responses are compared against the baseline page
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VhostCompare {
+    /// Body size differs
+    Size,
+    /// Normalized body content differs
+    Hash,
+    /// Both size and normalized content differ
+    Both,
+}
+
+/// DNS record types that can be queried
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DnsRecordType {
+    A,
+    Aaaa,
+    Cname,
+    Mx,
+    Txt,
+    Ns,
+    Soa,
+}
+
+/// How multiple fuzz wordlists are combined
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FuzzMode {
+    /// Every word of the first list with every word of the second
+    Product,
+    /// Words paired line by line, stopping at the shorter list
+    Zip,
 }
 
 /// AWS S3 bucket enumeration mode arguments
@@ -267,9 +870,31 @@ pub struct S3Args {
     #[arg(long, default_value = "5", value_name = "N")]
     pub max_files: usize,
 
+    /// Only report buckets whose listing holds at least N objects
+    #[arg(long, value_name = "N")]
+    pub min_files: Option<usize>,
+
     /// Request timeout in seconds
     #[arg(long, default_value = "10", value_name = "SECS")]
     pub timeout: u64,
+
+    /// Check whether the ACL and policy of found buckets can be read anonymously
+    #[arg(long)]
+    pub check_acl: bool,
+
+    /// Follow listing pages of public buckets until --max-files keys are listed
+    #[arg(long)]
+    pub list_all: bool,
+
+    /// Also probe regional endpoints for buckets the global endpoints don't find
+    #[arg(long)]
+    pub all_regions: bool,
+
+    /// Test found buckets for anonymous writes by uploading and then deleting
+    /// a small test object. This modifies the bucket: only use it on buckets
+    /// you are authorized to test
+    #[arg(long)]
+    pub check_writable: bool,
 }
 
 /// Google Cloud Storage enumeration mode arguments
@@ -282,6 +907,18 @@ pub struct GcsArgs {
     #[arg(long, default_value = "5", value_name = "N")]
     pub max_files: usize,
 
+    /// Only report buckets whose listing holds at least N objects
+    #[arg(long, value_name = "N")]
+    pub min_files: Option<usize>,
+
+    /// Also probe {name}.firebaseio.com for Realtime Databases readable without auth
+    #[arg(long)]
+    pub firebase: bool,
+
+    /// Also probe {name}.appspot.com for reachable App Engine apps
+    #[arg(long)]
+    pub appspot: bool,
+
     /// Request timeout in seconds
     #[arg(long, default_value = "10", value_name = "SECS")]
     pub timeout: u64,
@@ -300,26 +937,67 @@ pub struct TftpArgs {
     /// Request timeout in seconds
     #[arg(long, default_value = "5", value_name = "SECS")]
     pub timeout: u64,
+
+    /// Also send a write request for a random new name in each directory of
+    /// the wordlist to find where uploads are accepted. Only the handshake is
+    /// performed, but some servers create the empty file on request: only use
+    /// it on servers you are authorized to test
+    #[arg(long)]
+    pub check_writable: bool,
+}
+
+/// TCP banner grabbing mode arguments
+#[derive(Args, Debug)]
+pub struct TcpArgs {
+    #[command(flatten)]
+    pub global: GlobalOpts,
+
+    /// Host to connect to for wordlist entries that are only a port
+    /// (entries may also be host:port)
+    #[arg(long, value_name = "HOST")]
+    pub host: Option<String>,
+
+    /// Probe sent after connecting, before reading the banner (\r, \n, \t,
+    /// \0 and \xHH escapes are understood, e.g. "HEAD / HTTP/1.0\r\n\r\n")
+    #[arg(long, value_name = "DATA")]
+    pub send: Option<String>,
+
+    /// Connect and banner read timeout in seconds
+    #[arg(long, default_value = "3", value_name = "SECS")]
+    pub timeout: u64,
 }
 
 // Helper functions for parsing comma-separated values
+impl GlobalOpts {
+    /// Verbosity level from -v; --quiet turns it off
+    pub fn verbosity(&self) -> u8 {
+        if self.quiet {
+            0
+        } else {
+            self.verbose
+        }
+    }
+
+    /// Wordlist paths as shown in configs and reports
+    pub fn wordlist_display(&self) -> String {
+        self.wordlist
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
 impl DirArgs {
-    pub fn parse_status_codes(&self) -> Vec<u16> {
-        self.status_codes
-            .split(',')
-            .filter_map(|s| s.trim().parse::<u16>().ok())
-            .collect()
+    pub fn parse_status_codes(&self) -> Result<ScopedStatusCodes, String> {
+        parse_scoped_status_codes(&self.status_codes)
     }
 
-    pub fn parse_status_codes_blacklist(&self) -> Vec<u16> {
+    pub fn parse_status_codes_blacklist(&self) -> Result<Vec<u16>, String> {
         self.status_codes_blacklist
-            .as_ref()
-            .map(|s| {
-                s.split(',')
-                    .filter_map(|c| c.trim().parse::<u16>().ok())
-                    .collect()
-            })
-            .unwrap_or_default()
+            .as_deref()
+            .map(parse_status_codes)
+            .unwrap_or(Ok(Vec::new()))
     }
 
     pub fn parse_extensions(&self) -> Vec<String> {
@@ -328,11 +1006,232 @@ impl DirArgs {
             .map(|s| s.split(',').map(|e| e.trim().to_string()).collect())
             .unwrap_or_default()
     }
+}
 
-    pub fn parse_exclude_lengths(&self) -> Vec<usize> {
-        self.exclude_length
-            .as_ref()
-            .map(|s| s.split(',').filter_map(|l| l.trim().parse().ok()).collect())
-            .unwrap_or_default()
+/// Status codes shown by dir mode unless --status-codes says otherwise
+pub const DEFAULT_STATUS_CODES: &str = "200,204,301,302,307,308,401,403,405";
+
+/// Status codes from --status-codes, with optional per-extension sets
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScopedStatusCodes {
+    /// Codes for base paths and extensions without a set of their own
+    pub default: Vec<u16>,
+    /// Codes for paths generated with an extension, keyed without the dot
+    pub extensions: Vec<(String, Vec<u16>)>,
+}
+
+impl ScopedStatusCodes {
+    /// Codes that apply to paths generated with `ext`
+    pub fn for_extension(&self, ext: &str) -> &[u16] {
+        let ext = ext.trim_start_matches('.');
+        self.extensions
+            .iter()
+            .find(|(scope, _)| scope.eq_ignore_ascii_case(ext))
+            .map_or(&self.default, |(_, codes)| codes)
+    }
+}
+
+/// Parse --status-codes, where "EXT:" starts a set for one extension that
+/// runs until the next scope and "*:" sets the default. Codes before any
+/// scope are the default; without one, the built-in default applies.
+pub fn parse_scoped_status_codes(spec: &str) -> Result<ScopedStatusCodes, String> {
+    let mut default: Option<Vec<u16>> = None;
+    let mut extensions: Vec<(String, Vec<u16>)> = Vec::new();
+    let mut scope: Option<String> = None;
+    for part in spec.split(',') {
+        let codes = match part.split_once(':') {
+            Some((name, codes)) => {
+                let name = name.trim().trim_start_matches('.').to_ascii_lowercase();
+                if name.is_empty() {
+                    return Err(format!("'{}' has no extension before ':'", part.trim()));
+                }
+                scope = (name != "*").then_some(name);
+                codes
+            }
+            None => part,
+        };
+        let codes = parse_status_codes(codes)?;
+        match scope {
+            Some(ref name) => match extensions.iter_mut().find(|(scope, _)| scope == name) {
+                Some((_, existing)) => existing.extend(codes),
+                None => extensions.push((name.clone(), codes)),
+            },
+            None => default.get_or_insert_with(Vec::new).extend(codes),
+        }
+    }
+    let default = match default {
+        Some(default) => default,
+        None => parse_status_codes(DEFAULT_STATUS_CODES)?,
+    };
+    Ok(ScopedStatusCodes {
+        default,
+        extensions,
+    })
+}
+
+/// Parse comma-separated status codes, ranges ("200-299"), classes ("2xx")
+/// and "all"
+pub fn parse_status_codes(spec: &str) -> Result<Vec<u16>, String> {
+    let code = |text: &str| -> Result<u16, String> {
+        match text.trim().parse::<u16>() {
+            Ok(code) if (100..=599).contains(&code) => Ok(code),
+            _ => Err(format!("'{}' is not a status code (100-599)", text.trim())),
+        }
+    };
+
+    let mut codes = Vec::new();
+    for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let lower = part.to_ascii_lowercase();
+        if lower == "all" {
+            codes.extend(100..=599);
+        } else if let Some(class) = lower.strip_suffix("xx") {
+            match class.parse::<u16>() {
+                Ok(class @ 1..=5) => codes.extend(class * 100..=class * 100 + 99),
+                _ => return Err(format!("'{}' is not a status class (1xx-5xx)", part)),
+            }
+        } else if let Some((start, end)) = part.split_once('-') {
+            let (start, end) = (code(start)?, code(end)?);
+            if start > end {
+                return Err(format!("range '{}' ends before it starts", part));
+            }
+            codes.extend(start..=end);
+        } else {
+            codes.push(code(part)?);
+        }
+    }
+    Ok(codes)
+}
+
+/// Parse a millisecond range for --jitter, e.g. "100-500"
+fn parse_jitter(spec: &str) -> Result<(u64, u64), String> {
+    let (min, max) = spec
+        .split_once('-')
+        .ok_or_else(|| format!("'{}' is not a range (expected MIN-MAX)", spec))?;
+    let millis = |text: &str| {
+        text.trim()
+            .parse::<u64>()
+            .map_err(|_| format!("'{}' is not a number of milliseconds", text.trim()))
+    };
+    let (min, max) = (millis(min)?, millis(max)?);
+    if min > max {
+        return Err(format!("range '{}' ends before it starts", spec));
+    }
+    Ok((min, max))
+}
+
+/// Counts and inclusive ranges of counts, e.g. word counts to match
+pub type CountRanges = Vec<RangeInclusive<usize>>;
+
+/// Parse comma-separated counts and inclusive ranges, e.g. "5,10-20"
+pub fn parse_ranges(spec: &str) -> Result<CountRanges, String> {
+    let count = |text: &str| {
+        text.trim()
+            .parse::<usize>()
+            .map_err(|_| format!("'{}' is not a count", text.trim()))
+    };
+
+    let mut ranges = Vec::new();
+    for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        match part.split_once('-') {
+            Some((start, end)) => {
+                let (start, end) = (count(start)?, count(end)?);
+                if start > end {
+                    return Err(format!("range '{}' ends before it starts", part));
+                }
+                ranges.push(start..=end);
+            }
+            None => {
+                let n = count(part)?;
+                ranges.push(n..=n);
+            }
+        }
+    }
+    if ranges.is_empty() {
+        return Err("no counts given".to_string());
+    }
+    Ok(ranges)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_ranges_reads_counts_and_ranges() {
+        assert_eq!(parse_ranges("5, 10-20"), Ok(vec![5..=5, 10..=20]));
+        assert!(parse_ranges("ten").is_err());
+        assert!(parse_ranges("20-10").is_err());
+        assert!(parse_ranges("5-").is_err());
+        assert!(parse_ranges(",").is_err());
+    }
+
+    #[test]
+    fn bad_count_ranges_fail_argument_parsing() {
+        let parse = |spec: &str| {
+            Cli::try_parse_from([
+                "robuster",
+                "fuzz",
+                "-u",
+                "http://t/FUZZ",
+                "-w",
+                "words.txt",
+                "--match-words",
+                spec,
+            ])
+        };
+        assert!(parse("1-5,9").is_ok());
+        assert!(parse("1-x").is_err());
+    }
+
+    #[test]
+    fn status_codes_take_classes_ranges_and_all() {
+        let codes = |spec: &str| parse_status_codes(spec).unwrap();
+        assert_eq!(codes("2xx"), (200..=299).collect::<Vec<u16>>());
+        assert_eq!(codes("200-204"), [200, 201, 202, 203, 204]);
+        let mixed = codes("200,300-302,4XX");
+        assert_eq!(&mixed[..4], [200, 300, 301, 302]);
+        assert_eq!(&mixed[4..], (400..=499).collect::<Vec<u16>>());
+        assert_eq!(codes("all").len(), 500);
+    }
+
+    #[test]
+    fn malformed_status_codes_are_rejected() {
+        for spec in ["6xx", "xx", "204-200", "200-", "99", "600", "abc", "2x"] {
+            assert!(parse_status_codes(spec).is_err(), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn jitter_takes_an_ordered_millisecond_range() {
+        assert_eq!(parse_jitter("100-500"), Ok((100, 500)));
+        assert_eq!(parse_jitter(" 0 - 0 "), Ok((0, 0)));
+        for spec in ["500-100", "100", "a-5", "5-", "-5"] {
+            assert!(parse_jitter(spec).is_err(), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn status_codes_can_be_scoped_per_extension() {
+        let scoped = parse_scoped_status_codes("php:200,bak:200,403,*:200,301").unwrap();
+        assert_eq!(scoped.default, [200, 301]);
+        assert_eq!(scoped.for_extension("php"), [200]);
+        assert_eq!(scoped.for_extension(".BAK"), [200, 403]);
+        assert_eq!(scoped.for_extension("txt"), [200, 301]);
+
+        // Unscoped codes come first as the default
+        let leading = parse_scoped_status_codes("200,204,.php:200").unwrap();
+        assert_eq!(leading.default, [200, 204]);
+        assert_eq!(leading.extensions, [("php".to_string(), vec![200])]);
+
+        // Only scoped sets keep the built-in default for everything else
+        let builtin = parse_scoped_status_codes("bak:403").unwrap();
+        assert_eq!(
+            builtin.default,
+            parse_status_codes(DEFAULT_STATUS_CODES).unwrap()
+        );
+
+        for spec in [":200", " :404", "php:6xx"] {
+            assert!(parse_scoped_status_codes(spec).is_err(), "{}", spec);
+        }
     }
 }
@@ -39,17 +39,3 @@ pub fn print_config(mode: &str, config: &[(&str, String)]) {
     println!("Starting robuster in {} mode", mode.bright_yellow().bold());
     println!("{}", "═".repeat(60).bright_blue());
 }
-
-pub fn print_finished(found: usize, total: usize, duration: std::time::Duration) {
-    println!();
-    println!("{}", "═".repeat(60).bright_blue());
-    println!("{}", "Finished".bright_green().bold());
-    println!("{}", "═".repeat(60).bright_blue());
-    println!(
-        "[{}] Found: {} | Tested: {} | Duration: {:.2}s",
-        "*".bright_cyan(),
-        found.to_string().bright_green(),
-        total.to_string().bright_white(),
-        duration.as_secs_f64()
-    );
-}
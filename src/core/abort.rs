@@ -0,0 +1,87 @@
+//! Early abort after repeated request errors or when the user quits
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+/// Counts consecutive request errors across all workers and trips once a
+/// limit is exceeded, so a scan against a dead host stops early
+pub struct ErrorLimit {
+    max: Option<usize>,
+    consecutive: AtomicUsize,
+    tripped: AtomicBool,
+}
+
+impl ErrorLimit {
+    /// `None` disables the limit
+    pub fn new(max: Option<usize>) -> Self {
+        Self {
+            max,
+            consecutive: AtomicUsize::new(0),
+            tripped: AtomicBool::new(false),
+        }
+    }
+
+    /// Whether the scan should stop issuing requests
+    pub fn tripped(&self) -> bool {
+        self.tripped.load(Ordering::Relaxed)
+    }
+
+    /// Stop the scan as if the limit had tripped, e.g. when the user quits
+    /// the dashboard
+    pub fn cancel(&self) {
+        self.tripped.store(true, Ordering::Relaxed);
+    }
+
+    /// Record a successful request
+    pub fn success(&self) {
+        self.consecutive.store(0, Ordering::Relaxed);
+    }
+
+    /// Record a failed request. Returns true for the one call that trips the
+    /// limit, so the caller can report it once.
+    pub fn failure(&self) -> bool {
+        let Some(max) = self.max else {
+            return false;
+        };
+        let count = self.consecutive.fetch_add(1, Ordering::Relaxed) + 1;
+        count >= max && !self.tripped.swap(true, Ordering::Relaxed)
+    }
+
+    /// Message printed when the limit trips
+    pub fn message(&self) -> String {
+        format!(
+            "Aborting scan after {} consecutive errors (--max-errors)",
+            self.max.unwrap_or_default()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trips_once_after_consecutive_failures() {
+        let limit = ErrorLimit::new(Some(3));
+        assert!(!limit.failure());
+        assert!(!limit.failure());
+        limit.success();
+        assert!(!limit.failure());
+        assert!(!limit.failure());
+        assert!(!limit.tripped());
+        assert!(limit.failure());
+        assert!(limit.tripped());
+        // Later failures don't report it again
+        assert!(!limit.failure());
+    }
+
+    #[test]
+    fn no_limit_never_trips() {
+        let limit = ErrorLimit::new(None);
+        for _ in 0..1000 {
+            assert!(!limit.failure());
+        }
+        assert!(!limit.tripped());
+        limit.cancel();
+        assert!(limit.tripped());
+    }
+}
@@ -0,0 +1,109 @@
+//! On-disk response cache for replaying repeated scans
+
+use crate::core::fnv1a;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A cached HTTP response
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CachedResponse {
+    pub status: u16,
+    /// Length of the whole body, which may be longer than the cached `body`;
+    /// missing from entries written by older versions
+    #[serde(default)]
+    pub size: Option<usize>,
+    pub body: String,
+    /// Every response header; missing from entries written by older versions
+    #[serde(default)]
+    pub headers: Vec<(String, String)>,
+    /// Missing from entries written by older versions
+    #[serde(default)]
+    pub content_type: Option<String>,
+    pub stored_at: u64,
+}
+
+/// Response cache stored as one JSON file per request
+pub struct ResponseCache {
+    dir: PathBuf,
+    ttl: Option<Duration>,
+    bypass: bool,
+}
+
+impl ResponseCache {
+    /// Open (and create if needed) a cache directory.
+    ///
+    /// When `bypass` is set, lookups always miss but fresh responses are
+    /// still written, so the cache is refreshed by the run.
+    pub async fn new(dir: &Path, ttl: Option<Duration>, bypass: bool) -> std::io::Result<Self> {
+        tokio::fs::create_dir_all(dir).await?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            ttl,
+            bypass,
+        })
+    }
+
+    /// Build a cache key from the parts of a request that affect its response
+    pub fn key(method: &str, url: &str, headers: &[String], body: Option<&str>) -> String {
+        let hash = fnv1a(
+            [method, url]
+                .into_iter()
+                .chain(headers.iter().map(String::as_str))
+                .chain([body.unwrap_or_default()])
+                .map(str::as_bytes),
+        );
+        format!("{:016x}", hash)
+    }
+
+    /// Look up a response, ignoring entries older than the TTL
+    pub async fn get(&self, key: &str) -> Option<CachedResponse> {
+        if self.bypass {
+            return None;
+        }
+
+        let data = tokio::fs::read(self.path(key)).await.ok()?;
+        let entry: CachedResponse = serde_json::from_slice(&data).ok()?;
+
+        if let Some(ttl) = self.ttl {
+            if now_secs().saturating_sub(entry.stored_at) > ttl.as_secs() {
+                return None;
+            }
+        }
+
+        Some(entry)
+    }
+
+    /// Store a response under the given key
+    pub async fn put(
+        &self,
+        key: &str,
+        status: u16,
+        size: usize,
+        body: &str,
+        headers: &[(String, String)],
+        content_type: Option<&str>,
+    ) -> std::io::Result<()> {
+        let entry = CachedResponse {
+            status,
+            size: Some(size),
+            body: body.to_string(),
+            headers: headers.to_vec(),
+            content_type: content_type.map(str::to_string),
+            stored_at: now_secs(),
+        };
+        let json = serde_json::to_vec(&entry).map_err(std::io::Error::other)?;
+        tokio::fs::write(self.path(key), json).await
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
@@ -0,0 +1,178 @@
+//! Scan checkpoints for resuming interrupted runs
+
+use crate::core::fnv1a;
+use crate::error::{RbusterError, Result};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tokio::io::AsyncWriteExt;
+
+/// Number of completions between checkpoint writes
+const FLUSH_EVERY: usize = 100;
+
+/// Tracks which work items of a scan are done and persists them
+/// periodically. The file holds the scan fingerprint on its first line and
+/// then one completed index per line; writes only append the indices
+/// completed since the last one.
+pub struct Checkpoint {
+    path: PathBuf,
+    completed: Mutex<HashSet<usize>>,
+    /// Completed indices not yet on disk; locked while appending
+    unflushed: tokio::sync::Mutex<Vec<usize>>,
+}
+
+impl Checkpoint {
+    /// Open a checkpoint, loading completed items if the file already exists.
+    /// Fails if the file was written for a different scan configuration.
+    pub async fn open(path: &Path, fingerprint: String) -> Result<Self> {
+        let completed: HashSet<usize> = match tokio::fs::read_to_string(path).await {
+            Ok(data) => {
+                let mut lines = data.split_inclusive('\n');
+                if lines.next().and_then(|line| line.strip_suffix('\n')) != Some(&fingerprint) {
+                    return Err(RbusterError::ConfigError(format!(
+                        "Checkpoint '{}' was created for a different target or wordlist",
+                        path.display()
+                    )));
+                }
+                // A run killed mid-write may leave a torn last line; only
+                // lines ending in a newline were written completely
+                lines
+                    .filter_map(|line| line.strip_suffix('\n'))
+                    .filter_map(|line| line.parse().ok())
+                    .collect()
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashSet::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        // Start from a compact file that ends with a full line
+        let mut data = format!("{}\n", fingerprint);
+        for index in &completed {
+            data.push_str(&format!("{}\n", index));
+        }
+        let tmp = path.with_extension("tmp");
+        tokio::fs::write(&tmp, data).await?;
+        tokio::fs::rename(&tmp, path).await?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            completed: Mutex::new(completed),
+            unflushed: tokio::sync::Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Build a fingerprint from the settings that determine the work items
+    pub fn fingerprint(parts: &[&str]) -> String {
+        format!("{:016x}", fnv1a(parts.iter().map(|p| p.as_bytes())))
+    }
+
+    pub fn is_done(&self, index: usize) -> bool {
+        self.completed.lock().unwrap().contains(&index)
+    }
+
+    pub fn completed_count(&self) -> usize {
+        self.completed.lock().unwrap().len()
+    }
+
+    /// Mark an item as done, writing the checkpoint every `FLUSH_EVERY` items
+    pub async fn complete(&self, index: usize) {
+        self.completed.lock().unwrap().insert(index);
+        let mut unflushed = self.unflushed.lock().await;
+        unflushed.push(index);
+        if unflushed.len() >= FLUSH_EVERY {
+            let _ = self.append(&mut unflushed).await;
+        }
+    }
+
+    /// Write the items completed since the last write
+    pub async fn flush(&self) -> std::io::Result<()> {
+        let mut unflushed = self.unflushed.lock().await;
+        self.append(&mut unflushed).await
+    }
+
+    async fn append(&self, unflushed: &mut Vec<usize>) -> std::io::Result<()> {
+        if unflushed.is_empty() {
+            return Ok(());
+        }
+        let lines: String = unflushed.iter().map(|i| format!("{}\n", i)).collect();
+        let mut file = tokio::fs::OpenOptions::new()
+            .append(true)
+            .open(&self.path)
+            .await?;
+        file.write_all(lines.as_bytes()).await?;
+        file.flush().await?;
+        unflushed.clear();
+        Ok(())
+    }
+
+    /// Remove the checkpoint once the scan has finished
+    pub async fn remove(&self) -> std::io::Result<()> {
+        match tokio::fs::remove_file(&self.path).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch_path() -> PathBuf {
+        std::env::temp_dir().join(format!("rbuster-{}.ckpt", crate::core::random_label(12)))
+    }
+
+    #[tokio::test]
+    async fn resume_skips_work_written_before_a_kill() {
+        let path = scratch_path();
+        let fingerprint = Checkpoint::fingerprint(&["words.txt", "http://t"]);
+
+        // The first run completes 150 items; only the first 100 are written
+        // before it is killed without a final flush
+        let first = Checkpoint::open(&path, fingerprint.clone()).await.unwrap();
+        for index in 0..150 {
+            first.complete(index).await;
+        }
+        drop(first);
+
+        let second = Checkpoint::open(&path, fingerprint.clone()).await.unwrap();
+        assert_eq!(second.completed_count(), 100);
+        assert!(second.is_done(99));
+        assert!(!second.is_done(100));
+        second.complete(100).await;
+        second.flush().await.unwrap();
+
+        let third = Checkpoint::open(&path, fingerprint).await.unwrap();
+        let mismatch = Checkpoint::open(&path, "other".to_string()).await;
+        third.remove().await.unwrap();
+        assert_eq!(third.completed_count(), 101);
+        assert!(mismatch.is_err());
+    }
+
+    #[tokio::test]
+    async fn torn_last_line_is_not_trusted() {
+        let path = scratch_path();
+        let fingerprint = Checkpoint::fingerprint(&["words.txt", "http://t"]);
+
+        let first = Checkpoint::open(&path, fingerprint.clone()).await.unwrap();
+        for index in 0..10 {
+            first.complete(index).await;
+        }
+        first.flush().await.unwrap();
+        drop(first);
+        // The kill interrupted writing "123\n"
+        let mut file = tokio::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .await
+            .unwrap();
+        file.write_all(b"12").await.unwrap();
+        drop(file);
+
+        let second = Checkpoint::open(&path, fingerprint).await.unwrap();
+        second.remove().await.unwrap();
+        assert_eq!(second.completed_count(), 10);
+        assert!(second.is_done(9));
+        assert!(!second.is_done(12));
+    }
+}
@@ -0,0 +1,207 @@
+//! Concurrency limit, optionally steered by the error rate (--adaptive)
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+/// Requests completed between adjustments of an adaptive limit
+const WINDOW: usize = 20;
+
+/// Error rate up to which an adaptive limit grows by one worker
+const RAISE_AT_MOST: f64 = 0.02;
+
+/// Error rate from which an adaptive limit is halved
+const CUT_AT_LEAST: f64 = 0.1;
+
+/// Caps the requests in flight. A fixed limit behaves like a semaphore; an
+/// adaptive one starts at its minimum and follows an AIMD controller: one
+/// more worker after each window of requests with few errors, half as many
+/// after a window where errors (timeouts, resets) spike.
+pub struct Concurrency {
+    semaphore: Semaphore,
+    adaptive: Option<Aimd>,
+}
+
+struct Aimd {
+    min: usize,
+    max: usize,
+    window: Mutex<Window>,
+    /// Permits to drop as they are released, after the limit was cut
+    debt: AtomicUsize,
+}
+
+#[derive(Default)]
+struct Window {
+    limit: usize,
+    successes: usize,
+    failures: usize,
+}
+
+/// Slot held while a request is in flight
+pub struct ConcurrencyPermit<'a> {
+    permit: Option<SemaphorePermit<'a>>,
+    concurrency: &'a Concurrency,
+}
+
+impl Drop for ConcurrencyPermit<'_> {
+    fn drop(&mut self) {
+        let Some(permit) = self.permit.take() else {
+            return;
+        };
+        let Some(ref aimd) = self.concurrency.adaptive else {
+            return;
+        };
+        let repaid = aimd
+            .debt
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |debt| {
+                debt.checked_sub(1)
+            })
+            .is_ok();
+        if repaid {
+            permit.forget();
+        }
+    }
+}
+
+impl Concurrency {
+    /// Fixed limit of `threads` requests
+    pub fn fixed(threads: usize) -> Self {
+        Self {
+            semaphore: Semaphore::new(threads),
+            adaptive: None,
+        }
+    }
+
+    /// Limit that starts at `min` and moves between `min` and `max`
+    pub fn adaptive(min: usize, max: usize) -> Self {
+        Self {
+            semaphore: Semaphore::new(min),
+            adaptive: Some(Aimd {
+                min,
+                max,
+                window: Mutex::new(Window {
+                    limit: min,
+                    ..Default::default()
+                }),
+                debt: AtomicUsize::new(0),
+            }),
+        }
+    }
+
+    /// Wait for a free slot
+    pub async fn acquire(&self) -> ConcurrencyPermit<'_> {
+        ConcurrencyPermit {
+            permit: Some(
+                self.semaphore
+                    .acquire()
+                    .await
+                    .expect("semaphore never closed"),
+            ),
+            concurrency: self,
+        }
+    }
+
+    /// Current limit of an adaptive concurrency
+    pub fn adaptive_limit(&self) -> Option<usize> {
+        self.adaptive
+            .as_ref()
+            .map(|aimd| aimd.window.lock().unwrap().limit)
+    }
+
+    /// Record whether a request got a response, adjusting an adaptive limit
+    /// at the end of each window
+    pub fn record(&self, success: bool) {
+        let Some(ref aimd) = self.adaptive else {
+            return;
+        };
+        let mut window = aimd.window.lock().unwrap();
+        if success {
+            window.successes += 1;
+        } else {
+            window.failures += 1;
+        }
+        let total = window.successes + window.failures;
+        if total < WINDOW {
+            return;
+        }
+
+        let error_rate = window.failures as f64 / total as f64;
+        let limit = window.limit;
+        let target = if error_rate >= CUT_AT_LEAST {
+            (limit / 2).max(aimd.min)
+        } else if error_rate <= RAISE_AT_MOST {
+            (limit + 1).min(aimd.max)
+        } else {
+            limit
+        };
+        window.limit = target;
+        window.successes = 0;
+        window.failures = 0;
+
+        if target > limit {
+            // Cancel permits still owed from a cut before adding new ones
+            let grow = target - limit;
+            let (Ok(debt) | Err(debt)) =
+                aimd.debt
+                    .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |debt| {
+                        Some(debt - debt.min(grow))
+                    });
+            self.semaphore.add_permits(grow - debt.min(grow));
+        } else if target < limit {
+            // Take free permits now; the rest as requests finish
+            let mut cut = limit - target;
+            while cut > 0 {
+                match self.semaphore.try_acquire() {
+                    Ok(permit) => permit.forget(),
+                    Err(_) => break,
+                }
+                cut -= 1;
+            }
+            aimd.debt.fetch_add(cut, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn rising_errors_lower_the_limit() {
+        let concurrency = Concurrency::adaptive(2, 16);
+        for _ in 0..WINDOW * 6 {
+            concurrency.record(true);
+        }
+        let ramped = concurrency.adaptive_limit().unwrap();
+        assert_eq!(ramped, 8);
+
+        // A window where a quarter of requests fail halves the limit
+        for i in 0..WINDOW {
+            concurrency.record(i % 4 != 0);
+        }
+        assert_eq!(concurrency.adaptive_limit(), Some(4));
+
+        // Free permits were taken back along with the limit
+        let permits: Vec<_> =
+            futures::future::join_all((0..4).map(|_| concurrency.acquire())).await;
+        assert!(concurrency.semaphore.try_acquire().is_err());
+        drop(permits);
+
+        // Errors that keep coming bottom out at the minimum
+        for _ in 0..WINDOW * 4 {
+            concurrency.record(false);
+        }
+        assert_eq!(concurrency.adaptive_limit(), Some(2));
+        assert_eq!(concurrency.semaphore.available_permits(), 2);
+    }
+
+    #[test]
+    fn fixed_limit_ignores_errors() {
+        let concurrency = Concurrency::fixed(10);
+        for _ in 0..WINDOW * 2 {
+            concurrency.record(false);
+        }
+        assert_eq!(concurrency.adaptive_limit(), None);
+        assert_eq!(concurrency.semaphore.available_permits(), 10);
+    }
+}
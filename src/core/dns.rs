@@ -1,17 +1,91 @@
 //! DNS resolver wrapper using hickory-resolver
 
+use crate::core::{random_label, AddressFamily};
 use crate::error::{RbusterError, Result};
 use hickory_resolver::config::{NameServerConfig, Protocol, ResolverConfig, ResolverOpts};
+use hickory_resolver::proto::op::{Message, MessageType, OpCode, Query, ResponseCode};
+pub use hickory_resolver::proto::rr::RecordType;
+use hickory_resolver::proto::rr::{Name, RData};
+use hickory_resolver::proto::serialize::binary::BinEncodable;
 use hickory_resolver::TokioAsyncResolver;
+use std::collections::HashSet;
 use std::net::{IpAddr, SocketAddr};
 use std::str::FromStr;
+use std::sync::atomic::{AtomicU16, Ordering};
 use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// Consecutive wildcard probes that must add no new address before probing stops
+const WILDCARD_STABLE_PROBES: usize = 3;
+
+/// Upper bound on probes while a round-robin wildcard keeps returning new addresses
+const WILDCARD_MAX_PROBES: usize = 12;
+
+/// Lookups between refreshes of the known wildcard addresses during a scan,
+/// so rotating wildcards keep being recognized
+pub const WILDCARD_REFRESH_INTERVAL: usize = 500;
+
+/// Whether a lookup answer comes from a wildcard: at least `min_overlap`
+/// percent of its addresses must be ones the wildcard answered with, so 100
+/// requires a subset. Answers without addresses (CNAME only) never count.
+pub fn is_wildcard_answer(ips: &[IpAddr], wildcard_ips: &HashSet<IpAddr>, min_overlap: u8) -> bool {
+    if ips.is_empty() {
+        return false;
+    }
+    let shared = ips.iter().filter(|ip| wildcard_ips.contains(ip)).count();
+    shared * 100 >= ips.len() * min_overlap as usize
+}
+
+/// CNAME target suffixes of services that let anyone claim an unused name,
+/// so a dangling CNAME to them can be taken over
+const TAKEOVER_SUFFIXES: &[(&str, &str)] = &[
+    (".github.io", "GitHub Pages"),
+    (".herokuapp.com", "Heroku"),
+    (".herokudns.com", "Heroku"),
+    (".s3.amazonaws.com", "AWS S3"),
+    (".amazonaws.com", "AWS"),
+    (".cloudfront.net", "AWS CloudFront"),
+    (".azurewebsites.net", "Azure App Service"),
+    (".cloudapp.net", "Azure Cloud Services"),
+    (".cloudapp.azure.com", "Azure"),
+    (".trafficmanager.net", "Azure Traffic Manager"),
+    (".blob.core.windows.net", "Azure Blob Storage"),
+    (".azureedge.net", "Azure CDN"),
+    (".bitbucket.io", "Bitbucket"),
+    (".netlify.app", "Netlify"),
+    (".netlify.com", "Netlify"),
+    (".ghost.io", "Ghost"),
+    (".myshopify.com", "Shopify"),
+    (".pantheonsite.io", "Pantheon"),
+    (".surge.sh", "Surge"),
+    (".readme.io", "ReadMe"),
+    (".zendesk.com", "Zendesk"),
+    (".wordpress.com", "WordPress"),
+    (".fly.dev", "Fly.io"),
+    (".vercel.app", "Vercel"),
+];
+
+/// Service a CNAME target belongs to, if it is one known to allow takeovers
+pub fn takeover_service(cname: &str) -> Option<&'static str> {
+    let cname = cname.trim_end_matches('.').to_ascii_lowercase();
+    TAKEOVER_SUFFIXES
+        .iter()
+        .find(|(suffix, _)| cname.ends_with(suffix))
+        .map(|(_, service)| *service)
+}
 
 /// DNS client configuration
 #[derive(Clone, Debug)]
 pub struct DnsConfig {
     pub resolver: Option<String>,
     pub timeout: Duration,
+    /// SOCKS5 proxy to tunnel DNS-over-TCP queries through
+    pub socks_proxy: Option<String>,
+    /// Record types queried for each name
+    pub record_types: Vec<RecordType>,
+    /// Address family to report; A or AAAA records of the other are not queried
+    pub address_family: AddressFamily,
 }
 
 impl Default for DnsConfig {
@@ -19,6 +93,9 @@ impl Default for DnsConfig {
         Self {
             resolver: None,
             timeout: Duration::from_secs(5),
+            socks_proxy: None,
+            record_types: vec![RecordType::A, RecordType::AAAA, RecordType::CNAME],
+            address_family: AddressFamily::Any,
         }
     }
 }
@@ -30,15 +107,72 @@ pub struct DnsResult {
     pub subdomain: String,
     pub ips: Vec<IpAddr>,
     pub cnames: Vec<String>,
+    pub mx: Vec<String>,
+    pub txt: Vec<String>,
+    pub ns: Vec<String>,
+    pub soa: Vec<String>,
+}
+
+/// Records handed out by a nameserver that allowed a zone transfer
+#[derive(Debug, Clone)]
+pub struct ZoneTransfer {
+    pub nameserver: String,
+    pub address: SocketAddr,
+    pub records: Vec<String>,
 }
 
 /// DNS resolver client
 pub struct DnsClient {
-    resolver: TokioAsyncResolver,
+    backend: Backend,
+    record_types: Vec<RecordType>,
+    address_family: AddressFamily,
+    timeout: Duration,
+}
+
+/// How queries reach the nameserver
+enum Backend {
+    Resolver(Box<TokioAsyncResolver>),
+    Socks(SocksTransport),
 }
 
 impl DnsClient {
-    pub async fn new(config: DnsConfig) -> Result<Self> {
+    pub async fn new(mut config: DnsConfig) -> Result<Self> {
+        let excluded = match config.address_family {
+            AddressFamily::Any => None,
+            AddressFamily::Ipv4 => Some(RecordType::AAAA),
+            AddressFamily::Ipv6 => Some(RecordType::A),
+        };
+        config.record_types.retain(|t| Some(*t) != excluded);
+        if config.record_types.is_empty() {
+            return Err(RbusterError::ConfigError(
+                "No record types left to query for the chosen address family".to_string(),
+            ));
+        }
+
+        // Tunnel queries through a SOCKS5 proxy
+        if let Some(ref proxy) = config.socks_proxy {
+            let resolver_addr = config.resolver.as_ref().ok_or_else(|| {
+                RbusterError::ConfigError(
+                    "--dns-socks requires --resolver to name a nameserver behind the proxy"
+                        .to_string(),
+                )
+            })?;
+            let nameserver =
+                parse_resolver_address(resolver_addr).map_err(RbusterError::DnsError)?;
+
+            return Ok(Self {
+                backend: Backend::Socks(SocksTransport {
+                    proxy: parse_socks_url(proxy).map_err(RbusterError::ConfigError)?,
+                    nameserver,
+                    timeout: config.timeout,
+                    next_id: AtomicU16::new(1),
+                }),
+                record_types: config.record_types,
+                address_family: config.address_family,
+                timeout: config.timeout,
+            });
+        }
+
         let resolver = if let Some(ref resolver_addr) = config.resolver {
             // Parse custom resolver address
             let socket_addr =
@@ -58,35 +192,154 @@ impl DnsClient {
                 .map_err(|e| RbusterError::DnsError(e.to_string()))?
         };
 
-        Ok(Self { resolver })
+        Ok(Self {
+            backend: Backend::Resolver(Box::new(resolver)),
+            record_types: config.record_types,
+            address_family: config.address_family,
+            timeout: config.timeout,
+        })
     }
 
-    /// Resolve a subdomain and return IPs and CNAMEs
-    pub async fn resolve(&self, domain: &str) -> Result<DnsResult> {
-        let mut ips = Vec::new();
-        let mut cnames = Vec::new();
+    /// Look up A/AAAA records of the allowed family, following CNAMEs
+    async fn lookup_ips(&self, domain: &str) -> Vec<IpAddr> {
+        let mut ips = self.lookup_all_ips(domain).await;
+        ips.retain(|ip| self.address_family.allows(ip));
+        ips
+    }
 
-        // Try to resolve A records
-        if let Ok(response) = self.resolver.lookup_ip(domain).await {
-            for ip in response.iter() {
-                ips.push(ip);
+    async fn lookup_all_ips(&self, domain: &str) -> Vec<IpAddr> {
+        match self.backend {
+            Backend::Resolver(ref resolver) => resolver
+                .lookup_ip(domain)
+                .await
+                .map(|response| response.iter().collect())
+                .unwrap_or_default(),
+            Backend::Socks(ref socks) => {
+                let mut ips = Vec::new();
+                for record_type in [RecordType::A, RecordType::AAAA] {
+                    if let Ok(response) = socks.query(domain, record_type).await {
+                        ips.extend(response.answers().iter().filter_map(|r| match r.data() {
+                            Some(RData::A(a)) => Some(IpAddr::V4(a.0)),
+                            Some(RData::AAAA(aaaa)) => Some(IpAddr::V6(aaaa.0)),
+                            _ => None,
+                        }));
+                    }
+                }
+                ips
             }
         }
+    }
 
-        // Try to resolve CNAME records
-        if let Ok(response) = self
-            .resolver
-            .lookup(domain, hickory_resolver::proto::rr::RecordType::CNAME)
-            .await
-        {
-            for record in response.iter() {
-                if let Some(cname) = record.as_cname() {
-                    cnames.push(cname.to_utf8());
-                }
-            }
+    /// Look up CNAME records
+    async fn lookup_cnames(&self, domain: &str) -> Vec<String> {
+        match self.backend {
+            Backend::Resolver(ref resolver) => resolver
+                .lookup(domain, RecordType::CNAME)
+                .await
+                .map(|response| {
+                    response
+                        .iter()
+                        .filter_map(|r| r.as_cname().map(|c| c.to_utf8()))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            Backend::Socks(ref socks) => socks
+                .query(domain, RecordType::CNAME)
+                .await
+                .map(|response| {
+                    response
+                        .answers()
+                        .iter()
+                        .filter_map(|r| match r.data() {
+                            Some(RData::CNAME(c)) => Some(c.0.to_utf8()),
+                            _ => None,
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Look up records of one type, as rendered record data
+    async fn lookup_records(&self, domain: &str, record_type: RecordType) -> Vec<RData> {
+        let records: Vec<RData> = match self.backend {
+            Backend::Resolver(ref resolver) => resolver
+                .lookup(domain, record_type)
+                .await
+                .map(|response| response.iter().cloned().collect())
+                .unwrap_or_default(),
+            Backend::Socks(ref socks) => socks
+                .query(domain, record_type)
+                .await
+                .map(|response| {
+                    response
+                        .answers()
+                        .iter()
+                        .filter_map(|r| r.data().cloned())
+                        .collect()
+                })
+                .unwrap_or_default(),
+        };
+
+        // Answers can include the CNAME chain; keep only the requested type
+        records
+            .into_iter()
+            .filter(|r| r.record_type() == record_type)
+            .collect()
+    }
+
+    /// Look up records of one type if it was requested
+    async fn lookup_requested(&self, domain: &str, record_type: RecordType) -> Vec<String> {
+        if !self.record_types.contains(&record_type) {
+            return Vec::new();
         }
+        self.lookup_records(domain, record_type)
+            .await
+            .iter()
+            .map(|r| r.to_string())
+            .collect()
+    }
+
+    /// Resolve a subdomain and return the requested records
+    pub async fn resolve(&self, domain: &str) -> Result<DnsResult> {
+        let wants_a = self.record_types.contains(&RecordType::A);
+        let wants_aaaa = self.record_types.contains(&RecordType::AAAA);
 
-        if ips.is_empty() && cnames.is_empty() {
+        let ips = if wants_a && wants_aaaa {
+            self.lookup_ips(domain).await
+        } else if wants_a || wants_aaaa {
+            let record_type = if wants_a {
+                RecordType::A
+            } else {
+                RecordType::AAAA
+            };
+            self.lookup_records(domain, record_type)
+                .await
+                .iter()
+                .filter_map(|r| r.ip_addr())
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        let cnames = if self.record_types.contains(&RecordType::CNAME) {
+            self.lookup_cnames(domain).await
+        } else {
+            Vec::new()
+        };
+
+        let mx = self.lookup_requested(domain, RecordType::MX).await;
+        let txt = self.lookup_requested(domain, RecordType::TXT).await;
+        let ns = self.lookup_requested(domain, RecordType::NS).await;
+        let soa = self.lookup_requested(domain, RecordType::SOA).await;
+
+        if ips.is_empty()
+            && cnames.is_empty()
+            && mx.is_empty()
+            && txt.is_empty()
+            && ns.is_empty()
+            && soa.is_empty()
+        {
             return Err(RbusterError::DnsError(format!(
                 "No records found for {}",
                 domain
@@ -97,29 +350,278 @@ impl DnsClient {
             subdomain: domain.to_string(),
             ips,
             cnames,
+            mx,
+            txt,
+            ns,
+            soa,
         })
     }
 
+    /// Attempt a zone transfer (AXFR) of `domain` from each of its
+    /// nameservers. Servers that refuse, fail or time out are left out, so
+    /// only successful transfers are returned.
+    pub async fn zone_transfer(&self, domain: &str) -> Vec<ZoneTransfer> {
+        let nameservers: Vec<String> = self
+            .lookup_records(domain, RecordType::NS)
+            .await
+            .iter()
+            .filter_map(|r| match r {
+                RData::NS(ns) => Some(ns.0.to_utf8()),
+                _ => None,
+            })
+            .collect();
+
+        let mut transfers = Vec::new();
+        for nameserver in nameservers {
+            for ip in self.lookup_ips(&nameserver).await {
+                let address = SocketAddr::new(ip, 53);
+                let transfer = tokio::time::timeout(self.timeout, self.axfr(domain, address)).await;
+                if let Ok(Ok(records)) = transfer {
+                    if !records.is_empty() {
+                        transfers.push(ZoneTransfer {
+                            nameserver: nameserver.clone(),
+                            address,
+                            records,
+                        });
+                        // One address per nameserver is enough
+                        break;
+                    }
+                }
+            }
+        }
+        transfers
+    }
+
+    /// Run one AXFR over TCP, through the SOCKS proxy if one is configured.
+    /// The zone ends with a second copy of its SOA record.
+    async fn axfr(&self, domain: &str, nameserver: SocketAddr) -> std::io::Result<Vec<String>> {
+        let name = Name::from_str(domain)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+        let mut message = Message::new();
+        // The connection carries only this query, so any ID will do
+        message
+            .set_id(1)
+            .set_message_type(MessageType::Query)
+            .set_op_code(OpCode::Query)
+            .add_query(Query::query(name, RecordType::AXFR));
+        let packet = message.to_bytes().map_err(std::io::Error::other)?;
+
+        let mut stream = match self.backend {
+            Backend::Socks(ref socks) => socks5_connect(&socks.proxy, nameserver).await?,
+            Backend::Resolver(_) => TcpStream::connect(nameserver).await?,
+        };
+        stream
+            .write_all(&(packet.len() as u16).to_be_bytes())
+            .await?;
+        stream.write_all(&packet).await?;
+
+        let mut records = Vec::new();
+        let mut soa_seen = 0;
+        while soa_seen < 2 {
+            let mut len = [0u8; 2];
+            stream.read_exact(&mut len).await?;
+            let mut buf = vec![0u8; u16::from_be_bytes(len) as usize];
+            stream.read_exact(&mut buf).await?;
+
+            let response = Message::from_vec(&buf).map_err(std::io::Error::other)?;
+            // REFUSED, NOTAUTH and friends, or an empty answer
+            if response.response_code() != ResponseCode::NoError || response.answers().is_empty() {
+                return Ok(Vec::new());
+            }
+            for record in response.answers() {
+                if record.record_type() == RecordType::SOA {
+                    soa_seen += 1;
+                }
+                if soa_seen < 2 {
+                    records.push(record.to_string());
+                }
+            }
+        }
+        Ok(records)
+    }
+
     /// Check if a subdomain exists (simple check)
     #[allow(dead_code)]
     pub async fn exists(&self, domain: &str) -> bool {
-        self.resolver.lookup_ip(domain).await.is_ok()
+        !self.lookup_ips(domain).await.is_empty()
     }
 
-    /// Detect wildcard DNS
-    pub async fn detect_wildcard(&self, base_domain: &str) -> Option<Vec<IpAddr>> {
-        // Test with a random subdomain that shouldn't exist
-        let random_subdomain =
-            format!("robuster-wildcard-test-{}.{}", rand_string(16), base_domain);
+    /// The CNAME target of `result` if it doesn't resolve to any address,
+    /// which leaves the name open to takeover by whoever claims the target
+    pub async fn dangling_cname(&self, result: &DnsResult) -> Option<String> {
+        // The end of the chain is the name that has to resolve
+        let target = result.cnames.last()?;
+        if !result.ips.is_empty() || !self.lookup_all_ips(target).await.is_empty() {
+            return None;
+        }
+        Some(target.clone())
+    }
+
+    /// Detect wildcard DNS at `base_domain`. Several random names are looked
+    /// up and the domain only counts as a wildcard if every one resolves, so a
+    /// single fluke answer is ignored. Round-robin wildcards answer with
+    /// different addresses per query, so the union of all answers is returned
+    /// once several probes in a row add no new address. A zone that keeps
+    /// answering with new addresses has no stable set to filter by and is not
+    /// reported as a wildcard.
+    pub async fn detect_wildcard(&self, base_domain: &str) -> Option<HashSet<IpAddr>> {
+        let mut wildcard_ips = HashSet::new();
+        let mut stable = 0;
+
+        for probe in 0..WILDCARD_MAX_PROBES {
+            // Test with a random subdomain that shouldn't exist
+            let random_subdomain = format!(
+                "robuster-wildcard-test-{}-{}.{}",
+                probe,
+                random_label(16),
+                base_domain
+            );
 
-        if let Ok(response) = self.resolver.lookup_ip(&random_subdomain).await {
-            let ips: Vec<IpAddr> = response.iter().collect();
-            if !ips.is_empty() {
-                return Some(ips);
+            let ips = self.lookup_ips(&random_subdomain).await;
+            if ips.is_empty() {
+                return None;
+            }
+
+            let known = wildcard_ips.len();
+            wildcard_ips.extend(ips);
+            stable = if wildcard_ips.len() == known {
+                stable + 1
+            } else {
+                0
+            };
+            if stable == WILDCARD_STABLE_PROBES {
+                return Some(wildcard_ips);
             }
         }
+
         None
     }
+
+    /// Addresses a single random name under `domain` resolves to. Anything
+    /// at all means the level answers like a wildcard right now.
+    pub async fn probe_wildcard(&self, domain: &str) -> Vec<IpAddr> {
+        let random_subdomain = format!("robuster-wildcard-test-{}.{}", random_label(16), domain);
+        self.lookup_ips(&random_subdomain).await
+    }
+}
+
+/// DNS-over-TCP transport tunneled through a SOCKS5 proxy
+struct SocksTransport {
+    proxy: String,
+    nameserver: SocketAddr,
+    timeout: Duration,
+    next_id: AtomicU16,
+}
+
+impl SocksTransport {
+    /// Send a single query and return the parsed response
+    async fn query(&self, domain: &str, record_type: RecordType) -> Result<Message> {
+        tokio::time::timeout(self.timeout, self.exchange(domain, record_type))
+            .await
+            .map_err(|_| RbusterError::DnsError(format!("Query for {} timed out", domain)))?
+            .map_err(|e| RbusterError::DnsError(format!("SOCKS query for {}: {}", domain, e)))
+    }
+
+    async fn exchange(&self, domain: &str, record_type: RecordType) -> std::io::Result<Message> {
+        let name = Name::from_str(domain)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+
+        let mut message = Message::new();
+        message
+            .set_id(self.next_id.fetch_add(1, Ordering::Relaxed))
+            .set_message_type(MessageType::Query)
+            .set_op_code(OpCode::Query)
+            .set_recursion_desired(true)
+            .add_query(Query::query(name, record_type));
+        let packet = message.to_bytes().map_err(std::io::Error::other)?;
+
+        let mut stream = socks5_connect(&self.proxy, self.nameserver).await?;
+
+        // DNS over TCP prefixes each message with its length
+        stream
+            .write_all(&(packet.len() as u16).to_be_bytes())
+            .await?;
+        stream.write_all(&packet).await?;
+
+        let mut len = [0u8; 2];
+        stream.read_exact(&mut len).await?;
+        let mut buf = vec![0u8; u16::from_be_bytes(len) as usize];
+        stream.read_exact(&mut buf).await?;
+
+        Message::from_vec(&buf).map_err(std::io::Error::other)
+    }
+}
+
+/// Open a TCP connection to `target` through a SOCKS5 proxy (no authentication)
+async fn socks5_connect(proxy: &str, target: SocketAddr) -> std::io::Result<TcpStream> {
+    let mut stream = TcpStream::connect(proxy).await?;
+
+    // Greeting: version 5, one method, "no authentication"
+    stream.write_all(&[0x05, 0x01, 0x00]).await?;
+    let mut reply = [0u8; 2];
+    stream.read_exact(&mut reply).await?;
+    if reply != [0x05, 0x00] {
+        return Err(std::io::Error::other(
+            "SOCKS5 proxy rejected unauthenticated access",
+        ));
+    }
+
+    // CONNECT request
+    let mut request = vec![0x05, 0x01, 0x00];
+    match target.ip() {
+        IpAddr::V4(ip) => {
+            request.push(0x01);
+            request.extend_from_slice(&ip.octets());
+        }
+        IpAddr::V6(ip) => {
+            request.push(0x04);
+            request.extend_from_slice(&ip.octets());
+        }
+    }
+    request.extend_from_slice(&target.port().to_be_bytes());
+    stream.write_all(&request).await?;
+
+    // Reply header, then the bound address which we discard
+    let mut header = [0u8; 4];
+    stream.read_exact(&mut header).await?;
+    if header[1] != 0x00 {
+        return Err(std::io::Error::other(format!(
+            "SOCKS5 CONNECT failed with code {}",
+            header[1]
+        )));
+    }
+    let addr_len = match header[3] {
+        0x01 => 4,
+        0x04 => 16,
+        0x03 => {
+            let mut len = [0u8; 1];
+            stream.read_exact(&mut len).await?;
+            len[0] as usize
+        }
+        _ => return Err(std::io::Error::other("Invalid SOCKS5 address type")),
+    };
+    let mut bound = vec![0u8; addr_len + 2];
+    stream.read_exact(&mut bound).await?;
+
+    Ok(stream)
+}
+
+/// Parse a SOCKS proxy URL in format "socks5://host:port" or "host:port"
+fn parse_socks_url(url: &str) -> std::result::Result<String, String> {
+    let addr = url
+        .strip_prefix("socks5h://")
+        .or_else(|| url.strip_prefix("socks5://"))
+        .unwrap_or(url)
+        .trim_end_matches('/');
+
+    if url.contains("://") && addr == url.trim_end_matches('/') {
+        return Err(format!("Unsupported proxy scheme in '{}'", url));
+    }
+    if !addr.contains(':') {
+        return Err(format!("SOCKS proxy '{}' is missing a port", url));
+    }
+
+    Ok(addr.to_string())
 }
 
 /// Parse resolver address in format "IP" or "IP:port"
@@ -134,22 +636,382 @@ fn parse_resolver_address(addr: &str) -> std::result::Result<SocketAddr, String>
     }
 }
 
-/// Generate random string for wildcard detection
-fn rand_string(len: usize) -> String {
-    use std::time::{SystemTime, UNIX_EPOCH};
-    let seed = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hickory_resolver::proto::rr::rdata::{A, AAAA, CNAME, SOA};
+    use hickory_resolver::proto::rr::Record;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+    use std::sync::{Arc, Mutex};
+
+    type QueryLog = Arc<Mutex<Vec<(String, RecordType)>>>;
+
+    fn record(name: &str, data: RData) -> Record {
+        Record::from_rdata(Name::from_str(name).unwrap(), 60, data)
+    }
+
+    /// Nameserver on a local UDP port answering from `records`, following
+    /// CNAMEs for address queries. Names without records are NXDOMAIN.
+    async fn start_nameserver(records: Vec<Record>) -> (String, QueryLog) {
+        let socket = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap().to_string();
+        let log = QueryLog::default();
+        let queries = Arc::clone(&log);
+        tokio::spawn(async move {
+            let mut buf = [0u8; 4096];
+            while let Ok((n, peer)) = socket.recv_from(&mut buf).await {
+                let Ok(request) = Message::from_vec(&buf[..n]) else {
+                    continue;
+                };
+                let Some(query) = request.queries().first().cloned() else {
+                    continue;
+                };
+                let name = query.name().to_ascii().to_lowercase();
+                queries
+                    .lock()
+                    .unwrap()
+                    .push((name.trim_end_matches('.').to_string(), query.query_type()));
+
+                let wants = |r: &Record, name: &str, follow: bool| {
+                    r.name().to_ascii().to_lowercase() == name
+                        && (r.record_type() == query.query_type()
+                            || (follow && r.record_type() == RecordType::CNAME))
+                };
+                let address = matches!(query.query_type(), RecordType::A | RecordType::AAAA);
+                let mut answers: Vec<Record> = records
+                    .iter()
+                    .filter(|r| wants(r, &name, address))
+                    .cloned()
+                    .collect();
+                let targets: Vec<String> = answers
+                    .iter()
+                    .filter_map(|r| match r.data() {
+                        Some(RData::CNAME(c)) if address => Some(c.0.to_ascii().to_lowercase()),
+                        _ => None,
+                    })
+                    .collect();
+                for target in targets {
+                    answers.extend(records.iter().filter(|r| wants(r, &target, false)).cloned());
+                }
+
+                let known = records
+                    .iter()
+                    .any(|r| r.name().to_ascii().to_lowercase() == name);
+                let mut response = Message::new();
+                response
+                    .set_id(request.id())
+                    .set_message_type(MessageType::Response)
+                    .set_op_code(OpCode::Query)
+                    .set_recursion_desired(true)
+                    .set_recursion_available(true)
+                    .set_response_code(if known {
+                        ResponseCode::NoError
+                    } else {
+                        ResponseCode::NXDomain
+                    })
+                    .add_query(query);
+                response.add_answers(answers);
+                let _ = socket.send_to(&response.to_bytes().unwrap(), peer).await;
+            }
+        });
+        (addr, log)
+    }
+
+    async fn client(resolver: &str, address_family: AddressFamily) -> DnsClient {
+        DnsClient::new(DnsConfig {
+            resolver: Some(resolver.to_string()),
+            timeout: Duration::from_secs(2),
+            address_family,
+            ..Default::default()
+        })
+        .await
         .unwrap()
-        .as_nanos();
+    }
+
+    #[tokio::test]
+    async fn address_family_limits_queries_and_answers() {
+        let (resolver, log) = start_nameserver(vec![
+            record(
+                "www.example.test.",
+                RData::A(A(Ipv4Addr::new(192, 0, 2, 1))),
+            ),
+            record(
+                "www.example.test.",
+                RData::AAAA(AAAA(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1))),
+            ),
+        ])
+        .await;
+
+        let v4 = client(&resolver, AddressFamily::Ipv4).await;
+        let result = v4.resolve("www.example.test").await.unwrap();
+        assert_eq!(result.ips, [IpAddr::from([192, 0, 2, 1])]);
+        assert!(!log
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|(_, t)| *t == RecordType::AAAA));
 
-    let chars: Vec<char> = "abcdefghijklmnopqrstuvwxyz0123456789".chars().collect();
-    let mut result = String::with_capacity(len);
-    let mut n = seed as usize;
+        log.lock().unwrap().clear();
+        let v6 = client(&resolver, AddressFamily::Ipv6).await;
+        let result = v6.resolve("www.example.test").await.unwrap();
+        assert_eq!(
+            result.ips,
+            [IpAddr::from(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1))]
+        );
+        assert!(!log.lock().unwrap().iter().any(|(_, t)| *t == RecordType::A));
+    }
+
+    #[tokio::test]
+    async fn address_family_without_record_types_is_rejected() {
+        let config = DnsConfig {
+            resolver: Some("127.0.0.1:53".to_string()),
+            record_types: vec![RecordType::AAAA],
+            address_family: AddressFamily::Ipv4,
+            ..Default::default()
+        };
+        assert!(matches!(
+            DnsClient::new(config).await,
+            Err(RbusterError::ConfigError(_))
+        ));
+    }
+
+    /// Nameserver on a local UDP port answering every A query with the
+    /// addresses `answer` gives for the number of the query, or NXDOMAIN
+    /// when it gives none
+    async fn start_wildcard_nameserver(
+        answer: impl Fn(usize) -> Vec<Ipv4Addr> + Send + 'static,
+    ) -> String {
+        let socket = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            let mut buf = [0u8; 4096];
+            let mut queries = 0;
+            while let Ok((n, peer)) = socket.recv_from(&mut buf).await {
+                let Ok(request) = Message::from_vec(&buf[..n]) else {
+                    continue;
+                };
+                let Some(query) = request.queries().first().cloned() else {
+                    continue;
+                };
+                let ips = match query.query_type() {
+                    RecordType::A => {
+                        queries += 1;
+                        answer(queries - 1)
+                    }
+                    _ => Vec::new(),
+                };
+                let mut response = Message::new();
+                response
+                    .set_id(request.id())
+                    .set_message_type(MessageType::Response)
+                    .set_op_code(OpCode::Query)
+                    .set_recursion_desired(true)
+                    .set_recursion_available(true)
+                    .set_response_code(if ips.is_empty() {
+                        ResponseCode::NXDomain
+                    } else {
+                        ResponseCode::NoError
+                    });
+                response.add_answers(
+                    ips.into_iter()
+                        .map(|ip| Record::from_rdata(query.name().clone(), 60, RData::A(A(ip)))),
+                );
+                response.add_query(query);
+                let _ = socket.send_to(&response.to_bytes().unwrap(), peer).await;
+            }
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn wildcard_needs_probes_that_settle_on_the_same_addresses() {
+        let ip = |n: usize| Ipv4Addr::new(192, 0, 2, n as u8);
+        let detect = |resolver: String| async move {
+            client(&resolver, AddressFamily::Ipv4)
+                .await
+                .detect_wildcard("example.test")
+                .await
+        };
+
+        // The same address for every name
+        let consistent = start_wildcard_nameserver(move |_| vec![ip(1)]).await;
+        assert_eq!(detect(consistent).await, Some([ip(1).into()].into()));
+
+        // Round-robin over a pool: the union of the pool
+        let rotating = start_wildcard_nameserver(move |n| vec![ip(1 + n % 3)]).await;
+        assert_eq!(
+            detect(rotating).await,
+            Some([ip(1).into(), ip(2).into(), ip(3).into()].into())
+        );
+
+        // A new address for every query never settles
+        let unique = start_wildcard_nameserver(move |n| vec![ip(10 + n)]).await;
+        assert_eq!(detect(unique).await, None);
+
+        let none = start_wildcard_nameserver(|_| Vec::new()).await;
+        assert_eq!(detect(none).await, None);
+    }
+
+    #[test]
+    fn wildcard_answers_need_enough_shared_addresses() {
+        let ip = |last: u8| IpAddr::V4(Ipv4Addr::new(192, 0, 2, last));
+        let wildcard: HashSet<IpAddr> = [ip(1), ip(2), ip(3)].into();
+
+        // Subset by default, whatever the order or count
+        assert!(is_wildcard_answer(&[ip(2)], &wildcard, 100));
+        assert!(is_wildcard_answer(&[ip(3), ip(1)], &wildcard, 100));
+        // A stable wildcard address mixed with a unique one is a real host
+        assert!(!is_wildcard_answer(&[ip(1), ip(9)], &wildcard, 100));
+        assert!(!is_wildcard_answer(&[ip(9)], &wildcard, 100));
+
+        // Lower thresholds accept partial overlap
+        assert!(is_wildcard_answer(&[ip(1), ip(9)], &wildcard, 50));
+        assert!(!is_wildcard_answer(&[ip(1), ip(8), ip(9)], &wildcard, 50));
+        assert!(is_wildcard_answer(&[ip(1), ip(8), ip(9)], &wildcard, 30));
+
+        // CNAME-only answers are never wildcard hits
+        assert!(!is_wildcard_answer(&[], &wildcard, 100));
+        assert!(!is_wildcard_answer(&[], &wildcard, 1));
+    }
+
+    #[test]
+    fn takeover_service_matches_known_suffixes() {
+        assert_eq!(
+            takeover_service("Unclaimed.GitHub.io."),
+            Some("GitHub Pages")
+        );
+        assert_eq!(takeover_service("shop.myshopify.com"), Some("Shopify"));
+        assert_eq!(takeover_service("github.io.example.com"), None);
+        assert_eq!(takeover_service("cdn.example.com"), None);
+    }
+
+    #[tokio::test]
+    async fn cname_without_a_resolving_target_is_dangling() {
+        let cname = |name: &str, target: &str| {
+            record(name, RData::CNAME(CNAME(Name::from_str(target).unwrap())))
+        };
+        let (resolver, _) = start_nameserver(vec![
+            cname("gone.example.test.", "unclaimed.github.io."),
+            cname("live.example.test.", "claimed.github.io."),
+            record(
+                "claimed.github.io.",
+                RData::A(A(Ipv4Addr::new(192, 0, 2, 7))),
+            ),
+            record(
+                "plain.example.test.",
+                RData::A(A(Ipv4Addr::new(192, 0, 2, 8))),
+            ),
+        ])
+        .await;
+        let client = client(&resolver, AddressFamily::Any).await;
+
+        let gone = client.resolve("gone.example.test").await.unwrap();
+        let target = client.dangling_cname(&gone).await;
+        assert_eq!(target.as_deref(), Some("unclaimed.github.io."));
+        assert_eq!(takeover_service(&target.unwrap()), Some("GitHub Pages"));
+
+        let live = client.resolve("live.example.test").await.unwrap();
+        assert_eq!(live.cnames, ["claimed.github.io."]);
+        assert_eq!(client.dangling_cname(&live).await, None);
+
+        let plain = client.resolve("plain.example.test").await.unwrap();
+        assert!(plain.cnames.is_empty());
+        assert_eq!(client.dangling_cname(&plain).await, None);
+    }
+
+    /// Nameserver on a local TCP port answering one AXFR per connection with
+    /// `messages`, each a response code and answers
+    async fn start_axfr_server(messages: Vec<(ResponseCode, Vec<Record>)>) -> SocketAddr {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut len = [0u8; 2];
+                if stream.read_exact(&mut len).await.is_err() {
+                    continue;
+                }
+                let mut buf = vec![0u8; u16::from_be_bytes(len) as usize];
+                if stream.read_exact(&mut buf).await.is_err() {
+                    continue;
+                }
+                let request = Message::from_vec(&buf).unwrap();
+                for (code, answers) in &messages {
+                    let mut response = Message::new();
+                    response
+                        .set_id(request.id())
+                        .set_message_type(MessageType::Response)
+                        .set_op_code(OpCode::Query)
+                        .set_response_code(*code)
+                        .add_queries(request.queries().to_vec());
+                    response.add_answers(answers.clone());
+                    let packet = response.to_bytes().unwrap();
+                    let _ = stream.write_all(&(packet.len() as u16).to_be_bytes()).await;
+                    let _ = stream.write_all(&packet).await;
+                }
+            }
+        });
+        addr
+    }
+
+    fn soa(zone: &str) -> Record {
+        let name = Name::from_str(zone).unwrap();
+        let soa = SOA::new(name.clone(), name.clone(), 1, 3600, 600, 86400, 60);
+        Record::from_rdata(name, 60, RData::SOA(soa))
+    }
 
-    for _ in 0..len {
-        result.push(chars[n % chars.len()]);
-        n = n.wrapping_mul(1103515245).wrapping_add(12345);
+    #[tokio::test]
+    async fn axfr_collects_the_zone_up_to_the_closing_soa() {
+        let zone = "example.test.";
+        let server = start_axfr_server(vec![
+            (
+                ResponseCode::NoError,
+                vec![
+                    soa(zone),
+                    record(
+                        "www.example.test.",
+                        RData::A(A(Ipv4Addr::new(192, 0, 2, 1))),
+                    ),
+                ],
+            ),
+            (
+                ResponseCode::NoError,
+                vec![
+                    record(
+                        "vpn.example.test.",
+                        RData::A(A(Ipv4Addr::new(192, 0, 2, 2))),
+                    ),
+                    soa(zone),
+                ],
+            ),
+        ])
+        .await;
+        let client = client("127.0.0.1:53", AddressFamily::Any).await;
+
+        let records = client.axfr("example.test", server).await.unwrap();
+        assert_eq!(records.len(), 3);
+        assert!(records[0].contains("SOA"), "{:?}", records);
+        assert!(records[1].starts_with("www.example.test."), "{:?}", records);
+        assert!(records[2].starts_with("vpn.example.test."), "{:?}", records);
     }
 
-    result
+    #[tokio::test]
+    async fn refused_axfr_returns_nothing() {
+        let server = start_axfr_server(vec![(ResponseCode::Refused, vec![])]).await;
+        let client = client("127.0.0.1:53", AddressFamily::Any).await;
+        assert!(client
+            .axfr("example.test", server)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    /// zonetransfer.me allows AXFR on purpose for testing
+    #[tokio::test]
+    #[ignore = "needs network access to a permissive public nameserver"]
+    async fn zone_transfer_from_a_permissive_server() {
+        let client = DnsClient::new(DnsConfig::default()).await.unwrap();
+        let transfers = client.zone_transfer("zonetransfer.me").await;
+        assert!(!transfers.is_empty());
+        assert!(transfers[0].records.len() > 10);
+    }
 }
@@ -0,0 +1,881 @@
+//! Response filters shared by the HTTP modes
+
+use crate::error::{RbusterError, Result};
+use regex::Regex;
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::time::Duration;
+
+/// One length specification: an exact size, a range or a size with tolerance
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LengthSpec {
+    Exact(usize),
+    Range(usize, usize),
+    Tolerance(usize, usize),
+}
+
+impl LengthSpec {
+    fn contains(&self, size: usize) -> bool {
+        match *self {
+            LengthSpec::Exact(n) => size == n,
+            LengthSpec::Range(start, end) => (start..=end).contains(&size),
+            LengthSpec::Tolerance(n, tolerance) => size.abs_diff(n) <= tolerance,
+        }
+    }
+}
+
+/// Matches body lengths against a list of exact sizes (`1024`), inclusive
+/// ranges (`1024-2048`) and sizes with a tolerance (`1500±50` or `1500+-50`)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LengthMatcher {
+    specs: Vec<LengthSpec>,
+}
+
+impl LengthMatcher {
+    /// Parse a comma-separated length list; `None` gives an empty matcher
+    pub fn parse(spec: Option<&str>) -> Result<Self> {
+        let Some(spec) = spec else {
+            return Ok(Self::default());
+        };
+        let invalid = |part: &str| {
+            RbusterError::ConfigError(format!("Invalid length '{}': expected N, N-M or N±T", part))
+        };
+        let number = |s: &str, part: &str| s.trim().parse::<usize>().map_err(|_| invalid(part));
+
+        let mut specs = Vec::new();
+        for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let tolerance = part.split_once('±').or_else(|| part.split_once("+-"));
+            let spec = if let Some((size, tolerance)) = tolerance {
+                LengthSpec::Tolerance(number(size, part)?, number(tolerance, part)?)
+            } else if let Some((start, end)) = part.split_once('-') {
+                let (start, end) = (number(start, part)?, number(end, part)?);
+                if start > end {
+                    return Err(invalid(part));
+                }
+                LengthSpec::Range(start, end)
+            } else {
+                LengthSpec::Exact(number(part, part)?)
+            };
+            specs.push(spec);
+        }
+        Ok(Self { specs })
+    }
+
+    /// Add an exact size, e.g. one detected from wildcard responses
+    pub fn insert(&mut self, size: usize) {
+        self.specs.push(LengthSpec::Exact(size));
+    }
+
+    pub fn contains(&self, size: usize) -> bool {
+        self.specs.iter().any(|spec| spec.contains(size))
+    }
+}
+
+/// Matches a response header by name, and by value when a regex is given:
+/// `X-Powered-By` or `Server: ^nginx`
+#[derive(Debug, Clone)]
+pub struct HeaderMatcher {
+    /// Lowercased header name
+    name: String,
+    value: Option<Regex>,
+}
+
+impl HeaderMatcher {
+    /// Parse `Name` or `Name: regex`
+    pub fn parse(spec: &str) -> Result<Self> {
+        let (name, value) = match spec.split_once(':') {
+            Some((name, value)) => (name, Some(value.trim())),
+            None => (spec, None),
+        };
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(RbusterError::ConfigError(format!(
+                "Invalid header matcher '{}': expected Name or Name: regex",
+                spec
+            )));
+        }
+        let value = value
+            .filter(|v| !v.is_empty())
+            .map(|v| {
+                Regex::new(v)
+                    .map_err(|e| RbusterError::ConfigError(format!("Invalid regex '{}': {}", v, e)))
+            })
+            .transpose()?;
+        Ok(Self {
+            name: name.to_ascii_lowercase(),
+            value,
+        })
+    }
+
+    /// Whether any header of this name (case-insensitive) has a matching value
+    pub fn matches(&self, headers: &[(String, String)]) -> bool {
+        headers.iter().any(|(name, value)| {
+            name.eq_ignore_ascii_case(&self.name)
+                && self.value.as_ref().is_none_or(|re| re.is_match(value))
+        })
+    }
+}
+
+impl fmt::Display for HeaderMatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.value {
+            Some(ref re) => write!(f, "{}: {}", self.name, re),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+/// Why a response was not shown
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HideReason {
+    StatusNotMatched(u16),
+    StatusExcluded(u16),
+    LengthExcluded(usize),
+    /// Same size as the response to a random path
+    WildcardLength(usize),
+    WordsNotMatched(usize),
+    WordsExcluded(usize),
+    LinesNotMatched(usize),
+    LinesExcluded(usize),
+    RegexNotMatched,
+    RegexExcluded,
+    StringNotMatched,
+    StringExcluded,
+    ContentTypeNotMatched(String),
+    ContentTypeExcluded(String),
+    /// A --match-header that the headers don't satisfy
+    HeaderNotMatched(String),
+    /// The headers satisfy every --filter-header
+    HeaderExcluded,
+    /// Outside the --slower-than / --faster-than bounds
+    Timing(Duration),
+    /// Same page as an earlier result (--dedupe)
+    Duplicate,
+    /// Same page as the baseline response (vhost)
+    Baseline,
+}
+
+impl fmt::Display for HideReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HideReason::StatusNotMatched(status) => {
+                write!(f, "status {} not in include set", status)
+            }
+            HideReason::StatusExcluded(status) => write!(f, "status {} excluded", status),
+            HideReason::LengthExcluded(size) => write!(f, "size {} in exclude list", size),
+            HideReason::WildcardLength(size) => {
+                write!(f, "size {} matched wildcard baseline", size)
+            }
+            HideReason::WordsNotMatched(words) => write!(f, "{} words not in match set", words),
+            HideReason::WordsExcluded(words) => write!(f, "{} words excluded", words),
+            HideReason::LinesNotMatched(lines) => write!(f, "{} lines not in match set", lines),
+            HideReason::LinesExcluded(lines) => write!(f, "{} lines excluded", lines),
+            HideReason::RegexNotMatched => write!(f, "body doesn't match --match-regex"),
+            HideReason::RegexExcluded => write!(f, "body matches --filter-regex"),
+            HideReason::StringNotMatched => write!(f, "body doesn't contain the match string"),
+            HideReason::StringExcluded => write!(f, "body contains the filter string"),
+            HideReason::ContentTypeNotMatched(content_type) if content_type.is_empty() => {
+                write!(f, "no content type to match")
+            }
+            HideReason::ContentTypeNotMatched(content_type) => {
+                write!(f, "content type '{}' not in match set", content_type)
+            }
+            HideReason::ContentTypeExcluded(content_type) => {
+                write!(f, "content type '{}' excluded", content_type)
+            }
+            HideReason::HeaderNotMatched(matcher) => {
+                write!(f, "headers don't match '{}'", matcher)
+            }
+            HideReason::HeaderExcluded => write!(f, "headers match --filter-header"),
+            HideReason::Timing(elapsed) => {
+                write!(f, "response time {}ms out of bounds", elapsed.as_millis())
+            }
+            HideReason::Duplicate => write!(f, "duplicate of an earlier result"),
+            HideReason::Baseline => write!(f, "matched baseline response"),
+        }
+    }
+}
+
+/// Result of running a response through the filters
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterOutcome {
+    Show,
+    Hide(HideReason),
+}
+
+impl FilterOutcome {
+    pub fn is_shown(&self) -> bool {
+        *self == FilterOutcome::Show
+    }
+
+    /// Hide a shown response for `reason` unless `passes` holds
+    pub fn check(self, passes: impl FnOnce() -> bool, reason: HideReason) -> Self {
+        match self {
+            FilterOutcome::Show if !passes() => FilterOutcome::Hide(reason),
+            outcome => outcome,
+        }
+    }
+
+    /// Hide a shown response for the reason `hide` gives, if any
+    fn check_with(self, hide: impl FnOnce() -> Option<HideReason>) -> Self {
+        match self {
+            FilterOutcome::Show => hide().map_or(FilterOutcome::Show, FilterOutcome::Hide),
+            outcome => outcome,
+        }
+    }
+
+    pub fn reason(&self) -> Option<&HideReason> {
+        match self {
+            FilterOutcome::Show => None,
+            FilterOutcome::Hide(reason) => Some(reason),
+        }
+    }
+}
+
+/// Decides which responses are shown. Unset options let everything through;
+/// `match_*` options keep only matching responses, `exclude_*` ones hide them.
+#[derive(Debug, Clone, Default)]
+pub struct ResponseFilter {
+    /// Only show these status codes
+    pub match_status: Option<HashSet<u16>>,
+    pub exclude_status: HashSet<u16>,
+    pub exclude_length: LengthMatcher,
+    /// Sizes of wildcard responses, hidden like `exclude_length`
+    pub wildcard_length: LengthMatcher,
+    pub match_words: Option<Vec<RangeInclusive<usize>>>,
+    pub exclude_words: Option<Vec<RangeInclusive<usize>>>,
+    pub match_lines: Option<Vec<RangeInclusive<usize>>>,
+    pub exclude_lines: Option<Vec<RangeInclusive<usize>>>,
+    pub match_regex: Option<Regex>,
+    pub exclude_regex: Option<Regex>,
+    /// Only show responses whose body contains this string
+    pub match_string: Option<String>,
+    /// Hide responses whose body contains this string
+    pub exclude_string: Option<String>,
+    /// Only show responses whose Content-Type starts with one of these
+    pub match_content_type: Option<Vec<String>>,
+    /// Hide responses whose Content-Type starts with one of these
+    pub exclude_content_type: Option<Vec<String>>,
+    /// Only show responses whose headers satisfy all of these
+    pub match_headers: Vec<HeaderMatcher>,
+    /// Hide responses whose headers satisfy all of these
+    pub exclude_headers: Vec<HeaderMatcher>,
+}
+
+impl ResponseFilter {
+    /// Check a response against every configured filter, naming the one
+    /// that hid it. Modes that don't read bodies pass an empty body with
+    /// zero words and lines. A missing Content-Type matches no type.
+    /// `headers` only need to be captured when `needs_headers` says so.
+    #[allow(clippy::too_many_arguments)]
+    pub fn evaluate(
+        &self,
+        status: u16,
+        size: usize,
+        words: usize,
+        lines: usize,
+        body: &str,
+        content_type: Option<&str>,
+        headers: &[(String, String)],
+    ) -> FilterOutcome {
+        let in_ranges = |count: usize, ranges: &Vec<RangeInclusive<usize>>| {
+            ranges.iter().any(|r| r.contains(&count))
+        };
+        let content_type = content_type.unwrap_or_default().trim().to_ascii_lowercase();
+        let has_type = |prefixes: &Vec<String>| {
+            !content_type.is_empty() && prefixes.iter().any(|p| content_type.starts_with(p))
+        };
+        FilterOutcome::Show
+            .check(
+                || {
+                    self.match_status
+                        .as_ref()
+                        .is_none_or(|codes| codes.contains(&status))
+                },
+                HideReason::StatusNotMatched(status),
+            )
+            .check(
+                || !self.exclude_status.contains(&status),
+                HideReason::StatusExcluded(status),
+            )
+            .check(
+                || !self.exclude_length.contains(size),
+                HideReason::LengthExcluded(size),
+            )
+            .check(
+                || !self.wildcard_length.contains(size),
+                HideReason::WildcardLength(size),
+            )
+            .check(
+                || {
+                    self.match_words
+                        .as_ref()
+                        .is_none_or(|r| in_ranges(words, r))
+                },
+                HideReason::WordsNotMatched(words),
+            )
+            .check(
+                || {
+                    !self
+                        .exclude_words
+                        .as_ref()
+                        .is_some_and(|r| in_ranges(words, r))
+                },
+                HideReason::WordsExcluded(words),
+            )
+            .check(
+                || {
+                    self.match_lines
+                        .as_ref()
+                        .is_none_or(|r| in_ranges(lines, r))
+                },
+                HideReason::LinesNotMatched(lines),
+            )
+            .check(
+                || {
+                    !self
+                        .exclude_lines
+                        .as_ref()
+                        .is_some_and(|r| in_ranges(lines, r))
+                },
+                HideReason::LinesExcluded(lines),
+            )
+            .check(
+                || self.match_regex.as_ref().is_none_or(|re| re.is_match(body)),
+                HideReason::RegexNotMatched,
+            )
+            .check(
+                || {
+                    !self
+                        .exclude_regex
+                        .as_ref()
+                        .is_some_and(|re| re.is_match(body))
+                },
+                HideReason::RegexExcluded,
+            )
+            .check(
+                || {
+                    self.match_string
+                        .as_ref()
+                        .is_none_or(|needle| body.contains(needle.as_str()))
+                },
+                HideReason::StringNotMatched,
+            )
+            .check(
+                || {
+                    !self
+                        .exclude_string
+                        .as_ref()
+                        .is_some_and(|needle| body.contains(needle.as_str()))
+                },
+                HideReason::StringExcluded,
+            )
+            .check(
+                || self.match_content_type.as_ref().is_none_or(has_type),
+                HideReason::ContentTypeNotMatched(content_type.clone()),
+            )
+            .check(
+                || !self.exclude_content_type.as_ref().is_some_and(has_type),
+                HideReason::ContentTypeExcluded(content_type.clone()),
+            )
+            .check_with(|| {
+                self.match_headers
+                    .iter()
+                    .find(|m| !m.matches(headers))
+                    .map(|m| HideReason::HeaderNotMatched(m.to_string()))
+            })
+            .check(
+                || {
+                    self.exclude_headers.is_empty()
+                        || !self.exclude_headers.iter().all(|m| m.matches(headers))
+                },
+                HideReason::HeaderExcluded,
+            )
+    }
+
+    /// Whether any filter looks at the body
+    pub fn needs_body(&self) -> bool {
+        self.match_words.is_some()
+            || self.exclude_words.is_some()
+            || self.match_lines.is_some()
+            || self.exclude_lines.is_some()
+            || self.match_regex.is_some()
+            || self.exclude_regex.is_some()
+            || self.match_string.is_some()
+            || self.exclude_string.is_some()
+    }
+
+    /// Whether any filter looks at the headers
+    pub fn needs_headers(&self) -> bool {
+        !self.match_headers.is_empty() || !self.exclude_headers.is_empty()
+    }
+}
+
+/// Status, size and counts of a response to a path that shouldn't exist
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CalibrationSample {
+    pub status: u16,
+    pub size: usize,
+    pub words: usize,
+    pub lines: usize,
+}
+
+/// Exclude filter derived by auto-calibration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Calibrated {
+    Size(usize),
+    Words(usize),
+    Lines(usize),
+}
+
+impl fmt::Display for Calibrated {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Calibrated::Size(size) => write!(f, "size {}", size),
+            Calibrated::Words(words) => write!(f, "{} words", words),
+            Calibrated::Lines(lines) => write!(f, "{} lines", lines),
+        }
+    }
+}
+
+impl ResponseFilter {
+    /// Exclude filter that hides responses like `samples`, all answering
+    /// the same kind of nonexistent path: the first of size, word count and
+    /// line count they agree on. Samples whose status is hidden anyway are
+    /// ignored, and counts are only used when bodies were read.
+    pub fn calibration(
+        &self,
+        samples: &[CalibrationSample],
+        use_counts: bool,
+    ) -> Option<Calibrated> {
+        let shown: Vec<_> = samples
+            .iter()
+            .filter(|s| {
+                self.match_status
+                    .as_ref()
+                    .is_none_or(|codes| codes.contains(&s.status))
+                    && !self.exclude_status.contains(&s.status)
+            })
+            .collect();
+        // One response says nothing about which properties are stable
+        if shown.len() < 2 {
+            return None;
+        }
+        let stable = |value: fn(&CalibrationSample) -> usize| {
+            let first = value(shown[0]);
+            shown.iter().all(|s| value(s) == first).then_some(first)
+        };
+        let in_ranges = |count: usize, ranges: &Option<Vec<RangeInclusive<usize>>>| {
+            ranges
+                .as_ref()
+                .is_some_and(|r| r.iter().any(|r| r.contains(&count)))
+        };
+
+        if let Some(size) = stable(|s| s.size) {
+            return (!self.exclude_length.contains(size)).then_some(Calibrated::Size(size));
+        }
+        if !use_counts {
+            return None;
+        }
+        if let Some(words) = stable(|s| s.words) {
+            return (!in_ranges(words, &self.exclude_words)).then_some(Calibrated::Words(words));
+        }
+        if let Some(lines) = stable(|s| s.lines) {
+            return (!in_ranges(lines, &self.exclude_lines)).then_some(Calibrated::Lines(lines));
+        }
+        None
+    }
+
+    /// Add a calibrated exclude filter
+    pub fn exclude(&mut self, calibrated: Calibrated) {
+        match calibrated {
+            Calibrated::Size(size) => self.exclude_length.insert(size),
+            Calibrated::Words(words) => self
+                .exclude_words
+                .get_or_insert_with(Vec::new)
+                .push(words..=words),
+            Calibrated::Lines(lines) => self
+                .exclude_lines
+                .get_or_insert_with(Vec::new)
+                .push(lines..=lines),
+        }
+    }
+}
+
+/// Parse a comma-separated list of Content-Type prefixes, lowercased
+pub fn parse_content_types(spec: &str) -> Vec<String> {
+    spec.split(',')
+        .map(|t| t.trim().to_ascii_lowercase())
+        .filter(|t| !t.is_empty())
+        .collect()
+}
+
+/// Parse repeated --match-header / --filter-header values
+pub fn parse_header_matchers(specs: &[String]) -> Result<Vec<HeaderMatcher>> {
+    specs
+        .iter()
+        .map(|spec| HeaderMatcher::parse(spec))
+        .collect()
+}
+
+/// Word and line counts of a body, as used by the count filters
+pub fn body_counts(body: &str) -> (usize, usize) {
+    (body.split_whitespace().count(), body.lines().count())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn length_matcher_takes_exact_ranges_and_tolerances() {
+        let lengths = LengthMatcher::parse(Some("100, 1024-2048, 3000±50, 300+-5")).unwrap();
+        for size in [100, 1024, 1536, 2048, 2950, 3050, 295, 305] {
+            assert!(lengths.contains(size), "{}", size);
+        }
+        for size in [99, 101, 1023, 2049, 2949, 3051, 294, 306] {
+            assert!(!lengths.contains(size), "{}", size);
+        }
+    }
+
+    #[test]
+    fn malformed_lengths_are_rejected() {
+        for spec in ["abc", "20-10", "1500±", "-5", "10-x"] {
+            assert!(LengthMatcher::parse(Some(spec)).is_err(), "{}", spec);
+        }
+        assert!(!LengthMatcher::parse(None).unwrap().contains(0));
+    }
+
+    /// Whether `filter` shows a 200 text/html response with `body`
+    fn shows(filter: &ResponseFilter, size: usize, body: &str) -> bool {
+        let (words, lines) = body_counts(body);
+        filter
+            .evaluate(200, size, words, lines, body, Some("text/html"), &[])
+            .is_shown()
+    }
+
+    #[test]
+    fn unset_filter_shows_everything() {
+        let filter = ResponseFilter::default();
+        assert!(filter.evaluate(404, 0, 0, 0, "", None, &[]).is_shown());
+        assert!(!filter.needs_body());
+        assert!(!filter.needs_headers());
+    }
+
+    #[test]
+    fn match_options_keep_only_matching_responses() {
+        let filter = ResponseFilter {
+            match_status: Some(HashSet::from([200])),
+            match_words: Some(vec![2..=3]),
+            match_lines: Some(vec![1..=1]),
+            match_regex: Some(Regex::new("^Welcome").unwrap()),
+            match_string: Some("admin".to_string()),
+            match_content_type: Some(vec!["text/".to_string()]),
+            ..Default::default()
+        };
+        assert!(filter.needs_body());
+        assert!(shows(&filter, 10, "Welcome admin"));
+        assert!(!shows(&filter, 10, "Welcome back"));
+        assert!(!shows(&filter, 10, "Hello admin"));
+        assert!(!shows(&filter, 10, "Welcome admin user, hi"));
+        assert!(!shows(&filter, 10, "Welcome\nadmin"));
+        assert!(!filter
+            .evaluate(
+                200,
+                10,
+                2,
+                1,
+                "Welcome admin",
+                Some("application/json"),
+                &[]
+            )
+            .is_shown());
+        assert!(!filter
+            .evaluate(302, 10, 2, 1, "Welcome admin", Some("text/html"), &[])
+            .is_shown());
+    }
+
+    #[test]
+    fn exclude_options_hide_matching_responses() {
+        let filter = ResponseFilter {
+            exclude_status: HashSet::from([404]),
+            exclude_length: LengthMatcher::parse(Some("0")).unwrap(),
+            exclude_words: Some(vec![5..=5]),
+            exclude_lines: Some(vec![3..=3]),
+            exclude_regex: Some(Regex::new("(?i)not found").unwrap()),
+            exclude_string: Some("denied".to_string()),
+            ..Default::default()
+        };
+        assert!(shows(&filter, 10, "an ordinary page"));
+        // The exclude string hides responses that contain it
+        assert!(!shows(&filter, 10, "access denied"));
+        assert!(!shows(&filter, 10, "Page Not Found"));
+        assert!(!shows(&filter, 0, "empty"));
+        assert!(!shows(&filter, 10, "one two three four five"));
+        assert!(!shows(&filter, 10, "a\nb\nc"));
+        assert!(!filter.evaluate(404, 10, 1, 1, "x", None, &[]).is_shown());
+    }
+
+    #[test]
+    fn content_types_match_by_prefix() {
+        let shown = |filter: &ResponseFilter, content_type: &str| {
+            filter
+                .evaluate(200, 1, 0, 0, "", Some(content_type), &[])
+                .is_shown()
+        };
+        let matching = ResponseFilter {
+            match_content_type: Some(parse_content_types("image/, application/json")),
+            ..Default::default()
+        };
+        assert!(shown(&matching, "image/png; charset=binary"));
+        assert!(shown(&matching, "image/svg+xml"));
+        assert!(shown(&matching, "application/json; charset=utf-8"));
+        assert!(!shown(&matching, "text/html; charset=utf-8"));
+        assert!(!shown(&matching, "application/javascript"));
+
+        let excluding = ResponseFilter {
+            exclude_content_type: Some(parse_content_types("image/,font/")),
+            ..Default::default()
+        };
+        assert!(!shown(&excluding, "image/png; charset=binary"));
+        assert!(!shown(&excluding, "font/woff2"));
+        assert!(shown(&excluding, "text/html"));
+    }
+
+    #[test]
+    fn missing_content_type_matches_no_type() {
+        let filter = ResponseFilter {
+            exclude_content_type: Some(parse_content_types("Image/")),
+            ..Default::default()
+        };
+        assert!(filter.evaluate(200, 1, 0, 0, "", None, &[]).is_shown());
+        assert!(!filter
+            .evaluate(200, 1, 0, 0, "", Some(" IMAGE/png"), &[])
+            .is_shown());
+    }
+
+    #[test]
+    fn each_filter_names_itself_as_the_hide_reason() {
+        let ct = |types: &str| Some(parse_content_types(types));
+        let matcher = |spec: &str| vec![HeaderMatcher::parse(spec).unwrap()];
+        let cases = [
+            (
+                ResponseFilter {
+                    match_status: Some(HashSet::from([200])),
+                    ..Default::default()
+                },
+                HideReason::StatusNotMatched(404),
+            ),
+            (
+                ResponseFilter {
+                    exclude_status: HashSet::from([404]),
+                    ..Default::default()
+                },
+                HideReason::StatusExcluded(404),
+            ),
+            (
+                ResponseFilter {
+                    exclude_length: LengthMatcher::parse(Some("1234")).unwrap(),
+                    ..Default::default()
+                },
+                HideReason::LengthExcluded(1234),
+            ),
+            (
+                ResponseFilter {
+                    wildcard_length: LengthMatcher::parse(Some("1234")).unwrap(),
+                    ..Default::default()
+                },
+                HideReason::WildcardLength(1234),
+            ),
+            (
+                ResponseFilter {
+                    match_words: Some(vec![10..=20]),
+                    ..Default::default()
+                },
+                HideReason::WordsNotMatched(3),
+            ),
+            (
+                ResponseFilter {
+                    exclude_words: Some(vec![3..=3]),
+                    ..Default::default()
+                },
+                HideReason::WordsExcluded(3),
+            ),
+            (
+                ResponseFilter {
+                    match_lines: Some(vec![5..=5]),
+                    ..Default::default()
+                },
+                HideReason::LinesNotMatched(2),
+            ),
+            (
+                ResponseFilter {
+                    exclude_lines: Some(vec![2..=2]),
+                    ..Default::default()
+                },
+                HideReason::LinesExcluded(2),
+            ),
+            (
+                ResponseFilter {
+                    match_regex: Some(Regex::new("^admin").unwrap()),
+                    ..Default::default()
+                },
+                HideReason::RegexNotMatched,
+            ),
+            (
+                ResponseFilter {
+                    exclude_regex: Some(Regex::new("not found").unwrap()),
+                    ..Default::default()
+                },
+                HideReason::RegexExcluded,
+            ),
+            (
+                ResponseFilter {
+                    match_string: Some("admin".to_string()),
+                    ..Default::default()
+                },
+                HideReason::StringNotMatched,
+            ),
+            (
+                ResponseFilter {
+                    exclude_string: Some("sorry".to_string()),
+                    ..Default::default()
+                },
+                HideReason::StringExcluded,
+            ),
+            (
+                ResponseFilter {
+                    match_content_type: ct("application/json"),
+                    ..Default::default()
+                },
+                HideReason::ContentTypeNotMatched("text/html".to_string()),
+            ),
+            (
+                ResponseFilter {
+                    exclude_content_type: ct("text/"),
+                    ..Default::default()
+                },
+                HideReason::ContentTypeExcluded("text/html".to_string()),
+            ),
+            (
+                ResponseFilter {
+                    match_headers: matcher("X-Admin"),
+                    ..Default::default()
+                },
+                HideReason::HeaderNotMatched("x-admin".to_string()),
+            ),
+            (
+                ResponseFilter {
+                    exclude_headers: matcher("Server: nginx"),
+                    ..Default::default()
+                },
+                HideReason::HeaderExcluded,
+            ),
+        ];
+
+        let headers = [("Server".to_string(), "nginx/1.25".to_string())];
+        let body = "page not found\nsorry";
+        for (filter, reason) in cases {
+            let outcome = filter.evaluate(404, 1234, 3, 2, body, Some("text/html"), &headers);
+            assert_eq!(outcome.reason(), Some(&reason));
+            // A response the filter lets through has no reason
+            let shown = filter.evaluate(
+                200,
+                10,
+                15,
+                5,
+                "admin page",
+                Some("application/json"),
+                &[("X-Admin".to_string(), "1".to_string())],
+            );
+            assert_eq!(shown.reason(), None, "{:?}", reason);
+        }
+    }
+
+    #[test]
+    fn hide_reasons_read_as_sentences() {
+        assert_eq!(
+            HideReason::StatusNotMatched(404).to_string(),
+            "status 404 not in include set"
+        );
+        assert_eq!(
+            HideReason::LengthExcluded(1234).to_string(),
+            "size 1234 in exclude list"
+        );
+        assert_eq!(
+            HideReason::WildcardLength(1234).to_string(),
+            "size 1234 matched wildcard baseline"
+        );
+        assert_eq!(
+            HideReason::ContentTypeNotMatched(String::new()).to_string(),
+            "no content type to match"
+        );
+    }
+
+    fn headers(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(n, v)| (n.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn header_matchers_check_presence_and_value() {
+        let response = headers(&[
+            ("Server", "nginx/1.25.3"),
+            ("X-Powered-By", "PHP/8.2"),
+            ("Set-Cookie", "lang=en"),
+            ("Set-Cookie", "PHPSESSID=abc; HttpOnly"),
+        ]);
+        let matches = |spec: &str| HeaderMatcher::parse(spec).unwrap().matches(&response);
+
+        assert!(matches("x-powered-by"));
+        assert!(!matches("X-Frame-Options"));
+        assert!(matches("Server: ^nginx"));
+        assert!(!matches("Server: apache"));
+        // Any header of the name may match, e.g. one of several cookies
+        assert!(matches("set-cookie: ^PHPSESSID="));
+        // A value containing ':' stays part of the regex
+        assert!(HeaderMatcher::parse("Location: ^https://")
+            .unwrap()
+            .matches(&headers(&[("location", "https://t/login")])));
+        assert!(matches("Server:"));
+
+        for spec in ["", ": nginx", "Server: ("] {
+            assert!(HeaderMatcher::parse(spec).is_err(), "{:?}", spec);
+        }
+    }
+
+    #[test]
+    fn header_matchers_combine_with_and() {
+        let matchers = |specs: &[&str]| {
+            specs
+                .iter()
+                .map(|spec| HeaderMatcher::parse(spec).unwrap())
+                .collect::<Vec<_>>()
+        };
+        let nginx_php = headers(&[("Server", "nginx"), ("X-Powered-By", "PHP/8.2")]);
+        let nginx = headers(&[("Server", "nginx")]);
+        let shown = |filter: &ResponseFilter, headers: &[(String, String)]| {
+            filter.evaluate(200, 10, 0, 0, "", None, headers).is_shown()
+        };
+
+        let only = ResponseFilter {
+            match_headers: matchers(&["Server: nginx", "X-Powered-By: PHP"]),
+            ..Default::default()
+        };
+        assert!(only.needs_headers());
+        assert!(shown(&only, &nginx_php));
+        assert!(!shown(&only, &nginx));
+
+        // Hidden only when every --filter-header holds
+        let hide = ResponseFilter {
+            exclude_headers: matchers(&["Server: nginx", "X-Powered-By"]),
+            ..Default::default()
+        };
+        assert!(!shown(&hide, &nginx_php));
+        assert!(shown(&hide, &nginx));
+        assert!(shown(&hide, &[]));
+    }
+}
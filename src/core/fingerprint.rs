@@ -0,0 +1,55 @@
+//! Response body fingerprints that ignore per-request noise, and stable
+//! hashes for names that must not change between runs
+
+use regex::Regex;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// Hashes response bodies after stripping content that varies between
+/// requests for the same page: values reflected from the request (host name,
+/// path), numbers (timestamps, counters) and long token-like strings (CSRF
+/// tokens, nonces, session ids)
+pub struct BodyFingerprinter {
+    dynamic_tokens: Regex,
+}
+
+impl BodyFingerprinter {
+    pub fn new() -> Self {
+        Self {
+            dynamic_tokens: Regex::new(r"[A-Za-z0-9+/=_-]{20,}|\d+").expect("valid regex"),
+        }
+    }
+
+    /// Fingerprint a body, removing each of `reflected` first
+    pub fn fingerprint(&self, body: &[u8], reflected: &[&str]) -> u64 {
+        let mut text = String::from_utf8_lossy(body).into_owned();
+        for value in reflected.iter().filter(|v| !v.is_empty()) {
+            text = text.replace(value, "");
+        }
+        let normalized = self.dynamic_tokens.replace_all(&text, "");
+
+        let mut hasher = DefaultHasher::new();
+        normalized.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+impl Default for BodyFingerprinter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 64-bit FNV-1a hash of `parts`, each ended by a zero byte so ("ab", "c")
+/// and ("a", "bc") differ. Unlike `DefaultHasher` it is the same from run to
+/// run, for cache keys, checkpoint fingerprints and file names.
+pub fn fnv1a<'a>(parts: impl IntoIterator<Item = &'a [u8]>) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for part in parts {
+        for b in part.iter().chain(std::iter::once(&0)) {
+            hash ^= *b as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    hash
+}
@@ -1,71 +1,591 @@
 //! HTTP client wrapper with configurable options
 
-use crate::error::Result;
-use reqwest::{Client, ClientBuilder, Method, Proxy, Response};
+use crate::core::{ProxyPool, RetryAfterGate};
+use crate::error::{RbusterError, Result};
+use rand::seq::SliceRandom;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::header::{HeaderMap, ACCEPT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, USER_AGENT};
+use reqwest::{
+    Certificate, Client, ClientBuilder, Identity, Method, Proxy, RequestBuilder, Response,
+    StatusCode,
+};
 use std::collections::HashMap;
-use std::time::Duration;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use url::Url;
+
+/// Address family allowed for outgoing connections
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AddressFamily {
+    #[default]
+    Any,
+    Ipv4,
+    Ipv6,
+}
+
+impl AddressFamily {
+    pub fn from_flags(ipv4_only: bool, ipv6_only: bool) -> Self {
+        match (ipv4_only, ipv6_only) {
+            (true, _) => AddressFamily::Ipv4,
+            (_, true) => AddressFamily::Ipv6,
+            _ => AddressFamily::Any,
+        }
+    }
+
+    pub fn allows(&self, ip: &IpAddr) -> bool {
+        match self {
+            AddressFamily::Any => true,
+            AddressFamily::Ipv4 => ip.is_ipv4(),
+            AddressFamily::Ipv6 => ip.is_ipv6(),
+        }
+    }
+}
+
+/// Resolver that drops addresses outside the allowed family
+struct FamilyResolver(AddressFamily);
+
+impl Resolve for FamilyResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let family = self.0;
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .filter(|addr| family.allows(&addr.ip()))
+                .collect();
+
+            if addrs.is_empty() {
+                return Err(format!("No {:?} address found for {}", family, name.as_str()).into());
+            }
+
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Restrict a client builder to one address family.
+///
+/// Only hostnames are filtered: IP literals in URLs are used as given, and
+/// when a proxy is set only the proxy's own hostname goes through this resolver,
+/// plus the target's with `socks5://` (`socks5h://` leaves it to the proxy).
+pub fn restrict_address_family(builder: ClientBuilder, family: AddressFamily) -> ClientBuilder {
+    match family {
+        AddressFamily::Any => builder,
+        _ => builder.dns_resolver(Arc::new(FamilyResolver(family))),
+    }
+}
+
+/// Resolver that sends every hostname to one fixed address
+struct PinnedResolver(SocketAddr);
+
+impl Resolve for PinnedResolver {
+    fn resolve(&self, _name: Name) -> Resolving {
+        let addr = self.0;
+        Box::pin(async move { Ok(Box::new(std::iter::once(addr)) as Addrs) })
+    }
+}
+
+/// Connect to `addr` whatever hostname a URL names, so the URL's host is used
+/// for the Host header and TLS SNI while the connection goes to a fixed server.
+/// The port still comes from the URL. Not applied through a proxy.
+pub fn pin_address(builder: ClientBuilder, addr: SocketAddr) -> ClientBuilder {
+    builder.dns_resolver(Arc::new(PinnedResolver(addr)))
+}
+
+/// HTTP protocol version used for requests
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HttpProtocol {
+    /// Negotiate (HTTP/2 via ALPN on TLS, HTTP/1.1 otherwise)
+    #[default]
+    Auto,
+    Http1,
+    /// HTTP/2 with prior knowledge, also over plain HTTP
+    Http2,
+}
+
+/// Force a client builder to one HTTP version
+pub fn restrict_http_protocol(builder: ClientBuilder, protocol: HttpProtocol) -> ClientBuilder {
+    match protocol {
+        HttpProtocol::Auto => builder,
+        HttpProtocol::Http1 => builder.http1_only(),
+        HttpProtocol::Http2 => builder.http2_prior_knowledge(),
+    }
+}
+
+/// Common desktop and mobile browser User-Agents for --random-agent
+const BROWSER_USER_AGENTS: &[&str] = &[
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36",
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36 Edg/124.0.0.0",
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:125.0) Gecko/20100101 Firefox/125.0",
+    "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36",
+    "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.4.1 Safari/605.1.15",
+    "Mozilla/5.0 (Macintosh; Intel Mac OS X 14.4; rv:125.0) Gecko/20100101 Firefox/125.0",
+    "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36",
+    "Mozilla/5.0 (X11; Ubuntu; Linux x86_64; rv:125.0) Gecko/20100101 Firefox/125.0",
+    "Mozilla/5.0 (iPhone; CPU iPhone OS 17_4_1 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.4.1 Mobile/15E148 Safari/604.1",
+    "Mozilla/5.0 (iPad; CPU OS 17_4_1 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.4.1 Mobile/15E148 Safari/604.1",
+    "Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.6367.82 Mobile Safari/537.36",
+    "Mozilla/5.0 (Linux; Android 14; SM-S921B) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.6367.82 Mobile Safari/537.36",
+];
 
 /// HTTP client configuration
 #[derive(Clone, Debug)]
 pub struct HttpConfig {
     pub user_agent: String,
+    /// Send a random User-Agent per request instead of `user_agent`
+    pub random_agent: bool,
+    /// User-Agents to pick from, one per line, instead of the built-in list
+    pub user_agents_file: Option<PathBuf>,
     pub timeout: Duration,
     pub insecure: bool,
+    pub verify_hostname: bool,
+    /// PEM bundles of extra trusted root certificates
+    pub ca_certs: Vec<PathBuf>,
+    /// PEM client certificate for mutual TLS, with the key unless `client_key` is set
+    pub client_cert: Option<PathBuf>,
+    pub client_key: Option<PathBuf>,
+    /// PKCS#12 bundle with the client certificate and key, and its password
+    pub client_pkcs12: Option<PathBuf>,
+    pub client_pkcs12_pass: Option<String>,
     pub follow_redirect: bool,
-    pub proxy: Option<String>,
+    /// Redirects followed before the last redirect response is returned
+    pub max_redirects: usize,
+    pub address_family: AddressFamily,
+    pub protocol: HttpProtocol,
+    /// Connect every hostname to this address (overrides `address_family`)
+    pub pinned_address: Option<SocketAddr>,
+    /// Proxy URLs; requests rotate through them when there are several
+    pub proxies: Vec<String>,
+    /// More proxy URLs, one per line
+    pub proxy_file: Option<PathBuf>,
+    pub proxy_username: Option<String>,
+    pub proxy_password: Option<String>,
     pub headers: HashMap<String, String>,
     pub cookies: Option<String>,
     pub username: Option<String>,
     pub password: Option<String>,
+    pub retries: u32,
+    pub retry_backoff: Duration,
+    /// Pause all requests and retry on 429, or 503 with Retry-After
+    pub retry_after: bool,
+    /// Idle connections kept per host
+    pub pool_idle: usize,
+    pub tcp_nodelay: bool,
+    /// Transparently decompress bodies; when off, sizes are the bytes on the wire
+    pub decompress: bool,
+    /// Keep response headers in `UrlCheck` (for -vvv)
+    pub capture_headers: bool,
+    /// Longest body kept in `UrlCheck`; the rest is read for its size only
+    pub max_body: Option<usize>,
 }
 
 impl Default for HttpConfig {
     fn default() -> Self {
         Self {
             user_agent: "robuster/1.0".to_string(),
+            random_agent: false,
+            user_agents_file: None,
             timeout: Duration::from_secs(10),
             insecure: false,
+            verify_hostname: true,
+            ca_certs: Vec::new(),
+            client_cert: None,
+            client_key: None,
+            client_pkcs12: None,
+            client_pkcs12_pass: None,
             follow_redirect: false,
-            proxy: None,
+            max_redirects: 10,
+            address_family: AddressFamily::Any,
+            protocol: HttpProtocol::Auto,
+            pinned_address: None,
+            proxies: Vec::new(),
+            proxy_file: None,
+            proxy_username: None,
+            proxy_password: None,
             headers: HashMap::new(),
             cookies: None,
             username: None,
             password: None,
+            retries: 0,
+            retry_backoff: Duration::from_millis(500),
+            retry_after: true,
+            pool_idle: 100,
+            tcp_nodelay: true,
+            decompress: true,
+            capture_headers: false,
+            max_body: None,
+        }
+    }
+}
+
+/// Body sent with every URL check
+#[derive(Debug, Clone)]
+pub struct RequestBody {
+    pub data: String,
+    pub content_type: String,
+}
+
+/// Outcome of a single URL check
+#[derive(Debug)]
+pub struct UrlCheck {
+    pub status: u16,
+    pub size: usize,
+    pub redirect: Option<String>,
+    /// Body bytes, only kept when requested
+    pub body: Option<Vec<u8>>,
+    /// Time spent on the request and response body
+    pub elapsed: Duration,
+    /// Response headers, only kept when requested
+    pub headers: Vec<(String, String)>,
+    pub content_type: Option<String>,
+    /// Cross-origin access granted by the response, if any
+    pub cors: Option<CorsGrant>,
+}
+
+/// Cross-origin access granted by a response's CORS headers
+#[derive(Debug, Clone, PartialEq)]
+pub struct CorsGrant {
+    /// Value of Access-Control-Allow-Origin
+    pub allow_origin: String,
+    /// Whether Access-Control-Allow-Credentials is true
+    pub allow_credentials: bool,
+}
+
+impl CorsGrant {
+    /// Read the grant from header names and values; `None` without an
+    /// Access-Control-Allow-Origin header
+    pub fn from_headers<'a>(headers: impl IntoIterator<Item = (&'a str, &'a str)>) -> Option<Self> {
+        let mut allow_origin = None;
+        let mut allow_credentials = false;
+        for (name, value) in headers {
+            if name.eq_ignore_ascii_case("access-control-allow-origin") {
+                allow_origin = Some(value.trim().to_string());
+            } else if name.eq_ignore_ascii_case("access-control-allow-credentials") {
+                allow_credentials = value.trim().eq_ignore_ascii_case("true");
+            }
         }
+        Some(Self {
+            allow_origin: allow_origin?,
+            allow_credentials,
+        })
+    }
+
+    /// Whether pages on `origin` may read responses sent with the user's
+    /// cookies, the classic CORS misconfiguration
+    pub fn trusts_with_credentials(&self, origin: &str) -> bool {
+        self.allow_credentials && self.allow_origin == origin
     }
 }
 
+/// Read a response body, keeping at most `limit` bytes of it. Returns the
+/// kept bytes and the length of the whole body.
+pub async fn read_limited(
+    mut response: Response,
+    limit: Option<usize>,
+) -> reqwest::Result<(Vec<u8>, usize)> {
+    let mut body = Vec::new();
+    let mut length = 0;
+    while let Some(chunk) = response.chunk().await? {
+        length += chunk.len();
+        let room = limit.map_or(chunk.len(), |l| l.saturating_sub(body.len()));
+        body.extend_from_slice(&chunk[..room.min(chunk.len())]);
+    }
+    Ok((body, length))
+}
+
+/// Name and value of every header, for display
+pub fn header_pairs(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            (
+                name.to_string(),
+                String::from_utf8_lossy(value.as_bytes()).into_owned(),
+            )
+        })
+        .collect()
+}
+
 /// HTTP client with connection pooling and configurable options
 pub struct HttpClient {
     client: Client,
     config: HttpConfig,
+    /// Pool a User-Agent is drawn from per request; empty when not rotating
+    user_agents: Vec<String>,
+    /// Proxies requests rotate through when more than one is configured
+    proxy_pool: Option<ProxyPool>,
+    /// Holds every request while the target asks to slow down
+    gate: RetryAfterGate,
 }
 
-impl HttpClient {
-    pub fn new(config: HttpConfig) -> Result<Self> {
+/// Plain reqwest client whose requests wait out rate limiting like
+/// `HttpClient`'s, for modes that build their own client
+pub struct GatedClient {
+    client: Client,
+    gate: RetryAfterGate,
+}
+
+impl GatedClient {
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            gate: RetryAfterGate::new(true),
+        }
+    }
+
+    /// Send a request built from this client
+    pub async fn send(&self, request: RequestBuilder) -> reqwest::Result<Response> {
+        self.gate.send(request).await
+    }
+
+    /// Number of 429 and 503 Retry-After responses seen
+    pub fn rate_limited(&self) -> usize {
+        self.gate.rate_limited()
+    }
+}
+
+impl std::ops::Deref for GatedClient {
+    type Target = Client;
+
+    fn deref(&self) -> &Client {
+        &self.client
+    }
+}
+
+impl HttpConfig {
+    /// Build a client from the connection settings. Headers, cookies and
+    /// credentials are applied per request by `HttpClient::request_builder`.
+    pub fn build_client(&self) -> Result<Client> {
         let mut builder = ClientBuilder::new()
-            .user_agent(&config.user_agent)
-            .timeout(config.timeout)
-            .danger_accept_invalid_certs(config.insecure)
-            .pool_max_idle_per_host(100)
+            .user_agent(&self.user_agent)
+            .timeout(self.timeout)
+            .danger_accept_invalid_certs(self.insecure)
+            .danger_accept_invalid_hostnames(!self.verify_hostname)
+            .pool_max_idle_per_host(self.pool_idle)
             .pool_idle_timeout(Duration::from_secs(90))
-            .tcp_nodelay(true)
+            .tcp_nodelay(self.tcp_nodelay)
             .tcp_keepalive(Duration::from_secs(60));
 
-        // Configure redirect policy
-        if !config.follow_redirect {
+        // Trust extra CAs alongside the system roots
+        for path in &self.ca_certs {
+            for cert in load_ca_bundle(path)? {
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+
+        // Present a client certificate for mutual TLS
+        if let Some(ref cert) = self.client_cert {
+            builder = builder.identity(load_identity(cert, self.client_key.as_deref())?);
+        }
+        if let Some(ref bundle) = self.client_pkcs12 {
+            let pass = self.client_pkcs12_pass.as_deref().unwrap_or_default();
+            builder = builder.identity(load_pkcs12(bundle, pass)?);
+        }
+
+        // Configure redirect policy; a redirect loop or one hop too many
+        // yields the redirect response itself instead of an error
+        if self.follow_redirect {
+            let max_redirects = self.max_redirects;
+            builder = builder.redirect(reqwest::redirect::Policy::custom(move |attempt| {
+                if attempt.previous().len() > max_redirects
+                    || attempt.previous().contains(attempt.url())
+                {
+                    attempt.stop()
+                } else {
+                    attempt.follow()
+                }
+            }));
+        } else {
             builder = builder.redirect(reqwest::redirect::Policy::none());
         }
 
-        // Configure proxy
-        if let Some(ref proxy_url) = config.proxy {
-            let proxy = Proxy::all(proxy_url)?;
+        // Configure address family and protocol version
+        builder = restrict_address_family(builder, self.address_family);
+        builder = restrict_http_protocol(builder, self.protocol);
+        if let Some(addr) = self.pinned_address {
+            builder = pin_address(builder, addr);
+        }
+
+        // Keep compressed bodies as sent when raw sizes are wanted
+        if !self.decompress {
+            builder = builder.no_gzip().no_brotli().no_deflate();
+        }
+
+        // Configure proxy; further proxies are rotated by `HttpClient`
+        if let Some(proxy_url) = self.proxies.first() {
+            let mut proxy = parse_proxy(proxy_url)?;
+            if let Some(ref username) = self.proxy_username {
+                proxy = proxy.basic_auth(username, self.proxy_password.as_deref().unwrap_or(""));
+            }
             builder = builder.proxy(proxy);
         }
 
-        let client = builder.build()?;
+        Ok(builder.build()?)
+    }
+}
+
+/// Read a TLS file, naming it in the error
+fn read_tls_file(path: &Path, what: &str) -> Result<Vec<u8>> {
+    std::fs::read(path).map_err(|e| {
+        RbusterError::ConfigError(format!("Cannot read {} '{}': {}", what, path.display(), e))
+    })
+}
 
-        Ok(Self { client, config })
+/// Load every certificate in a PEM CA bundle
+fn load_ca_bundle(path: &Path) -> Result<Vec<Certificate>> {
+    let pem = read_tls_file(path, "CA certificate")?;
+    match Certificate::from_pem_bundle(&pem) {
+        Ok(certs) if !certs.is_empty() => Ok(certs),
+        _ => Err(RbusterError::ConfigError(format!(
+            "Invalid CA certificate '{}': expected one or more PEM certificates",
+            path.display()
+        ))),
+    }
+}
+
+/// Load a PEM client certificate and private key, from one file or two
+fn load_identity(cert: &Path, key: Option<&Path>) -> Result<Identity> {
+    let mut pem = read_tls_file(cert, "client certificate")?;
+    if let Some(key) = key {
+        pem.push(b'\n');
+        pem.extend(read_tls_file(key, "client key")?);
+    }
+
+    Identity::from_pem(&pem).map_err(|_| {
+        RbusterError::ConfigError(format!(
+            "Invalid client certificate '{}': expected PEM containing a certificate and its private key",
+            cert.display()
+        ))
+    })
+}
+
+/// Load a PKCS#12 client certificate bundle. The rustls backend only reads
+/// PEM identities, so the key and its certificate chain are re-encoded as PEM.
+fn load_pkcs12(bundle: &Path, pass: &str) -> Result<Identity> {
+    let der = read_tls_file(bundle, "client certificate bundle")?;
+    let invalid = |reason: String| {
+        RbusterError::ConfigError(format!(
+            "Cannot read client certificate bundle '{}': {}",
+            bundle.display(),
+            reason
+        ))
+    };
+
+    let store =
+        p12_keystore::KeyStore::from_pkcs12(&der, pass).map_err(|e| invalid(e.to_string()))?;
+    let (_, chain) = store
+        .private_key_chain()
+        .ok_or_else(|| invalid("the bundle has no private key".to_string()))?;
+
+    let mut blocks = vec![pem::Pem::new("PRIVATE KEY", chain.key())];
+    blocks.extend(
+        chain
+            .chain()
+            .iter()
+            .map(|cert| pem::Pem::new("CERTIFICATE", cert.as_der())),
+    );
+    Identity::from_pem(pem::encode_many(&blocks).as_bytes())
+        .map_err(|_| invalid("the bundle has no certificate and private key".to_string()))
+}
+
+/// Load a list of `what` (User-Agents, proxies), one per line, skipping
+/// blanks and `#` comments
+fn load_list(path: &Path, what: &str) -> Result<Vec<String>> {
+    let text = std::fs::read_to_string(path).map_err(|e| {
+        RbusterError::ConfigError(format!(
+            "Cannot read {} file '{}': {}",
+            what,
+            path.display(),
+            e
+        ))
+    })?;
+    let items: Vec<String> = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect();
+    if items.is_empty() {
+        return Err(RbusterError::ConfigError(format!(
+            "{} file '{}' is empty",
+            what,
+            path.display()
+        )));
+    }
+    Ok(items)
+}
+
+/// Validate a proxy URL and build the proxy for it
+fn parse_proxy(proxy_url: &str) -> Result<Proxy> {
+    let invalid = |reason: String| {
+        RbusterError::ConfigError(format!("Invalid proxy URL '{}': {}", proxy_url, reason))
+    };
+
+    let url = Url::parse(proxy_url).map_err(|e| invalid(e.to_string()))?;
+    if !matches!(url.scheme(), "http" | "https" | "socks5" | "socks5h") {
+        return Err(invalid(format!(
+            "unsupported scheme '{}' (use http, https, socks5 or socks5h)",
+            url.scheme()
+        )));
+    }
+    if url.host_str().is_none() {
+        return Err(invalid("missing host".to_string()));
+    }
+
+    Proxy::all(url).map_err(|e| invalid(e.to_string()))
+}
+
+impl HttpClient {
+    pub fn new(mut config: HttpConfig) -> Result<Self> {
+        if let Some(ref path) = config.proxy_file {
+            config.proxies.extend(load_list(path, "proxy")?);
+        }
+        let client = config.build_client()?;
+        let proxy_pool = match config.proxies.len() {
+            0 | 1 => None,
+            _ => Some(ProxyPool::new(
+                config
+                    .proxies
+                    .iter()
+                    .map(|url| {
+                        let single = HttpConfig {
+                            proxies: vec![url.clone()],
+                            ..config.clone()
+                        };
+                        Ok((url.clone(), single.build_client()?))
+                    })
+                    .collect::<Result<_>>()?,
+            )),
+        };
+        let user_agents = match config.user_agents_file {
+            _ if !config.random_agent => Vec::new(),
+            Some(ref path) => load_list(path, "User-Agent")?,
+            None => BROWSER_USER_AGENTS
+                .iter()
+                .map(|ua| ua.to_string())
+                .collect(),
+        };
+        Ok(Self {
+            client,
+            gate: RetryAfterGate::new(config.retry_after),
+            config,
+            user_agents,
+            proxy_pool,
+        })
+    }
+
+    /// Number of 429 and 503 Retry-After responses seen
+    pub fn rate_limited(&self) -> usize {
+        self.gate.rate_limited()
+    }
+
+    /// Proxies of the pool skipped after repeated failures
+    pub fn dead_proxies(&self) -> Vec<&str> {
+        self.proxy_pool
+            .as_ref()
+            .map(ProxyPool::dead)
+            .unwrap_or_default()
     }
 
     /// Make a GET request
@@ -74,10 +594,22 @@ impl HttpClient {
         self.request(Method::GET, url, None).await
     }
 
-    /// Make a request with specified method
-    pub async fn request(&self, method: Method, url: &str, body: Option<&str>) -> Result<Response> {
+    /// Start a request with the configured headers, cookies and credentials
+    pub fn request_builder(&self, method: Method, url: &str) -> RequestBuilder {
         let mut request = self.client.request(method, url);
 
+        // Rotate the User-Agent per request, unless -H sets one
+        let header_agent = self
+            .config
+            .headers
+            .keys()
+            .any(|k| k.eq_ignore_ascii_case("user-agent"));
+        if let Some(agent) = self.user_agents.choose(&mut rand::thread_rng()) {
+            if !header_agent {
+                request = request.header(USER_AGENT, agent.as_str());
+            }
+        }
+
         // Add custom headers
         for (key, value) in &self.config.headers {
             request = request.header(key.as_str(), value.as_str());
@@ -88,23 +620,98 @@ impl HttpClient {
             request = request.header("Cookie", cookies.as_str());
         }
 
+        // Without decompression reqwest stops advertising encodings; ask for
+        // them anyway so servers answer the same way as with decoding enabled
+        if !self.config.decompress
+            && !self
+                .config
+                .headers
+                .keys()
+                .any(|k| k.eq_ignore_ascii_case("accept-encoding"))
+        {
+            request = request.header(ACCEPT_ENCODING, "gzip, deflate, br");
+        }
+
         // Add basic auth
         if let (Some(ref user), Some(ref pass)) = (&self.config.username, &self.config.password) {
             request = request.basic_auth(user, Some(pass));
         }
 
+        request
+    }
+
+    /// Send a request with the configured retry policy. When the target
+    /// answers 429, or 503 with Retry-After, every request is held for the
+    /// time it asks and this one is sent again (see `RetryAfterGate`).
+    pub async fn send(&self, request: RequestBuilder) -> Result<Response> {
+        Ok(self.send_timed(request).await?.0)
+    }
+
+    /// Send a request like `send`, also returning when the attempt that got
+    /// the response was sent, after any pause for rate limiting or backoff
+    async fn send_timed(&self, request: RequestBuilder) -> Result<(Response, Instant)> {
+        let (retries, backoff) = (self.config.retries, self.config.retry_backoff);
+        let sent = self
+            .gate
+            .send_with(request, |request| async move {
+                match self.proxy_pool {
+                    Some(ref pool) => pool.send(request, retries, backoff).await,
+                    None => send_with_retry(request, retries, backoff).await,
+                }
+            })
+            .await?;
+        Ok(sent)
+    }
+
+    /// Make a request with specified method
+    pub async fn request(&self, method: Method, url: &str, body: Option<&str>) -> Result<Response> {
+        let mut request = self.request_builder(method, url);
+
         // Add body if present
         if let Some(data) = body {
             request = request.body(data.to_string());
         }
 
-        Ok(request.send().await?)
+        self.send(request).await
     }
 
-    /// Check if URL returns a valid response
-    pub async fn check_url(&self, url: &str, method: &str) -> Result<(u16, usize, Option<String>)> {
-        let method = Method::from_bytes(method.as_bytes()).unwrap_or(Method::GET);
-        let response = self.request(method, url, None).await?;
+    /// Check if URL returns a valid response, sending `body` if given. The
+    /// body is returned only when `read_body` is set, cut to `max_body`.
+    pub async fn check_url(
+        &self,
+        url: &str,
+        method: &str,
+        body: Option<&RequestBody>,
+        read_body: bool,
+    ) -> Result<UrlCheck> {
+        let mut method = Method::from_bytes(method.as_bytes()).unwrap_or(Method::GET);
+        // A HEAD response has no body to match against
+        if read_body && method == Method::HEAD {
+            method = Method::GET;
+        }
+
+        let mut request = self.request_builder(method.clone(), url);
+        if let Some(body) = body {
+            // A Content-Type given with -H takes precedence
+            if !self
+                .config
+                .headers
+                .keys()
+                .any(|k| k.eq_ignore_ascii_case("content-type"))
+            {
+                request = request.header(CONTENT_TYPE, body.content_type.as_str());
+            }
+            request = request.body(body.data.clone());
+        }
+
+        let (mut response, mut started) = self.send_timed(request).await?;
+        // Some servers reject HEAD; fall back to GET for this URL only
+        if method == Method::HEAD && response.status() == StatusCode::METHOD_NOT_ALLOWED {
+            method = Method::GET;
+            (response, started) = self
+                .send_timed(self.request_builder(Method::GET, url))
+                .await?;
+        }
 
         let status = response.status().as_u16();
         let redirect = response
@@ -112,11 +719,118 @@ impl HttpClient {
             .get("location")
             .and_then(|v| v.to_str().ok())
             .map(|s| s.to_string());
+        let headers = if self.config.capture_headers {
+            header_pairs(response.headers())
+        } else {
+            Vec::new()
+        };
+        let content_type = header_content_type(response.headers());
+        let cors = header_cors(response.headers());
+
+        // For HEAD, take the size from Content-Length instead of a body
+        if method == Method::HEAD {
+            let size = header_length(&response).unwrap_or(0);
+            return Ok(UrlCheck {
+                status,
+                size,
+                redirect,
+                body: None,
+                elapsed: started.elapsed(),
+                headers,
+                content_type,
+                cors,
+            });
+        }
+
+        // Raw mode prefers the advertised length of the encoded body
+        let content_length = (!self.config.decompress)
+            .then(|| header_length(&response))
+            .flatten();
+
+        let limit = if read_body {
+            self.config.max_body
+        } else {
+            Some(0)
+        };
+        let (body, length) = read_limited(response, limit).await?;
+        let elapsed = started.elapsed();
+        let size = content_length.unwrap_or(length);
+
+        Ok(UrlCheck {
+            status,
+            size,
+            redirect,
+            body: read_body.then_some(body),
+            elapsed,
+            headers,
+            content_type,
+            cors,
+        })
+    }
+
+    /// Probe a host for HTTP liveness with a HEAD request, trying HTTPS first.
+    /// Returns the URL that answered and its status code.
+    pub async fn probe_host(&self, host: &str) -> Option<(String, u16)> {
+        for scheme in ["https", "http"] {
+            let url = format!("{}://{}/", scheme, host);
+            if let Ok(response) = self.request(Method::HEAD, &url, None).await {
+                return Some((url, response.status().as_u16()));
+            }
+        }
+        None
+    }
+}
+
+/// CORS grant of a response, if it has an Access-Control-Allow-Origin header
+pub fn header_cors(headers: &HeaderMap) -> Option<CorsGrant> {
+    CorsGrant::from_headers(
+        headers
+            .iter()
+            .filter_map(|(name, value)| Some((name.as_str(), value.to_str().ok()?))),
+    )
+}
+
+/// Value of the Content-Type header, if present and readable
+pub fn header_content_type(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string())
+}
+
+/// Value of the Content-Length header, if present and valid
+fn header_length(response: &Response) -> Option<usize> {
+    response
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok())
+}
 
-        let body = response.bytes().await?;
-        let size = body.len();
+/// Send a request, retrying connection, send and timeout errors up to `retries`
+/// times and sleeping `backoff * attempt` in between. HTTP responses of any
+/// status are returned as-is, with the time the attempt that got them was
+/// sent, so failed attempts and backoff are not part of the response time.
+pub async fn send_with_retry(
+    request: RequestBuilder,
+    retries: u32,
+    backoff: Duration,
+) -> reqwest::Result<(Response, Instant)> {
+    let mut attempt = 0;
+    loop {
+        let started = Instant::now();
+        // Streaming bodies can't be cloned; send those once
+        let Some(next) = (attempt < retries).then(|| request.try_clone()).flatten() else {
+            return Ok((request.send().await?, started));
+        };
 
-        Ok((status, size, redirect))
+        match next.send().await.map(|response| (response, started)) {
+            Err(e) if e.is_connect() || e.is_timeout() || e.is_request() => {
+                attempt += 1;
+                tokio::time::sleep(backoff * attempt).await;
+            }
+            result => return result,
+        }
     }
 }
 
@@ -134,3 +848,823 @@ pub fn parse_headers(headers: &[String]) -> HashMap<String, String> {
         })
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::mock_server::{MockResponse, MockServer};
+    use std::collections::HashSet;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn retry_after_holds_and_resends() {
+        let calls = AtomicUsize::new(0);
+        let server = MockServer::start(move |_| {
+            if calls.fetch_add(1, Ordering::Relaxed) == 0 {
+                MockResponse::new(429, "slow down").header("Retry-After", "1")
+            } else {
+                MockResponse::new(200, "ok")
+            }
+        })
+        .await;
+        let client = HttpClient::new(HttpConfig::default()).unwrap();
+
+        let started = Instant::now();
+        let check = client
+            .check_url(&format!("{}/page", server.url()), "GET", None, false)
+            .await
+            .unwrap();
+
+        assert_eq!(check.status, 200);
+        assert_eq!(client.rate_limited(), 1);
+        assert_eq!(server.requests().len(), 2);
+        assert!(started.elapsed() >= Duration::from_secs(1));
+        // The pause is not part of the response time
+        assert!(check.elapsed < Duration::from_millis(500));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn elapsed_covers_only_the_successful_attempt() {
+        // The first attempt times out; the retry after the backoff is quick
+        let calls = AtomicUsize::new(0);
+        let server = MockServer::start(move |_| {
+            if calls.fetch_add(1, Ordering::Relaxed) == 0 {
+                std::thread::sleep(Duration::from_millis(600));
+            }
+            MockResponse::new(200, "ok")
+        })
+        .await;
+        let client = HttpClient::new(HttpConfig {
+            timeout: Duration::from_millis(300),
+            retries: 1,
+            retry_backoff: Duration::from_millis(500),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let started = Instant::now();
+        let check = client
+            .check_url(&format!("{}/slow", server.url()), "GET", None, false)
+            .await
+            .unwrap();
+
+        assert_eq!(check.status, 200);
+        assert!(started.elapsed() >= Duration::from_millis(800));
+        assert!(check.elapsed < Duration::from_millis(300));
+    }
+
+    #[tokio::test]
+    async fn check_url_keeps_at_most_max_body() {
+        let server =
+            MockServer::start(|_| MockResponse::new(200, b"\x00\xffabcdefgh".to_vec())).await;
+        let client = HttpClient::new(HttpConfig {
+            max_body: Some(4),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let check = client
+            .check_url(&format!("{}/big", server.url()), "GET", None, true)
+            .await
+            .unwrap();
+
+        assert_eq!(check.size, 10);
+        assert_eq!(check.body.as_deref(), Some(&b"\x00\xffab"[..]));
+    }
+
+    #[tokio::test]
+    async fn body_regexes_apply_to_the_checked_body() {
+        use crate::core::ResponseFilter;
+        use regex::Regex;
+
+        let server = MockServer::start(|req| match req.path.as_str() {
+            "/admin" => MockResponse::new(200, "<h1>Admin login</h1>"),
+            "/oops" => MockResponse::new(200, "<h1>Internal error</h1>"),
+            _ => MockResponse::new(200, "<h1>Welcome</h1>"),
+        })
+        .await;
+        let client = HttpClient::new(HttpConfig::default()).unwrap();
+        let filter = ResponseFilter {
+            match_regex: Some(Regex::new("(?i)admin|error").unwrap()),
+            exclude_regex: Some(Regex::new("Internal error").unwrap()),
+            ..Default::default()
+        };
+
+        let mut shown = Vec::new();
+        for path in ["/admin", "/oops", "/home"] {
+            // HEAD is upgraded to GET when the body is needed
+            let check = client
+                .check_url(&format!("{}{}", server.url(), path), "HEAD", None, true)
+                .await
+                .unwrap();
+            let body = String::from_utf8(check.body.unwrap()).unwrap();
+            if filter
+                .evaluate(check.status, check.size, 0, 0, &body, None, &[])
+                .is_shown()
+            {
+                shown.push(path);
+            }
+        }
+
+        assert_eq!(shown, ["/admin"]);
+        assert!(server.requests().iter().all(|r| r.method == "GET"));
+    }
+
+    #[tokio::test]
+    async fn head_rejected_with_405_is_retried_as_get() {
+        let server = MockServer::start(|req| match req.method.as_str() {
+            "HEAD" => MockResponse::new(405, ""),
+            _ => MockResponse::new(200, "from get"),
+        })
+        .await;
+        let client = HttpClient::new(HttpConfig::default()).unwrap();
+
+        let check = client
+            .check_url(&format!("{}/page", server.url()), "HEAD", None, false)
+            .await
+            .unwrap();
+
+        assert_eq!(check.status, 200);
+        assert_eq!(check.size, "from get".len());
+        let methods: Vec<String> = server.requests().into_iter().map(|r| r.method).collect();
+        assert_eq!(methods, ["HEAD", "GET"]);
+    }
+
+    #[tokio::test]
+    async fn raw_size_is_the_compressed_length() {
+        // 1000 times "a", gzipped
+        const GZIPPED: [u8; 29] = [
+            0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0x4b, 0x4c, 0x1c, 0x05,
+            0xa3, 0x60, 0x14, 0x0c, 0x77, 0x00, 0x00, 0x03, 0xda, 0x38, 0x9a, 0xe8, 0x03, 0x00,
+            0x00,
+        ];
+        let server = MockServer::start(|_| {
+            MockResponse::new(200, GZIPPED.to_vec()).header("Content-Encoding", "gzip")
+        })
+        .await;
+        let url = format!("{}/page", server.url());
+
+        let size = |decompress: bool| {
+            let url = url.clone();
+            async move {
+                let client = HttpClient::new(HttpConfig {
+                    decompress,
+                    ..Default::default()
+                })
+                .unwrap();
+                client
+                    .check_url(&url, "GET", None, false)
+                    .await
+                    .unwrap()
+                    .size
+            }
+        };
+        assert_eq!(size(true).await, 1000);
+        assert_eq!(size(false).await, GZIPPED.len());
+
+        // Raw mode still asks for compressed responses
+        let requests = server.requests();
+        assert!(requests[1]
+            .headers
+            .iter()
+            .any(|(n, v)| n.eq_ignore_ascii_case("accept-encoding") && v.contains("gzip")));
+    }
+
+    #[tokio::test]
+    async fn forced_protocol_is_spoken_on_the_wire() {
+        use tokio::io::AsyncReadExt;
+
+        // First bytes of the request a client built for `protocol` sends
+        async fn opening(protocol: HttpProtocol) -> Vec<u8> {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let url = format!("http://{}/", listener.local_addr().unwrap());
+            let read = tokio::spawn(async move {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buf = vec![0u8; 24];
+                stream.read_exact(&mut buf).await.unwrap();
+                buf
+            });
+            let client = HttpClient::new(HttpConfig {
+                protocol,
+                timeout: Duration::from_millis(500),
+                ..Default::default()
+            })
+            .unwrap();
+            // The listener never answers
+            let _ = client.get(&url).await;
+            read.await.unwrap()
+        }
+
+        assert_eq!(
+            opening(HttpProtocol::Http2).await,
+            b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n"
+        );
+        assert!(opening(HttpProtocol::Http1)
+            .await
+            .starts_with(b"GET / HTTP/1.1\r\n"));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs network access to a public HTTP/2 server"]
+    async fn forced_http2_negotiates_h2() {
+        let client = HttpClient::new(HttpConfig {
+            protocol: HttpProtocol::Http2,
+            ..Default::default()
+        })
+        .unwrap();
+        let response = client.get("https://www.google.com/").await.unwrap();
+        assert_eq!(response.version(), reqwest::Version::HTTP_2);
+    }
+
+    #[test]
+    fn pkcs12_bundle_is_loaded_with_its_password() {
+        use p12_keystore::{KeyStore, KeyStoreEntry, PrivateKeyChain};
+
+        let client = rcgen::generate_simple_self_signed(vec!["client".to_string()]).unwrap();
+        let cert = p12_keystore::Certificate::from_der(client.cert.der()).unwrap();
+        let mut store = KeyStore::new();
+        store.add_entry(
+            "client",
+            KeyStoreEntry::PrivateKeyChain(PrivateKeyChain::new(
+                client.key_pair.serialize_der(),
+                b"client",
+                [cert],
+            )),
+        );
+
+        let bundle =
+            std::env::temp_dir().join(format!("rbuster-{}", crate::core::random_label(12)));
+        std::fs::write(&bundle, store.writer("secret").write().unwrap()).unwrap();
+        let loaded = load_pkcs12(&bundle, "secret");
+        let rejected = load_pkcs12(&bundle, "wrong");
+        std::fs::remove_file(&bundle).unwrap();
+
+        assert!(loaded.is_ok());
+        assert!(matches!(rejected, Err(RbusterError::ConfigError(_))));
+    }
+
+    #[tokio::test]
+    async fn requests_carry_credentials_through_the_proxy() {
+        let proxy = MockServer::start(|_| MockResponse::new(200, "proxied")).await;
+        let client = HttpClient::new(HttpConfig {
+            proxies: vec![proxy.url()],
+            username: Some("user".to_string()),
+            password: Some("pass".to_string()),
+            cookies: Some("session=abc".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let response = client.get("http://target.invalid/page").await.unwrap();
+        assert_eq!(response.status(), 200);
+
+        let requests = proxy.requests();
+        assert_eq!(requests.len(), 1);
+        // Proxied requests carry the absolute URL
+        assert_eq!(requests[0].path, "http://target.invalid/page");
+        let header = |name: &str| {
+            requests[0]
+                .headers
+                .iter()
+                .find(|(n, _)| n.eq_ignore_ascii_case(name))
+                .map(|(_, v)| v.as_str())
+        };
+        assert_eq!(header("authorization"), Some("Basic dXNlcjpwYXNz"));
+        assert_eq!(header("cookie"), Some("session=abc"));
+    }
+
+    #[tokio::test]
+    async fn proxy_credentials_are_sent_to_the_proxy() {
+        let proxy = MockServer::start(|_| MockResponse::new(200, "proxied")).await;
+        let client = HttpClient::new(HttpConfig {
+            proxies: vec![proxy.url()],
+            proxy_username: Some("proxyuser".to_string()),
+            proxy_password: Some("proxypass".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        client.get("http://target.invalid/page").await.unwrap();
+
+        let requests = proxy.requests();
+        let header = requests[0]
+            .headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case("proxy-authorization"))
+            .map(|(_, v)| v.as_str());
+        assert_eq!(header, Some("Basic cHJveHl1c2VyOnByb3h5cGFzcw=="));
+        assert!(!requests[0]
+            .headers
+            .iter()
+            .any(|(n, _)| n.eq_ignore_ascii_case("authorization")));
+    }
+
+    /// SOCKS5 proxy answering every CONNECT itself with a 200 page; returns
+    /// its address and the destination hosts it was asked for
+    async fn socks_server() -> (SocketAddr, Arc<std::sync::Mutex<Vec<String>>>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let destinations = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let log = Arc::clone(&destinations);
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let log = Arc::clone(&log);
+                tokio::spawn(async move {
+                    // Greeting: version, method count and methods; pick no auth
+                    let mut head = [0u8; 2];
+                    stream.read_exact(&mut head).await?;
+                    let mut methods = vec![0u8; head[1] as usize];
+                    stream.read_exact(&mut methods).await?;
+                    stream.write_all(&[5, 0]).await?;
+
+                    // CONNECT with an address or a domain name
+                    let mut request = [0u8; 4];
+                    stream.read_exact(&mut request).await?;
+                    let host = match request[3] {
+                        1 => {
+                            let mut ip = [0u8; 4];
+                            stream.read_exact(&mut ip).await?;
+                            std::net::Ipv4Addr::from(ip).to_string()
+                        }
+                        4 => {
+                            let mut ip = [0u8; 16];
+                            stream.read_exact(&mut ip).await?;
+                            std::net::Ipv6Addr::from(ip).to_string()
+                        }
+                        3 => {
+                            let mut len = [0u8; 1];
+                            stream.read_exact(&mut len).await?;
+                            let mut name = vec![0u8; len[0] as usize];
+                            stream.read_exact(&mut name).await?;
+                            String::from_utf8_lossy(&name).into_owned()
+                        }
+                        _ => return Ok::<_, std::io::Error>(()),
+                    };
+                    let mut port = [0u8; 2];
+                    stream.read_exact(&mut port).await?;
+                    log.lock().unwrap().push(host);
+                    stream.write_all(&[5, 0, 0, 1, 0, 0, 0, 0, 0, 0]).await?;
+
+                    let mut buf = [0u8; 4096];
+                    let _ = stream.read(&mut buf).await?;
+                    stream
+                        .write_all(
+                            b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                        )
+                        .await
+                });
+            }
+        });
+        (addr, destinations)
+    }
+
+    #[tokio::test]
+    async fn socks5h_resolves_the_target_on_the_proxy() {
+        let (addr, destinations) = socks_server().await;
+        let client = |scheme: &str| {
+            HttpClient::new(HttpConfig {
+                proxies: vec![format!("{}://{}", scheme, addr)],
+                ..Default::default()
+            })
+            .unwrap()
+        };
+
+        // Only the proxy knows this name
+        let remote = client("socks5h")
+            .get("http://intranet.invalid:8080/")
+            .await
+            .unwrap();
+        assert_eq!(remote.status(), 200);
+        assert!(client("socks5")
+            .get("http://intranet.invalid:8080/")
+            .await
+            .is_err());
+        client("socks5")
+            .get("http://localhost:8080/")
+            .await
+            .unwrap();
+
+        // socks5 sends the address it resolved locally
+        let destinations = destinations.lock().unwrap().clone();
+        assert_eq!(destinations.len(), 2);
+        assert_eq!(destinations[0], "intranet.invalid");
+        assert!(destinations[1]
+            .parse::<IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback()));
+    }
+
+    #[tokio::test]
+    async fn redirect_limit_reports_the_last_redirect() {
+        // /hop/N/K redirects to /hop/N+1/K until N reaches K
+        let server = MockServer::start(|req| {
+            let parts: Vec<&str> = req.path.split('/').collect();
+            match parts.as_slice() {
+                ["", "hop", n, k] => {
+                    let (n, k): (usize, usize) = (n.parse().unwrap(), k.parse().unwrap());
+                    if n == k {
+                        MockResponse::new(200, "landed")
+                    } else {
+                        MockResponse::new(302, "")
+                            .header("Location", &format!("/hop/{}/{}", n + 1, k))
+                    }
+                }
+                ["", "loop"] => MockResponse::new(302, "").header("Location", "/loop"),
+                _ => MockResponse::new(404, "missing"),
+            }
+        })
+        .await;
+        let client = HttpClient::new(HttpConfig {
+            follow_redirect: true,
+            max_redirects: 3,
+            ..Default::default()
+        })
+        .unwrap();
+
+        let within = client
+            .get(&format!("{}/hop/0/3", server.url()))
+            .await
+            .unwrap();
+        assert_eq!(within.status(), 200);
+
+        let before = server.requests().len();
+        let over = client
+            .get(&format!("{}/hop/0/4", server.url()))
+            .await
+            .unwrap();
+        assert_eq!(over.status(), 302);
+        assert!(over.url().path().starts_with("/hop/3/"), "{}", over.url());
+        assert_eq!(server.requests().len() - before, 4);
+
+        let looped = client.get(&format!("{}/loop", server.url())).await.unwrap();
+        assert_eq!(looped.status(), 302);
+    }
+
+    #[tokio::test]
+    async fn requests_rotate_through_the_proxies() {
+        let first = MockServer::start(|_| MockResponse::new(200, "first")).await;
+        let second = MockServer::start(|_| MockResponse::new(200, "second")).await;
+        let path = std::env::temp_dir().join(format!("rbuster-{}", crate::core::random_label(12)));
+        std::fs::write(&path, format!("# pool\n{}\n", second.url())).unwrap();
+        let client = HttpClient::new(HttpConfig {
+            proxies: vec![first.url()],
+            proxy_file: Some(path.clone()),
+            ..Default::default()
+        });
+        std::fs::remove_file(&path).unwrap();
+        let client = client.unwrap();
+
+        for i in 0..10 {
+            let url = format!("http://target.invalid/{}", i);
+            assert_eq!(client.get(&url).await.unwrap().status(), 200);
+        }
+        assert_eq!(first.requests().len(), 5);
+        assert_eq!(second.requests().len(), 5);
+        assert!(client.dead_proxies().is_empty());
+    }
+
+    #[tokio::test]
+    async fn dead_proxy_is_skipped_after_repeated_failures() {
+        let live = MockServer::start(|_| MockResponse::new(200, "live")).await;
+        // Nothing listens on a port freed right after binding
+        let dead = {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            format!("http://{}", listener.local_addr().unwrap())
+        };
+        let client = HttpClient::new(HttpConfig {
+            proxies: vec![dead.clone(), live.url()],
+            retries: 1,
+            retry_backoff: Duration::ZERO,
+            ..Default::default()
+        })
+        .unwrap();
+
+        for i in 0..30 {
+            let url = format!("http://target.invalid/{}", i);
+            assert_eq!(client.get(&url).await.unwrap().status(), 200);
+        }
+        assert_eq!(client.dead_proxies(), [dead.as_str()]);
+        assert_eq!(live.requests().len(), 30);
+    }
+
+    #[test]
+    fn cors_grant_needs_the_exact_origin_and_credentials() {
+        let grant = |headers: &[(&str, &str)]| CorsGrant::from_headers(headers.iter().copied());
+        let origin = "https://evil.example";
+
+        let reflected = grant(&[
+            ("Access-Control-Allow-Origin", " https://evil.example "),
+            ("ACCESS-CONTROL-ALLOW-CREDENTIALS", "True"),
+        ])
+        .unwrap();
+        assert!(reflected.trusts_with_credentials(origin));
+        assert!(!reflected.trusts_with_credentials("https://other.example"));
+
+        let anonymous = grant(&[("access-control-allow-origin", origin)]).unwrap();
+        assert!(!anonymous.allow_credentials);
+        assert!(!anonymous.trusts_with_credentials(origin));
+
+        let any = grant(&[
+            ("access-control-allow-origin", "*"),
+            ("access-control-allow-credentials", "true"),
+        ])
+        .unwrap();
+        assert!(!any.trusts_with_credentials(origin));
+
+        assert_eq!(grant(&[("access-control-allow-credentials", "true")]), None);
+    }
+
+    /// User-Agents of `requests` sent through a client built from `config`
+    async fn sent_user_agents(config: HttpConfig, requests: usize) -> Vec<String> {
+        let server = MockServer::start(|_| MockResponse::new(200, "ok")).await;
+        let client = HttpClient::new(config).unwrap();
+        for _ in 0..requests {
+            client.get(&server.url()).await.unwrap();
+        }
+        server
+            .requests()
+            .into_iter()
+            .filter_map(|r| {
+                r.headers
+                    .into_iter()
+                    .find(|(n, _)| n.eq_ignore_ascii_case("user-agent"))
+                    .map(|(_, v)| v)
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn random_agent_varies_across_the_browser_pool() {
+        let agents = sent_user_agents(
+            HttpConfig {
+                random_agent: true,
+                ..Default::default()
+            },
+            40,
+        )
+        .await;
+        assert_eq!(agents.len(), 40);
+        assert!(agents
+            .iter()
+            .all(|ua| BROWSER_USER_AGENTS.contains(&ua.as_str())));
+        let distinct: HashSet<&String> = agents.iter().collect();
+        assert!(distinct.len() > 1, "{:?}", distinct);
+    }
+
+    #[tokio::test]
+    async fn user_agents_file_replaces_the_pool() {
+        let path = std::env::temp_dir().join(format!("rbuster-{}", crate::core::random_label(12)));
+        std::fs::write(&path, "agent-one\n\nagent-two\n").unwrap();
+        let agents = sent_user_agents(
+            HttpConfig {
+                random_agent: true,
+                user_agents_file: Some(path.clone()),
+                ..Default::default()
+            },
+            30,
+        )
+        .await;
+        std::fs::remove_file(&path).unwrap();
+
+        let distinct: HashSet<&str> = agents.iter().map(String::as_str).collect();
+        assert_eq!(distinct, HashSet::from(["agent-one", "agent-two"]));
+    }
+
+    #[tokio::test]
+    async fn fixed_agent_is_sent_without_rotation() {
+        let agents = sent_user_agents(HttpConfig::default(), 3).await;
+        assert_eq!(agents, vec![HttpConfig::default().user_agent; 3]);
+    }
+
+    /// HTTPS server on localhost presenting `certs`, answering 200 to
+    /// everything; returns its URL. With `client_ca`, clients must present a
+    /// certificate signed by it.
+    async fn tls_server(
+        certs: Vec<tokio_rustls::rustls::pki_types::CertificateDer<'static>>,
+        key: &rcgen::KeyPair,
+        client_ca: Option<&rcgen::Certificate>,
+    ) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio_rustls::rustls::crypto::ring;
+        use tokio_rustls::rustls::pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer};
+        use tokio_rustls::rustls::server::WebPkiClientVerifier;
+        use tokio_rustls::rustls::{RootCertStore, ServerConfig};
+
+        let provider = Arc::new(ring::default_provider());
+        let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(key.serialize_der()));
+        let builder = ServerConfig::builder_with_provider(Arc::clone(&provider))
+            .with_safe_default_protocol_versions()
+            .unwrap();
+        let builder = match client_ca {
+            Some(ca) => {
+                let mut roots = RootCertStore::empty();
+                roots.add(ca.der().clone()).unwrap();
+                let verifier =
+                    WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+                        .build()
+                        .unwrap();
+                builder.with_client_cert_verifier(verifier)
+            }
+            None => builder.with_no_client_auth(),
+        };
+        let config = builder.with_single_cert(certs, key).unwrap();
+        let acceptor = tokio_rustls::TlsAcceptor::from(Arc::new(config));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!(
+            "https://localhost:{}/",
+            listener.local_addr().unwrap().port()
+        );
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let acceptor = acceptor.clone();
+                tokio::spawn(async move {
+                    let Ok(mut tls) = acceptor.accept(stream).await else {
+                        return;
+                    };
+                    let mut buf = [0u8; 4096];
+                    let _ = tls.read(&mut buf).await;
+                    let _ = tls
+                        .write_all(
+                            b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                        )
+                        .await;
+                    let _ = tls.shutdown().await;
+                });
+            }
+        });
+        url
+    }
+
+    /// Self-signed CA for test certificates
+    fn test_ca() -> (rcgen::Certificate, rcgen::KeyPair) {
+        use rcgen::{BasicConstraints, CertificateParams, DnType, IsCa, KeyPair};
+
+        let mut params = CertificateParams::new(Vec::new()).unwrap();
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        params
+            .distinguished_name
+            .push(DnType::CommonName, "robuster test CA");
+        let key = KeyPair::generate().unwrap();
+        (params.self_signed(&key).unwrap(), key)
+    }
+
+    /// Certificate for `name` signed by `ca`
+    fn signed_by(
+        name: &str,
+        (ca, ca_key): &(rcgen::Certificate, rcgen::KeyPair),
+    ) -> (rcgen::Certificate, rcgen::KeyPair) {
+        let key = rcgen::KeyPair::generate().unwrap();
+        let cert = rcgen::CertificateParams::new(vec![name.to_string()])
+            .unwrap()
+            .signed_by(&key, ca, ca_key)
+            .unwrap();
+        (cert, key)
+    }
+
+    /// Check `url` with a client built from `config`, connecting to 127.0.0.1
+    async fn check_tls(url: &str, config: HttpConfig) -> Result<UrlCheck> {
+        let client = HttpClient::new(HttpConfig {
+            pinned_address: Some("127.0.0.1:0".parse().unwrap()),
+            ..config
+        })?;
+        client.check_url(url, "GET", None, false).await
+    }
+
+    #[tokio::test]
+    async fn insecure_accepts_a_self_signed_certificate() {
+        let server = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let url = tls_server(vec![server.cert.der().clone()], &server.key_pair, None).await;
+
+        assert!(check_tls(&url, HttpConfig::default()).await.is_err());
+        let insecure = HttpConfig {
+            insecure: true,
+            ..Default::default()
+        };
+        assert_eq!(check_tls(&url, insecure).await.unwrap().status, 200);
+    }
+
+    #[tokio::test]
+    async fn cacert_trusts_servers_signed_by_the_ca() {
+        let ca = test_ca();
+        let (server, server_key) = signed_by("localhost", &ca);
+        let url = tls_server(vec![server.der().clone()], &server_key, None).await;
+
+        let bundle =
+            std::env::temp_dir().join(format!("rbuster-{}", crate::core::random_label(12)));
+        std::fs::write(&bundle, ca.0.pem()).unwrap();
+        let trusted = HttpConfig {
+            ca_certs: vec![bundle.clone()],
+            ..Default::default()
+        };
+        let with_ca = check_tls(&url, trusted).await;
+        let without_ca = check_tls(&url, HttpConfig::default()).await;
+        std::fs::remove_file(&bundle).unwrap();
+
+        assert_eq!(with_ca.unwrap().status, 200);
+        assert!(without_ca.is_err());
+    }
+
+    #[tokio::test]
+    async fn hostname_check_can_be_skipped_for_a_trusted_chain() {
+        let ca = test_ca();
+        let (server, server_key) = signed_by("other.example", &ca);
+        let url = tls_server(vec![server.der().clone()], &server_key, None).await;
+
+        let bundle =
+            std::env::temp_dir().join(format!("rbuster-{}", crate::core::random_label(12)));
+        std::fs::write(&bundle, ca.0.pem()).unwrap();
+        let trusted = |verify_hostname| HttpConfig {
+            ca_certs: vec![bundle.clone()],
+            verify_hostname,
+            ..Default::default()
+        };
+        let verified = check_tls(&url, trusted(true)).await;
+        let unverified = check_tls(&url, trusted(false)).await;
+        // The chain is still checked without the hostname
+        let untrusted = check_tls(
+            &url,
+            HttpConfig {
+                verify_hostname: false,
+                ..Default::default()
+            },
+        )
+        .await;
+        std::fs::remove_file(&bundle).unwrap();
+
+        assert!(verified.is_err());
+        assert_eq!(unverified.unwrap().status, 200);
+        assert!(untrusted.is_err());
+    }
+
+    #[tokio::test]
+    async fn client_certificate_is_presented_to_servers_requiring_one() {
+        use p12_keystore::{KeyStore, KeyStoreEntry, PrivateKeyChain};
+
+        let ca = test_ca();
+        let (server, server_key) = signed_by("localhost", &ca);
+        let url = tls_server(vec![server.der().clone()], &server_key, Some(&ca.0)).await;
+        let (client, client_key) = signed_by("client", &ca);
+
+        let scratch =
+            std::env::temp_dir().join(format!("rbuster-{}", crate::core::random_label(12)));
+        std::fs::create_dir_all(&scratch).unwrap();
+        let ca_file = scratch.join("ca.pem");
+        std::fs::write(&ca_file, ca.0.pem()).unwrap();
+        let pem_file = scratch.join("client.pem");
+        std::fs::write(
+            &pem_file,
+            format!("{}{}", client.pem(), client_key.serialize_pem()),
+        )
+        .unwrap();
+        let mut store = KeyStore::new();
+        store.add_entry(
+            "client",
+            KeyStoreEntry::PrivateKeyChain(PrivateKeyChain::new(
+                client_key.serialize_der(),
+                b"client",
+                [p12_keystore::Certificate::from_der(client.der()).unwrap()],
+            )),
+        );
+        let p12_file = scratch.join("client.p12");
+        std::fs::write(&p12_file, store.writer("secret").write().unwrap()).unwrap();
+
+        let trusting = || HttpConfig {
+            ca_certs: vec![ca_file.clone()],
+            ..Default::default()
+        };
+        let with_pem = check_tls(
+            &url,
+            HttpConfig {
+                client_cert: Some(pem_file.clone()),
+                ..trusting()
+            },
+        )
+        .await;
+        let with_pkcs12 = check_tls(
+            &url,
+            HttpConfig {
+                client_pkcs12: Some(p12_file.clone()),
+                client_pkcs12_pass: Some("secret".to_string()),
+                ..trusting()
+            },
+        )
+        .await;
+        let without = check_tls(&url, trusting()).await;
+        std::fs::remove_dir_all(&scratch).unwrap();
+
+        assert_eq!(with_pem.unwrap().status, 200);
+        assert_eq!(with_pkcs12.unwrap().status, 200);
+        assert!(without.is_err());
+    }
+
+    #[test]
+    fn cacert_must_be_pem() {
+        let bundle =
+            std::env::temp_dir().join(format!("rbuster-{}", crate::core::random_label(12)));
+        std::fs::write(&bundle, "not a certificate").unwrap();
+        let loaded = load_ca_bundle(&bundle);
+        std::fs::remove_file(&bundle).unwrap();
+        assert!(matches!(loaded, Err(RbusterError::ConfigError(_))));
+    }
+}
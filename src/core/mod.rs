@@ -1,9 +1,37 @@
 //! Core modules
 
+pub mod abort;
+pub mod cache;
+pub mod checkpoint;
+pub mod concurrency;
 pub mod dns;
+pub mod filter;
+pub mod fingerprint;
 pub mod http;
+#[cfg(test)]
+pub mod mock_server;
+pub mod mutate;
+pub mod permute;
+pub mod proxy;
+pub mod random;
+pub mod ratelimit;
+pub mod raw_http;
 pub mod wordlist;
+pub mod xml;
 
+pub use abort::*;
+pub use cache::*;
+pub use checkpoint::*;
+pub use concurrency::*;
 pub use dns::*;
+pub use filter::*;
+pub use fingerprint::*;
 pub use http::*;
+pub use mutate::*;
+pub use permute::*;
+pub use proxy::*;
+pub use random::*;
+pub use ratelimit::*;
+pub use raw_http::*;
 pub use wordlist::*;
+pub use xml::*;
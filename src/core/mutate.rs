@@ -0,0 +1,152 @@
+//! Wordlist mutations (case variants and digit affixes)
+
+use std::collections::HashSet;
+
+/// Expands wordlist entries into mutated variants
+#[derive(Debug, Clone, Default)]
+pub struct Mutator {
+    case: bool,
+    suffixes: Vec<String>,
+    prefixes: Vec<String>,
+}
+
+impl Mutator {
+    pub fn new(case: bool, suffixes: Option<&str>, prefixes: Option<&str>) -> Self {
+        Self {
+            case,
+            suffixes: suffixes.map(parse_affixes).unwrap_or_default(),
+            prefixes: prefixes.map(parse_affixes).unwrap_or_default(),
+        }
+    }
+
+    /// Whether any mutation is enabled
+    pub fn is_active(&self) -> bool {
+        self.case || !self.suffixes.is_empty() || !self.prefixes.is_empty()
+    }
+
+    /// Expand each word into itself followed by its mutations. Words produced
+    /// more than once (including mutations equal to other entries) are kept
+    /// only at their first position.
+    pub fn apply(&self, words: Vec<String>) -> Vec<String> {
+        if !self.is_active() {
+            return words;
+        }
+
+        let mut seen = HashSet::new();
+        let mut mutated = Vec::new();
+        for word in &words {
+            for variant in self.variants(word) {
+                if seen.insert(variant.clone()) {
+                    mutated.push(variant);
+                }
+            }
+        }
+        mutated
+    }
+
+    /// The word itself followed by its mutations
+    fn variants(&self, word: &str) -> Vec<String> {
+        let mut variants = vec![word.to_string()];
+
+        if self.case {
+            variants.push(word.to_lowercase());
+            variants.push(word.to_uppercase());
+            variants.push(capitalize(word));
+        }
+
+        for suffix in &self.suffixes {
+            variants.push(format!("{}{}", word, suffix));
+        }
+
+        for prefix in &self.prefixes {
+            variants.push(format!("{}{}", prefix, word));
+        }
+
+        variants
+    }
+}
+
+/// Uppercase the first character and lowercase the rest
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first
+            .to_uppercase()
+            .chain(chars.flat_map(char::to_lowercase))
+            .collect(),
+        None => String::new(),
+    }
+}
+
+/// Parse comma-separated affixes; numeric ranges like "0-9" or "00-99" expand
+/// to every number in the range, zero-padded to the width of the start
+fn parse_affixes(spec: &str) -> Vec<String> {
+    let mut affixes = Vec::new();
+    for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let range = part.split_once('-').and_then(|(start, end)| {
+            Some((start, start.parse::<u64>().ok()?, end.parse::<u64>().ok()?))
+        });
+        match range {
+            Some((start, from, to)) if from <= to => {
+                let width = start.len();
+                affixes.extend((from..=to).map(|n| format!("{:0width$}", n, width = width)));
+            }
+            _ => affixes.push(part.to_string()),
+        }
+    }
+    affixes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(list: &[&str]) -> Vec<String> {
+        list.iter().map(|w| w.to_string()).collect()
+    }
+
+    #[test]
+    fn case_variants_follow_each_word() {
+        let mutator = Mutator::new(true, None, None);
+        assert_eq!(
+            mutator.apply(words(&["adMin"])),
+            ["adMin", "admin", "ADMIN", "Admin"]
+        );
+    }
+
+    #[test]
+    fn suffixes_expand_ranges_and_keep_padding() {
+        let mutator = Mutator::new(false, Some("1-3,2024"), None);
+        assert_eq!(
+            mutator.apply(words(&["admin"])),
+            ["admin", "admin1", "admin2", "admin3", "admin2024"]
+        );
+        assert_eq!(parse_affixes("08-10"), ["08", "09", "10"]);
+        assert_eq!(parse_affixes("dev, 9-7"), ["dev", "9-7"]);
+    }
+
+    #[test]
+    fn prefixes_go_before_the_word() {
+        let mutator = Mutator::new(false, None, Some("old_,_"));
+        assert_eq!(
+            mutator.apply(words(&["admin"])),
+            ["admin", "old_admin", "_admin"]
+        );
+    }
+
+    #[test]
+    fn mutations_colliding_with_entries_are_kept_once() {
+        let mutator = Mutator::new(true, Some("1"), None);
+        assert_eq!(
+            mutator.apply(words(&["admin", "Admin", "admin1"])),
+            ["admin", "ADMIN", "Admin", "admin1", "Admin1", "ADMIN1", "admin11"]
+        );
+    }
+
+    #[test]
+    fn inactive_mutator_leaves_the_wordlist_alone() {
+        let mutator = Mutator::default();
+        assert!(!mutator.is_active());
+        assert_eq!(mutator.apply(words(&["a", "a"])), ["a", "a"]);
+    }
+}
@@ -0,0 +1,83 @@
+//! Subdomain permutations for found names (dnsgen-style)
+
+/// Words combined with found labels when no word file is given
+const DEFAULT_WORDS: &[&str] = &[
+    "dev", "test", "stage", "staging", "prod", "qa", "uat", "beta", "api", "admin", "internal",
+    "old", "new", "v1", "v2", "backup",
+];
+
+/// Generates sibling names for a found subdomain
+#[derive(Debug, Clone)]
+pub struct Permutator {
+    words: Vec<String>,
+}
+
+impl Permutator {
+    pub fn new(words: Vec<String>) -> Self {
+        Self { words }
+    }
+
+    /// Permutations of `name` (the part in front of the base domain), applied
+    /// to its first label: `dev-api`, `api-dev`, `devapi`, `apidev`, `dev.api`,
+    /// and numbers next to any number in the label (`api2` gives `api1`, `api3`)
+    pub fn permute(&self, name: &str) -> Vec<String> {
+        let (label, rest) = match name.split_once('.') {
+            Some((label, rest)) => (label, Some(rest)),
+            None => (name, None),
+        };
+        let join = |label: String| match rest {
+            Some(rest) => format!("{}.{}", label, rest),
+            None => label,
+        };
+
+        let mut names = Vec::new();
+        for word in &self.words {
+            if word == label {
+                continue;
+            }
+            names.push(join(format!("{}-{}", word, label)));
+            names.push(join(format!("{}-{}", label, word)));
+            names.push(join(format!("{}{}", word, label)));
+            names.push(join(format!("{}{}", label, word)));
+            names.push(format!("{}.{}", word, name));
+        }
+        names.extend(number_neighbours(label).into_iter().map(join));
+        names
+    }
+}
+
+impl Default for Permutator {
+    fn default() -> Self {
+        Self::new(DEFAULT_WORDS.iter().map(|w| w.to_string()).collect())
+    }
+}
+
+/// Replace the last number in a label with the numbers next to it, keeping
+/// its width; labels without a number get `1` and `2` appended
+fn number_neighbours(label: &str) -> Vec<String> {
+    let end = match label.rfind(|c: char| c.is_ascii_digit()) {
+        Some(i) => i + 1,
+        None => return vec![format!("{}1", label), format!("{}2", label)],
+    };
+    let start = label[..end]
+        .rfind(|c: char| !c.is_ascii_digit())
+        .map_or(0, |i| i + 1);
+    let digits = &label[start..end];
+    let Ok(n) = digits.parse::<u64>() else {
+        return Vec::new();
+    };
+
+    [n.checked_sub(1), n.checked_add(1)]
+        .into_iter()
+        .flatten()
+        .map(|m| {
+            format!(
+                "{}{:0width$}{}",
+                &label[..start],
+                m,
+                &label[end..],
+                width = digits.len()
+            )
+        })
+        .collect()
+}
@@ -0,0 +1,103 @@
+//! Rotation through a pool of proxies
+
+use reqwest::{Client, RequestBuilder, Response};
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// Failures in a row after which a proxy is skipped
+const DEAD_PROXY_FAILURES: u32 = 5;
+
+/// Proxies used in turn, one request each. A proxy is skipped once its
+/// connections fail several times in a row; when every proxy is down, all
+/// are tried again.
+pub struct ProxyPool {
+    proxies: Vec<PooledProxy>,
+    next: AtomicUsize,
+}
+
+struct PooledProxy {
+    url: String,
+    client: Client,
+    failures: AtomicU32,
+}
+
+impl PooledProxy {
+    fn alive(&self) -> bool {
+        self.failures.load(Ordering::Relaxed) < DEAD_PROXY_FAILURES
+    }
+}
+
+impl ProxyPool {
+    /// Pool of proxy URLs with a client built for each
+    pub fn new(proxies: Vec<(String, Client)>) -> Self {
+        Self {
+            proxies: proxies
+                .into_iter()
+                .map(|(url, client)| PooledProxy {
+                    url,
+                    client,
+                    failures: AtomicU32::new(0),
+                })
+                .collect(),
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// Proxies skipped after repeated failures
+    pub fn dead(&self) -> Vec<&str> {
+        self.proxies
+            .iter()
+            .filter(|p| !p.alive())
+            .map(|p| p.url.as_str())
+            .collect()
+    }
+
+    /// Next live proxy in turn, or the next of all when none is live
+    fn pick(&self) -> &PooledProxy {
+        let turn = self.next.fetch_add(1, Ordering::Relaxed);
+        let live: Vec<&PooledProxy> = self.proxies.iter().filter(|p| p.alive()).collect();
+        match live.len() {
+            0 => &self.proxies[turn % self.proxies.len()],
+            len => live[turn % len],
+        }
+    }
+
+    /// Send a request through the next proxy. Connection, send and timeout
+    /// errors count against that proxy and are retried up to `retries` times
+    /// through the following ones, sleeping `backoff * attempt` in between.
+    /// The response comes with the time its attempt was sent.
+    pub async fn send(
+        &self,
+        request: RequestBuilder,
+        retries: u32,
+        backoff: Duration,
+    ) -> reqwest::Result<(Response, Instant)> {
+        let (_, request) = request.build_split();
+        let mut request = Some(request?);
+        let mut attempt = 0;
+        loop {
+            let proxy = self.pick();
+            let current = request.take().expect("request kept for retrying");
+            // Streaming bodies can't be cloned; send those once
+            if attempt < retries {
+                request = current.try_clone();
+            }
+
+            let started = Instant::now();
+            match proxy.client.execute(current).await {
+                Err(e) if e.is_connect() || e.is_timeout() || e.is_request() => {
+                    proxy.failures.fetch_add(1, Ordering::Relaxed);
+                    if request.is_none() {
+                        return Err(e);
+                    }
+                    attempt += 1;
+                    tokio::time::sleep(backoff * attempt).await;
+                }
+                result => {
+                    proxy.failures.store(0, Ordering::Relaxed);
+                    return result.map(|response| (response, started));
+                }
+            }
+        }
+    }
+}
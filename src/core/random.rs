@@ -0,0 +1,37 @@
+//! Random names for wildcard probes
+
+use rand::Rng;
+
+const LABEL_CHARS: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789";
+
+/// Random lowercase alphanumeric label of `len` characters, unpredictable
+/// and distinct across calls even when made in the same instant
+pub fn random_label(len: usize) -> String {
+    let mut rng = rand::thread_rng();
+    (0..len)
+        .map(|_| LABEL_CHARS[rng.gen_range(0..LABEL_CHARS.len())] as char)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn labels_are_distinct_across_threads() {
+        let threads: Vec<_> = (0..8)
+            .map(|_| std::thread::spawn(|| (0..2000).map(|_| random_label(16)).collect::<Vec<_>>()))
+            .collect();
+        let labels: Vec<String> = threads
+            .into_iter()
+            .flat_map(|t| t.join().unwrap())
+            .collect();
+
+        assert!(labels
+            .iter()
+            .all(|l| l.len() == 16 && l.bytes().all(|b| LABEL_CHARS.contains(&b))));
+        let distinct: HashSet<&String> = labels.iter().collect();
+        assert_eq!(distinct.len(), labels.len());
+    }
+}
@@ -0,0 +1,282 @@
+//! Shared request rate limiter and per-request delays
+
+use rand::Rng;
+use reqwest::header::RETRY_AFTER;
+use reqwest::{RequestBuilder, Response};
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Times one request is sent again after being told to slow down
+const RATE_LIMIT_RETRIES: u32 = 3;
+
+/// Pause after a 429 that doesn't say how long to wait
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(5);
+
+/// Longest pause taken for a Retry-After header
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// Pause before each request: a fixed delay plus, with jitter, a random
+/// extra drawn per request so the timing has no fixed rhythm
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RequestDelay {
+    fixed: Duration,
+    /// Inclusive bounds of the random extra, in milliseconds
+    jitter: Option<(u64, u64)>,
+}
+
+impl RequestDelay {
+    pub fn new(delay_ms: Option<u64>, jitter_ms: Option<(u64, u64)>) -> Self {
+        Self {
+            fixed: Duration::from_millis(delay_ms.unwrap_or(0)),
+            jitter: jitter_ms,
+        }
+    }
+
+    /// Length of the next pause
+    pub fn sample(&self) -> Duration {
+        match self.jitter {
+            Some((min, max)) => {
+                self.fixed + Duration::from_millis(rand::thread_rng().gen_range(min..=max))
+            }
+            None => self.fixed,
+        }
+    }
+
+    /// Sleep for one pause
+    pub async fn wait(&self) {
+        let pause = self.sample();
+        if !pause.is_zero() {
+            tokio::time::sleep(pause).await;
+        }
+    }
+}
+
+/// Rate limiter spacing requests evenly across all workers.
+///
+/// Each call to `acquire` reserves the next free slot, so the aggregate rate
+/// stays at or below the limit no matter how many tasks share the limiter.
+pub struct RateLimiter {
+    interval: Duration,
+    next_slot: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(per_second: u32) -> Self {
+        Self {
+            interval: Duration::from_secs(1) / per_second.max(1),
+            next_slot: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Wait until the caller may send its next request
+    pub async fn acquire(&self) {
+        let wait = {
+            let mut next_slot = self.next_slot.lock().unwrap();
+            let now = Instant::now();
+            let slot = (*next_slot).max(now);
+            *next_slot = slot + self.interval;
+            slot - now
+        };
+
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// Pause shared by all workers, set when the target asks to slow down.
+/// Requests wait until it has passed before they are sent.
+#[derive(Default)]
+pub struct PauseGate {
+    until: Mutex<Option<Instant>>,
+}
+
+impl PauseGate {
+    /// Hold requests for `duration` from now, unless already held longer
+    pub fn pause(&self, duration: Duration) {
+        let until = Instant::now() + duration;
+        let mut current = self.until.lock().unwrap();
+        if current.is_none_or(|current| current < until) {
+            *current = Some(until);
+        }
+    }
+
+    /// Wait until no pause is in effect
+    pub async fn wait(&self) {
+        loop {
+            let until = *self.until.lock().unwrap();
+            match until {
+                Some(until) if until > Instant::now() => {
+                    tokio::time::sleep_until(until.into()).await;
+                }
+                _ => return,
+            }
+        }
+    }
+}
+
+/// Holds every request of a scan while the target asks clients to slow
+/// down. After a 429, or a 503 with Retry-After, all requests wait out the
+/// delay and the limited one is sent again, up to `RATE_LIMIT_RETRIES` times
+/// before its response is returned as is.
+#[derive(Default)]
+pub struct RetryAfterGate {
+    pause: PauseGate,
+    /// Return every response as is (--ignore-retry-after)
+    ignore: bool,
+    /// Responses that asked to slow down
+    rate_limited: AtomicUsize,
+}
+
+impl RetryAfterGate {
+    pub fn new(honour: bool) -> Self {
+        Self {
+            ignore: !honour,
+            ..Default::default()
+        }
+    }
+
+    /// Number of 429 and 503 Retry-After responses seen
+    pub fn rate_limited(&self) -> usize {
+        self.rate_limited.load(Ordering::Relaxed)
+    }
+
+    /// Wait until no pause is in effect
+    pub async fn wait(&self) {
+        self.pause.wait().await;
+    }
+
+    /// Whether the response to `attempt` (counted from 0) of a request asks
+    /// to slow down and the request should be sent again; if so, every
+    /// request is held for the delay it asks
+    pub fn hold(&self, status: u16, retry_after: Option<&str>, attempt: u32) -> bool {
+        if self.ignore || attempt >= RATE_LIMIT_RETRIES {
+            return false;
+        }
+        match retry_after_delay(status, retry_after) {
+            Some(delay) => {
+                self.rate_limited.fetch_add(1, Ordering::Relaxed);
+                self.pause.pause(delay);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Send a request through `send`, waiting out pauses first. `send`
+    /// returns the response with the time its request was sent; any pause
+    /// comes before that.
+    pub async fn send_with<F, Fut>(
+        &self,
+        mut request: RequestBuilder,
+        mut send: F,
+    ) -> reqwest::Result<(Response, Instant)>
+    where
+        F: FnMut(RequestBuilder) -> Fut,
+        Fut: Future<Output = reqwest::Result<(Response, Instant)>>,
+    {
+        let mut attempt = 0;
+        loop {
+            self.wait().await;
+            // Streaming bodies can't be cloned; those aren't sent again
+            let again = (!self.ignore && attempt < RATE_LIMIT_RETRIES)
+                .then(|| request.try_clone())
+                .flatten();
+            let (response, started) = send(request).await?;
+            let retry_after = response
+                .headers()
+                .get(RETRY_AFTER)
+                .and_then(|v| v.to_str().ok());
+            match again {
+                Some(again) if self.hold(response.status().as_u16(), retry_after, attempt) => {
+                    attempt += 1;
+                    request = again;
+                }
+                _ => return Ok((response, started)),
+            }
+        }
+    }
+
+    /// Send a request as is, waiting out pauses first
+    pub async fn send(&self, request: RequestBuilder) -> reqwest::Result<Response> {
+        let (response, _) = self
+            .send_with(request, |request| async move {
+                let started = Instant::now();
+                Ok((request.send().await?, started))
+            })
+            .await?;
+        Ok(response)
+    }
+}
+
+/// How long a 429 response, or a 503 with Retry-After, asks clients to
+/// wait. Retry-After is read in seconds, capped at `MAX_RETRY_AFTER`; a
+/// 429 without one (or with an HTTP date) waits `DEFAULT_RETRY_AFTER`.
+fn retry_after_delay(status: u16, retry_after: Option<&str>) -> Option<Duration> {
+    let delay = match (status, retry_after) {
+        (429, None) => return Some(DEFAULT_RETRY_AFTER),
+        (429 | 503, Some(value)) => value
+            .trim()
+            .parse()
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_RETRY_AFTER),
+        _ => return None,
+    };
+    Some(delay.min(MAX_RETRY_AFTER))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_after_delay_reads_seconds() {
+        assert_eq!(
+            retry_after_delay(429, Some("1")),
+            Some(Duration::from_secs(1))
+        );
+        assert_eq!(
+            retry_after_delay(503, Some(" 7 ")),
+            Some(Duration::from_secs(7))
+        );
+        assert_eq!(retry_after_delay(429, None), Some(DEFAULT_RETRY_AFTER));
+        assert_eq!(
+            retry_after_delay(429, Some("Wed, 21 Oct 2015 07:28:00 GMT")),
+            Some(DEFAULT_RETRY_AFTER)
+        );
+        assert_eq!(retry_after_delay(429, Some("3600")), Some(MAX_RETRY_AFTER));
+        assert_eq!(retry_after_delay(503, None), None);
+        assert_eq!(retry_after_delay(200, Some("1")), None);
+    }
+
+    #[test]
+    fn gate_gives_up_after_retries() {
+        let gate = RetryAfterGate::new(true);
+        assert!(gate.hold(429, Some("0"), 0));
+        assert!(!gate.hold(429, Some("0"), RATE_LIMIT_RETRIES));
+        assert!(!RetryAfterGate::new(false).hold(429, Some("0"), 0));
+        assert_eq!(gate.rate_limited(), 1);
+    }
+
+    #[test]
+    fn jitter_samples_stay_within_bounds() {
+        let jitter = RequestDelay::new(None, Some((100, 250)));
+        let samples: Vec<Duration> = (0..500).map(|_| jitter.sample()).collect();
+        assert!(samples.iter().all(|d| (100..=250).contains(&d.as_millis())));
+        assert!(samples.iter().any(|d| *d != samples[0]), "{:?}", samples);
+
+        // Jitter adds to a fixed delay
+        let both = RequestDelay::new(Some(1000), Some((0, 50)));
+        assert!((0..200).all(|_| (1000..=1050).contains(&both.sample().as_millis())));
+
+        let fixed = RequestDelay::new(Some(40), None);
+        assert_eq!(fixed.sample(), Duration::from_millis(40));
+        assert_eq!(RequestDelay::default().sample(), Duration::ZERO);
+        assert_eq!(
+            RequestDelay::new(None, Some((7, 7))).sample(),
+            Duration::from_millis(7)
+        );
+    }
+}
@@ -0,0 +1,442 @@
+//! Raw HTTP requests over TCP or TLS, for requests reqwest refuses to send
+//! (bad methods, duplicate or missing headers, odd HTTP versions)
+
+use crate::core::{CorsGrant, RetryAfterGate, UrlCheck};
+use crate::error::{RbusterError, Result};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_rustls::rustls::client::danger::{
+    HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier,
+};
+use tokio_rustls::rustls::crypto::{ring, CryptoProvider};
+use tokio_rustls::rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use tokio_rustls::rustls::{ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme};
+use tokio_rustls::TlsConnector;
+
+/// Most response bytes read for one request
+const MAX_RESPONSE: usize = 10 * 1024 * 1024;
+
+/// Load a raw request template, turning bare `\n` line endings into `\r\n`
+/// so templates can be written in any editor
+pub async fn load_raw_template(path: &std::path::Path) -> Result<String> {
+    let template = tokio::fs::read_to_string(path).await.map_err(|e| {
+        RbusterError::ConfigError(format!(
+            "Cannot read raw request '{}': {}",
+            path.display(),
+            e
+        ))
+    })?;
+    Ok(template.replace("\r\n", "\n").replace('\n', "\r\n"))
+}
+
+/// Sends raw request bytes to one target and parses just enough of the
+/// response for filtering: status, Location and body length
+pub struct RawHttpClient {
+    host: String,
+    port: u16,
+    tls: Option<(TlsConnector, ServerName<'static>)>,
+    timeout: Duration,
+    /// Holds every request while the target asks to slow down
+    gate: RetryAfterGate,
+}
+
+impl RawHttpClient {
+    /// Client for the scheme, host and port of `url`, honouring Retry-After
+    /// like `HttpClient` when `retry_after` is set
+    pub fn new(url: &str, insecure: bool, timeout: Duration, retry_after: bool) -> Result<Self> {
+        let parsed = url::Url::parse(url)
+            .map_err(|e| RbusterError::ConfigError(format!("Invalid URL '{}': {}", url, e)))?;
+        let host = parsed
+            .host_str()
+            .ok_or_else(|| RbusterError::ConfigError(format!("URL '{}' has no host", url)))?
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_string();
+        let port = parsed.port_or_known_default().unwrap_or(80);
+
+        let tls = match parsed.scheme() {
+            "http" => None,
+            "https" => {
+                let server_name = ServerName::try_from(host.clone()).map_err(|e| {
+                    RbusterError::ConfigError(format!("Invalid TLS server name '{}': {}", host, e))
+                })?;
+                Some((
+                    TlsConnector::from(Arc::new(tls_config(insecure)?)),
+                    server_name,
+                ))
+            }
+            scheme => {
+                return Err(RbusterError::ConfigError(format!(
+                    "Unsupported scheme '{}' for raw requests: expected http or https",
+                    scheme
+                )))
+            }
+        };
+
+        Ok(Self {
+            host,
+            port,
+            tls,
+            timeout,
+            gate: RetryAfterGate::new(retry_after),
+        })
+    }
+
+    /// Number of 429 and 503 Retry-After responses seen
+    pub fn rate_limited(&self) -> usize {
+        self.gate.rate_limited()
+    }
+
+    /// Send `request` as is, apart from a `Connection: close` header when it
+    /// has no Connection header, and read the response. The body is returned
+    /// only when `read_body` is set.
+    pub async fn send(&self, request: &[u8], read_body: bool) -> Result<UrlCheck> {
+        let request = with_connection_close(request);
+        let request = request.as_ref();
+        let mut attempt = 0;
+        let (response, elapsed) = loop {
+            self.gate.wait().await;
+            let started = Instant::now();
+            let deadline = tokio::time::Instant::from_std(started + self.timeout);
+            let raw = self
+                .exchange(request, deadline)
+                .await
+                .map_err(|e| RbusterError::RawHttpError(e.to_string()))?;
+            let elapsed = started.elapsed();
+
+            let response = parse_response(&raw).ok_or_else(|| {
+                RbusterError::RawHttpError("response has no HTTP status line".to_string())
+            })?;
+            let retry_after = response
+                .headers
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case("retry-after"))
+                .map(|(_, value)| value.as_str());
+            if !self.gate.hold(response.status, retry_after, attempt) {
+                break (response, elapsed);
+            }
+            attempt += 1;
+        };
+        let content_type = response
+            .headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+            .map(|(_, value)| value.clone());
+        let cors = CorsGrant::from_headers(
+            response
+                .headers
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_str())),
+        );
+        Ok(UrlCheck {
+            status: response.status,
+            size: response.body.len(),
+            redirect: response.location,
+            body: read_body.then_some(response.body),
+            elapsed,
+            headers: response.headers,
+            content_type,
+            cors,
+        })
+    }
+
+    async fn exchange(
+        &self,
+        request: &[u8],
+        deadline: tokio::time::Instant,
+    ) -> std::io::Result<Vec<u8>> {
+        let tcp = before(
+            deadline,
+            TcpStream::connect((self.host.as_str(), self.port)),
+        )
+        .await?;
+        tcp.set_nodelay(true)?;
+        match &self.tls {
+            Some((connector, server_name)) => {
+                let stream = before(deadline, connector.connect(server_name.clone(), tcp)).await?;
+                write_and_read(stream, request, deadline).await
+            }
+            None => write_and_read(tcp, request, deadline).await,
+        }
+    }
+}
+
+/// Run an I/O future, failing with `TimedOut` at `deadline`
+async fn before<T>(
+    deadline: tokio::time::Instant,
+    io: impl std::future::Future<Output = std::io::Result<T>>,
+) -> std::io::Result<T> {
+    tokio::time::timeout_at(deadline, io)
+        .await
+        .unwrap_or_else(|_| Err(std::io::ErrorKind::TimedOut.into()))
+}
+
+/// Write the request, then read until the response is complete, the server
+/// closes the connection or the deadline passes. Responses without a length
+/// on kept-alive connections are returned as read so far at the deadline;
+/// `with_connection_close` makes servers close instead.
+async fn write_and_read<S: AsyncRead + AsyncWrite + Unpin>(
+    mut stream: S,
+    request: &[u8],
+    deadline: tokio::time::Instant,
+) -> std::io::Result<Vec<u8>> {
+    before(deadline, async {
+        stream.write_all(request).await?;
+        stream.flush().await
+    })
+    .await?;
+
+    let head_request = request.starts_with(b"HEAD ");
+    let mut response = Vec::new();
+    let mut buf = [0u8; 8192];
+    loop {
+        let n = match before(deadline, stream.read(&mut buf)).await {
+            Ok(n) => n,
+            // TLS servers often close without close_notify
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => 0,
+            Err(e) if e.kind() == std::io::ErrorKind::TimedOut && !response.is_empty() => 0,
+            Err(e) => return Err(e),
+        };
+        if n == 0 {
+            break;
+        }
+        response.extend_from_slice(&buf[..n]);
+        if response.len() >= MAX_RESPONSE || is_complete(&response, head_request) {
+            break;
+        }
+    }
+    Ok(response)
+}
+
+/// Parts of a raw response used for filtering
+struct RawResponse {
+    status: u16,
+    location: Option<String>,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+/// Split a response into head and body and read the status and Location
+fn parse_response(raw: &[u8]) -> Option<RawResponse> {
+    let (head, body) = split_head(raw)?;
+    let head = String::from_utf8_lossy(head);
+    let mut lines = head.split("\r\n");
+
+    // "HTTP/1.1 200 OK"; the version is not checked
+    let status = lines.next()?.split_whitespace().nth(1)?.parse().ok()?;
+    let mut location = None;
+    let mut chunked = false;
+    let mut headers = Vec::new();
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let (name, value) = (name.trim(), value.trim());
+        if name.eq_ignore_ascii_case("location") {
+            location = Some(value.to_string());
+        } else if name.eq_ignore_ascii_case("transfer-encoding") {
+            chunked = value.eq_ignore_ascii_case("chunked");
+        }
+        headers.push((name.to_string(), value.to_string()));
+    }
+
+    Some(RawResponse {
+        status,
+        location,
+        headers,
+        body: if chunked {
+            dechunk(body)
+        } else {
+            body.to_vec()
+        },
+    })
+}
+
+/// Head and body of a response, split at the first empty line
+fn split_head(raw: &[u8]) -> Option<(&[u8], &[u8])> {
+    let end = raw.windows(4).position(|w| w == b"\r\n\r\n")?;
+    Some((&raw[..end], &raw[end + 4..]))
+}
+
+/// Whether a response has all the bytes its headers announce, so reading
+/// can stop without waiting for a keep-alive connection to close. Responses
+/// to HEAD and 1xx, 204 and 304 responses end with the head, whatever their
+/// Content-Length says.
+fn is_complete(raw: &[u8], head_request: bool) -> bool {
+    let Some((head, body)) = split_head(raw) else {
+        return false;
+    };
+    let head = String::from_utf8_lossy(head).to_ascii_lowercase();
+    let status = head.split_whitespace().nth(1).unwrap_or_default();
+    if head_request || status.starts_with('1') || status == "204" || status == "304" {
+        return true;
+    }
+    let header = |name: &str| {
+        head.split("\r\n")
+            .skip(1)
+            .filter_map(|line| line.split_once(':'))
+            .find(|(n, _)| n.trim() == name)
+            .map(|(_, v)| v.trim().to_string())
+    };
+
+    if header("transfer-encoding").is_some_and(|v| v == "chunked") {
+        return body.ends_with(b"0\r\n\r\n");
+    }
+    header("content-length")
+        .and_then(|v| v.parse::<usize>().ok())
+        .is_some_and(|length| body.len() >= length)
+}
+
+/// The request with `Connection: close` added to its head unless it has a
+/// Connection header, so a response without a length ends when the server
+/// closes the connection instead of at the timeout
+fn with_connection_close(request: &[u8]) -> std::borrow::Cow<'_, [u8]> {
+    let Some(end) = request.windows(4).position(|w| w == b"\r\n\r\n") else {
+        return request.into();
+    };
+    let has_connection = request[..end]
+        .split(|&b| b == b'\n')
+        .skip(1)
+        .any(|line| line.len() > 11 && line[..11].eq_ignore_ascii_case(b"connection:"));
+    if has_connection {
+        return request.into();
+    }
+    let mut closed = request[..end].to_vec();
+    closed.extend_from_slice(b"\r\nConnection: close");
+    closed.extend_from_slice(&request[end..]);
+    closed.into()
+}
+
+/// Data of a chunked body. Malformed framing ends the body early.
+fn dechunk(mut body: &[u8]) -> Vec<u8> {
+    let mut data = Vec::new();
+    while let Some(line_end) = body.windows(2).position(|w| w == b"\r\n") {
+        let size_line = String::from_utf8_lossy(&body[..line_end]);
+        // Chunk extensions follow a ';'
+        let size = size_line.split(';').next().unwrap_or_default().trim();
+        let Ok(size) = usize::from_str_radix(size, 16) else {
+            break;
+        };
+        let start = line_end + 2;
+        if size == 0 || start + size > body.len() {
+            data.extend_from_slice(&body[start.min(body.len())..(start + size).min(body.len())]);
+            break;
+        }
+        data.extend_from_slice(&body[start..start + size]);
+        body = body.get(start + size + 2..).unwrap_or_default();
+    }
+    data
+}
+
+/// TLS settings: native roots, or no verification at all with -k
+fn tls_config(insecure: bool) -> Result<ClientConfig> {
+    let provider = Arc::new(ring::default_provider());
+    let builder = ClientConfig::builder_with_provider(Arc::clone(&provider))
+        .with_safe_default_protocol_versions()
+        .map_err(|e| RbusterError::ConfigError(format!("TLS setup failed: {}", e)))?;
+
+    let config = if insecure {
+        builder
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(AcceptAnyCert(provider)))
+            .with_no_client_auth()
+    } else {
+        let mut roots = RootCertStore::empty();
+        roots.add_parsable_certificates(rustls_native_certs::load_native_certs().certs);
+        builder.with_root_certificates(roots).with_no_client_auth()
+    };
+    Ok(config)
+}
+
+/// Certificate verifier for -k that accepts every certificate
+#[derive(Debug)]
+struct AcceptAnyCert(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for AcceptAnyCert {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> std::result::Result<ServerCertVerified, tokio_rustls::rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        _message: &[u8],
+        _cert: &CertificateDer<'_>,
+        _dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, tokio_rustls::rustls::Error> {
+        Ok(HandshakeSignatureValid::assertion())
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        _message: &[u8],
+        _cert: &CertificateDer<'_>,
+        _dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, tokio_rustls::rustls::Error> {
+        Ok(HandshakeSignatureValid::assertion())
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn bodiless_responses_end_with_the_head() {
+        let not_modified = b"HTTP/1.1 304 Not Modified\r\nContent-Length: 512\r\n\r\n";
+        assert!(is_complete(not_modified, false));
+        let head = b"HTTP/1.1 200 OK\r\nContent-Length: 512\r\n\r\n";
+        assert!(is_complete(head, true));
+        assert!(!is_complete(head, false));
+    }
+
+    #[test]
+    fn connection_close_is_added_once() {
+        let request = b"GET / HTTP/1.1\r\nHost: t\r\n\r\n";
+        assert_eq!(
+            with_connection_close(request).as_ref(),
+            b"GET / HTTP/1.1\r\nHost: t\r\nConnection: close\r\n\r\n"
+        );
+        let keep = b"GET / HTTP/1.1\r\nconnection: keep-alive\r\n\r\n";
+        assert_eq!(with_connection_close(keep).as_ref(), keep);
+    }
+
+    #[tokio::test]
+    async fn head_response_returns_without_waiting_for_the_body() {
+        // The server announces a body for HEAD and keeps the connection open
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 1024];
+            let _ = stream.read(&mut buf).await;
+            let _ = stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 512\r\n\r\n")
+                .await;
+            tokio::time::sleep(Duration::from_secs(10)).await;
+        });
+        let client = RawHttpClient::new(&url, false, Duration::from_secs(5), false).unwrap();
+
+        let started = Instant::now();
+        let check = client
+            .send(b"HEAD / HTTP/1.1\r\nHost: t\r\n\r\n", false)
+            .await
+            .unwrap();
+
+        assert_eq!(check.status, 200);
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+}
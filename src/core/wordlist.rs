@@ -1,22 +1,179 @@
 //! Wordlist loader with streaming support for memory efficiency
 
-use std::path::Path;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use tokio::fs::File;
-use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, BufReader};
 
-/// Load entire wordlist into memory
+/// Path that reads the wordlist from standard input
+pub const STDIN_PATH: &str = "-";
+
+/// How wordlist lines are cleaned up
+#[derive(Debug, Clone, Copy)]
+pub struct WordlistOptions {
+    /// Drop repeated entries, keeping the first occurrence
+    pub dedupe: bool,
+    /// Keep lines starting with `#` instead of treating them as comments
+    pub keep_comments: bool,
+}
+
+impl Default for WordlistOptions {
+    fn default() -> Self {
+        Self {
+            dedupe: true,
+            keep_comments: false,
+        }
+    }
+}
+
+/// Whether `path` is the standard input sentinel
+pub fn reads_stdin(path: &Path) -> bool {
+    path.as_os_str() == STDIN_PATH
+}
+
+/// Load entire wordlist into memory. A path of `-` reads standard input.
 pub async fn load_wordlist(path: &Path) -> std::io::Result<Vec<String>> {
+    load_wordlist_with(path, WordlistOptions::default()).await
+}
+
+/// Load a wordlist with explicit cleanup options
+pub async fn load_wordlist_with(
+    path: &Path,
+    options: WordlistOptions,
+) -> std::io::Result<Vec<String>> {
+    let mut entries = Vec::new();
+    read_file(path, options, &mut entries, &mut HashSet::new()).await?;
+    Ok(entries)
+}
+
+/// Load several wordlists as one, in order. Duplicates are dropped across
+/// files too, keeping the first occurrence.
+pub async fn load_wordlists_with(
+    paths: &[PathBuf],
+    options: WordlistOptions,
+) -> std::io::Result<Vec<String>> {
+    load_wordlists_from(paths, options, tokio::io::stdin()).await
+}
+
+/// Load several wordlists as one, reading `-` from `stdin`
+async fn load_wordlists_from<S: AsyncRead + Unpin>(
+    paths: &[PathBuf],
+    options: WordlistOptions,
+    stdin: S,
+) -> std::io::Result<Vec<String>> {
+    let mut entries = Vec::new();
+    let mut seen = HashSet::new();
+    let mut stdin = Some(stdin);
+    for path in paths {
+        if reads_stdin(path) {
+            // Standard input can only be read once
+            if let Some(stdin) = stdin.take() {
+                read_entries(BufReader::new(stdin), options, &mut entries, &mut seen).await?;
+            }
+        } else {
+            read_file(path, options, &mut entries, &mut seen).await?;
+        }
+    }
+    Ok(entries)
+}
+
+/// Append the entries of one file (or stdin)
+async fn read_file(
+    path: &Path,
+    options: WordlistOptions,
+    entries: &mut Vec<String>,
+    seen: &mut HashSet<String>,
+) -> std::io::Result<()> {
+    if reads_stdin(path) {
+        return read_entries(BufReader::new(tokio::io::stdin()), options, entries, seen).await;
+    }
+
     let file = File::open(path).await?;
-    let reader = BufReader::new(file);
+    read_entries(BufReader::new(file), options, entries, seen).await
+}
+
+/// Read trimmed lines, skipping blanks and (unless kept) comments and
+/// entries already in `seen`
+async fn read_entries<R: AsyncBufRead + Unpin>(
+    reader: R,
+    options: WordlistOptions,
+    entries: &mut Vec<String>,
+    seen: &mut HashSet<String>,
+) -> std::io::Result<()> {
     let mut lines = reader.lines();
-    let mut entries = Vec::new();
 
     while let Some(line) = lines.next_line().await? {
         let trimmed = line.trim();
-        if !trimmed.is_empty() && !trimmed.starts_with('#') {
-            entries.push(trimmed.to_string());
+        if trimmed.is_empty() || (!options.keep_comments && trimmed.starts_with('#')) {
+            continue;
+        }
+        if options.dedupe && !seen.insert(trimmed.to_string()) {
+            continue;
         }
+        entries.push(trimmed.to_string());
     }
 
-    Ok(entries)
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn read(text: &str, options: WordlistOptions) -> Vec<String> {
+        let mut entries = Vec::new();
+        read_entries(text.as_bytes(), options, &mut entries, &mut HashSet::new())
+            .await
+            .unwrap();
+        entries
+    }
+
+    #[tokio::test]
+    async fn lines_are_trimmed_and_cleaned() {
+        let text = "# comment\n  admin \n\n\t\nlogin\nadmin\n#backup\n";
+        assert_eq!(
+            read(text, WordlistOptions::default()).await,
+            ["admin", "login"]
+        );
+
+        let keep = WordlistOptions {
+            dedupe: false,
+            keep_comments: true,
+        };
+        assert_eq!(
+            read(text, keep).await,
+            ["# comment", "admin", "login", "admin", "#backup"]
+        );
+    }
+
+    #[tokio::test]
+    async fn wordlists_merge_in_first_seen_order() {
+        let dir = std::env::temp_dir().join(format!("rbuster-{}", crate::core::random_label(12)));
+        std::fs::create_dir_all(&dir).unwrap();
+        let first = dir.join("first.txt");
+        let second = dir.join("second.txt");
+        std::fs::write(&first, "admin\nlogin\nbackup\n").unwrap();
+        std::fs::write(&second, "login\nconfig\nadmin\nuploads\n").unwrap();
+
+        let merged = load_wordlists_with(&[first, second], WordlistOptions::default()).await;
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            merged.unwrap(),
+            ["admin", "login", "backup", "config", "uploads"]
+        );
+    }
+
+    #[tokio::test]
+    async fn dash_reads_the_wordlist_from_stdin() {
+        let path = std::env::temp_dir().join(format!("rbuster-{}", crate::core::random_label(12)));
+        std::fs::write(&path, "backup\nadmin\n").unwrap();
+        let stdin = "# generated\nadmin\nlogin\n".as_bytes();
+
+        let paths = [PathBuf::from(STDIN_PATH), path.clone(), PathBuf::from("-")];
+        let merged = load_wordlists_from(&paths, WordlistOptions::default(), stdin).await;
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(merged.unwrap(), ["admin", "login", "backup"]);
+    }
 }
@@ -0,0 +1,64 @@
+//! Text extraction from XML documents such as bucket listings
+
+use quick_xml::events::Event;
+use quick_xml::Reader;
+
+/// Unescaped text of every `<tag>` element, in document order. Formatting
+/// and namespaces don't matter; parsing stops at the first malformed part.
+pub fn element_texts(xml: &str, tag: &str) -> Vec<String> {
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+
+    let mut texts = Vec::new();
+    let mut current: Option<String> = None;
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) if e.local_name().as_ref() == tag.as_bytes() => {
+                current = Some(String::new());
+            }
+            Ok(Event::Text(t)) => {
+                if let (Some(text), Ok(unescaped)) = (current.as_mut(), t.unescape()) {
+                    text.push_str(&unescaped);
+                }
+            }
+            Ok(Event::CData(c)) => {
+                if let Some(text) = current.as_mut() {
+                    text.push_str(&String::from_utf8_lossy(&c));
+                }
+            }
+            Ok(Event::End(e)) if e.local_name().as_ref() == tag.as_bytes() => {
+                texts.extend(current.take());
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+    }
+    texts
+}
+
+/// Unescaped text of the first `<tag>` element
+pub fn element_text(xml: &str, tag: &str) -> Option<String> {
+    element_texts(xml, tag).into_iter().next()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_are_read_from_compact_xml() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?><ListBucketResult xmlns="http://s3.amazonaws.com/doc/2006-03-01/"><Name>files</Name><Contents><Key>a.txt</Key><Size>1</Size></Contents><Contents><Key>dir/b&amp;c.txt</Key></Contents><Contents><Key><![CDATA[d<e>.txt]]></Key></Contents></ListBucketResult>"#;
+        assert_eq!(
+            element_texts(xml, "Key"),
+            ["a.txt", "dir/b&c.txt", "d<e>.txt"]
+        );
+        assert_eq!(element_text(xml, "Name").as_deref(), Some("files"));
+        assert_eq!(element_text(xml, "Marker"), None);
+    }
+
+    #[test]
+    fn pretty_printed_and_prefixed_xml_read_alike() {
+        let xml = "<s3:Result xmlns:s3=\"urn:x\">\n  <s3:Key>\n    a.txt\n  </s3:Key>\n  <s3:Key>b.txt</s3:Key>\n</s3:Result>";
+        assert_eq!(element_texts(xml, "Key"), ["a.txt", "b.txt"]);
+    }
+}
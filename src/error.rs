@@ -16,6 +16,9 @@ pub enum RbusterError {
 
     #[error("Configuration error: {0}")]
     ConfigError(String),
+
+    #[error("Raw HTTP error: {0}")]
+    RawHttpError(String),
 }
 
 pub type Result<T> = std::result::Result<T, RbusterError>;
@@ -10,7 +10,7 @@
 
 use clap::Parser;
 use colored::Colorize;
-use std::time::Instant;
+use std::io::IsTerminal;
 
 mod cli;
 mod core;
@@ -18,7 +18,7 @@ mod error;
 mod modes;
 mod output;
 
-use cli::{print_banner, print_config, print_finished, Cli, Commands};
+use cli::{print_banner, print_config, Cli, Commands};
 
 #[tokio::main]
 async fn main() {
@@ -34,28 +34,45 @@ async fn main() {
     }
 }
 
+/// Whether to color output: not with --no-color or a non-empty NO_COLOR,
+/// and not when stdout is not a terminal unless CLICOLOR_FORCE asks for it
+fn use_color(no_color_flag: bool, no_color_env: bool, clicolor_force: bool, is_tty: bool) -> bool {
+    !no_color_flag && !no_color_env && (is_tty || clicolor_force)
+}
+
 async fn run(cli: Cli) -> error::Result<()> {
-    let start = Instant::now();
+    let global = match &cli.command {
+        Commands::Dir(args) => &args.global,
+        Commands::Dns(args) => &args.global,
+        Commands::Vhost(args) => &args.global,
+        Commands::Fuzz(args) => &args.global,
+        Commands::S3(args) => &args.global,
+        Commands::Gcs(args) => &args.global,
+        Commands::Tftp(args) => &args.global,
+        Commands::Tcp(args) => &args.global,
+    };
 
     // Determine if we should show the banner
-    let quiet = match &cli.command {
-        Commands::Dir(args) => args.global.quiet,
-        Commands::Dns(args) => args.global.quiet,
-        Commands::Vhost(args) => args.global.quiet,
-        Commands::Fuzz(args) => args.global.quiet,
-        Commands::S3(args) => args.global.quiet,
-        Commands::Gcs(args) => args.global.quiet,
-        Commands::Tftp(args) => args.global.quiet,
-    };
+    let quiet = global.quiet;
+
+    // Disable colors in results, messages and the progress bar
+    let env_set = |name: &str| std::env::var_os(name).is_some_and(|v| !v.is_empty());
+    if !use_color(
+        global.no_color,
+        env_set("NO_COLOR"),
+        env_set("CLICOLOR_FORCE"),
+        std::io::stdout().is_terminal(),
+    ) {
+        colored::control::set_override(false);
+        console::set_colors_enabled(false);
+        console::set_colors_enabled_stderr(false);
+    }
 
     // Print banner
     if !quiet {
         print_banner();
     }
 
-    // Get found count for final stats
-    let found_count = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
-
     // Execute the command
     match cli.command {
         Commands::Dir(args) => {
@@ -63,11 +80,29 @@ async fn run(cli: Cli) -> error::Result<()> {
                 print_config(
                     "directory enumeration",
                     &[
-                        ("Url", args.url.clone()),
+                        (
+                            "Url",
+                            match (&args.url, &args.url_file) {
+                                (_, Some(file)) => format!("targets from {}", file.display()),
+                                (Some(url), None) => url.clone(),
+                                (None, None) => String::new(),
+                            },
+                        ),
                         ("Method", args.http.method.clone()),
                         ("Threads", args.global.threads.to_string()),
-                        ("Wordlist", args.global.wordlist.display().to_string()),
-                        ("User Agent", args.http.user_agent.clone()),
+                        ("Wordlist", args.global.wordlist_display()),
+                        (
+                            "User Agent",
+                            match args.http.user_agent {
+                                Some(ref agent) => agent.clone(),
+                                None if args.http.random_agent
+                                    || args.http.user_agents_file.is_some() =>
+                                {
+                                    "random".to_string()
+                                }
+                                None => "robuster/1.0".to_string(),
+                            },
+                        ),
                         ("Timeout", format!("{}s", args.http.timeout)),
                     ],
                 );
@@ -81,7 +116,7 @@ async fn run(cli: Cli) -> error::Result<()> {
                     &[
                         ("Domain", args.domain.clone()),
                         ("Threads", args.global.threads.to_string()),
-                        ("Wordlist", args.global.wordlist.display().to_string()),
+                        ("Wordlist", args.global.wordlist_display()),
                         (
                             "Resolver",
                             args.resolver
@@ -101,7 +136,7 @@ async fn run(cli: Cli) -> error::Result<()> {
                     &[
                         ("Url", args.url.clone()),
                         ("Threads", args.global.threads.to_string()),
-                        ("Wordlist", args.global.wordlist.display().to_string()),
+                        ("Wordlist", args.global.wordlist_display()),
                         ("Append Domain", args.append_domain.to_string()),
                     ],
                 );
@@ -116,7 +151,7 @@ async fn run(cli: Cli) -> error::Result<()> {
                         ("Url", args.url.clone()),
                         ("Method", args.http.method.clone()),
                         ("Threads", args.global.threads.to_string()),
-                        ("Wordlist", args.global.wordlist.display().to_string()),
+                        ("Wordlist", args.global.wordlist_display()),
                     ],
                 );
             }
@@ -128,7 +163,7 @@ async fn run(cli: Cli) -> error::Result<()> {
                     "S3 bucket enumeration",
                     &[
                         ("Threads", args.global.threads.to_string()),
-                        ("Wordlist", args.global.wordlist.display().to_string()),
+                        ("Wordlist", args.global.wordlist_display()),
                         ("Max Files", args.max_files.to_string()),
                     ],
                 );
@@ -141,7 +176,7 @@ async fn run(cli: Cli) -> error::Result<()> {
                     "GCS bucket enumeration",
                     &[
                         ("Threads", args.global.threads.to_string()),
-                        ("Wordlist", args.global.wordlist.display().to_string()),
+                        ("Wordlist", args.global.wordlist_display()),
                         ("Max Files", args.max_files.to_string()),
                     ],
                 );
@@ -155,21 +190,54 @@ async fn run(cli: Cli) -> error::Result<()> {
                     &[
                         ("Server", args.server.clone()),
                         ("Threads", args.global.threads.to_string()),
-                        ("Wordlist", args.global.wordlist.display().to_string()),
+                        ("Wordlist", args.global.wordlist_display()),
                         ("Timeout", format!("{}s", args.timeout)),
                     ],
                 );
             }
             modes::tftp::run(args).await?;
         }
-    }
-
-    // Print completion stats
-    let duration = start.elapsed();
-    let found = found_count.load(std::sync::atomic::Ordering::Relaxed);
-    if !quiet {
-        print_finished(found, 0, duration);
+        Commands::Tcp(args) => {
+            if !quiet {
+                let mut config = vec![
+                    (
+                        "Host",
+                        args.host
+                            .clone()
+                            .unwrap_or_else(|| "from wordlist".to_string()),
+                    ),
+                    ("Threads", args.global.threads.to_string()),
+                    ("Wordlist", args.global.wordlist_display()),
+                    ("Timeout", format!("{}s", args.timeout)),
+                ];
+                if let Some(ref send) = args.send {
+                    config.push(("Send", send.clone()));
+                }
+                print_config("TCP banner grabbing", &config);
+            }
+            modes::tcp::run(args).await?;
+        }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colors_only_on_a_terminal_unless_disabled() {
+        // Terminal, or piped with CLICOLOR_FORCE
+        assert!(use_color(false, false, false, true));
+        assert!(use_color(false, false, true, false));
+        assert!(!use_color(false, false, false, false));
+        // --no-color and NO_COLOR win over both
+        for is_tty in [false, true] {
+            for clicolor_force in [false, true] {
+                assert!(!use_color(true, false, clicolor_force, is_tty));
+                assert!(!use_color(false, true, clicolor_force, is_tty));
+            }
+        }
+    }
+}
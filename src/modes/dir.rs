@@ -1,197 +1,611 @@
 //! Directory/file enumeration mode
 
-use crate::cli::DirArgs;
-use crate::core::{load_wordlist, parse_headers, HttpClient, HttpConfig};
+use crate::cli::{BackupExtensionsMode, DirArgs, SizeMode};
+use crate::core::{
+    body_counts, load_raw_template, load_wordlist, parse_content_types, parse_header_matchers,
+    random_label, BodyFingerprinter, CalibrationSample, Checkpoint, ErrorLimit, HideReason,
+    HttpClient, HttpConfig, LengthMatcher, Mutator, RateLimiter, RawHttpClient, RequestBody,
+    ResponseFilter, UrlCheck,
+};
 use crate::error::Result;
 use crate::output::{
-    print_dir_result, print_error, print_warning, DirResult, OutputHandler, ProgressTracker,
+    print_calibration, print_concurrency, print_cors_finding, print_dir_result, print_error,
+    print_response, print_warning, Dashboard, DirResult, OutputHandler, ProgressTracker,
+    ResponseSaver, ScanMeta,
 };
 use futures::stream::{self, StreamExt};
-use std::sync::Arc;
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
-use tokio::sync::Semaphore;
+use tokio::sync::mpsc::UnboundedSender;
 
 /// Backup file extensions to check
 const BACKUP_EXTENSIONS: &[&str] = &[
     ".bak", ".backup", ".old", ".orig", ".save", "~", ".swp", ".tmp", ".copy",
 ];
 
+/// Number of random paths requested to detect wildcard responses
+const WILDCARD_PROBES: usize = 3;
+
+/// Random paths requested per calibration group
+const CALIBRATION_PROBES: usize = 4;
+
 /// Run directory enumeration
 pub async fn run(args: DirArgs) -> Result<()> {
+    run_with_results(args, None).await
+}
+
+/// Run directory enumeration, also sending each found result to `result_tx`.
+/// Results arrive in completion order, not wordlist order.
+pub async fn run_with_results(
+    args: DirArgs,
+    result_tx: Option<UnboundedSender<DirResult>>,
+) -> Result<()> {
+    run_tracked(args, result_tx).await.map(|_| ())
+}
+
+/// Run directory enumeration, returning the progress counters of the scan
+async fn run_tracked(
+    args: DirArgs,
+    result_tx: Option<UnboundedSender<DirResult>>,
+) -> Result<ProgressTracker> {
+    super::check_stdin_inputs(
+        &args.global.wordlist,
+        &[args.url_file.as_deref(), args.extensions_file.as_deref()],
+    )?;
+
     // Parse configuration
-    let extensions = args.parse_extensions();
-    let valid_status_codes: std::collections::HashSet<u16> =
-        args.parse_status_codes().into_iter().collect();
-    let blacklist_codes: std::collections::HashSet<u16> =
-        args.parse_status_codes_blacklist().into_iter().collect();
-    let exclude_lengths: std::collections::HashSet<usize> =
-        args.parse_exclude_lengths().into_iter().collect();
+    let mut extensions = args.parse_extensions();
+    if let Some(ref path) = args.extensions_file {
+        let from_file = load_wordlist(path).await.map_err(|e| {
+            crate::error::RbusterError::ConfigError(format!(
+                "Cannot read extensions file '{}': {}",
+                path.display(),
+                e
+            ))
+        })?;
+        extensions.extend(from_file);
+    }
+    let extensions = dedup_extensions(extensions);
+    let status_error = |flag: &str, e: String| {
+        crate::error::RbusterError::ConfigError(format!("Invalid {}: {}", flag, e))
+    };
+    let status_codes = args
+        .parse_status_codes()
+        .map_err(|e| status_error("--status-codes", e))?;
+    let valid_status_codes: HashSet<u16> = status_codes.default.iter().copied().collect();
+    // Extensions with their own status codes get their own filter; scope 0
+    // is the default for base paths and everything else. Auto-calibration
+    // derives a filter per extension, so there each extension is a scope.
+    let mut scopes: Vec<&[u16]> = vec![&status_codes.default];
+    let extension_scopes: Vec<usize> = extensions
+        .iter()
+        .map(|ext| {
+            let codes = status_codes.for_extension(ext);
+            let shared = (!args.autocalibrate)
+                .then(|| scopes.iter().position(|&scope| scope == codes))
+                .flatten();
+            match shared {
+                Some(scope) => scope,
+                None => {
+                    scopes.push(codes);
+                    scopes.len() - 1
+                }
+            }
+        })
+        .collect();
+    let filter = ResponseFilter {
+        match_status: Some(valid_status_codes.clone()),
+        exclude_status: args
+            .parse_status_codes_blacklist()
+            .map_err(|e| status_error("--status-codes-blacklist", e))?
+            .into_iter()
+            .collect(),
+        exclude_length: LengthMatcher::parse(args.exclude_length.as_deref())?,
+        match_regex: args.match_regex.as_deref().map(compile_regex).transpose()?,
+        exclude_regex: args
+            .filter_regex
+            .as_deref()
+            .map(compile_regex)
+            .transpose()?,
+        match_content_type: args.match_content_type.as_deref().map(parse_content_types),
+        exclude_content_type: args.filter_content_type.as_deref().map(parse_content_types),
+        match_headers: parse_header_matchers(&args.match_headers)?,
+        exclude_headers: parse_header_matchers(&args.filter_headers)?,
+        ..Default::default()
+    };
+    // Crawling reads links from the bodies of found pages
+    let read_body =
+        filter.needs_body() || args.dedupe || args.save_responses.is_some() || args.crawl;
+    let raw_size = args.size == SizeMode::Raw;
+    if raw_size && read_body {
+        return Err(crate::error::RbusterError::ConfigError(
+            "--size raw keeps bodies compressed and cannot be combined with body regexes, --dedupe, --save-responses or --crawl"
+                .to_string(),
+        ));
+    }
 
-    // Normalize base URL
-    let base_url = args.url.trim_end_matches('/').to_string();
+    // Group shown responses by page to collapse soft-404s
+    let dedupe = args
+        .dedupe
+        .then(|| DedupeGroups::new(args.dedupe_threshold));
+    let fingerprinter = BodyFingerprinter::new();
+
+    // Collect and normalize target base URLs
+    let targets: Vec<String> = match (&args.url, &args.url_file) {
+        (_, Some(path)) => load_wordlist(path).await.map_err(|e| {
+            crate::error::RbusterError::ConfigError(format!(
+                "Cannot read target file '{}': {}",
+                path.display(),
+                e
+            ))
+        })?,
+        (Some(url), None) => vec![url.clone()],
+        (None, None) => Vec::new(),
+    }
+    .iter()
+    .map(|url| url.trim_end_matches('/').to_string())
+    .collect();
+    if targets.is_empty() {
+        return Err(crate::error::RbusterError::ConfigError(
+            "No target URL given".to_string(),
+        ));
+    }
+    // With several targets, results carry the target so they stay attributable
+    let multi_target = args.url_file.is_some();
 
     // Create HTTP client
+    let mut http_opts = args.http.clone();
+    let cors_origin = args
+        .check_cors
+        .then(|| super::cors_origin(&mut http_opts.headers));
     let http_config = HttpConfig {
-        user_agent: args.http.user_agent.clone(),
-        timeout: Duration::from_secs(args.http.timeout),
-        insecure: args.http.insecure,
-        follow_redirect: args.http.follow_redirect,
-        proxy: args.http.proxy.clone(),
-        headers: parse_headers(&args.http.headers),
-        cookies: args.http.cookies.clone(),
-        username: args.http.username.clone(),
-        password: args.http.password.clone(),
+        decompress: !raw_size,
+        capture_headers: args.global.verbosity() >= 3
+            || args.save_headers
+            || filter.needs_headers(),
+        // Crawled pages are read whole; saved copies are still cut
+        max_body: (args.save_responses.is_some() && !args.crawl).then_some(args.max_response_size),
+        ..super::http_config(&http_opts)
     };
     let http_client = Arc::new(HttpClient::new(http_config)?);
 
     // Load wordlist
-    let wordlist = load_wordlist(&args.global.wordlist)
-        .await
-        .map_err(crate::error::RbusterError::WordlistError)?;
+    let wordlist = super::load_mutated_words(
+        &args.global,
+        Mutator::new(
+            args.mutate.mutate_case,
+            args.mutate.mutate_suffix.as_deref(),
+            args.mutate.mutate_prefix.as_deref(),
+        ),
+    )
+    .await?;
 
-    // Calculate total requests (wordlist * extensions)
-    let ext_multiplier = if extensions.is_empty() {
-        1
+    let method = if args.head {
+        "HEAD".to_string()
     } else {
-        extensions.len() + 1
+        args.http.method.clone()
     };
-    let total_requests = wordlist.len() * ext_multiplier * (if args.add_slash { 2 } else { 1 });
 
-    // Create progress tracker
-    let progress = ProgressTracker::new(
-        total_requests as u64,
-        args.global.quiet || args.global.no_progress,
-    );
+    // Load the request body
+    let data = match (&args.data, &args.data_file) {
+        (Some(data), _) => Some(data.clone()),
+        (None, Some(path)) => Some(tokio::fs::read_to_string(path).await.map_err(|e| {
+            crate::error::RbusterError::ConfigError(format!(
+                "Cannot read data file '{}': {}",
+                path.display(),
+                e
+            ))
+        })?),
+        (None, None) => None,
+    };
+    if data.is_some() && method.eq_ignore_ascii_case("GET") {
+        return Err(crate::error::RbusterError::ConfigError(
+            "--data and --data-file need a method that takes a body, e.g. --method POST"
+                .to_string(),
+        ));
+    }
+    let request_body = data.map(|data| RequestBody {
+        data,
+        content_type: args.content_type.clone(),
+    });
 
-    // Create output handler
-    let output = OutputHandler::new(args.global.output.as_deref()).await?;
-    let output = Arc::new(output);
+    // Raw mode sends a request template to the single target instead
+    let raw = match args.raw {
+        Some(ref path) => Some((
+            RawHttpClient::new(
+                &targets[0],
+                args.http.insecure,
+                Duration::from_secs(args.http.timeout),
+                !args.http.ignore_retry_after,
+            )?,
+            load_raw_template(path).await?,
+        )),
+        None => None,
+    };
+    if raw
+        .as_ref()
+        .is_some_and(|(_, template)| !template.contains("FUZZ"))
+    {
+        return Err(crate::error::RbusterError::ConfigError(
+            "--raw request has no FUZZ keyword to replace with paths".to_string(),
+        ));
+    }
 
-    // Check for wildcard
-    if !args.wildcard {
-        let random_path = format!("{}/robuster-wildcard-test-{}", base_url, rand_string(16));
-        match http_client.check_url(&random_path, &args.http.method).await {
-            Ok((status, _, _)) if valid_status_codes.contains(&status) => {
-                print_warning("Wildcard response detected! Use --wildcard to force continue");
-                if !args.global.quiet {
-                    return Ok(());
+    // Check each target for wildcard responses
+    let mut target_filters: Vec<Vec<ResponseFilter>> = Vec::with_capacity(targets.len());
+    let mut active_targets = Vec::with_capacity(targets.len());
+    for (t, base_url) in targets.iter().enumerate() {
+        if args.autocalibrate {
+            // Calibrate on bare paths and each extension, within its scope
+            let mut filters = scoped_filters(&filter, &scopes);
+            let groups = std::iter::once((None, 0)).chain(
+                extensions
+                    .iter()
+                    .map(|ext| Some(ext.trim_start_matches('.')))
+                    .zip(extension_scopes.iter().copied()),
+            );
+            let mut calibrated_any = false;
+            for (ext, scope) in groups {
+                let samples = calibration_samples(
+                    &http_client,
+                    raw.as_ref(),
+                    base_url,
+                    ext,
+                    &method,
+                    request_body.as_ref(),
+                    !raw_size,
+                )
+                .await;
+                if let Some(calibrated) = filters[scope].calibration(&samples, !raw_size) {
+                    if !args.global.quiet {
+                        print_calibration(&format!(
+                            "{}Calibrated on random {}paths: hiding {}",
+                            target_prefix(multi_target, base_url),
+                            ext.map(|ext| format!(".{} ", ext)).unwrap_or_default(),
+                            calibrated
+                        ));
+                    }
+                    filters[scope].exclude(calibrated);
+                    calibrated_any = true;
                 }
             }
-            _ => {}
+            if !calibrated_any && !args.global.quiet {
+                print_calibration(&format!(
+                    "{}Calibration found nothing to hide",
+                    target_prefix(multi_target, base_url)
+                ));
+            }
+            target_filters.push(filters);
+            active_targets.push(t);
+            continue;
         }
+
+        let mut target_filter = filter.clone();
+        if !args.wildcard && raw.is_none() {
+            match detect_wildcard(
+                &http_client,
+                base_url,
+                &method,
+                request_body.as_ref(),
+                &valid_status_codes,
+            )
+            .await
+            {
+                Wildcard::None => {}
+                Wildcard::Size(size) => {
+                    print_warning(&format!(
+                        "{}Wildcard response detected with size {}; excluding responses of that size. Use --wildcard to disable",
+                        target_prefix(multi_target, base_url),
+                        size
+                    ));
+                    target_filter.wildcard_length.insert(size);
+                }
+                Wildcard::Varying => {
+                    print_warning(&format!(
+                        "{}Wildcard response detected! Use --wildcard to force continue",
+                        target_prefix(multi_target, base_url)
+                    ));
+                    if !args.global.quiet {
+                        target_filters.push(vec![target_filter]);
+                        continue;
+                    }
+                }
+            }
+        }
+        target_filters.push(scoped_filters(&target_filter, &scopes));
+        active_targets.push(t);
     }
 
-    // Create semaphore for concurrency control
-    let semaphore = Arc::new(Semaphore::new(args.global.threads));
-    let delay = args.global.delay.map(Duration::from_millis);
+    // Calibrated word and line filters need bodies
+    let read_body = read_body || target_filters.iter().flatten().any(|f| f.needs_body());
 
     // Generate all URLs to check
-    let mut urls_to_check: Vec<String> = Vec::with_capacity(total_requests);
-    for word in &wordlist {
-        // Base path
-        let path = if word.starts_with('/') {
-            word.clone()
-        } else {
-            format!("/{}", word)
-        };
+    let urls_to_check = generate_urls(
+        &targets,
+        &active_targets,
+        &wordlist,
+        &extensions,
+        &extension_scopes,
+        args.add_slash,
+        args.no_double_extension,
+    );
+
+    // Create progress tracker; the dashboard replaces its bar
+    let use_tui = args.tui && !args.global.quiet && Dashboard::available();
+    if args.tui && !args.global.quiet && !use_tui {
+        print_warning("--tui needs a terminal; showing the progress bar instead");
+    }
+    let progress = ProgressTracker::new(
+        urls_to_check.len() as u64,
+        args.global.quiet || args.global.no_progress || use_tui,
+    );
 
-        // Add base path
-        urls_to_check.push(format!("{}{}", base_url, path));
+    // Create output handler
+    let output = OutputHandler::new(
+        &args.global,
+        ScanMeta {
+            method: Some(method.clone()),
+            ..ScanMeta::new("dir", Some(targets.join(",")), &args.global).with_http(&args.http)
+        },
+    )
+    .await?;
+    let output = Arc::new(output);
+
+    // Limit concurrency, adjusted to the error rate with --adaptive, shared by all targets
+    let concurrency = Arc::new(super::concurrency(&args.global, args.global.threads)?);
+    let delay = super::request_delay(&args.global);
+    let limiter = args.global.rate.map(|r| Arc::new(RateLimiter::new(r)));
+    let error_limit = Arc::new(ErrorLimit::new(args.global.max_errors));
 
-        // Add with slash if requested
-        if args.add_slash && !path.ends_with('/') {
-            urls_to_check.push(format!("{}{}/", base_url, path));
+    // Load checkpoint and skip work completed by an earlier run
+    let checkpoint = match args.checkpoint {
+        Some(ref path) => {
+            let fingerprint = Checkpoint::fingerprint(&[
+                &args.global.wordlist_display(),
+                &targets.join(" "),
+                &extensions.join(","),
+                &args.add_slash.to_string(),
+                &args.no_double_extension.to_string(),
+                args.global.word_prefix.as_deref().unwrap_or_default(),
+                args.global.word_suffix.as_deref().unwrap_or_default(),
+                &format!("{:?}", args.mutate),
+            ]);
+            let checkpoint = Checkpoint::open(path, fingerprint).await?;
+            let done = checkpoint.completed_count();
+            if done > 0 {
+                print_warning(&format!(
+                    "Resuming scan, skipping {} completed requests",
+                    done
+                ));
+                progress.inc_by(done as u64);
+            }
+            // Ctrl-C stops the scan like --max-errors does, so the checkpoint
+            // is written; a second Ctrl-C quits at once
+            let error_limit = Arc::clone(&error_limit);
+            tokio::spawn(async move {
+                if tokio::signal::ctrl_c().await.is_ok() {
+                    print_warning("Interrupted, saving the checkpoint (Ctrl-C again to quit now)");
+                    error_limit.cancel();
+                    let _ = tokio::signal::ctrl_c().await;
+                    std::process::exit(130);
+                }
+            });
+            Some(Arc::new(checkpoint))
         }
+        None => None,
+    };
 
-        // Add extensions
-        for ext in &extensions {
-            let ext_path = if ext.starts_with('.') {
-                format!("{}{}", path, ext)
-            } else {
-                format!("{}.{}", path, ext)
-            };
-            urls_to_check.push(format!("{}{}", base_url, ext_path));
+    // Track each target of a multi-target scan, for the dashboard
+    if multi_target {
+        let mut per_target: Vec<(String, u64, u64)> =
+            targets.iter().map(|t| (t.clone(), 0, 0)).collect();
+        for (index, (t, _, _)) in urls_to_check.iter().enumerate() {
+            per_target[*t].1 += 1;
+            if checkpoint.as_ref().is_some_and(|c| c.is_done(index)) {
+                per_target[*t].2 += 1;
+            }
         }
+        progress.track_targets(per_target);
     }
 
+    // Take over the terminal for the rest of the scan
+    let dashboard = if use_tui {
+        let cancel = Arc::clone(&error_limit);
+        let dashboard = Dashboard::start("dir", progress.clone(), move || cancel.cancel())
+            .map_err(|e| {
+                crate::error::RbusterError::ConfigError(format!("Cannot start dashboard: {}", e))
+            })?;
+        output.attach_dashboard(dashboard.feed());
+        Some(dashboard)
+    } else {
+        None
+    };
+
+    // Remember what the wordlist requested so crawling doesn't repeat it
+    let mut seen: HashSet<String> = if args.crawl {
+        urls_to_check
+            .iter()
+            .map(|(_, _, url)| url.clone())
+            .collect()
+    } else {
+        HashSet::new()
+    };
+
     // Process URLs concurrently
-    let method = args.http.method.clone();
-    let show_length = args.show_length;
-    let expanded = args.expanded;
+    let sink = ResultSink {
+        output: Arc::clone(&output),
+        result_tx,
+        show_length: args.show_length,
+        show_time: args.show_time,
+        expanded: args.expanded || multi_target,
+        held: args.ordered.then(|| Mutex::new(Vec::new())),
+        cors_origin,
+        saver: super::response_saver(
+            args.save_responses.as_deref(),
+            args.max_response_size,
+            args.save_headers,
+        )
+        .await?,
+    };
+    let slower_than = args.slower_than.map(Duration::from_millis);
+    let faster_than = args.faster_than.map(Duration::from_millis);
     let discover_backup = args.discover_backup;
-    let verbose = args.global.verbose;
+    let backup_extensions = backup_extensions(
+        args.backup_extensions.as_deref(),
+        args.backup_extensions_mode,
+    );
+    let verbose = args.global.verbosity();
+    let link_regex = args.crawl.then(|| {
+        Regex::new(r#"(?i)(?:href|src|action)\s*=\s*["']([^"'#\s]+)"#).expect("valid regex")
+    });
+    let links_of = |status: u16, text: &str, page: &str, base_url: &str| match link_regex {
+        Some(ref link_regex) if status == 200 => extract_links(link_regex, text, page, base_url),
+        _ => Vec::new(),
+    };
+
+    let pending = urls_to_check
+        .into_iter()
+        .enumerate()
+        .filter(|(index, _)| checkpoint.as_ref().is_none_or(|c| !c.is_done(*index)));
 
-    let results: Vec<_> = stream::iter(urls_to_check)
-        .map(|url| {
-            let semaphore = Arc::clone(&semaphore);
+    let mut results: Vec<_> = stream::iter(pending)
+        .map(|(index, (t, scope, url))| {
+            let concurrency = Arc::clone(&concurrency);
             let http_client = Arc::clone(&http_client);
             let method = method.clone();
+            let request_body = request_body.as_ref();
             let progress = progress.clone();
-            let output = Arc::clone(&output);
-            let valid_status_codes = valid_status_codes.clone();
-            let blacklist_codes = blacklist_codes.clone();
-            let exclude_lengths = exclude_lengths.clone();
-            let base_url = base_url.clone();
+            let limiter = limiter.clone();
+            let sink = &sink;
+            let filter = &target_filters[t][scope];
+            let base_url = &targets[t];
+            let checkpoint = checkpoint.clone();
+            let error_limit = Arc::clone(&error_limit);
+            let dedupe = &dedupe;
+            let fingerprinter = &fingerprinter;
+            let raw = &raw;
+            let links_of = &links_of;
 
             async move {
-                let _permit = semaphore.acquire().await.unwrap();
+                let _permit = concurrency.acquire().await;
+
+                if error_limit.tripped() {
+                    return None;
+                }
+
+                delay.wait().await;
 
-                if let Some(d) = delay {
-                    tokio::time::sleep(d).await;
+                if let Some(ref limiter) = limiter {
+                    limiter.acquire().await;
                 }
 
-                let result = http_client.check_url(&url, &method).await;
+                let result = match raw {
+                    Some((raw_client, template)) => {
+                        let path = url.strip_prefix(base_url.as_str()).unwrap_or(&url);
+                        let request = template.replace("FUZZ", path.trim_start_matches('/'));
+                        raw_client.send(request.as_bytes(), read_body).await
+                    }
+                    None => {
+                        http_client
+                            .check_url(&url, &method, request_body, read_body)
+                            .await
+                    }
+                };
                 progress.inc();
+                progress.inc_target(t);
 
-                match result {
-                    Ok((status, size, redirect)) => {
-                        // Check if we should show this result
-                        let show = valid_status_codes.contains(&status)
-                            && !blacklist_codes.contains(&status)
-                            && !exclude_lengths.contains(&size);
+                if let (Ok(_), Some(ref checkpoint)) = (&result, &checkpoint) {
+                    checkpoint.complete(index).await;
+                }
 
-                        if show {
-                            progress.inc_found();
+                concurrency.record(result.is_ok());
 
-                            // Extract path from URL
-                            let path = url.strip_prefix(&base_url).unwrap_or(&url);
+                if result.is_ok() {
+                    error_limit.success();
+                } else if error_limit.failure() {
+                    print_warning(&error_limit.message());
+                }
 
-                            // Print to console
-                            print_dir_result(
-                                path,
+                match result {
+                    Ok(UrlCheck {
+                        status,
+                        size,
+                        redirect,
+                        body,
+                        elapsed,
+                        headers,
+                        content_type,
+                        cors,
+                    }) => {
+                        // Check if we should show this result
+                        let body = body.unwrap_or_default();
+                        let text = &*String::from_utf8_lossy(&body);
+                        let (words, lines) = body_counts(text);
+                        let path = url.strip_prefix(base_url.as_str()).unwrap_or(&url);
+                        let outcome = filter
+                            .evaluate(
+                                status,
+                                size,
+                                words,
+                                lines,
+                                text,
+                                content_type.as_deref(),
+                                &headers,
+                            )
+                            .check(
+                                || time_passes(elapsed, slower_than, faster_than),
+                                HideReason::Timing(elapsed),
+                            )
+                            .check(
+                                || {
+                                    dedupe.as_ref().is_none_or(|d| {
+                                        d.admit(fingerprinter, status, text, &url, path)
+                                    })
+                                },
+                                HideReason::Duplicate,
+                            );
+                        if verbose >= 2 {
+                            print_response(
+                                &url,
                                 status,
                                 size,
-                                redirect.as_deref(),
-                                show_length,
-                                expanded,
-                                &base_url,
+                                super::shown_headers(verbose, &headers),
+                                super::hidden(&outcome).as_deref(),
                             );
+                        }
 
-                            // Write to file if configured
-                            if let Some(writer) = output.file_writer() {
-                                let result = DirResult {
-                                    path: path.to_string(),
-                                    status,
-                                    size,
-                                    redirect,
-                                };
-                                if writer.is_json() {
-                                    let _ = writer.write_json(&result).await;
-                                } else {
-                                    let line =
-                                        format!("{} (Status: {}) [Size: {}]", path, status, size);
-                                    let _ = writer.write_line(&line).await;
-                                }
-                            }
+                        if outcome.is_shown() {
+                            progress.inc_found();
+                            progress.record_status(status);
 
-                            Some((url, status, size))
+                            let result = DirResult {
+                                target: multi_target.then(|| base_url.clone()),
+                                url: url.clone(),
+                                path: path.to_string(),
+                                status,
+                                size,
+                                redirect,
+                                time_ms: Some(elapsed.as_millis() as u64),
+                                content_type,
+                                cors: super::cors_finding(
+                                    sink.cors_origin.as_deref(),
+                                    cors.as_ref(),
+                                ),
+                            };
+                            sink.save(&result, &headers, &body).await;
+                            sink.emit((index, String::new()), base_url, result).await;
+
+                            let links = links_of(status, text, &url, base_url);
+                            Some((t, url, status, links))
                         } else {
                             None
                         }
                     }
                     Err(e) => {
                         progress.inc_error();
-                        if verbose {
+                        if verbose >= 1 {
                             print_error(&format!("{}: {}", url, e), true);
                         }
                         None
@@ -203,59 +617,1588 @@ pub async fn run(args: DirArgs) -> Result<()> {
         .collect()
         .await;
 
-    // Check for backup files if requested
-    if discover_backup {
-        let found_files: Vec<_> = results
-            .iter()
-            .filter_map(|r| r.as_ref())
-            .map(|(url, _, _)| url.clone())
+    // Follow links from found pages that the wordlist didn't cover
+    if args.crawl && !error_limit.tripped() {
+        let mut frontier: Vec<(usize, Vec<String>)> = results
+            .iter_mut()
+            .flatten()
+            .map(|(t, _, _, links)| (*t, std::mem::take(links)))
             .collect();
 
-        for file_url in found_files {
-            for ext in BACKUP_EXTENSIONS {
-                let backup_url = format!("{}{}", file_url, ext);
-                if let Ok((status, size, redirect)) =
-                    http_client.check_url(&backup_url, &method).await
-                {
-                    if valid_status_codes.contains(&status) {
-                        let path = backup_url.strip_prefix(&base_url).unwrap_or(&backup_url);
-                        print_dir_result(
-                            path,
+        for _ in 0..args.crawl_depth {
+            if error_limit.tripped() {
+                break;
+            }
+
+            // Links in scope that haven't been requested yet
+            let mut links = Vec::new();
+            for (t, page_links) in frontier {
+                for link in page_links {
+                    if seen.insert(link.clone()) {
+                        links.push((t, link));
+                    }
+                }
+            }
+            if links.is_empty() {
+                break;
+            }
+
+            // Check discovered links like wordlist entries
+            progress.inc_length(links.len() as u64);
+            for &(t, _) in &links {
+                progress.inc_target_length(t, 1);
+            }
+            let mut found: Vec<_> = stream::iter(links)
+                .map(|(t, url)| {
+                    let concurrency = Arc::clone(&concurrency);
+                    let http_client = Arc::clone(&http_client);
+                    let method = method.clone();
+                    let request_body = request_body.as_ref();
+                    let sink = &sink;
+                    let progress = progress.clone();
+                    let limiter = limiter.clone();
+                    let filter = &target_filters[t][0];
+                    let base_url = &targets[t];
+                    let dedupe = &dedupe;
+                    let fingerprinter = &fingerprinter;
+                    let links_of = &links_of;
+                    let error_limit = Arc::clone(&error_limit);
+
+                    async move {
+                        let _permit = concurrency.acquire().await;
+
+                        if error_limit.tripped() {
+                            return None;
+                        }
+
+                        delay.wait().await;
+
+                        if let Some(ref limiter) = limiter {
+                            limiter.acquire().await;
+                        }
+
+                        let check = http_client
+                            .check_url(&url, &method, request_body, read_body)
+                            .await;
+                        progress.inc();
+                        progress.inc_target(t);
+
+                        concurrency.record(check.is_ok());
+
+                        if check.is_ok() {
+                            error_limit.success();
+                        } else if error_limit.failure() {
+                            print_warning(&error_limit.message());
+                        }
+
+                        let UrlCheck {
                             status,
                             size,
-                            redirect.as_deref(),
-                            show_length,
-                            expanded,
-                            &base_url,
-                        );
+                            redirect,
+                            body,
+                            elapsed,
+                            headers,
+                            content_type,
+                            cors,
+                        } = match check {
+                            Ok(check) => check,
+                            Err(e) => {
+                                progress.inc_error();
+                                if verbose >= 1 {
+                                    print_error(&format!("{}: {}", url, e), true);
+                                }
+                                return None;
+                            }
+                        };
+                        let body = body.unwrap_or_default();
+                        let text = &*String::from_utf8_lossy(&body);
+                        let (words, lines) = body_counts(text);
+                        let path = url.strip_prefix(base_url.as_str()).unwrap_or(&url);
+                        let outcome = filter
+                            .evaluate(
+                                status,
+                                size,
+                                words,
+                                lines,
+                                text,
+                                content_type.as_deref(),
+                                &headers,
+                            )
+                            .check(
+                                || time_passes(elapsed, slower_than, faster_than),
+                                HideReason::Timing(elapsed),
+                            )
+                            .check(
+                                || {
+                                    dedupe.as_ref().is_none_or(|d| {
+                                        d.admit(fingerprinter, status, text, &url, path)
+                                    })
+                                },
+                                HideReason::Duplicate,
+                            );
+                        if verbose >= 2 {
+                            print_response(
+                                &url,
+                                status,
+                                size,
+                                super::shown_headers(verbose, &headers),
+                                super::hidden(&outcome).as_deref(),
+                            );
+                        }
+                        if !outcome.is_shown() {
+                            return None;
+                        }
+
+                        progress.inc_found();
+                        progress.record_status(status);
+                        let result = DirResult {
+                            target: multi_target.then(|| base_url.clone()),
+                            url: url.clone(),
+                            path: path.to_string(),
+                            status,
+                            size,
+                            redirect,
+                            time_ms: Some(elapsed.as_millis() as u64),
+                            content_type,
+                            cors: super::cors_finding(sink.cors_origin.as_deref(), cors.as_ref()),
+                        };
+                        sink.save(&result, &headers, &body).await;
+                        sink.emit((usize::MAX, url.clone()), base_url, result).await;
+
+                        let links = links_of(status, text, &url, base_url);
+                        Some((t, url, status, links))
                     }
+                })
+                .buffer_unordered(args.global.threads)
+                .collect()
+                .await;
+
+            frontier = found
+                .iter_mut()
+                .flatten()
+                .map(|(t, _, _, links)| (*t, std::mem::take(links)))
+                .collect();
+            results.extend(found);
+        }
+    }
+
+    // Check for backup files if requested
+    if discover_backup && !error_limit.tripped() {
+        let mut candidates: Vec<(usize, String)> = Vec::new();
+        let mut queued = HashSet::new();
+        for (t, url, status, _) in results.iter().flatten() {
+            if !looks_like_file(url, *status) {
+                continue;
+            }
+            for backup_url in backup_candidates(url, &backup_extensions) {
+                if queued.insert(backup_url.clone()) {
+                    candidates.push((*t, backup_url));
                 }
             }
         }
+        progress.inc_length(candidates.len() as u64);
+        for &(t, _) in &candidates {
+            progress.inc_target_length(t, 1);
+        }
+
+        stream::iter(candidates)
+            .map(|(t, backup_url)| {
+                let concurrency = Arc::clone(&concurrency);
+                let http_client = Arc::clone(&http_client);
+                let method = method.clone();
+                let request_body = request_body.as_ref();
+                let sink = &sink;
+                let progress = progress.clone();
+                let limiter = limiter.clone();
+                let error_limit = Arc::clone(&error_limit);
+                let filter = &target_filters[t][0];
+                let base_url = &targets[t];
+
+                async move {
+                    let _permit = concurrency.acquire().await;
+
+                    if error_limit.tripped() {
+                        return;
+                    }
+
+                    delay.wait().await;
+
+                    if let Some(ref limiter) = limiter {
+                        limiter.acquire().await;
+                    }
+
+                    let check = http_client
+                        .check_url(&backup_url, &method, request_body, read_body)
+                        .await;
+                    progress.inc();
+                    progress.inc_target(t);
+
+                    concurrency.record(check.is_ok());
+
+                    if check.is_ok() {
+                        error_limit.success();
+                    } else if error_limit.failure() {
+                        print_warning(&error_limit.message());
+                    }
+
+                    let check = match check {
+                        Ok(check) => check,
+                        Err(e) => {
+                            progress.inc_error();
+                            if verbose >= 1 {
+                                print_error(&format!("{}: {}", backup_url, e), true);
+                            }
+                            return;
+                        }
+                    };
+                    let body = check.body.as_deref().unwrap_or_default();
+                    let text = &*String::from_utf8_lossy(body);
+                    let (words, lines) = body_counts(text);
+                    let outcome = filter
+                        .evaluate(
+                            check.status,
+                            check.size,
+                            words,
+                            lines,
+                            text,
+                            check.content_type.as_deref(),
+                            &check.headers,
+                        )
+                        .check(
+                            || time_passes(check.elapsed, slower_than, faster_than),
+                            HideReason::Timing(check.elapsed),
+                        );
+                    if verbose >= 2 {
+                        print_response(
+                            &backup_url,
+                            check.status,
+                            check.size,
+                            super::shown_headers(verbose, &check.headers),
+                            super::hidden(&outcome).as_deref(),
+                        );
+                    }
+                    if !outcome.is_shown() {
+                        return;
+                    }
+
+                    progress.inc_found();
+                    progress.record_status(check.status);
+                    let path = backup_url
+                        .strip_prefix(base_url.as_str())
+                        .unwrap_or(&backup_url);
+                    let result = DirResult {
+                        target: multi_target.then(|| base_url.clone()),
+                        url: backup_url.clone(),
+                        path: path.to_string(),
+                        status: check.status,
+                        size: check.size,
+                        redirect: check.redirect,
+                        time_ms: Some(check.elapsed.as_millis() as u64),
+                        content_type: check.content_type,
+                        cors: super::cors_finding(sink.cors_origin.as_deref(), check.cors.as_ref()),
+                    };
+                    sink.save(&result, &check.headers, body).await;
+                    sink.emit((usize::MAX, backup_url.clone()), base_url, result)
+                        .await;
+                }
+            })
+            .buffer_unordered(args.global.threads)
+            .collect::<Vec<()>>()
+            .await;
+    }
+
+    if let Some(dashboard) = dashboard {
+        dashboard.finish();
+    }
+    sink.release().await;
+
+    // Keep the checkpoint of an aborted scan for resuming; once the scan has
+    // run to completion there is nothing left to resume
+    if let Some(ref checkpoint) = checkpoint {
+        if error_limit.tripped() {
+            checkpoint.flush().await?;
+        } else {
+            checkpoint.remove().await?;
+        }
     }
 
     progress.finish();
-    output.finalize().await?;
+    super::warn_dead_proxies(&http_client);
+    super::warn_rate_limited(
+        http_client.rate_limited()
+            + raw
+                .as_ref()
+                .map_or(0, |(raw_client, _)| raw_client.rate_limited()),
+    );
+    if let Some(ref dedupe) = dedupe {
+        dedupe.print_collapsed();
+    }
+    progress.print_summary(args.global.quiet);
+    print_concurrency(&concurrency, args.global.quiet);
+    output.finalize(&progress.stats()).await?;
+
+    Ok(progress)
+}
+
+/// Every URL to request, as target, filter scope and URL: each word as a
+/// path on each active target, with a trailing slash for --add-slash, and
+/// with each extension unless it names a directory (or, with
+/// --no-double-extension, already has an extension)
+fn generate_urls(
+    targets: &[String],
+    active_targets: &[usize],
+    wordlist: &[String],
+    extensions: &[String],
+    extension_scopes: &[usize],
+    add_slash: bool,
+    no_double_extension: bool,
+) -> Vec<(usize, usize, String)> {
+    let mut urls = Vec::new();
+    for &t in active_targets {
+        let base_url = &targets[t];
+        for word in wordlist {
+            // Base path
+            let path = if word.starts_with('/') {
+                word.clone()
+            } else {
+                format!("/{}", word)
+            };
+
+            // Add base path
+            urls.push((t, 0, format!("{}{}", base_url, path)));
+
+            // Add with slash if requested
+            if add_slash && !path.ends_with('/') {
+                urls.push((t, 0, format!("{}{}/", base_url, path)));
+            }
+
+            // Add extensions to file candidates only
+            if path.ends_with('/') || (no_double_extension && has_extension(&path)) {
+                continue;
+            }
+            for (ext, &scope) in extensions.iter().zip(extension_scopes) {
+                let ext_path = if ext.starts_with('.') {
+                    format!("{}{}", path, ext)
+                } else {
+                    format!("{}.{}", path, ext)
+                };
+                urls.push((t, scope, format!("{}{}", base_url, ext_path)));
+            }
+        }
+    }
+    urls
+}
+
+/// Responses to a few random paths, ending in `.ext` if given, for
+/// auto-calibration
+async fn calibration_samples(
+    http_client: &HttpClient,
+    raw: Option<&(RawHttpClient, String)>,
+    base_url: &str,
+    ext: Option<&str>,
+    method: &str,
+    body: Option<&RequestBody>,
+    read_body: bool,
+) -> Vec<CalibrationSample> {
+    let mut samples = Vec::with_capacity(CALIBRATION_PROBES);
+    for i in 0..CALIBRATION_PROBES {
+        // Varying lengths expose pages that echo the path in their size
+        let mut path = random_label(8 + 4 * i);
+        if let Some(ext) = ext {
+            path = format!("{}.{}", path, ext);
+        }
+        let check = match raw {
+            Some((raw_client, template)) => {
+                let request = template.replace("FUZZ", &path);
+                raw_client.send(request.as_bytes(), read_body).await
+            }
+            None => {
+                let url = format!("{}/{}", base_url, path);
+                http_client.check_url(&url, method, body, read_body).await
+            }
+        };
+        if let Ok(check) = check {
+            let body = String::from_utf8_lossy(check.body.as_deref().unwrap_or_default());
+            let (words, lines) = body_counts(&body);
+            samples.push(CalibrationSample {
+                status: check.status,
+                size: check.size,
+                words,
+                lines,
+            });
+        }
+    }
+    samples
+}
+
+/// Outcome of probing a target with random paths
+enum Wildcard {
+    /// Random paths are not answered with a valid status
+    None,
+    /// Every random path returns the same size, which can be filtered out
+    Size(usize),
+    /// Random paths return valid responses of differing sizes
+    Varying,
+}
+
+/// Probe a few random paths of equal length; soft-404 pages then come back
+/// with identical sizes that can be filtered out
+async fn detect_wildcard(
+    http_client: &HttpClient,
+    base_url: &str,
+    method: &str,
+    body: Option<&RequestBody>,
+    valid_status_codes: &HashSet<u16>,
+) -> Wildcard {
+    let mut wildcard_sizes = Vec::new();
+    for i in 0..WILDCARD_PROBES {
+        let random_path = format!(
+            "{}/robuster-wildcard-test-{}{}",
+            base_url,
+            i,
+            random_label(16)
+        );
+        match http_client
+            .check_url(&random_path, method, body, false)
+            .await
+        {
+            Ok(check) if valid_status_codes.contains(&check.status) => {
+                wildcard_sizes.push(check.size)
+            }
+            _ => return Wildcard::None,
+        }
+    }
+
+    if wildcard_sizes.iter().all(|&size| size == wildcard_sizes[0]) {
+        Wildcard::Size(wildcard_sizes[0])
+    } else {
+        Wildcard::Varying
+    }
+}
+
+/// A target's filter for each status code scope, differing only in the
+/// status codes they let through
+fn scoped_filters(filter: &ResponseFilter, scopes: &[&[u16]]) -> Vec<ResponseFilter> {
+    scopes
+        .iter()
+        .map(|codes| ResponseFilter {
+            match_status: Some(codes.iter().copied().collect()),
+            ..filter.clone()
+        })
+        .collect()
+}
+
+/// Prefix identifying the target in messages when scanning several
+fn target_prefix(multi_target: bool, base_url: &str) -> String {
+    if multi_target {
+        format!("{}: ", base_url)
+    } else {
+        String::new()
+    }
+}
+
+/// Whether a found URL is worth probing for backups: directories and
+/// redirects to them are skipped
+fn looks_like_file(url: &str, status: u16) -> bool {
+    if url.ends_with('/') {
+        return false;
+    }
+    let name = url.rsplit('/').next().unwrap_or(url);
+    name.contains('.') || status == 200
+}
+
+/// Backup suffixes to try: the built-in ones, the --backup-extensions list,
+/// or both, without duplicates
+fn backup_extensions(custom: Option<&str>, mode: BackupExtensionsMode) -> Vec<String> {
+    let builtin = match (custom, mode) {
+        (Some(_), BackupExtensionsMode::Replace) => &[][..],
+        _ => BACKUP_EXTENSIONS,
+    };
+    let custom = custom
+        .into_iter()
+        .flat_map(|list| list.split(','))
+        .map(str::trim)
+        .filter(|ext| !ext.is_empty());
+
+    let mut seen = HashSet::new();
+    builtin
+        .iter()
+        .copied()
+        .chain(custom)
+        .filter(|ext| seen.insert(*ext))
+        .map(|ext| ext.to_string())
+        .collect()
+}
+
+/// Backup URLs for a found file: every backup extension appended to the full
+/// name, and for names with an extension also to the name without it
+/// (`config.php` gives `config.php.bak` and `config.bak`)
+fn backup_candidates(url: &str, extensions: &[String]) -> Vec<String> {
+    let name_start = url.rfind('/').map_or(0, |i| i + 1);
+    let stem = url[name_start..]
+        .rfind('.')
+        .filter(|&i| i > 0)
+        .map(|i| &url[..name_start + i]);
+
+    let mut candidates = Vec::new();
+    for ext in extensions {
+        candidates.push(format!("{}{}", url, ext));
+        if let Some(stem) = stem {
+            candidates.push(format!("{}{}", stem, ext));
+        }
+    }
+    candidates
+}
 
-    Ok(())
+/// Paths grouped by response status and page for --dedupe
+struct DedupeGroups {
+    threshold: usize,
+    groups: Mutex<HashMap<(u16, u64), Vec<String>>>,
 }
 
-/// Generate random string for wildcard detection
-fn rand_string(len: usize) -> String {
-    use std::time::{SystemTime, UNIX_EPOCH};
-    let seed = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_nanos();
+impl DedupeGroups {
+    fn new(threshold: usize) -> Self {
+        Self {
+            threshold,
+            groups: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Record a path that passed the other filters. Returns false once more
+    /// than `threshold` paths have returned the same page with the same
+    /// status; the note is printed when that happens for the first time.
+    fn admit(
+        &self,
+        fingerprinter: &BodyFingerprinter,
+        status: u16,
+        body: &str,
+        url: &str,
+        path: &str,
+    ) -> bool {
+        // Soft-404 pages often echo the requested URL or path back
+        let hash = fingerprinter.fingerprint(body.as_bytes(), &[url, path]);
+        let mut groups = self.groups.lock().unwrap();
+        let paths = groups.entry((status, hash)).or_default();
+        paths.push(path.to_string());
+
+        if paths.len() == self.threshold + 1 {
+            print_warning(&format!(
+                "More than {} paths return the same {} page ({}, ...); hiding further matches",
+                self.threshold,
+                status,
+                paths[..self.threshold].join(", ")
+            ));
+        }
+        paths.len() <= self.threshold
+    }
+
+    /// Print one line per page that was collapsed
+    fn print_collapsed(&self) {
+        let groups = self.groups.lock().unwrap();
+        for ((status, _), paths) in groups.iter().filter(|(_, p)| p.len() > self.threshold) {
+            print_warning(&format!(
+                "Collapsed {} paths returning the same {} page, e.g. {}",
+                paths.len(),
+                status,
+                paths[..self.threshold.min(3)].join(", ")
+            ));
+        }
+    }
+}
+
+/// Whether the last segment of `path` has an extension, e.g. /index.php
+/// but not /admin or /.htaccess
+fn has_extension(path: &str) -> bool {
+    let name = path.rsplit('/').next().unwrap_or_default();
+    name.trim_start_matches('.').contains('.')
+}
+
+/// Drop empty and repeated extensions, treating "php" and ".php" as the same
+fn dedup_extensions(extensions: Vec<String>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    extensions
+        .into_iter()
+        .filter(|ext| {
+            let key = ext.trim_start_matches('.');
+            !key.is_empty() && seen.insert(key.to_string())
+        })
+        .collect()
+}
+
+/// Check a response time against the --slower-than/--faster-than bounds
+fn time_passes(
+    elapsed: Duration,
+    slower_than: Option<Duration>,
+    faster_than: Option<Duration>,
+) -> bool {
+    slower_than.is_none_or(|min| elapsed > min) && faster_than.is_none_or(|max| elapsed < max)
+}
+
+/// Prints and records shown results (console, file output, hooks and the
+/// result channel), or holds them back until the scan ends for --ordered
+struct ResultSink {
+    output: Arc<OutputHandler>,
+    result_tx: Option<UnboundedSender<DirResult>>,
+    show_length: bool,
+    show_time: bool,
+    expanded: bool,
+    held: Option<Mutex<Vec<HeldResult>>>,
+    /// Origin sent by --check-cors
+    cors_origin: Option<String>,
+    saver: Option<ResponseSaver>,
+}
+
+/// Result held by --ordered: sort key, target base URL and result
+type HeldResult = ((usize, String), String, DirResult);
+
+impl ResultSink {
+    /// Emit a shown result, or hold it for --ordered. Wordlist results are
+    /// keyed by their position in the wordlist; crawled links and backups
+    /// follow them, keyed by URL.
+    async fn emit(&self, order: (usize, String), base_url: &str, result: DirResult) {
+        match self.held {
+            Some(ref held) => {
+                held.lock()
+                    .unwrap()
+                    .push((order, base_url.to_string(), result));
+            }
+            None => self.show(base_url, result).await,
+        }
+    }
+
+    /// Save a shown response for --save-responses
+    async fn save(&self, result: &DirResult, headers: &[(String, String)], body: &[u8]) {
+        let Some(ref saver) = self.saver else {
+            return;
+        };
+        let name = match result.target {
+            Some(_) => &result.url,
+            None => &result.path,
+        };
+        if let Err(e) = saver.save(name, result.status, headers, body).await {
+            print_warning(&format!("Cannot save response of {}: {}", result.url, e));
+        }
+    }
+
+    /// Emit the held results in order
+    async fn release(&self) {
+        let Some(ref held) = self.held else {
+            return;
+        };
+        let mut held = std::mem::take(&mut *held.lock().unwrap());
+        held.sort_by(|a, b| a.0.cmp(&b.0));
+        for (_, base_url, result) in held {
+            self.show(&base_url, result).await;
+        }
+    }
+
+    async fn show(&self, base_url: &str, result: DirResult) {
+        let time = result
+            .time_ms
+            .filter(|_| self.show_time)
+            .map(Duration::from_millis);
+        print_dir_result(
+            &result.path,
+            result.status,
+            result.size,
+            result.redirect.as_deref(),
+            time,
+            self.show_length,
+            self.expanded,
+            base_url,
+        );
+        if let (Some(true), Some(origin)) = (result.cors, &self.cors_origin) {
+            print_cors_finding(&result.url, origin);
+        }
+
+        // Record result (file output and hooks)
+        let shown = match result.target {
+            Some(_) => &result.url,
+            None => &result.path,
+        };
+        let mut line = dir_line(shown, result.status, result.size, time);
+        if result.cors == Some(true) {
+            line.push_str(" [cors]");
+        }
+        self.output.record(&result, &line).await;
+        if let Some(ref result_tx) = self.result_tx {
+            let _ = result_tx.send(result);
+        }
+    }
+}
+
+/// Plain-text result line for file output and hooks
+fn dir_line(path: &str, status: u16, size: usize, time: Option<Duration>) -> String {
+    match time {
+        Some(time) => format!(
+            "{} (Status: {}) [Size: {}] [Time: {}ms]",
+            path,
+            status,
+            size,
+            time.as_millis()
+        ),
+        None => format!("{} (Status: {}) [Size: {}]", path, status, size),
+    }
+}
+
+/// Compile a user-supplied regex
+fn compile_regex(pattern: &str) -> Result<Regex> {
+    Regex::new(pattern).map_err(|e| {
+        crate::error::RbusterError::ConfigError(format!("Invalid regex '{}': {}", pattern, e))
+    })
+}
+
+/// Extract same-origin links under `base_url` from an HTML page.
+/// Query strings and fragments are dropped so each path is requested once.
+fn extract_links(link_regex: &Regex, body: &str, page_url: &str, base_url: &str) -> Vec<String> {
+    let Ok(page) = url::Url::parse(page_url) else {
+        return Vec::new();
+    };
+
+    link_regex
+        .captures_iter(body)
+        .filter_map(|caps| page.join(&caps[1]).ok())
+        .filter_map(|mut link| {
+            link.set_query(None);
+            link.set_fragment(None);
+            let link = link.to_string();
+            let rest = link.strip_prefix(base_url)?;
+            (rest.starts_with('/') && rest.len() > 1).then_some(link)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{Cli, Commands};
+    use crate::core::mock_server::{MockResponse, MockServer};
+    use crate::core::random_label;
+    use clap::Parser;
+    use tokio::sync::mpsc;
+
+    /// Scratch directory for test files, removed when dropped
+    struct Scratch(std::path::PathBuf);
+
+    impl Scratch {
+        fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("rbuster-{}", random_label(12)));
+            std::fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+
+        /// Path of `name` inside the directory
+        fn path(&self, name: &str) -> String {
+            self.0.join(name).to_string_lossy().into_owned()
+        }
+
+        /// Write `contents` to `name` and return its path
+        fn file(&self, name: &str, contents: impl AsRef<[u8]>) -> String {
+            let path = self.path(name);
+            std::fs::write(&path, contents).unwrap();
+            path
+        }
+    }
+
+    impl Drop for Scratch {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// Run `robuster dir -q` over `words` with `flags` added, returning the
+    /// found results in completion order
+    async fn scan(words: &str, flags: &[&str]) -> Result<Vec<DirResult>> {
+        scan_tracked(words, flags).await.map(|(found, _)| found)
+    }
+
+    /// Like `scan`, also returning the progress counters of the scan
+    async fn scan_tracked(
+        words: &str,
+        flags: &[&str],
+    ) -> Result<(Vec<DirResult>, ProgressTracker)> {
+        let scratch = Scratch::new();
+        let wordlist = scratch.file("words.txt", words);
+        let argv = ["robuster", "dir", "-q", "-w", &wordlist]
+            .into_iter()
+            .chain(flags.iter().copied());
+        let Commands::Dir(args) = Cli::parse_from(argv).command else {
+            unreachable!()
+        };
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let progress = run_tracked(args, Some(tx)).await?;
+        let mut found = Vec::new();
+        while let Ok(result) = rx.try_recv() {
+            found.push(result);
+        }
+        Ok((found, progress))
+    }
+
+    /// Paths of the found results, sorted
+    fn paths(found: &[DirResult]) -> Vec<&str> {
+        let mut paths: Vec<&str> = found.iter().map(|r| r.path.as_str()).collect();
+        paths.sort_unstable();
+        paths
+    }
+
+    #[test]
+    fn dedupe_groups_by_status_and_page() {
+        let groups = DedupeGroups::new(1);
+        let fingerprinter = BodyFingerprinter::new();
+        let page = "<h1>Not here</h1>";
+        assert!(groups.admit(&fingerprinter, 200, page, "http://t/a", "/a"));
+        assert!(groups.admit(&fingerprinter, 403, page, "http://t/b", "/b"));
+        assert!(!groups.admit(&fingerprinter, 200, page, "http://t/c", "/c"));
+        assert!(!groups.admit(&fingerprinter, 403, page, "http://t/d", "/d"));
+    }
+
+    #[tokio::test]
+    async fn save_responses_writes_raw_bytes_of_hits() {
+        let server = MockServer::start(|req| match req.path.as_str() {
+            "/admin" => MockResponse::new(200, b"\xff\xfeadmin page".to_vec()),
+            _ => MockResponse::new(404, "missing"),
+        })
+        .await;
+
+        let scratch = Scratch::new();
+        let saved = scratch.path("responses");
+        scan(
+            "admin\nnope\n",
+            &[
+                "-u",
+                &server.url(),
+                "--save-responses",
+                &saved,
+                "--max-response-size",
+                "8",
+            ],
+        )
+        .await
+        .unwrap();
+
+        let files: Vec<(String, Vec<u8>)> = std::fs::read_dir(&saved)
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .map(|p| {
+                (
+                    p.file_name().unwrap().to_string_lossy().into_owned(),
+                    std::fs::read(&p).unwrap(),
+                )
+            })
+            .collect();
+
+        // One file for the hit, named after the path plus a 64-bit hash,
+        // holding the first bytes exactly as sent
+        assert_eq!(files.len(), 1);
+        let (name, content) = &files[0];
+        assert!(
+            name.starts_with("admin_") && name.ends_with(".txt"),
+            "{}",
+            name
+        );
+        assert_eq!(name.len(), "admin_".len() + 16 + ".txt".len());
+        assert_eq!(content, b"\xff\xfeadmin ");
+    }
+
+    #[tokio::test]
+    async fn url_list_holds_exactly_the_found_urls() {
+        let server = MockServer::start(|req| match req.path.as_str() {
+            "/app/admin" | "/app/login" => MockResponse::new(200, "page"),
+            _ => MockResponse::new(404, "missing"),
+        })
+        .await;
+
+        let scratch = Scratch::new();
+        let output = scratch.path("found.txt");
+        let base = format!("{}/app", server.url());
+        scan(
+            "admin\nnope\nlogin\n",
+            &["-u", &base, "-o", &output, "--urls"],
+        )
+        .await
+        .unwrap();
+        let written = std::fs::read_to_string(&output).unwrap();
+
+        let mut lines: Vec<&str> = written.lines().collect();
+        lines.sort_unstable();
+        assert_eq!(
+            lines,
+            [format!("{}/admin", base), format!("{}/login", base)]
+        );
+    }
+
+    #[tokio::test]
+    async fn word_prefix_and_suffix_wrap_each_target() {
+        let server = MockServer::start(|req| match req.path.as_str() {
+            "/api/users.json" => MockResponse::new(200, "[]"),
+            _ => MockResponse::new(404, "missing"),
+        })
+        .await;
+
+        let found = scan(
+            "users\norders\n",
+            &[
+                "-u",
+                &server.url(),
+                "--word-prefix",
+                "api/",
+                "--word-suffix",
+                ".json",
+                "-x",
+                "bak",
+            ],
+        )
+        .await
+        .unwrap();
+
+        // Extensions go after the suffix
+        let mut requested: Vec<String> = server
+            .requests()
+            .into_iter()
+            .map(|req| req.path)
+            .filter(|path| path.starts_with("/api/"))
+            .collect();
+        requested.sort_unstable();
+        requested.dedup();
+        assert_eq!(
+            requested,
+            [
+                "/api/orders.json",
+                "/api/orders.json.bak",
+                "/api/users.json",
+                "/api/users.json.bak",
+            ]
+        );
+        assert_eq!(paths(&found), ["/api/users.json"]);
+    }
+
+    #[tokio::test]
+    async fn crawled_pages_are_requested_once() {
+        let server = MockServer::start(|req| match req.path.as_str() {
+            "/" | "/index" => MockResponse::new(200, r#"<a href="/hidden">x</a>"#),
+            "/hidden" => MockResponse::new(200, r#"<form action="/deeper"></form>"#),
+            "/deeper" => MockResponse::new(200, "end"),
+            _ => MockResponse::new(404, "missing"),
+        })
+        .await;
+
+        let found = scan("index\nnope\n", &["-u", &server.url(), "--crawl"])
+            .await
+            .unwrap();
+        assert_eq!(paths(&found), ["/deeper", "/hidden", "/index"]);
+
+        // Links come from the bodies of the first responses
+        let requests: Vec<String> = server.requests().into_iter().map(|r| r.path).collect();
+        for path in ["/index", "/hidden", "/deeper"] {
+            assert_eq!(
+                requests.iter().filter(|p| *p == path).count(),
+                1,
+                "{:?}",
+                requests
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn crawled_links_and_backups_count_toward_their_target() {
+        let site = |links: &'static [&'static str]| {
+            MockServer::start(move |req| {
+                if req.path == "/index" {
+                    let body: String = links
+                        .iter()
+                        .map(|link| format!(r#"<a href="{}">x</a>"#, link))
+                        .collect();
+                    MockResponse::new(200, body)
+                } else if links.contains(&req.path.as_str()) {
+                    MockResponse::new(200, "page")
+                } else {
+                    MockResponse::new(404, "missing")
+                }
+            })
+        };
+        let first = site(&["/a1", "/a2"]).await;
+        let second = site(&["/b1"]).await;
+
+        let scratch = Scratch::new();
+        let targets = scratch.file(
+            "targets.txt",
+            format!("{}\n{}\n", first.url(), second.url()),
+        );
+        let (found, progress) = scan_tracked(
+            "index\nnope\n",
+            &[
+                "--url-file",
+                &targets,
+                "--crawl",
+                "--discover-backup",
+                "--backup-extensions",
+                ".bak",
+                "--backup-extensions-mode",
+                "replace",
+            ],
+        )
+        .await
+        .unwrap();
+        assert_eq!(found.len(), 5);
+
+        // Two words, the crawled links and one backup of each found page
+        let targets: Vec<(u64, u64)> = progress
+            .targets()
+            .iter()
+            .map(|target| (target.total(), target.done()))
+            .collect();
+        assert_eq!(targets, [(7, 7), (5, 5)]);
+        assert_eq!(progress.position(), (12, 12));
+    }
+
+    #[tokio::test]
+    async fn crawl_failures_count_toward_max_errors() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // /index links to many pages whose connections are closed unanswered
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let drops = Arc::new(AtomicUsize::new(0));
+        let dropped = Arc::clone(&drops);
+        tokio::spawn(async move {
+            let links: String = (0..50)
+                .map(|i| format!(r#"<a href="/dead/{}">x</a>"#, i))
+                .collect();
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = [0u8; 4096];
+                let n = stream.read(&mut buf).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&buf[..n]);
+                let (status, body) = if request.starts_with("GET /index ") {
+                    ("200 OK", links.as_str())
+                } else if request.starts_with("GET /dead/") {
+                    dropped.fetch_add(1, Ordering::Relaxed);
+                    continue;
+                } else {
+                    ("404 Not Found", "")
+                };
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+
+        let url = format!("http://{}", addr);
+        scan(
+            "index\n",
+            &["-u", &url, "--crawl", "-t", "2", "--max-errors", "5"],
+        )
+        .await
+        .unwrap();
+
+        // The limit and requests already in flight
+        let requests = drops.load(Ordering::Relaxed);
+        assert!((5..=5 + 2).contains(&requests), "{}", requests);
+    }
+
+    #[tokio::test]
+    async fn word_is_found_after_dropped_connections() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // Hangs up on the first two requests for /admin, then answers
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let drops = Arc::new(AtomicUsize::new(0));
+        let dropped = Arc::clone(&drops);
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = [0u8; 4096];
+                let n = stream.read(&mut buf).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&buf[..n]);
+                let status = if !request.starts_with("GET /admin ") {
+                    "404 Not Found"
+                } else if dropped.fetch_add(1, Ordering::Relaxed) < 2 {
+                    continue;
+                } else {
+                    "200 OK"
+                };
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    status
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+
+        let url = format!("http://{}", addr);
+        let found = scan(
+            "admin\n",
+            &["-u", &url, "--retries", "2", "--retry-backoff", "10"],
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(drops.load(Ordering::Relaxed), 3);
+        assert_eq!(paths(&found), ["/admin"]);
+    }
+
+    #[tokio::test]
+    async fn extensions_file_merges_with_inline_extensions() {
+        let server = MockServer::start(|_| MockResponse::new(404, "missing")).await;
+
+        let scratch = Scratch::new();
+        let extensions = scratch.file("extensions.txt", "# archives\n.bak\n\nphp\n.txt\n");
+        scan(
+            "admin\n",
+            &[
+                "-u",
+                &server.url(),
+                "-x",
+                "php,.txt",
+                "--extensions-file",
+                &extensions,
+            ],
+        )
+        .await
+        .unwrap();
+
+        let mut paths: Vec<String> = server
+            .requests()
+            .into_iter()
+            .map(|req| req.path)
+            .filter(|path| path.starts_with("/admin"))
+            .collect();
+        paths.sort_unstable();
+        assert_eq!(paths, ["/admin", "/admin.bak", "/admin.php", "/admin.txt"]);
+    }
+
+    #[tokio::test]
+    async fn scan_stops_near_max_errors() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        // Every connection is closed without an answer
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let accepted = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&accepted);
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                counter.fetch_add(1, Ordering::Relaxed);
+                drop(stream);
+            }
+        });
+
+        let words: Vec<String> = (0..500).map(|i| format!("word{}", i)).collect();
+        let url = format!("http://{}", addr);
+        scan(
+            &words.join("\n"),
+            &["-u", &url, "-t", "2", "--max-errors", "10"],
+        )
+        .await
+        .unwrap();
+
+        // The limit, requests already in flight and the wildcard probes
+        let requests = accepted.load(Ordering::Relaxed);
+        assert!(
+            (10..=10 + 2 + WILDCARD_PROBES).contains(&requests),
+            "{}",
+            requests
+        );
+    }
+
+    #[tokio::test]
+    async fn result_channel_gets_every_found_result() {
+        let server = MockServer::start(|req| match req.path.as_str() {
+            "/admin" | "/login" | "/backup" => MockResponse::new(200, "page"),
+            _ => MockResponse::new(404, "missing"),
+        })
+        .await;
+
+        let scratch = Scratch::new();
+        let output = scratch.path("found.txt");
+        let found = scan(
+            "admin\nnope\nlogin\nmissing\nbackup\n",
+            &["-u", &server.url(), "-o", &output],
+        )
+        .await
+        .unwrap();
+        let written = std::fs::read_to_string(&output).unwrap();
+
+        assert_eq!(found.len(), 3);
+        assert_eq!(found.len(), written.lines().count());
+    }
+
+    #[tokio::test]
+    async fn extension_status_codes_apply_to_their_paths() {
+        let server = MockServer::start(|req| match req.path.as_str() {
+            "/admin" => MockResponse::new(301, "moved"),
+            "/admin.php" | "/admin.bak" | "/admin.txt" => MockResponse::new(403, "forbidden"),
+            "/login" | "/login.php" => MockResponse::new(200, "page"),
+            _ => MockResponse::new(404, "missing"),
+        })
+        .await;
+
+        let found = scan(
+            "admin\nlogin\n",
+            &[
+                "-u",
+                &server.url(),
+                "-x",
+                "php,bak,txt",
+                "-s",
+                "php:200,bak:200,403,*:200,301",
+            ],
+        )
+        .await
+        .unwrap();
+
+        let mut found: Vec<(String, u16)> = found.into_iter().map(|r| (r.path, r.status)).collect();
+        found.sort();
+        assert_eq!(
+            found,
+            [
+                ("/admin".to_string(), 301),
+                ("/admin.bak".to_string(), 403),
+                ("/login".to_string(), 200),
+                ("/login.php".to_string(), 200),
+            ]
+        );
+    }
 
-    let chars: Vec<char> = "abcdefghijklmnopqrstuvwxyz0123456789".chars().collect();
-    let mut result = String::with_capacity(len);
-    let mut n = seed as usize;
+    #[tokio::test(flavor = "multi_thread")]
+    async fn ordered_output_follows_the_wordlist() {
+        let words = ["zeta", "alpha", "mid", "beta", "omega"];
+        // Earlier words answer later, so they arrive out of order
+        let server = MockServer::start(move |req| {
+            match words.iter().position(|w| req.path == format!("/{}", w)) {
+                Some(i) => {
+                    std::thread::sleep(Duration::from_millis(40 * (words.len() - i) as u64));
+                    MockResponse::new(200, "page")
+                }
+                None => MockResponse::new(404, "missing"),
+            }
+        })
+        .await;
+
+        let scratch = Scratch::new();
+        let mut runs = Vec::new();
+        for run in 0..2 {
+            let output = scratch.path(&format!("found-{}.txt", run));
+            scan(
+                &words.join("\n"),
+                &["-t", "5", "--ordered", "-u", &server.url(), "-o", &output],
+            )
+            .await
+            .unwrap();
+            runs.push(std::fs::read_to_string(&output).unwrap());
+        }
+
+        let expected: Vec<String> = words.iter().map(|w| format!("/{}", w)).collect();
+        for written in &runs {
+            let paths: Vec<&str> = written
+                .lines()
+                .map(|line| line.split_whitespace().next().unwrap())
+                .collect();
+            assert_eq!(paths, expected);
+        }
+        assert_eq!(runs[0], runs[1]);
+    }
+
+    #[test]
+    fn extension_is_read_from_the_last_segment() {
+        assert!(has_extension("/index.php"));
+        assert!(has_extension("/v1.2/app.tar.gz"));
+        assert!(!has_extension("/admin"));
+        assert!(!has_extension("/v1.2/admin"));
+        assert!(!has_extension("/.htaccess"));
+    }
+
+    #[tokio::test]
+    async fn extensions_skip_directories_and_named_files() {
+        let server = MockServer::start(|_| MockResponse::new(404, "missing")).await;
+
+        let words = "admin\ndocs/\nindex.php\n.htaccess\nv1.2/api\n";
+        let url = server.url();
+        let flags = ["-u", &url, "-x", "php,.bak", "--add-slash"];
+        let before = server.requests().len();
+        scan(words, &flags).await.unwrap();
+        let plain: Vec<String> = server.requests()[before..]
+            .iter()
+            .map(|r| r.path.clone())
+            .collect();
+        let before = server.requests().len();
+        scan(words, &[&flags[..], &["--no-double-extension"]].concat())
+            .await
+            .unwrap();
+        let single: Vec<String> = server.requests()[before..]
+            .iter()
+            .map(|r| r.path.clone())
+            .collect();
+
+        // Wildcard probes use random names outside the wordlist
+        let generated = |paths: Vec<String>| {
+            let mut paths: Vec<String> = paths
+                .into_iter()
+                .filter(|p| {
+                    ["/admin", "/docs", "/index", "/.htaccess", "/v1.2"]
+                        .iter()
+                        .any(|w| p.starts_with(w))
+                })
+                .collect();
+            paths.sort();
+            paths
+        };
+        assert_eq!(
+            generated(plain),
+            [
+                "/.htaccess",
+                "/.htaccess.bak",
+                "/.htaccess.php",
+                "/.htaccess/",
+                "/admin",
+                "/admin.bak",
+                "/admin.php",
+                "/admin/",
+                "/docs/",
+                "/index.php",
+                "/index.php.bak",
+                "/index.php.php",
+                "/index.php/",
+                "/v1.2/api",
+                "/v1.2/api.bak",
+                "/v1.2/api.php",
+                "/v1.2/api/",
+            ]
+        );
+        assert_eq!(
+            generated(single),
+            [
+                "/.htaccess",
+                "/.htaccess.bak",
+                "/.htaccess.php",
+                "/.htaccess/",
+                "/admin",
+                "/admin.bak",
+                "/admin.php",
+                "/admin/",
+                "/docs/",
+                "/index.php",
+                "/index.php/",
+                "/v1.2/api",
+                "/v1.2/api.bak",
+                "/v1.2/api.php",
+                "/v1.2/api/",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn progress_total_matches_the_generated_urls() {
+        let server = MockServer::start(|_| MockResponse::new(404, "missing")).await;
+
+        let words = ["admin", "docs/", "index.php"].map(String::from);
+        let extensions = ["php", ".bak"].map(String::from);
+        let urls = generate_urls(
+            &[server.url()],
+            &[0],
+            &words,
+            &extensions,
+            &[0, 0],
+            true,
+            true,
+        );
+        let paths: Vec<&str> = urls
+            .iter()
+            .map(|(_, _, url)| url.strip_prefix(&server.url()).unwrap())
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                "/admin",
+                "/admin/",
+                "/admin.php",
+                "/admin.bak",
+                "/docs/",
+                "/index.php",
+                "/index.php/",
+            ]
+        );
+
+        let (_, progress) = scan_tracked(
+            &words.join("\n"),
+            &[
+                "-u",
+                &server.url(),
+                "-x",
+                "php,.bak",
+                "--add-slash",
+                "--no-double-extension",
+                "--wildcard",
+            ],
+        )
+        .await
+        .unwrap();
+        assert_eq!(progress.position(), (urls.len() as u64, urls.len() as u64));
+        assert_eq!(server.requests().len(), urls.len());
+    }
+
+    #[tokio::test]
+    async fn reflected_origin_with_credentials_is_flagged() {
+        let server = MockServer::start(|req| {
+            let origin = req
+                .headers
+                .iter()
+                .find(|(n, _)| n.eq_ignore_ascii_case("origin"))
+                .map(|(_, v)| v.clone())
+                .unwrap_or_default();
+            match req.path.as_str() {
+                "/api" => MockResponse::new(200, "{}")
+                    .header("Access-Control-Allow-Origin", &origin)
+                    .header("Access-Control-Allow-Credentials", "true"),
+                "/public" => {
+                    MockResponse::new(200, "{}").header("Access-Control-Allow-Origin", "*")
+                }
+                "/plain" => MockResponse::new(200, "page"),
+                _ => MockResponse::new(404, "missing"),
+            }
+        })
+        .await;
+
+        let scratch = Scratch::new();
+        let output = scratch.path("found.txt");
+        let found = scan(
+            "api\npublic\nplain\n",
+            &["--check-cors", "-u", &server.url(), "-o", &output],
+        )
+        .await
+        .unwrap();
+        let written = std::fs::read_to_string(&output).unwrap();
+
+        let mut found: Vec<(String, Option<bool>)> =
+            found.into_iter().map(|r| (r.path, r.cors)).collect();
+        found.sort();
+        assert_eq!(
+            found,
+            [
+                ("/api".to_string(), Some(true)),
+                ("/plain".to_string(), Some(false)),
+                ("/public".to_string(), Some(false)),
+            ]
+        );
+        let flagged: Vec<&str> = written.lines().filter(|l| l.ends_with("[cors]")).collect();
+        assert_eq!(flagged.len(), 1);
+        assert!(flagged[0].starts_with("/api"), "{}", written);
+    }
+
+    #[tokio::test]
+    async fn data_file_is_sent_with_its_content_type() {
+        let server = MockServer::start(|_| MockResponse::new(404, "missing")).await;
+
+        let scratch = Scratch::new();
+        let data = scratch.file("body.json", r#"{"name":"test"}"#);
+        scan(
+            "users\n",
+            &[
+                "-u",
+                &server.url(),
+                "--method",
+                "PUT",
+                "--data-file",
+                &data,
+                "--content-type",
+                "application/json",
+            ],
+        )
+        .await
+        .unwrap();
 
-    for _ in 0..len {
-        result.push(chars[n % chars.len()]);
-        n = n.wrapping_mul(1103515245).wrapping_add(12345);
+        let request = server
+            .requests()
+            .into_iter()
+            .find(|req| req.path == "/users")
+            .unwrap();
+        assert_eq!(request.method, "PUT");
+        assert_eq!(request.body, br#"{"name":"test"}"#);
+        let content_type = request
+            .headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case("content-type"))
+            .map(|(_, v)| v.as_str());
+        assert_eq!(content_type, Some("application/json"));
+    }
+
+    #[tokio::test]
+    async fn calibration_hides_a_catch_all_page() {
+        let server = MockServer::start(|req| match req.path.as_str() {
+            "/admin" => MockResponse::new(200, "<h1>Admin panel</h1>"),
+            _ => MockResponse::new(200, "<h1>Welcome</h1>"),
+        })
+        .await;
+        let http_client = HttpClient::new(HttpConfig::default()).unwrap();
+        let samples =
+            calibration_samples(&http_client, None, &server.url(), None, "GET", None, true).await;
+        assert_eq!(samples.len(), CALIBRATION_PROBES);
+
+        let mut filter = ResponseFilter::default();
+        let calibrated = filter.calibration(&samples, true).unwrap();
+        assert_eq!(calibrated, crate::core::Calibrated::Size(16));
+        filter.exclude(calibrated);
+
+        let catch_all = "<h1>Welcome</h1>";
+        let admin = "<h1>Admin panel</h1>";
+        assert!(!filter
+            .evaluate(200, catch_all.len(), 1, 1, catch_all, None, &[])
+            .is_shown());
+        assert!(filter
+            .evaluate(200, admin.len(), 2, 1, admin, None, &[])
+            .is_shown());
     }
 
-    result
+    #[tokio::test]
+    async fn malformed_status_codes_are_a_config_error() {
+        let Err(crate::error::RbusterError::ConfigError(message)) =
+            scan("admin\n", &["-u", "http://127.0.0.1:9", "-b", "404-400"]).await
+        else {
+            panic!("expected a config error");
+        };
+        assert!(message.contains("--status-codes-blacklist"), "{}", message);
+    }
+
+    #[test]
+    fn backup_extensions_replace_or_append_to_the_defaults() {
+        assert_eq!(
+            backup_extensions(None, BackupExtensionsMode::Replace).len(),
+            BACKUP_EXTENSIONS.len()
+        );
+        assert_eq!(
+            backup_extensions(Some(".php~, .inc,,.inc"), BackupExtensionsMode::Replace),
+            [".php~", ".inc"]
+        );
+        let appended = backup_extensions(Some(".bak,.inc"), BackupExtensionsMode::Append);
+        assert_eq!(appended.len(), BACKUP_EXTENSIONS.len() + 1);
+        assert_eq!(appended.last().map(String::as_str), Some(".inc"));
+    }
+
+    #[test]
+    fn backup_urls_cover_the_name_and_its_stem() {
+        let extensions = vec![".bak".to_string(), "~".to_string()];
+        assert_eq!(
+            backup_candidates("http://t/app/config.php", &extensions),
+            [
+                "http://t/app/config.php.bak",
+                "http://t/app/config.bak",
+                "http://t/app/config.php~",
+                "http://t/app/config~",
+            ]
+        );
+        // Dot files and names without an extension have no stem
+        assert_eq!(
+            backup_candidates("http://t/.env", &extensions),
+            ["http://t/.env.bak", "http://t/.env~"]
+        );
+        assert_eq!(
+            backup_candidates("http://t.example/admin", &extensions),
+            ["http://t.example/admin.bak", "http://t.example/admin~"]
+        );
+    }
 }
@@ -1,33 +1,107 @@
 //! DNS subdomain enumeration mode
 
-use crate::cli::DnsArgs;
-use crate::core::{load_wordlist, DnsClient, DnsConfig};
+use crate::cli::{DnsArgs, DnsRecordType};
+use crate::core::{
+    is_wildcard_answer, load_wordlist, takeover_service, AddressFamily, DnsClient, DnsConfig,
+    HttpClient, HttpConfig, Mutator, Permutator, RateLimiter, RecordType,
+    WILDCARD_REFRESH_INTERVAL,
+};
 use crate::error::Result;
 use crate::output::{
-    print_dns_result, print_error, print_warning, DnsResultJson, OutputHandler, ProgressTracker,
+    print_dns_result, print_error, print_warning, print_zone_transfer, DnsResultJson,
+    OutputHandler, ProgressTracker, ScanMeta,
 };
 use futures::stream::{self, StreamExt};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::net::IpAddr;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
 use std::time::Duration;
-use tokio::sync::Semaphore;
+use tokio::sync::mpsc::UnboundedSender;
 
 /// Run DNS subdomain enumeration
 pub async fn run(args: DnsArgs) -> Result<()> {
+    run_with_results(args, None).await
+}
+
+/// Run DNS subdomain enumeration, also sending each found result to `result_tx`.
+/// Results arrive in completion order, not wordlist order.
+pub async fn run_with_results(
+    args: DnsArgs,
+    result_tx: Option<UnboundedSender<DnsResultJson>>,
+) -> Result<()> {
+    // Failed lookups are mostly names that don't exist, not an overloaded
+    // server, so there is no error rate to steer by
+    if args.global.adaptive {
+        return Err(crate::error::RbusterError::ConfigError(
+            "--adaptive is not supported in dns mode".to_string(),
+        ));
+    }
+
     // Create DNS client
+    let mut record_types: Vec<RecordType> = args
+        .record_types
+        .iter()
+        .map(|t| match t {
+            DnsRecordType::A => RecordType::A,
+            DnsRecordType::Aaaa => RecordType::AAAA,
+            DnsRecordType::Cname => RecordType::CNAME,
+            DnsRecordType::Mx => RecordType::MX,
+            DnsRecordType::Txt => RecordType::TXT,
+            DnsRecordType::Ns => RecordType::NS,
+            DnsRecordType::Soa => RecordType::SOA,
+        })
+        .collect();
+    // Takeover hunting needs the CNAMEs whatever --record-types says
+    if args.cname_only && !record_types.contains(&RecordType::CNAME) {
+        record_types.push(RecordType::CNAME);
+    }
     let dns_config = DnsConfig {
         resolver: args.resolver.clone(),
         timeout: Duration::from_secs(args.timeout),
+        socks_proxy: args.dns_socks.clone(),
+        record_types,
+        address_family: AddressFamily::from_flags(args.ipv4_only, args.ipv6_only),
     };
     let dns_client = Arc::new(DnsClient::new(dns_config).await?);
 
+    // Create HTTP client for liveness checks
+    let http_client = if args.http_check {
+        let http_config = HttpConfig {
+            timeout: Duration::from_secs(args.timeout),
+            insecure: true,
+            address_family: AddressFamily::from_flags(args.ipv4_only, args.ipv6_only),
+            ..HttpConfig::default()
+        };
+        Some(Arc::new(HttpClient::new(http_config)?))
+    } else {
+        None
+    };
+
     // Load wordlist
-    let wordlist = load_wordlist(&args.global.wordlist)
-        .await
-        .map_err(crate::error::RbusterError::WordlistError)?;
+    super::check_stdin_inputs(&args.global.wordlist, &[args.permutation_words.as_deref()])?;
+    let wordlist = super::load_mutated_words(
+        &args.global,
+        Mutator::new(
+            args.mutate.mutate_case,
+            args.mutate.mutate_suffix.as_deref(),
+            args.mutate.mutate_prefix.as_deref(),
+        ),
+    )
+    .await?;
     let total = wordlist.len();
 
+    // Load permutation words
+    let permutator = match args.permutation_words {
+        Some(ref path) if args.permute => Some(Permutator::new(
+            load_wordlist(path)
+                .await
+                .map_err(crate::error::RbusterError::WordlistError)?,
+        )),
+        _ if args.permute => Some(Permutator::default()),
+        _ => None,
+    };
+
     // Normalize domain
     let base_domain = args.domain.trim_start_matches('.').to_string();
 
@@ -35,119 +109,295 @@ pub async fn run(args: DnsArgs) -> Result<()> {
     let progress = ProgressTracker::new(total as u64, args.global.quiet || args.global.no_progress);
 
     // Create output handler
-    let output = OutputHandler::new(args.global.output.as_deref()).await?;
+    let output = OutputHandler::new(
+        &args.global,
+        ScanMeta::new("dns", Some(base_domain.clone()), &args.global),
+    )
+    .await?;
     let output = Arc::new(output);
 
-    // Check for wildcard DNS
-    let wildcard_ips: HashSet<IpAddr> = if !args.wildcard {
-        if let Some(ips) = dns_client.detect_wildcard(&base_domain).await {
-            print_warning(&format!(
-                "Wildcard DNS detected! IPs: {}. Use --wildcard to force continue",
-                ips.iter()
-                    .map(|ip| ip.to_string())
-                    .collect::<Vec<_>>()
-                    .join(", ")
-            ));
-            if !args.global.quiet {
-                ips.into_iter().collect()
-            } else {
-                HashSet::new()
-            }
-        } else {
-            HashSet::new()
+    let verbose = args.global.verbosity();
+
+    // Try a zone transfer first; it can hand out every name at once
+    if args.axfr {
+        let transfers = dns_client.zone_transfer(&base_domain).await;
+        if transfers.is_empty() && verbose >= 1 {
+            print_error(
+                &format!("No nameserver of {} allowed a zone transfer", base_domain),
+                true,
+            );
         }
-    } else {
-        HashSet::new()
-    };
+        for transfer in transfers {
+            print_zone_transfer(
+                &transfer.nameserver,
+                &transfer.address.to_string(),
+                &transfer.records,
+            );
+        }
+    }
+
+    // Check for wildcard DNS, per domain level as deeper names get queued
+    let mut wildcards: HashMap<String, HashSet<IpAddr>> = HashMap::new();
+    if !args.wildcard {
+        if let Some(ips) = detect_wildcard(&dns_client, &base_domain).await {
+            wildcards.insert(base_domain.clone(), ips);
+        }
+    }
+    // Shared with the lookups, which refresh it and add to it under --wildcard-strict
+    let mut wildcards = RwLock::new(wildcards);
+    let lookups = AtomicUsize::new(0);
 
-    // Create semaphore for concurrency control
-    let semaphore = Arc::new(Semaphore::new(args.global.threads));
-    let delay = args.global.delay.map(Duration::from_millis);
+    // Limit concurrency
+    let concurrency = Arc::new(super::concurrency(&args.global, args.global.threads)?);
+    let delay = super::request_delay(&args.global);
+    let limiter = args.global.rate.map(|r| Arc::new(RateLimiter::new(r)));
 
     let show_ips = args.show_ips;
     let show_cname = args.show_cname;
-    let verbose = args.global.verbose;
-
-    // Process subdomains concurrently
-    let _results: Vec<_> = stream::iter(wordlist)
-        .map(|word| {
-            let semaphore = Arc::clone(&semaphore);
-            let dns_client = Arc::clone(&dns_client);
-            let progress = progress.clone();
-            let output = Arc::clone(&output);
-            let base_domain = base_domain.clone();
-            let wildcard_ips = wildcard_ips.clone();
-
-            async move {
-                let _permit = semaphore.acquire().await.unwrap();
-
-                if let Some(d) = delay {
-                    tokio::time::sleep(d).await;
-                }
+    let cname_only = args.cname_only;
+    let check_wildcards = !args.wildcard;
+    let wildcard_strict = args.wildcard_strict;
+    let wildcard_overlap = args.wildcard_overlap;
+
+    // Names already queued, so permutations never repeat a lookup
+    let mut seen: HashSet<String> = wordlist.iter().cloned().collect();
+    let mut probed = HashSet::from([base_domain.clone()]);
+    let mut queue = wordlist;
+    let mut round = 0;
 
-                let subdomain = format!("{}.{}", word, base_domain);
-                let result = dns_client.resolve(&subdomain).await;
-                progress.inc();
+    while !queue.is_empty() {
+        // Process subdomains concurrently
+        let found: Vec<String> = stream::iter(queue)
+            .map(|word| {
+                let concurrency = Arc::clone(&concurrency);
+                let dns_client = Arc::clone(&dns_client);
+                let http_client = http_client.clone();
+                let progress = progress.clone();
+                let limiter = limiter.clone();
+                let output = Arc::clone(&output);
+                let result_tx = result_tx.clone();
+                let base_domain = base_domain.clone();
+                let wildcards = &wildcards;
+                let lookups = &lookups;
 
-                match result {
-                    Ok(dns_result) => {
-                        // Check if this is a wildcard response
-                        let is_wildcard = !wildcard_ips.is_empty()
-                            && dns_result.ips.iter().all(|ip| wildcard_ips.contains(ip));
+                async move {
+                    let _permit = concurrency.acquire().await;
+
+                    delay.wait().await;
+
+                    if let Some(ref limiter) = limiter {
+                        limiter.acquire().await;
+                    }
+
+                    let subdomain = format!("{}.{}", word, base_domain);
+                    let result = dns_client.resolve(&subdomain).await;
+                    progress.inc();
+
+                    let done = lookups.fetch_add(1, Ordering::Relaxed) + 1;
+                    if check_wildcards && done.is_multiple_of(WILDCARD_REFRESH_INTERVAL) {
+                        refresh_wildcards(&dns_client, wildcards).await;
+                    }
+
+                    match result {
+                        Ok(dns_result) => {
+                            // Check if this is a wildcard response at its level
+                            let parent = subdomain
+                                .split_once('.')
+                                .map_or(base_domain.as_str(), |(_, parent)| parent);
+                            let is_wildcard = |wildcards: &HashMap<String, HashSet<IpAddr>>| {
+                                wildcards
+                                    .get(parent)
+                                    .or_else(|| wildcards.get(&base_domain))
+                                    .is_some_and(|ips| {
+                                        is_wildcard_answer(&dns_result.ips, ips, wildcard_overlap)
+                                    })
+                            };
+                            if is_wildcard(&wildcards.read().unwrap()) {
+                                return None;
+                            }
+
+                            // Re-probe the level in case its wildcard changed addresses
+                            if wildcard_strict && !dns_result.ips.is_empty() {
+                                let sibling = dns_client.probe_wildcard(parent).await;
+                                if !sibling.is_empty() {
+                                    let mut wildcards = wildcards.write().unwrap();
+                                    wildcards
+                                        .entry(parent.to_string())
+                                        .or_default()
+                                        .extend(sibling);
+                                    if is_wildcard(&wildcards) {
+                                        return None;
+                                    }
+                                }
+                            }
+
+                            // With --cname-only, keep only CNAMEs whose target doesn't resolve
+                            let dangling = if cname_only {
+                                Some(dns_client.dangling_cname(&dns_result).await?)
+                            } else {
+                                None
+                            };
+                            let takeover = dangling.as_deref().and_then(takeover_service);
 
-                        if !is_wildcard {
                             progress.inc_found();
 
+                            // Check HTTP liveness if requested
+                            let liveness = match http_client {
+                                Some(ref client) => Some(client.probe_host(&subdomain).await),
+                                None => None,
+                            };
+
                             // Print to console
                             print_dns_result(
                                 &subdomain,
                                 &dns_result.ips,
                                 &dns_result.cnames,
                                 show_ips,
-                                show_cname,
+                                show_cname || cname_only,
+                                &[
+                                    ("MX", &dns_result.mx),
+                                    ("TXT", &dns_result.txt),
+                                    ("NS", &dns_result.ns),
+                                    ("SOA", &dns_result.soa),
+                                ],
+                                liveness
+                                    .as_ref()
+                                    .map(|l| l.as_ref().map(|(_, status)| *status)),
+                                takeover,
                             );
 
-                            // Write to file if configured
-                            if let Some(writer) = output.file_writer() {
-                                let result = DnsResultJson {
-                                    subdomain: subdomain.clone(),
-                                    ips: dns_result.ips.iter().map(|ip| ip.to_string()).collect(),
-                                    cnames: dns_result.cnames.clone(),
-                                };
-                                if writer.is_json() {
-                                    let _ = writer.write_json(&result).await;
-                                } else {
-                                    let ips_str = dns_result
-                                        .ips
-                                        .iter()
-                                        .map(|ip| ip.to_string())
-                                        .collect::<Vec<_>>()
-                                        .join(", ");
-                                    let line = format!("{} [{}]", subdomain, ips_str);
-                                    let _ = writer.write_line(&line).await;
+                            // Record result (file output and hooks)
+                            let result = DnsResultJson {
+                                subdomain: subdomain.clone(),
+                                ips: dns_result.ips.iter().map(|ip| ip.to_string()).collect(),
+                                cnames: dns_result.cnames.clone(),
+                                live: liveness.as_ref().map(|l| l.is_some()),
+                                http_url: liveness
+                                    .as_ref()
+                                    .and_then(|l| l.as_ref().map(|(url, _)| url.clone())),
+                                http_status: liveness
+                                    .as_ref()
+                                    .and_then(|l| l.as_ref().map(|(_, status)| *status)),
+                                mx: dns_result.mx.clone(),
+                                txt: dns_result.txt.clone(),
+                                ns: dns_result.ns.clone(),
+                                soa: dns_result.soa.clone(),
+                                takeover: takeover.map(str::to_string),
+                            };
+                            let ips_str = dns_result
+                                .ips
+                                .iter()
+                                .map(|ip| ip.to_string())
+                                .collect::<Vec<_>>()
+                                .join(", ");
+                            let mut line = format!("{} [{}]", subdomain, ips_str);
+                            for (label, records) in [
+                                ("MX", &result.mx),
+                                ("TXT", &result.txt),
+                                ("NS", &result.ns),
+                                ("SOA", &result.soa),
+                            ] {
+                                if !records.is_empty() {
+                                    line.push_str(&format!(" [{}: {}]", label, records.join(", ")));
                                 }
                             }
+                            if let Some(status) = result.http_status {
+                                line.push_str(&format!(" (HTTP: {})", status));
+                            }
+                            if let Some(ref target) = dangling {
+                                line.push_str(&format!(" [CNAME: {}]", target));
+                            }
+                            if let Some(service) = takeover {
+                                line.push_str(&format!(" (possible takeover: {})", service));
+                            }
+                            output.record(&result, &line).await;
+                            if let Some(ref result_tx) = result_tx {
+                                let _ = result_tx.send(result.clone());
+                            }
 
-                            Some(dns_result)
-                        } else {
+                            Some(word)
+                        }
+                        Err(e) => {
+                            if verbose >= 1 {
+                                print_error(&format!("{}: {}", subdomain, e), true);
+                            }
                             None
                         }
                     }
-                    Err(e) => {
-                        if verbose {
-                            print_error(&format!("{}: {}", subdomain, e), true);
-                        }
-                        None
+                }
+            })
+            .buffer_unordered(args.global.threads)
+            .filter_map(|word| async move { word })
+            .collect()
+            .await;
+
+        // Queue permutations of this round's finds
+        queue = match permutator {
+            Some(ref permutator) if round < args.permute_depth => found
+                .iter()
+                .flat_map(|word| permutator.permute(word))
+                .filter(|name| seen.insert(name.clone()))
+                .collect(),
+            _ => Vec::new(),
+        };
+        progress.inc_length(queue.len() as u64);
+
+        // Probe the new levels permutations reach (staging.dev-api under dev-api)
+        let wildcards = wildcards.get_mut().unwrap();
+        if !args.wildcard {
+            for name in &queue {
+                let Some((_, parent)) = name.split_once('.') else {
+                    continue;
+                };
+                let level = format!("{}.{}", parent, base_domain);
+                if probed.insert(level.clone()) {
+                    if let Some(ips) = detect_wildcard(&dns_client, &level).await {
+                        wildcards.insert(level, ips);
                     }
                 }
             }
-        })
-        .buffer_unordered(args.global.threads)
-        .collect()
-        .await;
+        }
+        round += 1;
+    }
 
     progress.finish();
-    output.finalize().await?;
+    if let Some(ref http_client) = http_client {
+        super::warn_dead_proxies(http_client);
+        super::warn_rate_limited(http_client.rate_limited());
+    }
+    progress.print_summary(args.global.quiet);
+    output.finalize(&progress.stats()).await?;
 
     Ok(())
 }
+
+/// Probe the known wildcard levels again and add any new addresses they answer with
+async fn refresh_wildcards(
+    dns_client: &DnsClient,
+    wildcards: &RwLock<HashMap<String, HashSet<IpAddr>>>,
+) {
+    let levels: Vec<String> = wildcards.read().unwrap().keys().cloned().collect();
+    for level in levels {
+        if let Some(ips) = dns_client.detect_wildcard(&level).await {
+            wildcards
+                .write()
+                .unwrap()
+                .entry(level)
+                .or_default()
+                .extend(ips);
+        }
+    }
+}
+
+/// Probe `domain` for wildcard DNS, warning about the addresses it answers with
+async fn detect_wildcard(dns_client: &DnsClient, domain: &str) -> Option<HashSet<IpAddr>> {
+    let ips = dns_client.detect_wildcard(domain).await?;
+    let mut listed: Vec<String> = ips.iter().map(|ip| ip.to_string()).collect();
+    listed.sort();
+    print_warning(&format!(
+        "Wildcard DNS detected for *.{}! IPs: {}. Names resolving only to these are hidden; use --wildcard to show them",
+        domain,
+        listed.join(", ")
+    ));
+    Some(ips)
+}
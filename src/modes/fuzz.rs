@@ -1,203 +1,455 @@
 //! Fuzzing mode with FUZZ keyword replacement
 
-use crate::cli::FuzzArgs;
-use crate::core::{load_wordlist, parse_headers};
+use crate::cli::{FuzzArgs, FuzzMode};
+use crate::core::{
+    body_counts, header_content_type, header_cors, header_pairs, load_raw_template,
+    load_wordlist_with, parse_content_types, parse_header_matchers, random_label, read_limited,
+    CalibrationSample, CorsGrant, ErrorLimit, HttpClient, HttpConfig, LengthMatcher, RateLimiter,
+    RawHttpClient, RequestDelay, ResponseCache, ResponseFilter,
+};
 use crate::error::Result;
-use crate::output::{print_error, print_fuzz_result, FuzzResult, OutputHandler, ProgressTracker};
+use crate::output::{
+    print_calibration, print_concurrency, print_cors_finding, print_error, print_fuzz_result,
+    print_response, print_warning, FuzzResult, OutputHandler, ProgressTracker, ScanMeta,
+};
 use futures::stream::{self, StreamExt};
-use reqwest::{ClientBuilder, Method};
-use std::collections::HashSet;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use reqwest::Method;
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::Semaphore;
+use tokio::sync::mpsc::UnboundedSender;
 
 const FUZZ_KEYWORD: &str = "FUZZ";
+const FUZZ1_KEYWORD: &str = "FUZZ1";
+const FUZZ2_KEYWORD: &str = "FUZZ2";
 
-/// Run fuzzing mode
-pub async fn run(args: FuzzArgs) -> Result<()> {
-    // Validate FUZZ keyword is present
-    let has_fuzz_in_url = args.url.contains(FUZZ_KEYWORD);
-    let has_fuzz_in_headers = args.http.headers.iter().any(|h| h.contains(FUZZ_KEYWORD));
-    let has_fuzz_in_data = args
-        .data
-        .as_ref()
-        .map(|d| d.contains(FUZZ_KEYWORD))
-        .unwrap_or(false);
-
-    if !has_fuzz_in_url && !has_fuzz_in_headers && !has_fuzz_in_data {
-        return Err(crate::error::RbusterError::ConfigError(
-            "FUZZ keyword not found in URL, headers, or data".to_string(),
-        ));
-    }
-
-    // Parse exclude status codes and lengths
-    let exclude_status: HashSet<u16> = args
-        .exclude_status
-        .as_ref()
-        .map(|s| s.split(',').filter_map(|c| c.trim().parse().ok()).collect())
-        .unwrap_or_default();
+/// Random payloads sent for auto-calibration
+const CALIBRATION_PROBES: usize = 4;
 
-    let exclude_lengths: HashSet<usize> = args
-        .exclude_length
-        .as_ref()
-        .map(|s| s.split(',').filter_map(|l| l.trim().parse().ok()).collect())
-        .unwrap_or_default();
+/// Everything but unreserved characters (RFC 3986) is encoded in payloads
+const PAYLOAD_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
 
-    // Build HTTP client
-    let mut builder = ClientBuilder::new()
-        .user_agent(&args.http.user_agent)
-        .timeout(Duration::from_secs(args.http.timeout))
-        .danger_accept_invalid_certs(args.http.insecure)
-        .pool_max_idle_per_host(100)
-        .tcp_nodelay(true);
+/// Run fuzzing mode
+pub async fn run(args: FuzzArgs) -> Result<()> {
+    run_with_results(args, None).await
+}
 
-    if !args.http.follow_redirect {
-        builder = builder.redirect(reqwest::redirect::Policy::none());
+/// Run fuzzing mode, also sending each found result to `result_tx`.
+/// Results arrive in completion order, not wordlist order.
+pub async fn run_with_results(
+    args: FuzzArgs,
+    result_tx: Option<UnboundedSender<FuzzResult>>,
+) -> Result<()> {
+    // Load the body template
+    let base_data = match (&args.data, &args.data_file) {
+        (Some(data), _) => Some(data.clone()),
+        (None, Some(path)) => Some(tokio::fs::read_to_string(path).await.map_err(|e| {
+            crate::error::RbusterError::ConfigError(format!(
+                "Cannot read data file '{}': {}",
+                path.display(),
+                e
+            ))
+        })?),
+        (None, None) => None,
+    };
+
+    // Parameter discovery adds the FUZZ parameter with a value to look for
+    // in responses
+    let canary = args.param_discovery.then(|| random_label(10));
+    let url = match canary {
+        Some(ref canary) => {
+            if args.url.contains(FUZZ_KEYWORD) {
+                return Err(crate::error::RbusterError::ConfigError(
+                    "--param-discovery adds the FUZZ parameter itself; remove FUZZ from the URL"
+                        .to_string(),
+                ));
+            }
+            let separator = if args.url.contains('?') { '&' } else { '?' };
+            format!("{}{}{}={}", args.url, separator, FUZZ_KEYWORD, canary)
+        }
+        None => args.url.clone(),
+    };
+
+    // Load the raw request template; the URL then only names the target
+    let raw = match args.raw {
+        Some(ref path) => Some((
+            RawHttpClient::new(
+                &args.url,
+                args.http.insecure,
+                Duration::from_secs(args.http.timeout),
+                !args.http.ignore_retry_after,
+            )?,
+            load_raw_template(path).await?,
+        )),
+        None => None,
+    };
+    let keyword_sources = if raw.is_some() {
+        "the raw request"
+    } else {
+        "URL, headers, or data"
+    };
+
+    // Validate FUZZ keywords are present (FUZZ1 contains FUZZ, so one check covers both)
+    let has_keyword = |keyword: &str| match raw {
+        Some((_, ref template)) => template.contains(keyword),
+        None => {
+            url.contains(keyword)
+                || args.http.headers.iter().any(|h| h.contains(keyword))
+                || args
+                    .http
+                    .cookies
+                    .as_ref()
+                    .is_some_and(|c| c.contains(keyword))
+                || base_data.as_ref().is_some_and(|d| d.contains(keyword))
+        }
+    };
+
+    if !has_keyword(FUZZ_KEYWORD) {
+        return Err(crate::error::RbusterError::ConfigError(format!(
+            "FUZZ keyword not found in {}",
+            keyword_sources
+        )));
     }
-
-    if let Some(ref proxy_url) = args.http.proxy {
-        builder = builder.proxy(reqwest::Proxy::all(proxy_url)?);
+    if args.wordlist2.is_some() && !has_keyword(FUZZ2_KEYWORD) {
+        return Err(crate::error::RbusterError::ConfigError(format!(
+            "--wordlist2 requires a FUZZ2 keyword in {}",
+            keyword_sources
+        )));
+    }
+    if args.wordlist2.is_none() && has_keyword(FUZZ2_KEYWORD) {
+        return Err(crate::error::RbusterError::ConfigError(format!(
+            "FUZZ2 in {} needs a second wordlist (--wordlist2)",
+            keyword_sources
+        )));
     }
 
-    let client = Arc::new(builder.build()?);
+    // Build response filters
+    let mut filter = ResponseFilter {
+        exclude_status: args
+            .exclude_status
+            .as_ref()
+            .map(|s| s.split(',').filter_map(|c| c.trim().parse().ok()).collect())
+            .unwrap_or_default(),
+        exclude_length: LengthMatcher::parse(args.exclude_length.as_deref())?,
+        match_words: args.match_words.clone(),
+        exclude_words: args.exclude_words.clone(),
+        match_lines: args.match_lines.clone(),
+        exclude_lines: args.exclude_lines.clone(),
+        match_string: args.match_string.clone(),
+        exclude_string: args.filter_string.clone(),
+        match_content_type: args.match_content_type.as_deref().map(parse_content_types),
+        exclude_content_type: args.filter_content_type.as_deref().map(parse_content_types),
+        match_headers: parse_header_matchers(&args.match_headers)?,
+        exclude_headers: parse_header_matchers(&args.filter_headers)?,
+        ..Default::default()
+    };
+
+    // Build HTTP client. Headers and cookies may contain FUZZ keywords, so
+    // they are filled in per request instead of by the client
+    let http_client = Arc::new(HttpClient::new(HttpConfig {
+        headers: HashMap::new(),
+        cookies: None,
+        ..super::http_config(&args.http)
+    })?);
 
     // Load wordlist
-    let wordlist = load_wordlist(&args.global.wordlist)
-        .await
-        .map_err(crate::error::RbusterError::WordlistError)?;
-    let total = wordlist.len();
+    super::check_stdin_inputs(&args.global.wordlist, &[args.wordlist2.as_deref()])?;
+    let wordlist = super::load_words(&args.global).await?;
+    let wordlist2_given = args.wordlist2.is_some();
+    let wordlist2 = match args.wordlist2 {
+        Some(ref path) => Some(
+            load_wordlist_with(path, super::wordlist_options(&args.global))
+                .await
+                .map_err(crate::error::RbusterError::WordlistError)?,
+        ),
+        None => None,
+    };
+
+    // Combine wordlists into payload tuples
+    let (total, payloads): (usize, Box<dyn Iterator<Item = Vec<String>> + Send + '_>) =
+        match (wordlist2.as_ref(), args.fuzz_mode) {
+            (None, _) => (
+                wordlist.len(),
+                Box::new(wordlist.into_iter().map(|w| vec![w])),
+            ),
+            (Some(second), FuzzMode::Product) => (
+                wordlist.len() * second.len(),
+                Box::new(
+                    wordlist
+                        .into_iter()
+                        .flat_map(move |a| second.iter().map(move |b| vec![a.clone(), b.clone()])),
+                ),
+            ),
+            (Some(second), FuzzMode::Zip) => (
+                wordlist.len().min(second.len()),
+                Box::new(
+                    wordlist
+                        .into_iter()
+                        .zip(second.iter().cloned())
+                        .map(|(a, b)| vec![a, b]),
+                ),
+            ),
+        };
 
     // Create progress tracker
     let progress = ProgressTracker::new(total as u64, args.global.quiet || args.global.no_progress);
 
     // Create output handler
-    let output = OutputHandler::new(args.global.output.as_deref()).await?;
+    let output = OutputHandler::new(
+        &args.global,
+        ScanMeta::new("fuzz", Some(args.url.clone()), &args.global).with_http(&args.http),
+    )
+    .await?;
     let output = Arc::new(output);
-
-    // Create semaphore for concurrency control
-    let semaphore = Arc::new(Semaphore::new(args.global.threads));
-    let delay = args.global.delay.map(Duration::from_millis);
-
-    let _base_headers = parse_headers(&args.http.headers);
-    let raw_headers = args.http.headers.clone();
-    let verbose = args.global.verbose;
-    let base_url = args.url.clone();
-    let base_data = args.data.clone();
+    let saver = super::response_saver(
+        args.save_responses.as_deref(),
+        args.max_response_size,
+        args.save_headers,
+    )
+    .await?;
+
+    // Open response cache if configured
+    let cache = match args.cache {
+        Some(ref dir) => Some(Arc::new(
+            ResponseCache::new(dir, args.cache_ttl.map(Duration::from_secs), args.no_cache).await?,
+        )),
+        None => None,
+    };
+
+    // Limit concurrency, adjusted to the error rate with --adaptive
+    let concurrency = Arc::new(super::concurrency(&args.global, args.global.threads)?);
+    let delay = super::request_delay(&args.global);
+    let limiter = args.global.rate.map(|r| Arc::new(RateLimiter::new(r)));
+    let error_limit = Arc::new(ErrorLimit::new(args.global.max_errors));
+
+    let mut raw_headers = args.http.headers.clone();
+    let cors_origin = args
+        .check_cors
+        .then(|| super::cors_origin(&mut raw_headers));
+    let verbose = args.global.verbosity();
+    let base_url = url;
     let method_str = args.http.method.clone();
-    let filter_string = args.filter_string.clone();
+    let encode_url = args.encode || args.encode_all;
+    let encode_all = args.encode_all;
+    // A Content-Type given with -H takes precedence over the detected one
+    let content_type = base_data.as_deref().and_then(|data| {
+        let overridden = raw_headers.iter().any(|h| {
+            h.split_once(':')
+                .is_some_and(|(name, _)| name.trim().eq_ignore_ascii_case("content-type"))
+        });
+        (!overridden)
+            .then(|| body_content_type(data, &method_str))
+            .flatten()
+    });
     let cookies = args.http.cookies.clone();
 
+    let sender = PayloadSender {
+        http_client: &http_client,
+        raw: raw.as_ref(),
+        base_url: &base_url,
+        headers: &raw_headers,
+        data: base_data.as_deref(),
+        method: &method_str,
+        content_type,
+        cookies: cookies.as_deref(),
+        cache: cache.as_deref(),
+        delay,
+        limiter: limiter.as_deref(),
+        encode_url,
+        encode_all,
+        capture_headers: verbose >= 3 || args.save_headers || filter.needs_headers(),
+        max_body: args
+            .save_responses
+            .is_some()
+            .then_some(args.max_response_size),
+    };
+
+    // Hide responses like those to random payloads; for parameter discovery
+    // they are unknown parameters
+    let mut baseline_reflections = 0;
+    let mut baseline_statuses = Vec::new();
+    if args.autocalibrate || args.param_discovery {
+        let width = if wordlist2_given { 2 } else { 1 };
+        let probe = PayloadSender {
+            cache: None,
+            ..sender
+        };
+        let mut samples = Vec::with_capacity(CALIBRATION_PROBES);
+        for i in 0..CALIBRATION_PROBES {
+            // Varying lengths expose pages that echo the payload in their size
+            let words: Vec<String> = (0..width).map(|_| random_label(8 + 4 * i)).collect();
+            if let Ok(response) = probe.send(&words).await {
+                let text = String::from_utf8_lossy(&response.body);
+                let body = without_echo(&text, &words, canary.as_deref());
+                if let Some(ref canary) = canary {
+                    baseline_reflections = baseline_reflections.max(reflections(&body, canary));
+                }
+                baseline_statuses.push(response.status);
+                let (words, lines) = body_counts(&body);
+                samples.push(CalibrationSample {
+                    status: response.status,
+                    size: response.size.saturating_sub(text.len() - body.len()),
+                    words,
+                    lines,
+                });
+            }
+        }
+        let message = match filter.calibration(&samples, true) {
+            Some(calibrated) => {
+                filter.exclude(calibrated);
+                format!("Calibrated on random payloads: hiding {}", calibrated)
+            }
+            None => "Calibration found nothing to hide".to_string(),
+        };
+        if !args.global.quiet {
+            print_calibration(&message);
+        }
+    }
+
     // Process payloads concurrently
-    let _results: Vec<_> = stream::iter(wordlist)
-        .map(|payload| {
-            let semaphore = Arc::clone(&semaphore);
-            let client = Arc::clone(&client);
+    let _results: Vec<_> = stream::iter(payloads)
+        .map(|words| {
+            let concurrency = Arc::clone(&concurrency);
             let progress = progress.clone();
+            let error_limit = Arc::clone(&error_limit);
             let output = Arc::clone(&output);
-            let exclude_status = exclude_status.clone();
-            let exclude_lengths = exclude_lengths.clone();
-            let base_url = base_url.clone();
-            let raw_headers = raw_headers.clone();
-            let base_data = base_data.clone();
-            let method_str = method_str.clone();
-            let filter_string = filter_string.clone();
-            let cookies = cookies.clone();
+            let result_tx = result_tx.clone();
+            let filter = &filter;
+            let sender = &sender;
+            let canary = canary.as_deref();
+            let cors_origin = cors_origin.as_deref();
+            let baseline_statuses = &baseline_statuses;
+            let saver = saver.as_ref();
 
             async move {
-                let _permit = semaphore.acquire().await.unwrap();
+                let _permit = concurrency.acquire().await;
 
-                if let Some(d) = delay {
-                    tokio::time::sleep(d).await;
+                if error_limit.tripped() {
+                    return None;
                 }
 
-                // Replace FUZZ keyword
-                let url = base_url.replace(FUZZ_KEYWORD, &payload);
-                let data = base_data
-                    .as_ref()
-                    .map(|d| d.replace(FUZZ_KEYWORD, &payload));
-
-                // Build request
-                let method = Method::from_bytes(method_str.as_bytes()).unwrap_or(Method::GET);
-                let mut request = client.request(method, &url);
-
-                // Replace FUZZ in headers
-                for raw_header in &raw_headers {
-                    let replaced = raw_header.replace(FUZZ_KEYWORD, &payload);
-                    let parts: Vec<&str> = replaced.splitn(2, ':').collect();
-                    if parts.len() == 2 {
-                        request = request.header(parts[0].trim(), parts[1].trim());
-                    }
-                }
+                let payload = payload_label(&words);
+                let result = sender.send(&words).await;
+                progress.inc();
 
-                // Add cookies
-                if let Some(ref c) = cookies {
-                    request = request.header("Cookie", c.replace(FUZZ_KEYWORD, &payload));
-                }
+                concurrency.record(result.is_ok());
 
-                // Add body if present
-                if let Some(ref body) = data {
-                    request = request.body(body.clone());
-                    if method_str == "POST" {
-                        request =
-                            request.header("Content-Type", "application/x-www-form-urlencoded");
-                    }
+                if result.is_ok() {
+                    error_limit.success();
+                } else if error_limit.failure() {
+                    print_warning(&error_limit.message());
                 }
 
-                let result = request.send().await;
-                progress.inc();
-
                 match result {
-                    Ok(response) => {
-                        let status = response.status().as_u16();
-                        let body = response.text().await.unwrap_or_default();
-                        let size = body.len();
-                        let words = body.split_whitespace().count();
-                        let lines = body.lines().count();
-
-                        // Apply filters
-                        let mut should_show =
-                            !exclude_status.contains(&status) && !exclude_lengths.contains(&size);
-
-                        // Apply string filter
-                        if let Some(ref filter) = filter_string {
-                            if body.contains(filter) {
-                                should_show = false;
-                            }
+                    Ok(FuzzResponse {
+                        status,
+                        size,
+                        body: bytes,
+                        headers,
+                        content_type,
+                        cors,
+                    }) => {
+                        let body = String::from_utf8_lossy(&bytes);
+                        // Parameters are compared to the baseline without the
+                        // URL a page may echo back
+                        let compared = without_echo(&body, &words, canary);
+                        let (compared_words, compared_lines) = body_counts(&compared);
+                        let (words, lines) = body_counts(&body);
+                        let outcome = filter.evaluate(
+                            status,
+                            size.saturating_sub(body.len() - compared.len()),
+                            compared_words,
+                            compared_lines,
+                            &body,
+                            content_type.as_deref(),
+                            &headers,
+                        );
+                        // A parameter that is reflected or changes the status
+                        // is worth reporting even when the page otherwise
+                        // looks like the baseline
+                        let reflected =
+                            canary.map(|c| reflections(&compared, c) > baseline_reflections);
+                        let new_status = canary.is_some()
+                            && !baseline_statuses.contains(&status)
+                            && !filter.exclude_status.contains(&status);
+                        let shown = outcome.is_shown() || reflected == Some(true) || new_status;
+                        if verbose >= 2 {
+                            print_response(
+                                &payload,
+                                status,
+                                size,
+                                super::shown_headers(verbose, &headers),
+                                super::hidden(&outcome).filter(|_| !shown).as_deref(),
+                            );
                         }
 
-                        if should_show {
+                        if shown {
                             progress.inc_found();
+                            progress.record_status(status);
 
                             // Print to console
-                            print_fuzz_result(&payload, status, size, words, lines);
-
-                            // Write to file if configured
-                            if let Some(writer) = output.file_writer() {
-                                let result = FuzzResult {
-                                    payload: payload.clone(),
-                                    status,
-                                    size,
-                                    words,
-                                    lines,
-                                };
-                                if writer.is_json() {
-                                    let _ = writer.write_json(&result).await;
-                                } else {
-                                    let line = format!(
-                                        "{} [Status: {}, Size: {}, Words: {}, Lines: {}]",
-                                        payload, status, size, words, lines
-                                    );
-                                    let _ = writer.write_line(&line).await;
+                            print_fuzz_result(
+                                &payload,
+                                status,
+                                size,
+                                words,
+                                lines,
+                                reflected == Some(true),
+                            );
+                            let cors = super::cors_finding(cors_origin, cors.as_ref());
+                            if let (Some(true), Some(origin)) = (cors, cors_origin) {
+                                print_cors_finding(&payload, origin);
+                            }
+                            if let Some(saver) = saver {
+                                if let Err(e) = saver.save(&payload, status, &headers, &bytes).await
+                                {
+                                    print_warning(&format!(
+                                        "Cannot save response of {}: {}",
+                                        payload, e
+                                    ));
                                 }
                             }
 
+                            // Record result (file output and hooks)
+                            let result = FuzzResult {
+                                payload: payload.clone(),
+                                status,
+                                size,
+                                words,
+                                lines,
+                                content_type,
+                                reflected,
+                                cors,
+                            };
+                            let mut line = format!(
+                                "{} [Status: {}, Size: {}, Words: {}, Lines: {}]",
+                                payload, status, size, words, lines
+                            );
+                            if reflected == Some(true) {
+                                line.push_str(" [reflected]");
+                            }
+                            if cors == Some(true) {
+                                line.push_str(" [cors]");
+                            }
+                            output.record(&result, &line).await;
+                            if let Some(ref result_tx) = result_tx {
+                                let _ = result_tx.send(result.clone());
+                            }
+
                             Some((payload, status, size))
                         } else {
                             None
                         }
                     }
                     Err(e) => {
-                        if verbose {
+                        progress.inc_error();
+                        if verbose >= 1 {
                             print_error(&format!("{}: {}", payload, e), true);
                         }
                         None
@@ -210,7 +462,514 @@ pub async fn run(args: FuzzArgs) -> Result<()> {
         .await;
 
     progress.finish();
-    output.finalize().await?;
+    super::warn_dead_proxies(&http_client);
+    super::warn_rate_limited(
+        http_client.rate_limited()
+            + raw
+                .as_ref()
+                .map_or(0, |(raw_client, _)| raw_client.rate_limited()),
+    );
+    progress.print_summary(args.global.quiet);
+    print_concurrency(&concurrency, args.global.quiet);
+    output.finalize(&progress.stats()).await?;
 
     Ok(())
 }
+
+/// Response to one payload
+struct FuzzResponse {
+    status: u16,
+    /// Length of the whole body, which may be longer than `body`
+    size: usize,
+    /// Body bytes, cut to --max-response-size when saving responses
+    body: Vec<u8>,
+    /// Response headers, only kept when requested
+    headers: Vec<(String, String)>,
+    content_type: Option<String>,
+    cors: Option<CorsGrant>,
+}
+
+/// Fills the request template with payloads and sends it; shared by
+/// calibration and the scan
+#[derive(Clone, Copy)]
+struct PayloadSender<'a> {
+    http_client: &'a HttpClient,
+    raw: Option<&'a (RawHttpClient, String)>,
+    base_url: &'a str,
+    headers: &'a [String],
+    data: Option<&'a str>,
+    method: &'a str,
+    content_type: Option<&'static str>,
+    cookies: Option<&'a str>,
+    cache: Option<&'a ResponseCache>,
+    delay: RequestDelay,
+    limiter: Option<&'a RateLimiter>,
+    encode_url: bool,
+    encode_all: bool,
+    capture_headers: bool,
+    max_body: Option<usize>,
+}
+
+impl PayloadSender<'_> {
+    async fn send(&self, words: &[String]) -> Result<FuzzResponse> {
+        // Replace FUZZ keywords, encoding payloads where asked
+        let encoded = self.encode_url.then(|| encode_payloads(words));
+        let url_words = encoded.as_deref().unwrap_or(words);
+        let words = match encoded {
+            Some(ref encoded) if self.encode_all => encoded,
+            _ => words,
+        };
+
+        if let Some((raw_client, template)) = self.raw {
+            self.throttle().await;
+            let request = fill_keywords(template, url_words);
+            let check = raw_client.send(request.as_bytes(), true).await?;
+            let mut body = check.body.unwrap_or_default();
+            body.truncate(self.max_body.unwrap_or(usize::MAX));
+            return Ok(FuzzResponse {
+                status: check.status,
+                size: check.size,
+                body,
+                headers: check.headers,
+                content_type: check.content_type,
+                cors: check.cors,
+            });
+        }
+
+        // Build request
+        let url = fill_keywords(self.base_url, url_words);
+        let data = self.data.map(|d| fill_keywords(d, words));
+        let method = Method::from_bytes(self.method.as_bytes()).unwrap_or(Method::GET);
+        let mut request = self.http_client.request_builder(method, &url);
+
+        // Replace FUZZ in headers
+        let replaced_headers: Vec<String> = self
+            .headers
+            .iter()
+            .map(|h| fill_keywords(h, words))
+            .collect();
+        for replaced in &replaced_headers {
+            let parts: Vec<&str> = replaced.splitn(2, ':').collect();
+            if parts.len() == 2 {
+                request = request.header(parts[0].trim(), parts[1].trim());
+            }
+        }
+
+        // Add cookies
+        let cookie = self.cookies.map(|c| fill_keywords(c, words));
+        if let Some(ref c) = cookie {
+            request = request.header("Cookie", c.as_str());
+        }
+
+        // Add body if present
+        if let Some(ref body) = data {
+            request = request.body(body.clone());
+            if let Some(content_type) = self.content_type {
+                request = request.header("Content-Type", content_type);
+            }
+        }
+
+        // Serve from cache when possible
+        let cache_key = self.cache.map(|_| {
+            let mut key_headers = replaced_headers.clone();
+            key_headers.extend(cookie.iter().map(|c| format!("Cookie: {}", c)));
+            ResponseCache::key(self.method, &url, &key_headers, data.as_deref())
+        });
+        if let (Some(cache), Some(key)) = (self.cache, &cache_key) {
+            if let Some(hit) = cache.get(key).await {
+                let cors = CorsGrant::from_headers(
+                    hit.headers.iter().map(|(n, v)| (n.as_str(), v.as_str())),
+                );
+                return Ok(FuzzResponse {
+                    status: hit.status,
+                    size: hit.size.unwrap_or(hit.body.len()),
+                    body: hit.body.into_bytes(),
+                    headers: if self.capture_headers {
+                        hit.headers
+                    } else {
+                        Vec::new()
+                    },
+                    content_type: hit.content_type,
+                    cors,
+                });
+            }
+        }
+
+        self.throttle().await;
+        let response = self.http_client.send(request).await?;
+        let status = response.status().as_u16();
+        // Cached entries keep every header so later runs can filter on them
+        let mut headers = if self.capture_headers || self.cache.is_some() {
+            header_pairs(response.headers())
+        } else {
+            Vec::new()
+        };
+        let content_type = header_content_type(response.headers());
+        let cors = header_cors(response.headers());
+        let (body, size) = read_limited(response, self.max_body)
+            .await
+            .unwrap_or_default();
+        if let (Some(cache), Some(key)) = (self.cache, &cache_key) {
+            let text = String::from_utf8_lossy(&body);
+            let _ = cache
+                .put(key, status, size, &text, &headers, content_type.as_deref())
+                .await;
+            if !self.capture_headers {
+                headers.clear();
+            }
+        }
+        Ok(FuzzResponse {
+            status,
+            size,
+            body,
+            headers,
+            content_type,
+            cors,
+        })
+    }
+
+    /// Wait out --delay and the rate limit before a request
+    async fn throttle(&self) {
+        self.delay.wait().await;
+        if let Some(limiter) = self.limiter {
+            limiter.acquire().await;
+        }
+    }
+}
+
+/// Content-Type for a body template: JSON when it looks like JSON, form
+/// encoding for other POST bodies
+fn body_content_type(data: &str, method: &str) -> Option<&'static str> {
+    let data = data.trim_start();
+    if data.starts_with('{') || data.starts_with('[') {
+        Some("application/json")
+    } else if method.eq_ignore_ascii_case("POST") {
+        Some("application/x-www-form-urlencoded")
+    } else {
+        None
+    }
+}
+
+/// Replace FUZZ1/FUZZ2 with their words, plain FUZZ being an alias for FUZZ1,
+/// in one pass, so keywords inside a word are never replaced again
+fn fill_keywords(template: &str, words: &[String]) -> String {
+    let mut filled = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(at) = rest.find(FUZZ_KEYWORD) {
+        filled.push_str(&rest[..at]);
+        rest = &rest[at..];
+        let (word, keyword) = if rest.starts_with(FUZZ1_KEYWORD) {
+            (words.first(), FUZZ1_KEYWORD)
+        } else if rest.starts_with(FUZZ2_KEYWORD) {
+            (words.get(1), FUZZ2_KEYWORD)
+        } else {
+            (words.first(), FUZZ_KEYWORD)
+        };
+        filled.push_str(word.map_or(keyword, String::as_str));
+        rest = &rest[keyword.len()..];
+    }
+    filled.push_str(rest);
+    filled
+}
+
+/// Response body without the `word=canary` pair a page echoing the request
+/// URL repeats, so parameter discovery compares pages, not URL lengths
+fn without_echo<'a>(body: &'a str, words: &[String], canary: Option<&str>) -> Cow<'a, str> {
+    match (canary, words.first()) {
+        (Some(canary), Some(word)) => {
+            let echo = format!("{}={}", word, canary);
+            if body.contains(&echo) {
+                Cow::Owned(body.replace(&echo, ""))
+            } else {
+                Cow::Borrowed(body)
+            }
+        }
+        _ => Cow::Borrowed(body),
+    }
+}
+
+/// Times the parameter value appears in a body
+fn reflections(body: &str, canary: &str) -> usize {
+    body.matches(canary).count()
+}
+
+/// Percent-encode each payload for substitution
+fn encode_payloads(words: &[String]) -> Vec<String> {
+    words
+        .iter()
+        .map(|w| utf8_percent_encode(w, PAYLOAD_ENCODE_SET).to_string())
+        .collect()
+}
+
+/// Display form of a payload tuple
+fn payload_label(words: &[String]) -> String {
+    match words {
+        [single] => single.clone(),
+        _ => words
+            .iter()
+            .enumerate()
+            .map(|(i, w)| format!("FUZZ{}={}", i + 1, w))
+            .collect::<Vec<_>>()
+            .join(" "),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{Cli, Commands};
+    use crate::core::mock_server::{MockResponse, MockServer};
+    use clap::Parser;
+    use tokio::sync::mpsc;
+
+    #[tokio::test]
+    async fn param_discovery_reports_reflecting_parameter() {
+        // Every page echoes the request URL; only `q` also prints its value
+        let server = MockServer::start(|req| {
+            let query = req.path.split_once('?').map_or("", |(_, q)| q);
+            let value = query
+                .split('&')
+                .find_map(|pair| pair.strip_prefix("q="))
+                .unwrap_or_default();
+            let body = format!("<p>You asked for {}</p><p>{}</p>", req.path, value);
+            MockResponse::new(200, body)
+        })
+        .await;
+
+        let wordlist = std::env::temp_dir().join(format!("rbuster-{}.txt", random_label(12)));
+        std::fs::write(&wordlist, "id\nq\npage\nsort\n").unwrap();
+        let url = format!("{}/search", server.url());
+        let cli = Cli::parse_from([
+            "robuster",
+            "fuzz",
+            "-q",
+            "-u",
+            &url,
+            "-w",
+            wordlist.to_str().unwrap(),
+            "--param-discovery",
+        ]);
+        let Commands::Fuzz(args) = cli.command else {
+            unreachable!()
+        };
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let run = run_with_results(args, Some(tx)).await;
+        std::fs::remove_file(&wordlist).unwrap();
+        run.unwrap();
+
+        let mut found = Vec::new();
+        while let Ok(result) = rx.try_recv() {
+            found.push((result.payload, result.reflected));
+        }
+        assert_eq!(found, [("q".to_string(), Some(true))]);
+    }
+
+    #[tokio::test]
+    async fn cached_responses_keep_headers_and_cors() {
+        let server = MockServer::start(|_| {
+            MockResponse::new(200, "page")
+                .header("Access-Control-Allow-Origin", "https://evil.example")
+                .header("Access-Control-Allow-Credentials", "true")
+                .header("X-Powered-By", "test")
+        })
+        .await;
+        let dir = std::env::temp_dir().join(format!("rbuster-{}", random_label(12)));
+        let cache = ResponseCache::new(&dir, None, false).await.unwrap();
+        let http_client = HttpClient::new(HttpConfig::default()).unwrap();
+        let base_url = format!("{}/FUZZ", server.url());
+        let headers = ["Origin: https://evil.example".to_string()];
+        let sender = PayloadSender {
+            http_client: &http_client,
+            raw: None,
+            base_url: &base_url,
+            headers: &headers,
+            data: None,
+            method: "GET",
+            content_type: None,
+            cookies: None,
+            cache: Some(&cache),
+            delay: RequestDelay::new(None, None),
+            limiter: None,
+            encode_url: false,
+            encode_all: false,
+            capture_headers: true,
+            max_body: None,
+        };
+
+        let words = ["admin".to_string()];
+        let fresh = sender.send(&words).await.unwrap();
+        let cached = sender.send(&words).await.unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(server.requests().len(), 1);
+        assert_eq!(cached.status, fresh.status);
+        assert_eq!(cached.headers, fresh.headers);
+        assert!(cached
+            .headers
+            .iter()
+            .any(|(n, v)| n == "x-powered-by" && v == "test"));
+        assert!(cached
+            .cors
+            .is_some_and(|grant| grant.trusts_with_credentials("https://evil.example")));
+    }
+
+    #[tokio::test]
+    async fn cached_responses_keep_the_uncapped_size() {
+        let server = MockServer::start(|_| MockResponse::new(200, "x".repeat(100))).await;
+        let dir = std::env::temp_dir().join(format!("rbuster-{}", random_label(12)));
+        let cache = ResponseCache::new(&dir, None, false).await.unwrap();
+        let http_client = HttpClient::new(HttpConfig::default()).unwrap();
+        let base_url = format!("{}/FUZZ", server.url());
+        let sender = PayloadSender {
+            http_client: &http_client,
+            raw: None,
+            base_url: &base_url,
+            headers: &[],
+            data: None,
+            method: "GET",
+            content_type: None,
+            cookies: None,
+            cache: Some(&cache),
+            delay: RequestDelay::new(None, None),
+            limiter: None,
+            encode_url: false,
+            encode_all: false,
+            capture_headers: false,
+            max_body: Some(10),
+        };
+
+        let words = ["big".to_string()];
+        let fresh = sender.send(&words).await.unwrap();
+        let cached = sender.send(&words).await.unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(server.requests().len(), 1);
+        assert_eq!((fresh.size, fresh.body.len()), (100, 10));
+        assert_eq!((cached.size, cached.body.len()), (100, 10));
+    }
+
+    #[tokio::test]
+    async fn autocalibrate_hides_a_catch_all_page() {
+        let server = MockServer::start(|req| match req.path.as_str() {
+            "/admin" => MockResponse::new(200, "<h1>Admin panel</h1>"),
+            _ => MockResponse::new(200, "<h1>Welcome</h1>"),
+        })
+        .await;
+
+        let wordlist = std::env::temp_dir().join(format!("rbuster-{}.txt", random_label(12)));
+        std::fs::write(&wordlist, "index\nadmin\nlogin\n").unwrap();
+        let url = format!("{}/FUZZ", server.url());
+        let cli = Cli::parse_from([
+            "robuster",
+            "fuzz",
+            "-q",
+            "-u",
+            &url,
+            "-w",
+            wordlist.to_str().unwrap(),
+            "--autocalibrate",
+        ]);
+        let Commands::Fuzz(args) = cli.command else {
+            unreachable!()
+        };
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let run = run_with_results(args, Some(tx)).await;
+        std::fs::remove_file(&wordlist).unwrap();
+        run.unwrap();
+
+        let mut found = Vec::new();
+        while let Ok(result) = rx.try_recv() {
+            found.push(result.payload);
+        }
+        assert_eq!(found, ["admin"]);
+    }
+
+    #[test]
+    fn fill_keywords_substitutes_in_one_pass() {
+        let words = ["FUZZ2".to_string(), "b".to_string()];
+        assert_eq!(
+            fill_keywords("/?FUZZ1=FUZZ2&x=FUZZ", &words),
+            "/?FUZZ2=b&x=FUZZ2"
+        );
+        let words = ["aFUZZ".to_string()];
+        assert_eq!(fill_keywords("FUZZ/FUZZ1", &words), "aFUZZ/aFUZZ");
+    }
+
+    #[tokio::test]
+    async fn fuzz2_without_second_wordlist_is_rejected() {
+        let cli = Cli::parse_from([
+            "robuster",
+            "fuzz",
+            "-q",
+            "-u",
+            "http://127.0.0.1:9/?FUZZ1=FUZZ2",
+            "-w",
+            "params.txt",
+        ]);
+        let Commands::Fuzz(args) = cli.command else {
+            unreachable!()
+        };
+        let run = run_with_results(args, None).await;
+        assert!(matches!(
+            run,
+            Err(crate::error::RbusterError::ConfigError(ref m)) if m.contains("--wordlist2")
+        ));
+    }
+
+    #[test]
+    fn without_echo_strips_the_parameter_pair() {
+        let words = ["id".to_string()];
+        let body = "<p>/search?id=abc</p>";
+        assert_eq!(without_echo(body, &words, Some("abc")), "<p>/search?</p>");
+        assert_eq!(without_echo(body, &words, None), body);
+    }
+
+    #[test]
+    fn body_content_type_follows_the_template() {
+        let json = Some("application/json");
+        let form = Some("application/x-www-form-urlencoded");
+        assert_eq!(body_content_type(" {\"id\": \"FUZZ\"}", "PUT"), json);
+        assert_eq!(body_content_type("a=FUZZ&b=1", "POST"), form);
+        assert_eq!(body_content_type("a=FUZZ&b=1", "post"), form);
+        assert_eq!(body_content_type("a=FUZZ&b=1", "PUT"), None);
+    }
+
+    #[tokio::test]
+    async fn encode_applies_to_the_url_but_not_the_body() {
+        let server = MockServer::start(|_| MockResponse::new(200, "ok")).await;
+
+        let wordlist = std::env::temp_dir().join(format!("rbuster-{}.txt", random_label(12)));
+        std::fs::write(&wordlist, "a b/c\n").unwrap();
+        let url = format!("{}/search?q=FUZZ", server.url());
+        let cli = Cli::parse_from([
+            "robuster",
+            "fuzz",
+            "-q",
+            "-u",
+            &url,
+            "-w",
+            wordlist.to_str().unwrap(),
+            "--method",
+            "POST",
+            "-d",
+            "v=FUZZ",
+            "--encode",
+        ]);
+        let Commands::Fuzz(args) = cli.command else {
+            unreachable!()
+        };
+        let run = run_with_results(args, None).await;
+        std::fs::remove_file(&wordlist).unwrap();
+        run.unwrap();
+
+        let request = server
+            .requests()
+            .into_iter()
+            .find(|req| req.path.starts_with("/search?q=a"))
+            .unwrap();
+        assert_eq!(request.path, "/search?q=a%20b%2Fc");
+        assert_eq!(request.body, b"v=a b/c");
+    }
+}
@@ -1,153 +1,389 @@
 //! Google Cloud Storage bucket enumeration mode
 
 use crate::cli::GcsArgs;
-use crate::core::load_wordlist;
+use crate::core::{element_texts, ErrorLimit, GatedClient, RateLimiter};
 use crate::error::Result;
 use crate::output::{
-    print_bucket_result, print_error, BucketResult, OutputHandler, ProgressTracker,
+    print_bucket_result, print_concurrency, print_error, print_warning, BucketResult,
+    OutputHandler, ProgressTracker, ScanMeta,
 };
 use futures::stream::{self, StreamExt};
-use reqwest::{Client, ClientBuilder, StatusCode};
+use reqwest::{ClientBuilder, StatusCode};
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::Semaphore;
+use tokio::sync::mpsc::UnboundedSender;
 
 /// Run GCS bucket enumeration
 pub async fn run(args: GcsArgs) -> Result<()> {
+    run_with_results(args, None).await
+}
+
+/// Run GCS bucket enumeration, also sending each found result to `result_tx`.
+/// Results arrive in completion order, not wordlist order.
+pub async fn run_with_results(
+    args: GcsArgs,
+    result_tx: Option<UnboundedSender<BucketResult>>,
+) -> Result<()> {
     // Build HTTP client
-    let client = Arc::new(
+    let client = Arc::new(GatedClient::new(
         ClientBuilder::new()
             .user_agent("robuster/1.0")
             .timeout(Duration::from_secs(args.timeout))
             .pool_max_idle_per_host(50)
             .tcp_nodelay(true)
             .build()?,
-    );
+    ));
 
     // Load wordlist
-    let wordlist = load_wordlist(&args.global.wordlist)
-        .await
-        .map_err(crate::error::RbusterError::WordlistError)?;
+    let wordlist = super::load_words(&args.global).await?;
     let total = wordlist.len();
 
     // Create progress tracker
     let progress = ProgressTracker::new(total as u64, args.global.quiet || args.global.no_progress);
 
     // Create output handler
-    let output = OutputHandler::new(args.global.output.as_deref()).await?;
+    let output = OutputHandler::new(&args.global, ScanMeta::new("gcs", None, &args.global)).await?;
     let output = Arc::new(output);
 
-    // Create semaphore for concurrency control
-    let semaphore = Arc::new(Semaphore::new(args.global.threads));
-    let delay = args.global.delay.map(Duration::from_millis);
+    // Limit concurrency, adjusted to the error rate with --adaptive
+    let concurrency = Arc::new(super::concurrency(&args.global, args.global.threads)?);
+    let delay = super::request_delay(&args.global);
+    let limiter = args.global.rate.map(|r| Arc::new(RateLimiter::new(r)));
+    let error_limit = Arc::new(ErrorLimit::new(args.global.max_errors));
     let max_files = args.max_files;
-    let verbose = args.global.verbose;
+    let min_files = args.min_files;
+    let verbose = args.global.verbosity();
+    let services: Vec<CloudService> = [
+        (args.firebase, CloudService::Firebase),
+        (args.appspot, CloudService::AppEngine),
+    ]
+    .into_iter()
+    .filter_map(|(enabled, service)| enabled.then_some(service))
+    .collect();
 
     // Process bucket names concurrently
-    let _results: Vec<_> = stream::iter(wordlist)
+    stream::iter(wordlist)
         .map(|bucket_name| {
-            let semaphore = Arc::clone(&semaphore);
+            let concurrency = Arc::clone(&concurrency);
             let client = Arc::clone(&client);
             let progress = progress.clone();
+            let limiter = limiter.clone();
+            let error_limit = Arc::clone(&error_limit);
             let output = Arc::clone(&output);
+            let result_tx = result_tx.clone();
+            let services = &services;
 
             async move {
-                let _permit = semaphore.acquire().await.unwrap();
+                let _permit = concurrency.acquire().await;
 
-                if let Some(d) = delay {
-                    tokio::time::sleep(d).await;
+                if error_limit.tripped() {
+                    return;
                 }
 
+                delay.wait().await;
+
                 progress.inc();
 
-                // GCS bucket URL
-                let url = format!("https://storage.googleapis.com/{}", bucket_name);
+                // Try both GCS URL formats
+                let urls = vec![
+                    format!("https://storage.googleapis.com/{}", bucket_name),
+                    format!("https://{}.storage.googleapis.com", bucket_name),
+                ];
 
-                match check_gcs_bucket(&client, &url, max_files).await {
-                    Ok(Some((status, files))) => {
-                        progress.inc_found();
+                for url in urls {
+                    if let Some(ref limiter) = limiter {
+                        limiter.acquire().await;
+                    }
 
-                        print_bucket_result(&bucket_name, &status, &files);
+                    let result = check_gcs_bucket(&client, &url, max_files).await;
+                    concurrency.record(result.is_ok());
+                    if result.is_ok() {
+                        error_limit.success();
+                    } else if error_limit.failure() {
+                        print_warning(&error_limit.message());
+                    }
 
-                        // Write to file if configured
-                        if let Some(writer) = output.file_writer() {
+                    match result {
+                        Ok(Some((status, files, object_count)))
+                            if min_files.is_none_or(|n| object_count.unwrap_or(0) >= n) =>
+                        {
                             let result = BucketResult {
                                 name: bucket_name.clone(),
-                                status: status.clone(),
-                                files: files.clone(),
+                                status,
+                                files,
+                                object_count,
+                                writable: None,
+                                acl_public: None,
+                                policy_public: None,
                             };
-                            if writer.is_json() {
-                                let _ = writer.write_json(&result).await;
-                            } else {
-                                let line =
-                                    format!("{} [{}] files: {}", bucket_name, status, files.len());
-                                let _ = writer.write_line(&line).await;
+                            report(result, &progress, &output, result_tx.as_ref()).await;
+                            break;
+                        }
+                        // Bucket exists but holds fewer objects than --min-files
+                        Ok(Some(_)) => break,
+                        Ok(None) => continue,
+                        Err(e) => {
+                            progress.inc_error();
+                            if verbose >= 1 {
+                                print_error(&format!("{}: {}", bucket_name, e), true);
                             }
+                            continue;
                         }
+                    }
+                }
+
+                // Firebase and App Engine share the name
+                for service in services {
+                    if let Some(ref limiter) = limiter {
+                        limiter.acquire().await;
+                    }
 
-                        Some((bucket_name, status, files))
+                    let host = service.host(&bucket_name);
+                    let result = service.probe(&client, &host, max_files).await;
+                    concurrency.record(result.is_ok());
+                    if result.is_ok() {
+                        error_limit.success();
+                    } else if error_limit.failure() {
+                        print_warning(&error_limit.message());
                     }
-                    Ok(None) => None,
-                    Err(e) => {
-                        if verbose {
-                            print_error(&format!("{}: {}", bucket_name, e), true);
+
+                    match result {
+                        Ok(Some((status, files, object_count))) => {
+                            let result = BucketResult {
+                                name: host,
+                                status,
+                                files,
+                                object_count,
+                                writable: None,
+                                acl_public: None,
+                                policy_public: None,
+                            };
+                            report(result, &progress, &output, result_tx.as_ref()).await;
+                        }
+                        Ok(None) => {}
+                        Err(e) => {
+                            progress.inc_error();
+                            if verbose >= 1 {
+                                print_error(&format!("{}: {}", host, e), true);
+                            }
                         }
-                        None
                     }
                 }
             }
         })
         .buffer_unordered(args.global.threads)
-        .collect()
+        .collect::<Vec<()>>()
         .await;
 
     progress.finish();
-    output.finalize().await?;
+    progress.print_summary(args.global.quiet);
+    print_concurrency(&concurrency, args.global.quiet);
+    super::warn_rate_limited(client.rate_limited());
+    output.finalize(&progress.stats()).await?;
 
     Ok(())
 }
 
+/// Print and record a found bucket or service
+async fn report(
+    result: BucketResult,
+    progress: &ProgressTracker,
+    output: &OutputHandler,
+    result_tx: Option<&UnboundedSender<BucketResult>>,
+) {
+    progress.inc_found();
+    print_bucket_result(&result);
+
+    // Record result (file output and hooks)
+    output.record(&result, &result.line()).await;
+    if let Some(result_tx) = result_tx {
+        let _ = result_tx.send(result);
+    }
+}
+
+/// Google services probed under the same names as buckets
+#[derive(Debug, Clone, Copy)]
+enum CloudService {
+    /// Realtime Database at {name}.firebaseio.com
+    Firebase,
+    /// App Engine app at {name}.appspot.com
+    AppEngine,
+}
+
+impl CloudService {
+    fn host(self, name: &str) -> String {
+        match self {
+            CloudService::Firebase => format!("{}.firebaseio.com", name),
+            CloudService::AppEngine => format!("{}.appspot.com", name),
+        }
+    }
+
+    /// Status, top-level keys and key count of the service at `host`, or
+    /// `None` when it doesn't exist
+    async fn probe(
+        self,
+        client: &GatedClient,
+        host: &str,
+        max_files: usize,
+    ) -> std::result::Result<Option<(String, Vec<String>, Option<usize>)>, reqwest::Error> {
+        match self {
+            CloudService::Firebase => {
+                let response = client
+                    .send(client.get(format!("https://{}/.json", host)))
+                    .await?;
+                let status = response.status();
+                let body = response.text().await.unwrap_or_default();
+                Ok(classify_firebase(status, &body, max_files))
+            }
+            CloudService::AppEngine => {
+                let response = client
+                    .send(client.get(format!("https://{}/", host)))
+                    .await?;
+                // Apps that don't exist answer 404; anything else is served
+                // by a deployed app, even if it refuses us
+                Ok((response.status() != StatusCode::NOT_FOUND)
+                    .then(|| ("reachable".to_string(), vec![], None)))
+            }
+        }
+    }
+}
+
+/// Classify a Realtime Database's answer to GET /.json: JSON means anyone
+/// can read it, 401 or 403 that it exists behind rules. Missing and
+/// deactivated (423) databases give `None`.
+fn classify_firebase(
+    status: StatusCode,
+    body: &str,
+    max_files: usize,
+) -> Option<(String, Vec<String>, Option<usize>)> {
+    match status {
+        StatusCode::OK => {
+            let data: serde_json::Value = serde_json::from_str(body).ok()?;
+            let keys: Vec<String> = data
+                .as_object()
+                .map(|object| object.keys().cloned().collect())
+                .unwrap_or_default();
+            let total = keys.len();
+            Some((
+                "public".to_string(),
+                keys.into_iter().take(max_files).collect(),
+                Some(total),
+            ))
+        }
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+            Some(("private".to_string(), vec![], None))
+        }
+        _ => None,
+    }
+}
+
 /// Check if a GCS bucket exists and get its status
 async fn check_gcs_bucket(
-    client: &Client,
+    client: &GatedClient,
     url: &str,
     max_files: usize,
-) -> std::result::Result<Option<(String, Vec<String>)>, reqwest::Error> {
-    let response = client.get(url).send().await?;
+) -> std::result::Result<Option<(String, Vec<String>, Option<usize>)>, reqwest::Error> {
+    let response = client.send(client.get(url)).await?;
     let status = response.status();
 
     match status {
         StatusCode::OK => {
             // Bucket is public, try to list files
             let body = response.text().await.unwrap_or_default();
-            let files = parse_gcs_listing(&body, max_files);
-            Ok(Some(("public".to_string(), files)))
+            let (files, total) = parse_gcs_listing(&body, max_files);
+            Ok(Some(("public".to_string(), files, Some(total))))
         }
         StatusCode::FORBIDDEN => {
             // Bucket exists but is private
-            Ok(Some(("private".to_string(), vec![])))
+            Ok(Some(("private".to_string(), vec![], None)))
         }
         StatusCode::NOT_FOUND => Ok(None),
         _ => Ok(None),
     }
 }
 
-/// Parse GCS bucket listing XML to extract file keys
-fn parse_gcs_listing(xml: &str, max_files: usize) -> Vec<String> {
-    let mut files = Vec::new();
-
-    // Simple XML parsing for <Key> elements (GCS uses similar format to S3)
-    for line in xml.lines() {
-        if let Some(start) = line.find("<Key>") {
-            if let Some(end) = line.find("</Key>") {
-                let key = &line[start + 5..end];
-                files.push(key.to_string());
-                if files.len() >= max_files {
-                    break;
-                }
-            }
+/// Parse GCS bucket listing XML to extract file keys, along with the total
+/// number of keys in the listing
+fn parse_gcs_listing(xml: &str, max_files: usize) -> (Vec<String>, usize) {
+    // GCS uses the same <Key> elements as S3
+    let keys = element_texts(xml, "Key");
+    let total = keys.len();
+    (keys.into_iter().take(max_files).collect(), total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::mock_server::{MockResponse, MockServer};
+
+    #[tokio::test]
+    async fn empty_json_database_is_public() {
+        let server = MockServer::start(|req| match req.path.as_str() {
+            "/open/.json" => MockResponse::new(200, "{}"),
+            "/locked/.json" => MockResponse::new(401, r#"{"error":"Permission denied"}"#),
+            _ => MockResponse::new(404, "not found"),
+        })
+        .await;
+
+        let mut found = Vec::new();
+        for name in ["open", "locked", "missing"] {
+            let response = reqwest::get(format!("{}/{}/.json", server.url(), name))
+                .await
+                .unwrap();
+            let status = response.status();
+            let body = response.text().await.unwrap();
+            found.push(classify_firebase(status, &body, 10));
         }
+
+        assert_eq!(
+            found,
+            [
+                Some(("public".to_string(), vec![], Some(0))),
+                Some(("private".to_string(), vec![], None)),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn firebase_keys_are_capped_but_counted() {
+        let body = r#"{"users":{},"orders":[],"config":1}"#;
+        let (status, keys, total) = classify_firebase(StatusCode::OK, body, 2).unwrap();
+        assert_eq!(status, "public");
+        assert_eq!(keys.len(), 2);
+        assert_eq!(total, Some(3));
     }
 
-    files
+    #[test]
+    fn listing_keys_are_capped_but_counted() {
+        let xml = r#"<?xml version='1.0' encoding='UTF-8'?>
+<ListBucketResult xmlns="http://doc.s3.amazonaws.com/2006-03-01">
+  <Name>acme-backups</Name>
+  <Prefix></Prefix>
+  <Marker></Marker>
+  <IsTruncated>false</IsTruncated>
+  <Contents>
+    <Key>db/dump.sql.gz</Key>
+    <Generation>1700000000000000</Generation>
+    <LastModified>2024-01-01T00:00:00.000Z</LastModified>
+    <Size>1048576</Size>
+  </Contents>
+  <Contents>
+    <Key>config/.env</Key>
+    <Size>128</Size>
+  </Contents>
+  <Contents>
+    <Key>logs/app.log</Key>
+    <Size>4096</Size>
+  </Contents>
+</ListBucketResult>"#;
+
+        let (keys, total) = parse_gcs_listing(xml, 2);
+        assert_eq!(keys, ["db/dump.sql.gz", "config/.env"]);
+        assert_eq!(total, 3);
+
+        let (keys, total) = parse_gcs_listing(xml, 10);
+        assert_eq!(keys.len(), 3);
+        assert_eq!(total, 3);
+    }
 }
@@ -5,5 +5,314 @@ pub mod dns;
 pub mod fuzz;
 pub mod gcs;
 pub mod s3;
+pub mod tcp;
 pub mod tftp;
 pub mod vhost;
+
+use crate::cli::{GlobalOpts, HttpOpts, HttpVersion, Switch};
+use crate::core::{
+    load_wordlists_with, parse_headers, reads_stdin, AddressFamily, Concurrency, CorsGrant,
+    FilterOutcome, HttpClient, HttpConfig, HttpProtocol, Mutator, RequestDelay, WordlistOptions,
+};
+use crate::error::{RbusterError, Result};
+use crate::output::{print_warning, ResponseSaver};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Client configuration for the shared HTTP options
+pub(crate) fn http_config(opts: &HttpOpts) -> HttpConfig {
+    HttpConfig {
+        user_agent: opts
+            .user_agent
+            .clone()
+            .unwrap_or_else(|| HttpConfig::default().user_agent),
+        // An explicit --user-agent wins over rotation
+        random_agent: opts.user_agent.is_none()
+            && (opts.random_agent || opts.user_agents_file.is_some()),
+        user_agents_file: opts.user_agents_file.clone(),
+        timeout: Duration::from_secs(opts.timeout),
+        insecure: opts.insecure,
+        verify_hostname: opts.verify_tls_hostname,
+        ca_certs: opts.cacert.clone(),
+        client_cert: opts.client_cert.clone(),
+        client_key: opts.client_key.clone(),
+        client_pkcs12: opts.client_pkcs12.clone(),
+        client_pkcs12_pass: opts.client_pkcs12_pass.clone(),
+        follow_redirect: opts.follow_redirect,
+        max_redirects: opts.max_redirects,
+        address_family: AddressFamily::from_flags(opts.ipv4_only, opts.ipv6_only),
+        protocol: http_protocol(opts.http_version),
+        proxies: opts.proxy.clone(),
+        proxy_file: opts.proxy_file.clone(),
+        proxy_username: opts.proxy_user.clone(),
+        proxy_password: opts.proxy_pass.clone(),
+        headers: parse_headers(&opts.headers),
+        cookies: opts.cookies.clone(),
+        username: opts.username.clone(),
+        password: opts.password.clone(),
+        retries: opts.retries,
+        retry_backoff: Duration::from_millis(opts.retry_backoff),
+        retry_after: !opts.ignore_retry_after,
+        pool_idle: opts.pool_idle,
+        tcp_nodelay: opts.tcp_nodelay == Switch::On,
+        ..HttpConfig::default()
+    }
+}
+
+/// Map the --http-version option to the client setting
+fn http_protocol(version: HttpVersion) -> HttpProtocol {
+    match version {
+        HttpVersion::Auto => HttpProtocol::Auto,
+        HttpVersion::Http1 => HttpProtocol::Http1,
+        HttpVersion::Http2 => HttpProtocol::Http2,
+    }
+}
+
+/// Origin sent by --check-cors unless -H sets one
+const CORS_TEST_ORIGIN: &str = "https://evil.example";
+
+/// Origin for --check-cors: the Origin header given with -H, or a foreign
+/// test origin that is added to `headers`
+pub(crate) fn cors_origin(headers: &mut Vec<String>) -> String {
+    let given = headers
+        .iter()
+        .filter_map(|h| h.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("origin"))
+        .map(|(_, value)| value.trim().to_string());
+    given.unwrap_or_else(|| {
+        headers.push(format!("Origin: {}", CORS_TEST_ORIGIN));
+        CORS_TEST_ORIGIN.to_string()
+    })
+}
+
+/// Whether a response trusts the --check-cors origin with credentials;
+/// `None` when the check is off
+pub(crate) fn cors_finding(origin: Option<&str>, grant: Option<&CorsGrant>) -> Option<bool> {
+    origin.map(|origin| grant.is_some_and(|g| g.trusts_with_credentials(origin)))
+}
+
+/// Warn about --proxy pool members that were skipped after failing repeatedly
+pub(crate) fn warn_dead_proxies(http_client: &HttpClient) {
+    for proxy in http_client.dead_proxies() {
+        print_warning(&format!(
+            "Proxy {} stopped answering and was skipped for the rest of the scan",
+            proxy
+        ));
+    }
+}
+
+/// Warn that the target rate-limited `count` requests, so the user can slow
+/// down
+pub(crate) fn warn_rate_limited(count: usize) {
+    if count > 0 {
+        print_warning(&format!(
+            "The target rate-limited {} requests (429 or 503 with Retry-After); they were \
+             held and sent again. Consider --rate or --delay",
+            count
+        ));
+    }
+}
+
+/// Saver for --save-responses, creating its directory
+pub(crate) async fn response_saver(
+    dir: Option<&Path>,
+    max_size: usize,
+    headers: bool,
+) -> Result<Option<ResponseSaver>> {
+    let Some(dir) = dir else {
+        return Ok(None);
+    };
+    ResponseSaver::new(dir, max_size, headers)
+        .await
+        .map(Some)
+        .map_err(|e| {
+            RbusterError::ConfigError(format!(
+                "Cannot create response directory '{}': {}",
+                dir.display(),
+                e
+            ))
+        })
+}
+
+/// Headers printed with a response: they are only shown at -vvv, though
+/// they may be captured for other uses
+pub(crate) fn shown_headers(verbose: u8, headers: &[(String, String)]) -> &[(String, String)] {
+    if verbose >= 3 {
+        headers
+    } else {
+        &[]
+    }
+}
+
+/// Reason text for a hidden response, as shown at -vv
+pub(crate) fn hidden(outcome: &FilterOutcome) -> Option<String> {
+    outcome.reason().map(|reason| reason.to_string())
+}
+
+/// Pause applied before each request from --delay and --jitter
+pub(crate) fn request_delay(opts: &GlobalOpts) -> RequestDelay {
+    RequestDelay::new(opts.delay, opts.jitter)
+}
+
+/// Concurrency limit of at most `max` requests, adaptive with --adaptive
+pub(crate) fn concurrency(opts: &GlobalOpts, max: usize) -> Result<Concurrency> {
+    if !opts.adaptive {
+        return Ok(Concurrency::fixed(max));
+    }
+    if opts.min_threads == 0 || opts.min_threads > max {
+        return Err(RbusterError::ConfigError(format!(
+            "--min-threads must be between 1 and the thread count ({})",
+            max
+        )));
+    }
+    Ok(Concurrency::adaptive(opts.min_threads, max))
+}
+
+/// Cleanup options for wordlists given on the command line
+pub(crate) fn wordlist_options(opts: &GlobalOpts) -> WordlistOptions {
+    WordlistOptions {
+        dedupe: !opts.keep_duplicates,
+        keep_comments: opts.keep_comments,
+    }
+}
+
+/// Load the --wordlist files, wrapped in --word-prefix and --word-suffix
+pub(crate) async fn load_words(opts: &GlobalOpts) -> Result<Vec<String>> {
+    load_mutated_words(opts, Mutator::default()).await
+}
+
+/// Load the --wordlist files, expanded by `mutator` and then wrapped in
+/// --word-prefix and --word-suffix
+pub(crate) async fn load_mutated_words(opts: &GlobalOpts, mutator: Mutator) -> Result<Vec<String>> {
+    let words = load_wordlists_with(&opts.wordlist, wordlist_options(opts))
+        .await
+        .map_err(RbusterError::WordlistError)?;
+    let words = mutator.apply(words);
+    if opts.word_prefix.is_none() && opts.word_suffix.is_none() {
+        return Ok(words);
+    }
+    let prefix = opts.word_prefix.as_deref().unwrap_or_default();
+    let suffix = opts.word_suffix.as_deref().unwrap_or_default();
+    Ok(words
+        .into_iter()
+        .map(|word| format!("{}{}{}", prefix, word, suffix))
+        .collect())
+}
+
+/// Reject more than one input list reading standard input (`-`), counting
+/// each wordlist
+pub(crate) fn check_stdin_inputs(wordlists: &[PathBuf], paths: &[Option<&Path>]) -> Result<()> {
+    let wordlists = wordlists.iter().map(PathBuf::as_path);
+    if wordlists
+        .chain(paths.iter().flatten().copied())
+        .filter(|p| reads_stdin(p))
+        .count()
+        > 1
+    {
+        return Err(RbusterError::ConfigError(
+            "Only one input list can be read from stdin ('-')".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{Cli, Commands};
+    use clap::Parser;
+
+    #[test]
+    fn http_options_reach_the_client_config() {
+        let cli = Cli::parse_from([
+            "robuster",
+            "vhost",
+            "-u",
+            "http://10.0.0.1",
+            "-w",
+            "hosts.txt",
+            "-k",
+            "-U",
+            "admin",
+            "-P",
+            "secret",
+            "--proxy",
+            "http://127.0.0.1:8080",
+            "-c",
+            "session=abc",
+        ]);
+        let Commands::Vhost(args) = cli.command else {
+            unreachable!()
+        };
+
+        let config = http_config(&args.http);
+        assert!(config.insecure);
+        assert_eq!(config.username.as_deref(), Some("admin"));
+        assert_eq!(config.password.as_deref(), Some("secret"));
+        assert_eq!(config.proxies, ["http://127.0.0.1:8080"]);
+        assert_eq!(config.cookies.as_deref(), Some("session=abc"));
+    }
+
+    #[test]
+    fn pool_options_reach_the_client_config() {
+        let parse = |extra: &[&str]| {
+            let mut argv = vec!["robuster", "dir", "-u", "http://t", "-w", "words.txt"];
+            argv.extend_from_slice(extra);
+            let Commands::Dir(args) = Cli::parse_from(argv).command else {
+                unreachable!()
+            };
+            http_config(&args.http)
+        };
+
+        let defaults = parse(&[]);
+        assert_eq!(defaults.pool_idle, 100);
+        assert!(defaults.tcp_nodelay);
+
+        let tuned = parse(&["--pool-idle", "0", "--tcp-nodelay", "off"]);
+        assert_eq!(tuned.pool_idle, 0);
+        assert!(!tuned.tcp_nodelay);
+        assert!(tuned.build_client().is_ok());
+    }
+
+    #[test]
+    fn explicit_user_agent_wins_over_rotation() {
+        let parse = |extra: &[&str]| {
+            let mut argv = vec!["robuster", "dir", "-u", "http://t", "-w", "words.txt"];
+            argv.extend_from_slice(extra);
+            let Commands::Dir(args) = Cli::parse_from(argv).command else {
+                unreachable!()
+            };
+            http_config(&args.http)
+        };
+
+        assert!(!parse(&[]).random_agent);
+        assert!(parse(&["--random-agent"]).random_agent);
+        assert!(parse(&["--user-agents-file", "agents.txt"]).random_agent);
+
+        let fixed = parse(&["--random-agent", "-a", "scanner/2.0"]);
+        assert!(!fixed.random_agent);
+        assert_eq!(fixed.user_agent, "scanner/2.0");
+    }
+
+    #[test]
+    fn cors_check_uses_the_given_or_a_foreign_origin() {
+        let mut headers = vec!["X-Test: 1".to_string()];
+        assert_eq!(cors_origin(&mut headers), CORS_TEST_ORIGIN);
+        assert_eq!(headers[1], format!("Origin: {}", CORS_TEST_ORIGIN));
+
+        let mut headers = vec!["origin: https://partner.example".to_string()];
+        assert_eq!(cors_origin(&mut headers), "https://partner.example");
+        assert_eq!(headers.len(), 1);
+
+        let grant = CorsGrant {
+            allow_origin: CORS_TEST_ORIGIN.to_string(),
+            allow_credentials: true,
+        };
+        assert_eq!(cors_finding(None, Some(&grant)), None);
+        assert_eq!(
+            cors_finding(Some(CORS_TEST_ORIGIN), Some(&grant)),
+            Some(true)
+        );
+        assert_eq!(cors_finding(Some(CORS_TEST_ORIGIN), None), Some(false));
+    }
+}
@@ -1,18 +1,29 @@
 //! AWS S3 bucket enumeration mode
 
 use crate::cli::S3Args;
-use crate::core::load_wordlist;
+use crate::core::{
+    element_text, element_texts, random_label, ErrorLimit, GatedClient, RateLimiter,
+};
 use crate::error::Result;
 use crate::output::{
-    print_bucket_result, print_error, BucketResult, OutputHandler, ProgressTracker,
+    print_bucket_result, print_concurrency, print_error, print_warning, BucketResult,
+    OutputHandler, ProgressTracker, ScanMeta,
 };
 use futures::stream::{self, StreamExt};
-use reqwest::{Client, ClientBuilder, StatusCode};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use reqwest::header::HeaderMap;
+use reqwest::{ClientBuilder, Response, StatusCode};
+use std::collections::VecDeque;
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::Semaphore;
+use tokio::sync::mpsc::UnboundedSender;
+
+/// Object uploaded by --check-writable, followed by a random part so no
+/// existing object is overwritten
+const WRITE_TEST_PREFIX: &str = "robuster-write-test-";
+const WRITE_TEST_BODY: &str = "robuster write test, safe to delete\n";
 
-#[allow(dead_code)]
+/// Regions probed by --all-regions
 const S3_REGIONS: &[&str] = &[
     "us-east-1",
     "us-east-2",
@@ -29,90 +40,161 @@ const S3_REGIONS: &[&str] = &[
 
 /// Run S3 bucket enumeration
 pub async fn run(args: S3Args) -> Result<()> {
+    run_with_results(args, None).await
+}
+
+/// Run S3 bucket enumeration, also sending each found result to `result_tx`.
+/// Results arrive in completion order, not wordlist order.
+pub async fn run_with_results(
+    args: S3Args,
+    result_tx: Option<UnboundedSender<BucketResult>>,
+) -> Result<()> {
     // Build HTTP client
-    let client = Arc::new(
+    let client = Arc::new(GatedClient::new(
         ClientBuilder::new()
             .user_agent("robuster/1.0")
             .timeout(Duration::from_secs(args.timeout))
             .pool_max_idle_per_host(50)
             .tcp_nodelay(true)
             .build()?,
-    );
+    ));
 
     // Load wordlist
-    let wordlist = load_wordlist(&args.global.wordlist)
-        .await
-        .map_err(crate::error::RbusterError::WordlistError)?;
+    let wordlist = super::load_words(&args.global).await?;
     let total = wordlist.len();
 
     // Create progress tracker
     let progress = ProgressTracker::new(total as u64, args.global.quiet || args.global.no_progress);
 
     // Create output handler
-    let output = OutputHandler::new(args.global.output.as_deref()).await?;
+    let output = OutputHandler::new(&args.global, ScanMeta::new("s3", None, &args.global)).await?;
     let output = Arc::new(output);
 
-    // Create semaphore for concurrency control
-    let semaphore = Arc::new(Semaphore::new(args.global.threads));
-    let delay = args.global.delay.map(Duration::from_millis);
+    // Limit concurrency, adjusted to the error rate with --adaptive
+    let concurrency = Arc::new(super::concurrency(&args.global, args.global.threads)?);
+    let delay = super::request_delay(&args.global);
+    let limiter = args.global.rate.map(|r| Arc::new(RateLimiter::new(r)));
+    let error_limit = Arc::new(ErrorLimit::new(args.global.max_errors));
     let max_files = args.max_files;
-    let verbose = args.global.verbose;
+    let min_files = args.min_files;
+    let check_writable = args.check_writable;
+    let check_acl = args.check_acl;
+    let all_regions = args.all_regions;
+    let list_all = args.list_all;
+    let verbose = args.global.verbosity();
 
     // Process bucket names concurrently
     let _results: Vec<_> = stream::iter(wordlist)
         .map(|bucket_name| {
-            let semaphore = Arc::clone(&semaphore);
+            let concurrency = Arc::clone(&concurrency);
             let client = Arc::clone(&client);
             let progress = progress.clone();
+            let limiter = limiter.clone();
+            let error_limit = Arc::clone(&error_limit);
             let output = Arc::clone(&output);
+            let result_tx = result_tx.clone();
 
             async move {
-                let _permit = semaphore.acquire().await.unwrap();
+                let _permit = concurrency.acquire().await;
 
-                if let Some(d) = delay {
-                    tokio::time::sleep(d).await;
+                if error_limit.tripped() {
+                    return None;
                 }
 
+                delay.wait().await;
+
                 progress.inc();
 
-                // Try different S3 URL formats
-                let urls = vec![
+                // Try different S3 URL formats, then regional endpoints if asked
+                let mut urls = VecDeque::from([
                     format!("https://{}.s3.amazonaws.com", bucket_name),
                     format!("https://s3.amazonaws.com/{}", bucket_name),
-                ];
+                ]);
+                if all_regions {
+                    urls.extend(S3_REGIONS.iter().map(|r| regional_url(&bucket_name, r)));
+                }
+                let mut jumped = false;
+
+                while let Some(url) = urls.pop_front() {
+                    if let Some(ref limiter) = limiter {
+                        limiter.acquire().await;
+                    }
 
-                for url in urls {
-                    match check_s3_bucket(&client, &url, max_files).await {
-                        Ok(Some((status, files))) => {
+                    let listing = Listing {
+                        max_files,
+                        all_pages: list_all,
+                        limiter: limiter.as_deref(),
+                    };
+                    let result = check_s3_bucket(&client, &bucket_name, &url, &listing).await;
+                    concurrency.record(result.is_ok());
+                    if result.is_ok() {
+                        error_limit.success();
+                    } else if error_limit.failure() {
+                        print_warning(&error_limit.message());
+                    }
+
+                    match result {
+                        Ok(S3Probe::Found {
+                            url,
+                            status,
+                            files,
+                            object_count,
+                        }) if min_files.is_none_or(|n| object_count.unwrap_or(0) >= n) => {
                             progress.inc_found();
 
-                            print_bucket_result(&bucket_name, &status, &files);
-
-                            // Write to file if configured
-                            if let Some(writer) = output.file_writer() {
-                                let result = BucketResult {
-                                    name: bucket_name.clone(),
-                                    status: status.clone(),
-                                    files: files.clone(),
-                                };
-                                if writer.is_json() {
-                                    let _ = writer.write_json(&result).await;
-                                } else {
-                                    let line = format!(
-                                        "{} [{}] files: {}",
-                                        bucket_name,
-                                        status,
-                                        files.len()
-                                    );
-                                    let _ = writer.write_line(&line).await;
+                            let writable = if check_writable {
+                                if let Some(ref limiter) = limiter {
+                                    limiter.acquire().await;
                                 }
+                                Some(check_s3_writable(&client, &url).await)
+                            } else {
+                                None
+                            };
+
+                            let (acl_public, policy_public) = if check_acl {
+                                if let Some(ref limiter) = limiter {
+                                    limiter.acquire().await;
+                                }
+                                let acl = check_s3_acl(&client, &url).await;
+                                if let Some(ref limiter) = limiter {
+                                    limiter.acquire().await;
+                                }
+                                let policy = check_s3_policy(&client, &url).await;
+                                (Some(acl), Some(policy))
+                            } else {
+                                (None, None)
+                            };
+
+                            let result = BucketResult {
+                                name: bucket_name.clone(),
+                                status: status.clone(),
+                                files: files.clone(),
+                                object_count,
+                                writable,
+                                acl_public,
+                                policy_public,
+                            };
+                            print_bucket_result(&result);
+
+                            // Record result (file output and hooks)
+                            output.record(&result, &result.line()).await;
+                            if let Some(ref result_tx) = result_tx {
+                                let _ = result_tx.send(result.clone());
                             }
 
                             return Some((bucket_name, status, files));
                         }
-                        Ok(None) => continue,
+                        // Bucket exists but holds fewer objects than --min-files
+                        Ok(S3Probe::Found { .. }) => return None,
+                        // Jump straight to the region S3 named instead of trying them all
+                        Ok(S3Probe::WrongRegion(region)) if all_regions && !jumped => {
+                            jumped = true;
+                            urls = VecDeque::from([regional_url(&bucket_name, &region)]);
+                        }
+                        Ok(_) => continue,
                         Err(e) => {
-                            if verbose {
+                            progress.inc_error();
+                            if verbose >= 1 {
                                 print_error(&format!("{}: {}", bucket_name, e), true);
                             }
                             continue;
@@ -128,51 +210,407 @@ pub async fn run(args: S3Args) -> Result<()> {
         .await;
 
     progress.finish();
-    output.finalize().await?;
+    progress.print_summary(args.global.quiet);
+    print_concurrency(&concurrency, args.global.quiet);
+    super::warn_rate_limited(client.rate_limited());
+    output.finalize(&progress.stats()).await?;
 
     Ok(())
 }
 
-/// Check if an S3 bucket exists and get its status
+/// Outcome of probing one S3 endpoint
+enum S3Probe {
+    /// Bucket exists at `url`, which differs from the probed one after a
+    /// region redirect
+    Found {
+        url: String,
+        status: String,
+        files: Vec<String>,
+        object_count: Option<usize>,
+    },
+    /// Bucket lives in the named region
+    WrongRegion(String),
+    NotFound,
+}
+
+/// Virtual-hosted URL of a bucket in a region
+fn regional_url(bucket: &str, region: &str) -> String {
+    format!("https://{}.s3.{}.amazonaws.com", bucket, region)
+}
+
+/// Check if an S3 bucket exists and get its status. A bucket in another
+/// region answers with a 301 naming its region or endpoint; that endpoint is
+/// requested once and its response classified instead.
 async fn check_s3_bucket(
-    client: &Client,
+    client: &GatedClient,
+    bucket: &str,
     url: &str,
+    listing: &Listing<'_>,
+) -> std::result::Result<S3Probe, reqwest::Error> {
+    let response = client.send(client.get(url)).await?;
+    if response.status() != StatusCode::MOVED_PERMANENTLY {
+        return Ok(classify_s3_response(client, response, url, listing).await);
+    }
+
+    let region = bucket_region(response.headers());
+    let body = response.text().await.unwrap_or_default();
+    match redirect_url(bucket, region, &body) {
+        Some(target) if target != url => {
+            let response = client.send(client.get(&target)).await?;
+            Ok(classify_s3_response(client, response, &target, listing).await)
+        }
+        _ => Ok(S3Probe::NotFound),
+    }
+}
+
+/// How much of a public bucket's listing to read
+struct Listing<'a> {
     max_files: usize,
-) -> std::result::Result<Option<(String, Vec<String>)>, reqwest::Error> {
-    let response = client.get(url).send().await?;
+    /// Follow continuation tokens past the first page
+    all_pages: bool,
+    limiter: Option<&'a RateLimiter>,
+}
+
+/// Classify a bucket response by status
+async fn classify_s3_response(
+    client: &GatedClient,
+    response: Response,
+    url: &str,
+    listing: &Listing<'_>,
+) -> S3Probe {
+    let found = |status: &str, files, object_count| S3Probe::Found {
+        url: url.to_string(),
+        status: status.to_string(),
+        files,
+        object_count,
+    };
 
     match response.status() {
         StatusCode::OK => {
             // Bucket is public, try to list files
             let body = response.text().await.unwrap_or_default();
-            let files = parse_s3_listing(&body, max_files);
-            Ok(Some(("public".to_string(), files)))
+            let (files, total) = list_s3_bucket(client, url, &body, listing).await;
+            found("public", files, Some(total))
+        }
+        // Bucket exists but is private
+        StatusCode::FORBIDDEN => found("private", vec![], None),
+        _ => bucket_region(response.headers()).map_or(S3Probe::NotFound, S3Probe::WrongRegion),
+    }
+}
+
+/// Where a region redirect points: the `x-amz-bucket-region` header, else
+/// the `<Region>` or `<Endpoint>` element of the error body
+fn redirect_url(bucket: &str, header_region: Option<String>, body: &str) -> Option<String> {
+    let region =
+        header_region.or_else(|| element_text(body, "Region").filter(|r| is_region_name(r)));
+    if let Some(region) = region {
+        return Some(regional_url(bucket, &region));
+    }
+
+    // Only follow endpoints that are S3 hosts
+    element_text(body, "Endpoint")
+        .filter(|host| {
+            host.ends_with(".amazonaws.com")
+                && host
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'.')
+        })
+        .map(|host| format!("https://{}", host))
+}
+
+/// Region named by the `x-amz-bucket-region` header. It ends up in a host
+/// name, so anything but a plain region name is ignored
+fn bucket_region(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get("x-amz-bucket-region")
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|r| is_region_name(r))
+        .map(|r| r.to_string())
+}
+
+fn is_region_name(region: &str) -> bool {
+    !region.is_empty()
+        && region
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+}
+
+/// Try an anonymous upload of a test object under a new random key. The
+/// object is deleted again if the upload succeeded, with a warning if it
+/// could not be.
+async fn check_s3_writable(client: &GatedClient, bucket_url: &str) -> bool {
+    let url = format!(
+        "{}/{}{}.txt",
+        bucket_url,
+        WRITE_TEST_PREFIX,
+        random_label(16)
+    );
+    let upload = client
+        .put(&url)
+        .header("Content-Type", "text/plain")
+        .body(WRITE_TEST_BODY);
+    let uploaded = client
+        .send(upload)
+        .await
+        .is_ok_and(|r| r.status().is_success());
+
+    if uploaded {
+        let deleted = client
+            .send(client.delete(&url))
+            .await
+            .is_ok_and(|r| r.status().is_success());
+        if !deleted {
+            print_warning(&format!(
+                "Could not delete the --check-writable test object {}; remove it by hand",
+                url
+            ));
+        }
+    }
+
+    uploaded
+}
+
+/// Whether the bucket ACL can be read anonymously
+async fn check_s3_acl(client: &GatedClient, bucket_url: &str) -> bool {
+    match client.send(client.get(format!("{}?acl", bucket_url))).await {
+        Ok(response) if response.status() == StatusCode::OK => {
+            is_acl_document(&response.text().await.unwrap_or_default())
+        }
+        _ => false,
+    }
+}
+
+/// A readable ACL is an `<AccessControlPolicy>` document; other 200 bodies
+/// (proxies, website endpoints) don't count
+fn is_acl_document(body: &str) -> bool {
+    element_text(body, "AccessControlList").is_some()
+}
+
+/// Whether the bucket policy can be read anonymously
+async fn check_s3_policy(client: &GatedClient, bucket_url: &str) -> bool {
+    match client
+        .send(client.get(format!("{}?policy", bucket_url)))
+        .await
+    {
+        Ok(response) if response.status() == StatusCode::OK => {
+            let body = response.text().await.unwrap_or_default();
+            serde_json::from_str::<serde_json::Value>(&body)
+                .is_ok_and(|policy| policy.get("Statement").is_some())
         }
-        StatusCode::FORBIDDEN => {
-            // Bucket exists but is private
-            Ok(Some(("private".to_string(), vec![])))
+        _ => false,
+    }
+}
+
+/// Collect keys from the first listing page and, with --list-all, from the
+/// pages after it until the listing ends or `max_files` keys are collected.
+/// Returns the kept keys and the number of keys seen.
+async fn list_s3_bucket(
+    client: &GatedClient,
+    url: &str,
+    first_page: &str,
+    listing: &Listing<'_>,
+) -> (Vec<String>, usize) {
+    let mut page = parse_s3_listing(first_page, listing.max_files);
+    let mut files = std::mem::take(&mut page.files);
+    let mut total = page.total;
+
+    while listing.all_pages && files.len() < listing.max_files {
+        let Some(query) = page.next_query.take() else {
+            break;
+        };
+        if let Some(limiter) = listing.limiter {
+            limiter.acquire().await;
         }
-        StatusCode::NOT_FOUND => Ok(None),
-        _ => Ok(None),
+        let body = match client.send(client.get(format!("{}?{}", url, query))).await {
+            Ok(response) if response.status().is_success() => {
+                response.text().await.unwrap_or_default()
+            }
+            _ => break,
+        };
+        page = parse_s3_listing(&body, listing.max_files - files.len());
+        files.append(&mut page.files);
+        total += page.total;
     }
+
+    (files, total)
+}
+
+/// One page of a bucket listing
+struct S3ListingPage {
+    /// Keys on the page, up to the requested maximum
+    files: Vec<String>,
+    /// Number of keys on the page
+    total: usize,
+    /// Query string for the next page when the listing is truncated
+    next_query: Option<String>,
 }
 
-/// Parse S3 bucket listing XML to extract file keys
-fn parse_s3_listing(xml: &str, max_files: usize) -> Vec<String> {
-    let mut files = Vec::new();
+/// Parse S3 bucket listing XML to extract file keys, the number of keys on
+/// the page and where the next page starts. V2 listings name a continuation
+/// token; V1 listings (a plain GET on the bucket) continue after the last key.
+fn parse_s3_listing(xml: &str, max_files: usize) -> S3ListingPage {
+    let keys = element_texts(xml, "Key");
+    let total = keys.len();
 
-    // Simple XML parsing for <Key> elements
-    for line in xml.lines() {
-        if let Some(start) = line.find("<Key>") {
-            if let Some(end) = line.find("</Key>") {
-                let key = &line[start + 5..end];
-                files.push(key.to_string());
-                if files.len() >= max_files {
-                    break;
-                }
+    let truncated = element_text(xml, "IsTruncated").as_deref() == Some("true");
+    let encode = |s: &str| utf8_percent_encode(s, NON_ALPHANUMERIC).to_string();
+    let next_query = truncated
+        .then(|| match element_text(xml, "NextContinuationToken") {
+            Some(token) => Some(format!("list-type=2&continuation-token={}", encode(&token))),
+            None => keys
+                .last()
+                .map(|key| format!("list-type=2&start-after={}", encode(key))),
+        })
+        .flatten();
+
+    S3ListingPage {
+        files: keys.into_iter().take(max_files).collect(),
+        total,
+        next_query,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::mock_server::{MockResponse, MockServer};
+
+    #[tokio::test]
+    async fn write_test_uses_a_new_key_each_time() {
+        let server = MockServer::start(|request| match request.method.as_str() {
+            "PUT" => MockResponse::new(200, ""),
+            _ => MockResponse::new(403, "AccessDenied"),
+        })
+        .await;
+        let client = GatedClient::new(reqwest::Client::new());
+        let bucket = format!("{}/bucket", server.url());
+
+        assert!(check_s3_writable(&client, &bucket).await);
+        assert!(check_s3_writable(&client, &bucket).await);
+
+        let requests = server.requests();
+        let puts: Vec<&str> = requests
+            .iter()
+            .filter(|r| r.method == "PUT")
+            .map(|r| r.path.as_str())
+            .collect();
+        assert_eq!(puts.len(), 2);
+        assert_ne!(puts[0], puts[1]);
+        assert!(puts[0].starts_with("/bucket/robuster-write-test-"));
+        // The failed delete was still attempted for the uploaded key
+        assert!(requests
+            .iter()
+            .any(|r| r.method == "DELETE" && r.path == puts[0]));
+    }
+
+    #[test]
+    fn region_comes_from_the_bucket_region_header() {
+        let headers = |value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert("x-amz-bucket-region", value.parse().unwrap());
+            headers
+        };
+        assert_eq!(
+            bucket_region(&headers(" eu-west-2 ")).as_deref(),
+            Some("eu-west-2")
+        );
+        // It ends up in a host name, so anything else is dropped
+        assert_eq!(bucket_region(&headers("evil.example/x")), None);
+        assert_eq!(bucket_region(&headers("")), None);
+        assert_eq!(bucket_region(&HeaderMap::new()), None);
+        assert_eq!(
+            regional_url("files", "eu-west-2"),
+            "https://files.s3.eu-west-2.amazonaws.com"
+        );
+    }
+
+    #[test]
+    fn redirect_body_names_the_regional_endpoint() {
+        let body = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<Error><Code>PermanentRedirect</Code>\
+            <Message>The bucket you are attempting to access must be addressed using the specified endpoint.</Message>\
+            <Endpoint>files.s3.ap-southeast-2.amazonaws.com</Endpoint><Bucket>files</Bucket></Error>";
+        assert_eq!(
+            redirect_url("files", None, body).as_deref(),
+            Some("https://files.s3.ap-southeast-2.amazonaws.com")
+        );
+
+        // A region, from the header or the body, wins over the endpoint
+        let with_region = body.replace("<Bucket>", "<Region>eu-west-1</Region><Bucket>");
+        assert_eq!(
+            redirect_url("files", None, &with_region).as_deref(),
+            Some("https://files.s3.eu-west-1.amazonaws.com")
+        );
+        assert_eq!(
+            redirect_url("files", Some("us-west-2".to_string()), body).as_deref(),
+            Some("https://files.s3.us-west-2.amazonaws.com")
+        );
+
+        // Endpoints outside S3 are not followed
+        let elsewhere = body.replace("files.s3.ap-southeast-2.amazonaws.com", "evil.example");
+        assert_eq!(redirect_url("files", None, &elsewhere), None);
+    }
+
+    #[tokio::test]
+    async fn list_all_follows_the_continuation_token() {
+        let first = "<ListBucketResult><IsTruncated>true</IsTruncated>\
+            <Contents><Key>a.txt</Key></Contents><Contents><Key>b.txt</Key></Contents>\
+            <NextContinuationToken>next/page=</NextContinuationToken></ListBucketResult>";
+        let server = MockServer::start(|request| {
+            if request.path == "/bucket?list-type=2&continuation-token=next%2Fpage%3D" {
+                MockResponse::new(
+                    200,
+                    "<ListBucketResult><IsTruncated>false</IsTruncated>\
+                     <Contents><Key>c.txt</Key></Contents></ListBucketResult>",
+                )
+            } else {
+                MockResponse::new(404, "NoSuchKey")
             }
-        }
+        })
+        .await;
+        let client = GatedClient::new(reqwest::Client::new());
+        let url = format!("{}/bucket", server.url());
+        let listing = |all_pages, max_files| Listing {
+            max_files,
+            all_pages,
+            limiter: None,
+        };
+
+        let (files, total) = list_s3_bucket(&client, &url, first, &listing(true, 10)).await;
+        assert_eq!(files, ["a.txt", "b.txt", "c.txt"]);
+        assert_eq!(total, 3);
+
+        // Without --list-all only the first page is read
+        let (files, total) = list_s3_bucket(&client, &url, first, &listing(false, 10)).await;
+        assert_eq!(files, ["a.txt", "b.txt"]);
+        assert_eq!(total, 2);
+
+        // --max-files caps the keys kept, and no page is fetched past it
+        let requests = server.requests().len();
+        let (files, _) = list_s3_bucket(&client, &url, first, &listing(true, 2)).await;
+        assert_eq!(files, ["a.txt", "b.txt"]);
+        assert_eq!(server.requests().len(), requests);
     }
 
-    files
+    #[tokio::test]
+    async fn readable_acl_and_policy_are_classified() {
+        let acl = r#"<AccessControlPolicy><Owner><ID>1</ID></Owner><AccessControlList><Grant><Grantee><URI>http://acs.amazonaws.com/groups/global/AllUsers</URI></Grantee><Permission>READ</Permission></Grant></AccessControlList></AccessControlPolicy>"#;
+        let server = MockServer::start(move |request| match request.path.as_str() {
+            "/open?acl" => MockResponse::new(200, acl),
+            "/open?policy" => MockResponse::new(200, r#"{"Version":"2012-10-17","Statement":[]}"#),
+            "/site?acl" | "/site?policy" => MockResponse::new(200, "<html>welcome</html>"),
+            _ => MockResponse::new(403, "<Error><Code>AccessDenied</Code></Error>"),
+        })
+        .await;
+        let client = GatedClient::new(reqwest::Client::new());
+        let bucket = |name: &str| format!("{}/{}", server.url(), name);
+
+        assert!(check_s3_acl(&client, &bucket("open")).await);
+        assert!(check_s3_policy(&client, &bucket("open")).await);
+        // 200 pages that aren't ACL or policy documents don't count
+        assert!(!check_s3_acl(&client, &bucket("site")).await);
+        assert!(!check_s3_policy(&client, &bucket("site")).await);
+        assert!(!check_s3_acl(&client, &bucket("closed")).await);
+        assert!(!check_s3_policy(&client, &bucket("closed")).await);
+    }
 }
@@ -0,0 +1,327 @@
+//! TCP port and banner grabbing mode
+
+use crate::cli::TcpArgs;
+use crate::core::{ErrorLimit, RateLimiter};
+use crate::error::{RbusterError, Result};
+use crate::output::{
+    print_concurrency, print_error, print_tcp_result, print_warning, OutputHandler,
+    ProgressTracker, ScanMeta, TcpResult,
+};
+use futures::stream::{self, StreamExt};
+use std::io::ErrorKind;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc::UnboundedSender;
+
+/// Most banner bytes read per port
+const MAX_BANNER: usize = 1024;
+
+/// Run TCP banner grabbing
+pub async fn run(args: TcpArgs) -> Result<()> {
+    run_with_results(args, None).await
+}
+
+/// Run TCP banner grabbing, also sending each open port to `result_tx`.
+/// Results arrive in completion order, not wordlist order.
+pub async fn run_with_results(
+    args: TcpArgs,
+    result_tx: Option<UnboundedSender<TcpResult>>,
+) -> Result<()> {
+    super::check_stdin_inputs(&args.global.wordlist, &[])?;
+    // Wrapped ports would no longer parse as targets
+    if args.global.word_prefix.is_some() || args.global.word_suffix.is_some() {
+        return Err(RbusterError::ConfigError(
+            "--word-prefix and --word-suffix are not available in tcp mode".to_string(),
+        ));
+    }
+
+    // Load wordlist: ports for --host, or host:port entries
+    let wordlist = super::load_words(&args.global).await?;
+    if args.host.is_none() && wordlist.iter().any(|entry| entry.parse::<u16>().is_ok()) {
+        return Err(RbusterError::ConfigError(
+            "Wordlist entries that are only a port need --host".to_string(),
+        ));
+    }
+    let targets: Vec<(String, u16)> = wordlist
+        .iter()
+        .filter_map(|entry| parse_target(entry, args.host.as_deref()))
+        .collect();
+    if targets.len() < wordlist.len() {
+        print_warning(&format!(
+            "Skipping {} wordlist entries that are not a port or host:port",
+            wordlist.len() - targets.len()
+        ));
+    }
+    let probe = args.send.as_deref().map(unescape);
+
+    // Create progress tracker
+    let progress = ProgressTracker::new(
+        targets.len() as u64,
+        args.global.quiet || args.global.no_progress,
+    );
+
+    // Create output handler
+    let output = OutputHandler::new(
+        &args.global,
+        ScanMeta::new("tcp", args.host.clone(), &args.global),
+    )
+    .await?;
+    let output = Arc::new(output);
+
+    let concurrency = Arc::new(super::concurrency(&args.global, args.global.threads)?);
+    let delay = super::request_delay(&args.global);
+    let limiter = args.global.rate.map(|r| Arc::new(RateLimiter::new(r)));
+    let error_limit = Arc::new(ErrorLimit::new(args.global.max_errors));
+    let timeout = Duration::from_secs(args.timeout);
+    let verbose = args.global.verbosity();
+
+    // Connect to ports concurrently
+    stream::iter(targets)
+        .map(|(host, port)| {
+            let concurrency = Arc::clone(&concurrency);
+            let progress = progress.clone();
+            let limiter = limiter.clone();
+            let error_limit = Arc::clone(&error_limit);
+            let output = Arc::clone(&output);
+            let result_tx = result_tx.clone();
+            let probe = probe.as_deref();
+
+            async move {
+                let _permit = concurrency.acquire().await;
+
+                if error_limit.tripped() {
+                    return;
+                }
+
+                delay.wait().await;
+
+                if let Some(ref limiter) = limiter {
+                    limiter.acquire().await;
+                }
+
+                let result = grab_banner(&host, port, probe, timeout).await;
+                progress.inc();
+                concurrency.record(result.is_ok());
+                if result.is_ok() {
+                    error_limit.success();
+                } else if error_limit.failure() {
+                    print_warning(&error_limit.message());
+                }
+
+                let banner = match result {
+                    Ok(Some(banner)) => banner,
+                    // Closed or filtered
+                    Ok(None) => return,
+                    Err(e) => {
+                        progress.inc_error();
+                        if verbose >= 1 {
+                            print_error(&format!("{}:{}: {}", host, port, e), true);
+                        }
+                        return;
+                    }
+                };
+
+                progress.inc_found();
+                let result = TcpResult {
+                    host,
+                    port,
+                    banner: (!banner.is_empty()).then_some(banner),
+                };
+
+                print_tcp_result(&result);
+
+                // Record result (file output and hooks)
+                let address = result.address();
+                let line = match result.banner {
+                    Some(ref banner) => format!("{} {}", address, banner),
+                    None => address,
+                };
+                output.record(&result, &line).await;
+                if let Some(ref result_tx) = result_tx {
+                    let _ = result_tx.send(result);
+                }
+            }
+        })
+        .buffer_unordered(args.global.threads)
+        .collect::<Vec<()>>()
+        .await;
+
+    progress.finish();
+    progress.print_summary(args.global.quiet);
+    print_concurrency(&concurrency, args.global.quiet);
+    output.finalize(&progress.stats()).await?;
+
+    Ok(())
+}
+
+/// Host and port of a wordlist entry: a port (on `host`), or host:port with
+/// IPv6 addresses in brackets
+fn parse_target(entry: &str, host: Option<&str>) -> Option<(String, u16)> {
+    if let Ok(port) = entry.parse() {
+        return host.map(|host| (host.to_string(), port));
+    }
+    let (host, port) = entry.rsplit_once(':')?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if host.is_empty() {
+        return None;
+    }
+    Some((host.to_string(), port.parse().ok()?))
+}
+
+/// Connect, send `probe` if given and read what the service sends within
+/// `timeout`. Returns `None` for closed or filtered ports and the printable
+/// banner, possibly empty, for open ones.
+async fn grab_banner(
+    host: &str,
+    port: u16,
+    probe: Option<&[u8]>,
+    timeout: Duration,
+) -> std::result::Result<Option<String>, String> {
+    let mut stream = match tokio::time::timeout(timeout, TcpStream::connect((host, port))).await {
+        Ok(Ok(stream)) => stream,
+        Ok(Err(e)) if e.kind() == ErrorKind::ConnectionRefused => return Ok(None),
+        Ok(Err(e)) => return Err(e.to_string()),
+        // No answer, most likely dropped by a firewall
+        Err(_) => return Ok(None),
+    };
+
+    // The port is open even if the service hangs up before the probe
+    if let Some(probe) = probe {
+        if stream.write_all(probe).await.is_err() {
+            return Ok(Some(String::new()));
+        }
+    }
+
+    // Silent services and resets leave an empty banner
+    let mut buf = vec![0u8; MAX_BANNER];
+    let read = match tokio::time::timeout(timeout, stream.read(&mut buf)).await {
+        Ok(Ok(read)) => read,
+        Ok(Err(_)) | Err(_) => 0,
+    };
+    Ok(Some(printable(&buf[..read])))
+}
+
+/// Banner text with surrounding whitespace trimmed and control characters
+/// escaped, so it stays on one line
+fn printable(bytes: &[u8]) -> String {
+    let mut text = String::new();
+    for c in String::from_utf8_lossy(bytes).trim().chars() {
+        if c.is_control() {
+            text.extend(c.escape_default());
+        } else {
+            text.push(c);
+        }
+    }
+    text
+}
+
+/// Bytes of a --send probe, with \r, \n, \t, \0, \\ and \xHH escapes
+fn unescape(text: &str) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut utf8 = [0u8; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut utf8).as_bytes());
+            continue;
+        }
+        match chars.next() {
+            Some('r') => bytes.push(b'\r'),
+            Some('n') => bytes.push(b'\n'),
+            Some('t') => bytes.push(b'\t'),
+            Some('0') => bytes.push(0),
+            Some('\\') => bytes.push(b'\\'),
+            Some('x') => {
+                let hex: String = (0..2)
+                    .filter_map(|_| chars.next_if(char::is_ascii_hexdigit))
+                    .collect();
+                match u8::from_str_radix(&hex, 16) {
+                    Ok(byte) => bytes.push(byte),
+                    Err(_) => bytes.extend_from_slice(format!("\\x{}", hex).as_bytes()),
+                }
+            }
+            Some(other) => {
+                bytes.push(b'\\');
+                let mut utf8 = [0u8; 4];
+                bytes.extend_from_slice(other.encode_utf8(&mut utf8).as_bytes());
+            }
+            None => bytes.push(b'\\'),
+        }
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{Cli, Commands};
+    use crate::core::random_label;
+    use clap::Parser;
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+
+    #[tokio::test]
+    async fn open_port_is_reported_with_its_banner() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let open = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let _ = stream.write_all(b"SSH-2.0-test\r\n").await;
+            }
+        });
+        let closed = {
+            let spare = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            spare.local_addr().unwrap().port()
+        };
+
+        let wordlist = std::env::temp_dir().join(format!("rbuster-{}.txt", random_label(12)));
+        std::fs::write(&wordlist, format!("{}\n{}\n", open, closed)).unwrap();
+        let cli = Cli::parse_from([
+            "robuster",
+            "tcp",
+            "-q",
+            "--host",
+            "127.0.0.1",
+            "-w",
+            wordlist.to_str().unwrap(),
+        ]);
+        let Commands::Tcp(args) = cli.command else {
+            unreachable!()
+        };
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let run = run_with_results(args, Some(tx)).await;
+        std::fs::remove_file(&wordlist).unwrap();
+        run.unwrap();
+
+        let mut found = Vec::new();
+        while let Ok(result) = rx.try_recv() {
+            found.push((result.port, result.banner));
+        }
+        assert_eq!(found, [(open, Some("SSH-2.0-test".to_string()))]);
+    }
+
+    #[tokio::test]
+    async fn word_prefix_is_rejected() {
+        let cli = Cli::parse_from([
+            "robuster",
+            "tcp",
+            "-q",
+            "--host",
+            "127.0.0.1",
+            "-w",
+            "ports.txt",
+            "--word-prefix",
+            "1",
+        ]);
+        let Commands::Tcp(args) = cli.command else {
+            unreachable!()
+        };
+        assert!(matches!(
+            run_with_results(args, None).await,
+            Err(RbusterError::ConfigError(_))
+        ));
+    }
+}
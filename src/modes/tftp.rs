@@ -1,24 +1,38 @@
 //! TFTP file enumeration mode
 
 use crate::cli::TftpArgs;
-use crate::core::load_wordlist;
+use crate::core::{random_label, ErrorLimit, RateLimiter};
 use crate::error::Result;
-use crate::output::{print_error, OutputHandler, ProgressTracker};
-use colored::*;
+use crate::output::{
+    print_concurrency, print_error, print_tftp_result, print_tftp_writable, print_warning,
+    OutputHandler, ProgressTracker, ScanMeta, TftpResult,
+};
 use futures::stream::{self, StreamExt};
+use std::collections::BTreeSet;
 use std::net::{SocketAddr, UdpSocket};
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::Semaphore;
+use tokio::sync::mpsc::UnboundedSender;
 
 // TFTP opcodes
 const TFTP_RRQ: u16 = 1; // Read request
+const TFTP_WRQ: u16 = 2; // Write request
 const TFTP_DATA: u8 = 3;
+const TFTP_ACK: u8 = 4;
 const TFTP_ERROR: u8 = 5;
 const TFTP_OACK: u8 = 6;
 
 /// Run TFTP file enumeration
 pub async fn run(args: TftpArgs) -> Result<()> {
+    run_with_results(args, None).await
+}
+
+/// Run TFTP file enumeration, also sending each found result to `result_tx`.
+/// Results arrive in completion order, not wordlist order.
+pub async fn run_with_results(
+    args: TftpArgs,
+    result_tx: Option<UnboundedSender<TftpResult>>,
+) -> Result<()> {
     // Parse server address
     let server_addr: SocketAddr = if args.server.contains(':') {
         args.server.parse().map_err(|e| {
@@ -31,64 +45,134 @@ pub async fn run(args: TftpArgs) -> Result<()> {
     };
 
     // Load wordlist
-    let wordlist = load_wordlist(&args.global.wordlist)
-        .await
-        .map_err(crate::error::RbusterError::WordlistError)?;
+    let wordlist = super::load_words(&args.global).await?;
     let total = wordlist.len();
 
     // Create progress tracker
     let progress = ProgressTracker::new(total as u64, args.global.quiet || args.global.no_progress);
 
     // Create output handler
-    let output = OutputHandler::new(args.global.output.as_deref()).await?;
+    let output = OutputHandler::new(
+        &args.global,
+        ScanMeta::new("tftp", Some(args.server.clone()), &args.global),
+    )
+    .await?;
     let output = Arc::new(output);
 
-    // Create semaphore for concurrency control
-    // TFTP uses UDP, so we limit concurrency more strictly
-    let semaphore = Arc::new(Semaphore::new(args.global.threads.min(50)));
-    let delay = args.global.delay.map(Duration::from_millis);
+    // Limit concurrency, adjusted to the error rate with --adaptive. TFTP
+    // uses UDP, so we limit concurrency more strictly
+    let concurrency = Arc::new(super::concurrency(
+        &args.global,
+        args.global.threads.min(50),
+    )?);
+    let delay = super::request_delay(&args.global);
+    let limiter = args.global.rate.map(|r| Arc::new(RateLimiter::new(r)));
+    let error_limit = Arc::new(ErrorLimit::new(args.global.max_errors));
     let timeout = Duration::from_secs(args.timeout);
-    let verbose = args.global.verbose;
+    let verbose = args.global.verbosity();
+
+    // Probe each directory of the wordlist once for uploads, under a random
+    // name: a write request for a file found readable would truncate it
+    if args.check_writable {
+        let dirs: BTreeSet<&str> = wordlist.iter().map(|f| upload_dir(f)).collect();
+        for dir in dirs {
+            delay.wait().await;
+            if let Some(ref limiter) = limiter {
+                limiter.acquire().await;
+            }
+            let probe = format!("{}{}", dir, random_label(16));
+            let shown = if dir.is_empty() { "/" } else { dir };
+            match check_tftp_writable(&server_addr, &probe, timeout).await {
+                Ok(true) => {
+                    progress.inc_found();
+                    print_tftp_writable(shown, &probe);
+                    let result = TftpResult {
+                        filename: shown.to_string(),
+                        readable: false,
+                        writable: Some(true),
+                    };
+                    output
+                        .record(&result, &format!("{} [writable]", shown))
+                        .await;
+                    if let Some(ref result_tx) = result_tx {
+                        let _ = result_tx.send(result);
+                    }
+                }
+                Ok(false) => {}
+                Err(e) => {
+                    progress.inc_error();
+                    if verbose >= 1 {
+                        print_error(&format!("{}: {}", probe, e), true);
+                    }
+                }
+            }
+        }
+    }
 
     // Process filenames concurrently
     let _results: Vec<_> = stream::iter(wordlist)
         .map(|filename| {
-            let semaphore = Arc::clone(&semaphore);
+            let concurrency = Arc::clone(&concurrency);
             let progress = progress.clone();
+            let limiter = limiter.clone();
+            let error_limit = Arc::clone(&error_limit);
             let output = Arc::clone(&output);
+            let result_tx = result_tx.clone();
 
             async move {
-                let _permit = semaphore.acquire().await.unwrap();
+                let _permit = concurrency.acquire().await;
 
-                if let Some(d) = delay {
-                    tokio::time::sleep(d).await;
+                if error_limit.tripped() {
+                    return None;
                 }
 
-                progress.inc();
+                delay.wait().await;
 
-                // Check if file exists via TFTP
-                match check_tftp_file(&server_addr, &filename, timeout).await {
-                    Ok(true) => {
-                        progress.inc_found();
+                if let Some(ref limiter) = limiter {
+                    limiter.acquire().await;
+                }
 
-                        // Print found file
-                        println!("{} {}", "Found:".bright_green(), filename.bright_white());
+                progress.inc();
 
-                        // Write to file if configured
-                        if let Some(writer) = output.file_writer() {
-                            let _ = writer.write_line(&filename).await;
-                        }
+                // Check if file exists via TFTP
+                let result = check_tftp_file(&server_addr, &filename, timeout).await;
+                concurrency.record(result.is_ok());
+                if result.is_ok() {
+                    error_limit.success();
+                } else if error_limit.failure() {
+                    print_warning(&error_limit.message());
+                }
 
-                        Some(filename)
-                    }
-                    Ok(false) => None,
+                let readable = match result {
+                    Ok(readable) => readable,
                     Err(e) => {
-                        if verbose {
+                        progress.inc_error();
+                        if verbose >= 1 {
                             print_error(&format!("{}: {}", filename, e), true);
                         }
-                        None
+                        return None;
                     }
+                };
+
+                if !readable {
+                    return None;
+                }
+
+                progress.inc_found();
+
+                // Record result (file output and hooks)
+                let result = TftpResult {
+                    filename: filename.clone(),
+                    readable,
+                    writable: None,
+                };
+                print_tftp_result(&result);
+                output.record(&result, &filename).await;
+                if let Some(ref result_tx) = result_tx {
+                    let _ = result_tx.send(result.clone());
                 }
+
+                Some(filename)
             }
         })
         .buffer_unordered(args.global.threads.min(50))
@@ -96,7 +180,9 @@ pub async fn run(args: TftpArgs) -> Result<()> {
         .await;
 
     progress.finish();
-    output.finalize().await?;
+    progress.print_summary(args.global.quiet);
+    print_concurrency(&concurrency, args.global.quiet);
+    output.finalize(&progress.stats()).await?;
 
     Ok(())
 }
@@ -107,6 +193,58 @@ async fn check_tftp_file(
     filename: &str,
     timeout: Duration,
 ) -> std::result::Result<bool, String> {
+    let packet = build_request(TFTP_RRQ, filename);
+    match send_probe(server, &packet, timeout)? {
+        // File exists
+        Some(response) => Ok(matches!(response[1], TFTP_DATA | TFTP_OACK)),
+        // Timeout, assume file doesn't exist
+        None => Ok(false),
+    }
+}
+
+/// Check if a TFTP server accepts a write request for a file. Only the
+/// handshake is performed; no data is sent, but servers may create or
+/// truncate the file, so only probe names that do not exist.
+async fn check_tftp_writable(
+    server: &SocketAddr,
+    filename: &str,
+    timeout: Duration,
+) -> std::result::Result<bool, String> {
+    let packet = build_request(TFTP_WRQ, filename);
+    match send_probe(server, &packet, timeout)? {
+        // Writes are acknowledged with OACK (options) or ACK of block 0
+        Some(response) => {
+            Ok(response[1] == TFTP_OACK || (response[1] == TFTP_ACK && response[2..4] == [0, 0]))
+        }
+        None => Ok(false),
+    }
+}
+
+/// Directory part of a filename, with its trailing slash ("" for the root)
+fn upload_dir(filename: &str) -> &str {
+    filename.rfind('/').map_or("", |i| &filename[..=i])
+}
+
+/// Build a TFTP read or write request packet
+/// Format: opcode (2 bytes) | filename | 0 | mode | 0 | blksize | 0 | 512 | 0
+fn build_request(opcode: u16, filename: &str) -> Vec<u8> {
+    let mut packet = Vec::new();
+    packet.extend_from_slice(&opcode.to_be_bytes());
+    for field in [filename, "octet", "blksize", "512"] {
+        packet.extend_from_slice(field.as_bytes());
+        packet.push(0);
+    }
+    packet
+}
+
+/// Send a request and return the first response of at least 4 bytes, or
+/// `None` on timeout. The transfer is aborted right away with an error
+/// packet, so no file data moves in either direction.
+fn send_probe(
+    server: &SocketAddr,
+    packet: &[u8],
+    timeout: Duration,
+) -> std::result::Result<Option<Vec<u8>>, String> {
     // Create UDP socket
     let socket =
         UdpSocket::bind("0.0.0.0:0").map_err(|e| format!("Failed to bind socket: {}", e))?;
@@ -119,42 +257,62 @@ async fn check_tftp_file(
         .set_write_timeout(Some(timeout))
         .map_err(|e| format!("Failed to set timeout: {}", e))?;
 
-    // Build TFTP read request packet
-    // Format: opcode (2 bytes) | filename | 0 | mode | 0 | blksize | 0 | 512 | 0
-    let mut packet = Vec::new();
-    packet.extend_from_slice(&TFTP_RRQ.to_be_bytes());
-    packet.extend_from_slice(filename.as_bytes());
-    packet.push(0);
-    packet.extend_from_slice(b"octet");
-    packet.push(0);
-    packet.extend_from_slice(b"blksize");
-    packet.push(0);
-    packet.extend_from_slice(b"512");
-    packet.push(0);
-
     // Send request
     socket
-        .send_to(&packet, server)
+        .send_to(packet, server)
         .map_err(|e| format!("Failed to send request: {}", e))?;
 
     // Receive response
     let mut buf = [0u8; 516];
     match socket.recv_from(&mut buf) {
-        Ok((size, _)) if size >= 4 => {
-            let opcode = buf[1];
-            match opcode {
-                TFTP_DATA | TFTP_OACK => Ok(true), // File exists
-                TFTP_ERROR => Ok(false),           // File not found or access denied
-                _ => Ok(false),
+        Ok((size, peer)) if size >= 4 => {
+            if buf[1] != TFTP_ERROR {
+                // The server answers from a new port (its transfer ID)
+                let _ = socket.send_to(&abort_packet(), peer);
             }
+            Ok(Some(buf[..size].to_vec()))
         }
-        Ok(_) => Ok(false),
+        Ok(_) => Ok(None),
         Err(e)
             if e.kind() == std::io::ErrorKind::WouldBlock
                 || e.kind() == std::io::ErrorKind::TimedOut =>
         {
-            Ok(false) // Timeout, assume file doesn't exist
+            Ok(None)
         }
         Err(e) => Err(format!("Failed to receive response: {}", e)),
     }
 }
+
+/// Error packet that terminates a transfer
+fn abort_packet() -> Vec<u8> {
+    let mut packet = Vec::new();
+    packet.extend_from_slice(&(TFTP_ERROR as u16).to_be_bytes());
+    packet.extend_from_slice(&0u16.to_be_bytes());
+    packet.extend_from_slice(b"probe aborted");
+    packet.push(0);
+    packet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_request_encodes_opcode_and_fields() {
+        let packet = build_request(TFTP_WRQ, "uploads/a.txt");
+        assert_eq!(&packet[..2], &[0, 2]);
+        let fields: Vec<&[u8]> = packet[2..].split(|&b| b == 0).collect();
+        assert_eq!(
+            fields,
+            [&b"uploads/a.txt"[..], b"octet", b"blksize", b"512", b""]
+        );
+        assert_eq!(packet.last(), Some(&0));
+    }
+
+    #[test]
+    fn upload_dir_keeps_trailing_slash() {
+        assert_eq!(upload_dir("a.txt"), "");
+        assert_eq!(upload_dir("cfg/a.txt"), "cfg/");
+        assert_eq!(upload_dir("a/b/c"), "a/b/");
+    }
+}
@@ -1,47 +1,54 @@
 //! Virtual host enumeration mode
 
-use crate::cli::VhostArgs;
-use crate::core::{load_wordlist, parse_headers};
+use crate::cli::{VhostArgs, VhostCompare};
+use crate::core::{
+    body_counts, header_pairs, AddressFamily, BodyFingerprinter, ErrorLimit, FilterOutcome,
+    HideReason, HttpClient, LengthMatcher, RateLimiter, ResponseFilter,
+};
 use crate::error::Result;
-use crate::output::{print_error, print_vhost_result, OutputHandler, ProgressTracker, VhostResult};
+use crate::output::{
+    print_concurrency, print_error, print_response, print_vhost_result, print_warning,
+    OutputHandler, ProgressTracker, ScanMeta, VhostResult,
+};
 use futures::stream::{self, StreamExt};
-use reqwest::ClientBuilder;
+use reqwest::Method;
 use std::collections::HashSet;
+use std::net::SocketAddr;
 use std::sync::Arc;
-use std::time::Duration;
-use tokio::sync::Semaphore;
+use tokio::sync::mpsc::UnboundedSender;
 
 /// Run virtual host enumeration
 pub async fn run(args: VhostArgs) -> Result<()> {
-    // Parse exclude lengths
-    let exclude_lengths: HashSet<usize> = args
-        .exclude_length
-        .as_ref()
-        .map(|s| s.split(',').filter_map(|l| l.trim().parse().ok()).collect())
-        .unwrap_or_default();
-
-    // Build HTTP client
-    let mut builder = ClientBuilder::new()
-        .user_agent(&args.http.user_agent)
-        .timeout(Duration::from_secs(args.http.timeout))
-        .danger_accept_invalid_certs(args.http.insecure)
-        .pool_max_idle_per_host(100)
-        .tcp_nodelay(true);
+    run_with_results(args, None).await
+}
 
-    if !args.http.follow_redirect {
-        builder = builder.redirect(reqwest::redirect::Policy::none());
-    }
+/// Run virtual host enumeration, also sending each found result to `result_tx`.
+/// Results arrive in completion order, not wordlist order.
+pub async fn run_with_results(
+    args: VhostArgs,
+    result_tx: Option<UnboundedSender<VhostResult>>,
+) -> Result<()> {
+    // Build response filters; bad request errors are never interesting
+    let filter = ResponseFilter {
+        exclude_status: HashSet::from([400]),
+        exclude_length: LengthMatcher::parse(args.exclude_length.as_deref())?,
+        ..Default::default()
+    };
 
-    if let Some(ref proxy_url) = args.http.proxy {
-        builder = builder.proxy(reqwest::Proxy::all(proxy_url)?);
+    // Build HTTP client. With --sni every connection is pinned to the target's
+    // address so candidate hosts can go in the URL, which sets Host and SNI alike
+    let target = reqwest::Url::parse(&args.url).map_err(|e| {
+        crate::error::RbusterError::ConfigError(format!("Invalid URL '{}': {}", args.url, e))
+    })?;
+    let mut http_config = super::http_config(&args.http);
+    if args.sni {
+        http_config.pinned_address =
+            Some(resolve_target(&target, http_config.address_family).await?);
     }
-
-    let client = Arc::new(builder.build()?);
+    let http_client = Arc::new(HttpClient::new(http_config)?);
 
     // Load wordlist
-    let wordlist = load_wordlist(&args.global.wordlist)
-        .await
-        .map_err(crate::error::RbusterError::WordlistError)?;
+    let wordlist = super::load_words(&args.global).await?;
     let total = wordlist.len();
 
     // Get base domain for appending
@@ -51,41 +58,70 @@ pub async fn run(args: VhostArgs) -> Result<()> {
     let progress = ProgressTracker::new(total as u64, args.global.quiet || args.global.no_progress);
 
     // Create output handler
-    let output = OutputHandler::new(args.global.output.as_deref()).await?;
+    let output = OutputHandler::new(
+        &args.global,
+        ScanMeta::new("vhost", Some(args.url.clone()), &args.global).with_http(&args.http),
+    )
+    .await?;
     let output = Arc::new(output);
 
     // Get baseline response for comparison
-    let baseline_size = {
-        let resp = client.get(&args.url).send().await?;
-        resp.bytes().await?.len()
+    let fingerprinter = BodyFingerprinter::new();
+    let baseline_host = reqwest::Url::parse(&args.url)
+        .ok()
+        .and_then(|u| u.host_str().map(|h| h.to_string()))
+        .unwrap_or_default();
+    let baseline = {
+        // Sent with the same headers, cookies and credentials as each probe
+        let resp = http_client.request(Method::GET, &args.url, None).await?;
+        if matches!(resp.status().as_u16(), 401 | 407) {
+            print_warning(&format!(
+                "Baseline request returned {}; check -U/-P, --cookies and -H, or every host will look the same",
+                resp.status().as_u16()
+            ));
+        }
+        let body = resp.bytes().await?;
+        Baseline::new(&fingerprinter, &body, &baseline_host)
     };
+    let compare = args.compare;
 
-    // Create semaphore for concurrency control
-    let semaphore = Arc::new(Semaphore::new(args.global.threads));
-    let delay = args.global.delay.map(Duration::from_millis);
+    // Limit concurrency, adjusted to the error rate with --adaptive
+    let concurrency = Arc::new(super::concurrency(&args.global, args.global.threads)?);
+    let delay = super::request_delay(&args.global);
+    let limiter = args.global.rate.map(|r| Arc::new(RateLimiter::new(r)));
+    let error_limit = Arc::new(ErrorLimit::new(args.global.max_errors));
 
-    let headers = parse_headers(&args.http.headers);
-    let verbose = args.global.verbose;
-    let url = args.url.clone();
+    let verbose = args.global.verbosity();
     let append_domain = args.append_domain;
+    let sni = args.sni;
 
     // Process vhosts concurrently
     let _results: Vec<_> = stream::iter(wordlist)
         .map(|word| {
-            let semaphore = Arc::clone(&semaphore);
-            let client = Arc::clone(&client);
+            let concurrency = Arc::clone(&concurrency);
+            let http_client = Arc::clone(&http_client);
             let progress = progress.clone();
+            let limiter = limiter.clone();
+            let error_limit = Arc::clone(&error_limit);
             let output = Arc::clone(&output);
-            let url = url.clone();
-            let headers = headers.clone();
-            let exclude_lengths = exclude_lengths.clone();
+            let result_tx = result_tx.clone();
+            let target = &target;
+            let filter = &filter;
             let base_domain = base_domain.clone();
+            let fingerprinter = &fingerprinter;
+            let baseline = &baseline;
 
             async move {
-                let _permit = semaphore.acquire().await.unwrap();
+                let _permit = concurrency.acquire().await;
+
+                if error_limit.tripped() {
+                    return None;
+                }
 
-                if let Some(d) = delay {
-                    tokio::time::sleep(d).await;
+                delay.wait().await;
+
+                if let Some(ref limiter) = limiter {
+                    limiter.acquire().await;
                 }
 
                 // Build the host header value
@@ -99,48 +135,63 @@ pub async fn run(args: VhostArgs) -> Result<()> {
                     word.clone()
                 };
 
-                // Build request with Host header
-                let mut request = client.get(&url).header("Host", &host);
+                let result = send_probe(&http_client, target, &host, sni).await;
+                progress.inc();
 
-                // Add custom headers
-                for (key, value) in &headers {
-                    request = request.header(key.as_str(), value.as_str());
-                }
+                concurrency.record(result.is_ok());
 
-                let result = request.send().await;
-                progress.inc();
+                if result.is_ok() {
+                    error_limit.success();
+                } else if error_limit.failure() {
+                    print_warning(&error_limit.message());
+                }
 
                 match result {
                     Ok(response) => {
                         let status = response.status().as_u16();
+                        let headers = if verbose >= 3 {
+                            header_pairs(response.headers())
+                        } else {
+                            Vec::new()
+                        };
                         let body = response.bytes().await.unwrap_or_default();
                         let size = body.len();
+                        // Skip if the page matches the baseline or is filtered out
+                        let differs = baseline.differs(compare, fingerprinter, &body, &host);
+                        let text = String::from_utf8_lossy(&body);
+                        let (words, lines) = body_counts(&text);
+                        let outcome = if differs {
+                            filter.evaluate(status, size, words, lines, &text, None, &[])
+                        } else {
+                            FilterOutcome::Hide(HideReason::Baseline)
+                        };
+                        if verbose >= 2 {
+                            print_response(
+                                &host,
+                                status,
+                                size,
+                                &headers,
+                                super::hidden(&outcome).as_deref(),
+                            );
+                        }
 
-                        // Skip if size matches baseline or is in exclude list
-                        let should_show = size != baseline_size
-                            && !exclude_lengths.contains(&size)
-                            && status != 400; // Skip bad request errors
-
-                        if should_show {
+                        if outcome.is_shown() {
                             progress.inc_found();
+                            progress.record_status(status);
 
                             // Print to console
                             print_vhost_result(&host, status, size);
 
-                            // Write to file if configured
-                            if let Some(writer) = output.file_writer() {
-                                let result = VhostResult {
-                                    host: host.clone(),
-                                    status,
-                                    size,
-                                };
-                                if writer.is_json() {
-                                    let _ = writer.write_json(&result).await;
-                                } else {
-                                    let line =
-                                        format!("{} (Status: {}) [Size: {}]", host, status, size);
-                                    let _ = writer.write_line(&line).await;
-                                }
+                            // Record result (file output and hooks)
+                            let result = VhostResult {
+                                host: host.clone(),
+                                status,
+                                size,
+                            };
+                            let line = format!("{} (Status: {}) [Size: {}]", host, status, size);
+                            output.record(&result, &line).await;
+                            if let Some(ref result_tx) = result_tx {
+                                let _ = result_tx.send(result.clone());
                             }
 
                             Some((host, status, size))
@@ -149,7 +200,8 @@ pub async fn run(args: VhostArgs) -> Result<()> {
                         }
                     }
                     Err(e) => {
-                        if verbose {
+                        progress.inc_error();
+                        if verbose >= 1 {
                             print_error(&format!("{}: {}", host, e), true);
                         }
                         None
@@ -162,7 +214,164 @@ pub async fn run(args: VhostArgs) -> Result<()> {
         .await;
 
     progress.finish();
-    output.finalize().await?;
+    super::warn_dead_proxies(&http_client);
+    super::warn_rate_limited(http_client.rate_limited());
+    progress.print_summary(args.global.quiet);
+    print_concurrency(&concurrency, args.global.quiet);
+    output.finalize(&progress.stats()).await?;
 
     Ok(())
 }
+
+/// Request the target for a candidate host, with the configured headers,
+/// cookies and credentials. The candidate goes in the Host header, or with
+/// `sni` in the URL host so it is also sent as SNI.
+async fn send_probe(
+    http_client: &HttpClient,
+    target: &reqwest::Url,
+    host: &str,
+    sni: bool,
+) -> Result<reqwest::Response> {
+    if sni {
+        let mut candidate = target.clone();
+        candidate.set_host(Some(host)).map_err(|e| {
+            crate::error::RbusterError::ConfigError(format!("invalid host name: {}", e))
+        })?;
+        let request = http_client.request_builder(Method::GET, candidate.as_str());
+        http_client.send(request).await
+    } else {
+        let request = http_client
+            .request_builder(Method::GET, target.as_str())
+            .header("Host", host);
+        http_client.send(request).await
+    }
+}
+
+/// Size and fingerprint of the page served for the target's own host name
+struct Baseline {
+    size: usize,
+    hash: u64,
+}
+
+impl Baseline {
+    fn new(fingerprinter: &BodyFingerprinter, body: &[u8], host: &str) -> Self {
+        Self {
+            size: body.len(),
+            hash: fingerprinter.fingerprint(body, &[host]),
+        }
+    }
+
+    /// Whether the page served for `host` differs from the baseline by the
+    /// chosen comparison
+    fn differs(
+        &self,
+        compare: VhostCompare,
+        fingerprinter: &BodyFingerprinter,
+        body: &[u8],
+        host: &str,
+    ) -> bool {
+        let size_differs = body.len() != self.size;
+        let hash_differs = || fingerprinter.fingerprint(body, &[host]) != self.hash;
+        match compare {
+            VhostCompare::Size => size_differs,
+            VhostCompare::Hash => hash_differs(),
+            VhostCompare::Both => size_differs && hash_differs(),
+        }
+    }
+}
+
+/// Resolve the target URL to the address all --sni connections go to
+async fn resolve_target(target: &reqwest::Url, family: AddressFamily) -> Result<SocketAddr> {
+    let host = target.host_str().unwrap_or_default();
+    let port = target.port_or_known_default().unwrap_or(443);
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+
+    tokio::net::lookup_host((host, port))
+        .await?
+        .find(|addr| family.allows(&addr.ip()))
+        .ok_or_else(|| {
+            crate::error::RbusterError::ConfigError(format!("Cannot resolve target '{}'", host))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::mock_server::{MockResponse, MockServer};
+    use crate::core::HttpConfig;
+    use std::collections::HashMap;
+
+    #[tokio::test]
+    async fn probes_carry_credentials_cookies_and_headers() {
+        // Basic auth for user:pass is required
+        let server = MockServer::start(|req| {
+            let authorized = req
+                .headers
+                .iter()
+                .any(|(n, v)| n.eq_ignore_ascii_case("authorization") && v == "Basic dXNlcjpwYXNz");
+            if authorized {
+                MockResponse::new(200, "vhost")
+            } else {
+                MockResponse::new(401, "unauthorized")
+            }
+        })
+        .await;
+        let target = reqwest::Url::parse(&server.url()).unwrap();
+
+        let anonymous = HttpClient::new(HttpConfig::default()).unwrap();
+        let response = send_probe(&anonymous, &target, "dev.example.com", false)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 401);
+
+        let client = HttpClient::new(HttpConfig {
+            username: Some("user".to_string()),
+            password: Some("pass".to_string()),
+            cookies: Some("session=abc".to_string()),
+            headers: HashMap::from([("X-Team".to_string(), "red".to_string())]),
+            ..Default::default()
+        })
+        .unwrap();
+        let response = send_probe(&client, &target, "dev.example.com", false)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+
+        let requests = server.requests();
+        let header = |name: &str| {
+            requests[1]
+                .headers
+                .iter()
+                .find(|(n, _)| n.eq_ignore_ascii_case(name))
+                .map(|(_, v)| v.as_str())
+        };
+        assert_eq!(header("host"), Some("dev.example.com"));
+        assert_eq!(header("cookie"), Some("session=abc"));
+        assert_eq!(header("x-team"), Some("red"));
+    }
+
+    #[test]
+    fn same_page_with_other_dynamic_values_matches_the_baseline() {
+        let fingerprinter = BodyFingerprinter::new();
+        let baseline = Baseline::new(
+            &fingerprinter,
+            b"<p>Welcome to example.com</p><p>Generated 1700000000</p>\
+              <input name=csrf value=Zm9vYmFyYmF6cXV4cXV1eDEy>",
+            "example.com",
+        );
+        // Longer host name, timestamp and token: a different size, same page
+        let same = b"<p>Welcome to staging.example.com</p><p>Generated 1700000123456</p>\
+              <input name=csrf value=cXV1eGJhemJhcmZvbzk4NzY1NDMyMQ==>";
+        let other = b"<p>Staging admin</p>";
+
+        let differs =
+            |compare, body: &[u8], host| baseline.differs(compare, &fingerprinter, body, host);
+        let host = "staging.example.com";
+        assert!(differs(VhostCompare::Size, same, host));
+        assert!(!differs(VhostCompare::Hash, same, host));
+        assert!(!differs(VhostCompare::Both, same, host));
+        for compare in [VhostCompare::Size, VhostCompare::Hash, VhostCompare::Both] {
+            assert!(differs(compare, other, host));
+        }
+    }
+}
@@ -1,24 +1,72 @@
 //! Console output with colors
 
+use super::{BucketResult, DashboardEvent, TcpResult, TftpResult};
+use crate::core::Concurrency;
 use colored::*;
 use std::net::IpAddr;
+use std::sync::mpsc::Sender;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Receives console messages while a dashboard owns the terminal
+static DASHBOARD: Mutex<Option<Sender<DashboardEvent>>> = Mutex::new(None);
+
+/// Color an HTTP status code by class: 2xx green, 3xx cyan, 4xx yellow, 5xx red
+pub fn color_status(status: u16) -> ColoredString {
+    let text = status.to_string();
+    match status {
+        200..=299 => text.bright_green(),
+        300..=399 => text.bright_cyan(),
+        400..=499 => text.bright_yellow(),
+        500..=599 => text.bright_red(),
+        _ => text.white(),
+    }
+}
 
 /// Print a found result for directory mode
+#[allow(clippy::too_many_arguments)]
 pub fn print_dir_result(
     path: &str,
     status: u16,
     size: usize,
     redirect: Option<&str>,
+    time: Option<Duration>,
     show_length: bool,
     expanded: bool,
     base_url: &str,
 ) {
-    let status_colored = match status {
-        200..=299 => status.to_string().bright_green(),
-        300..=399 => status.to_string().bright_yellow(),
-        400..=499 => status.to_string().bright_red(),
-        _ => status.to_string().white(),
-    };
+    // The dashboard gets results from the output handler
+    if redirected() {
+        return;
+    }
+    println!(
+        "{}",
+        dir_result_line(
+            path,
+            status,
+            size,
+            redirect,
+            time,
+            show_length,
+            expanded,
+            base_url
+        )
+    );
+}
+
+/// Console line for a found result in directory mode
+#[allow(clippy::too_many_arguments)]
+fn dir_result_line(
+    path: &str,
+    status: u16,
+    size: usize,
+    redirect: Option<&str>,
+    time: Option<Duration>,
+    show_length: bool,
+    expanded: bool,
+    base_url: &str,
+) -> String {
+    let status_colored = color_status(status);
 
     let display_path = if expanded {
         format!("{}{}", base_url.trim_end_matches('/'), path)
@@ -36,20 +84,31 @@ pub fn print_dir_result(
         line.push_str(&format!(" [Size: {}]", size.to_string().bright_cyan()));
     }
 
+    if let Some(time) = time {
+        line.push_str(&format!(
+            " [Time: {}ms]",
+            time.as_millis().to_string().bright_blue()
+        ));
+    }
+
     if let Some(loc) = redirect {
         line.push_str(&format!(" [--> {}]", loc.bright_magenta()));
     }
 
-    println!("{}", line);
+    line
 }
 
 /// Print a found result for DNS mode
+#[allow(clippy::too_many_arguments)]
 pub fn print_dns_result(
     subdomain: &str,
     ips: &[IpAddr],
     cnames: &[String],
     show_ips: bool,
     show_cname: bool,
+    records: &[(&str, &Vec<String>)],
+    http: Option<Option<u16>>,
+    takeover: Option<&str>,
 ) {
     let mut line = format!("{}", subdomain.bright_green());
 
@@ -67,69 +126,321 @@ pub fn print_dns_result(
         line.push_str(&format!(" [CNAME: {}]", cname_str.bright_yellow()));
     }
 
+    for (label, values) in records {
+        if !values.is_empty() {
+            line.push_str(&format!(
+                " [{}: {}]",
+                label,
+                values.join(", ").bright_magenta()
+            ));
+        }
+    }
+
+    match http {
+        Some(Some(status)) => {
+            line.push_str(&format!(" [HTTP: {}]", color_status(status)));
+        }
+        Some(None) => line.push_str(&format!(" [HTTP: {}]", "down".bright_red())),
+        None => {}
+    }
+
+    if let Some(service) = takeover {
+        line.push_str(&format!(
+            " [{}]",
+            format!("possible takeover: {}", service)
+                .bright_red()
+                .bold()
+        ));
+    }
+
     println!("{}", line);
 }
 
+/// Print the records of a successful zone transfer
+pub fn print_zone_transfer(nameserver: &str, address: &str, records: &[String]) {
+    println!(
+        "{} {} ({}) allowed a zone transfer: {} records",
+        "[AXFR]".bright_red().bold(),
+        nameserver.bright_green(),
+        address,
+        records.len().to_string().bright_cyan()
+    );
+    for record in records {
+        println!("  {}", record);
+    }
+}
+
 /// Print a found result for vhost mode
 pub fn print_vhost_result(host: &str, status: u16, size: usize) {
-    let status_colored = match status {
-        200..=299 => status.to_string().bright_green(),
-        300..=399 => status.to_string().bright_yellow(),
-        400..=499 => status.to_string().bright_red(),
-        _ => status.to_string().white(),
-    };
+    println!("{}", vhost_result_line(host, status, size));
+}
 
-    println!(
+/// Console line for a found result in vhost mode
+fn vhost_result_line(host: &str, status: u16, size: usize) -> String {
+    format!(
         "Found: {} (Status: {}) [Size: {}]",
         host.bright_green(),
-        status_colored,
+        color_status(status),
         size.to_string().bright_cyan()
-    );
+    )
 }
 
 /// Print a found result for fuzz mode
-pub fn print_fuzz_result(payload: &str, status: u16, size: usize, words: usize, lines: usize) {
-    let status_colored = match status {
-        200..=299 => status.to_string().bright_green(),
-        300..=399 => status.to_string().bright_yellow(),
-        400..=499 => status.to_string().bright_red(),
-        _ => status.to_string().white(),
-    };
-
+pub fn print_fuzz_result(
+    payload: &str,
+    status: u16,
+    size: usize,
+    words: usize,
+    lines: usize,
+    reflected: bool,
+) {
     println!(
+        "{}",
+        fuzz_result_line(payload, status, size, words, lines, reflected)
+    );
+}
+
+/// Console line for a found result in fuzz mode
+fn fuzz_result_line(
+    payload: &str,
+    status: u16,
+    size: usize,
+    words: usize,
+    lines: usize,
+    reflected: bool,
+) -> String {
+    let mut line = format!(
         "{:<30} [Status: {}, Size: {}, Words: {}, Lines: {}]",
         payload.bright_white(),
-        status_colored,
+        color_status(status),
         size.to_string().bright_cyan(),
         words,
         lines
     );
+    if reflected {
+        line.push_str(&format!(" [{}]", "REFLECTED".bright_red()));
+    }
+    line
+}
+
+/// Print an open port for tcp mode, with its banner if it sent one
+pub fn print_tcp_result(result: &TcpResult) {
+    let address = result.address();
+    match result.banner {
+        Some(ref banner) => println!(
+            "{} {} {}",
+            "Open:".bright_green(),
+            address.bright_white(),
+            banner.dimmed()
+        ),
+        None => println!("{} {}", "Open:".bright_green(), address.bright_white()),
+    }
+}
+
+/// Print a readable file for tftp mode
+pub fn print_tftp_result(result: &TftpResult) {
+    println!(
+        "{} {}",
+        "Found:".bright_green(),
+        result.filename.bright_white()
+    );
+}
+
+/// Print a directory that accepted a write request for `probe` in tftp mode
+pub fn print_tftp_writable(dir: &str, probe: &str) {
+    println!(
+        "{} {} (accepted a write request for {})",
+        "Writable:".bright_red(),
+        dir.bright_white(),
+        probe
+    );
 }
 
 /// Print S3/GCS bucket result
-pub fn print_bucket_result(bucket: &str, status: &str, files: &[String]) {
+pub fn print_bucket_result(result: &BucketResult) {
+    let status = result.status.as_str();
     let status_colored = match status {
-        "public" => status.bright_green(),
+        "public" | "reachable" => status.bright_green(),
         "private" => status.bright_yellow(),
         "not_found" => status.bright_red(),
         _ => status.white(),
     };
 
-    println!("{} [{}]", bucket.bright_white(), status_colored);
+    let mut line = format!("{} [{}]", result.name.bright_white(), status_colored);
+
+    if let Some(count) = result.object_count {
+        line.push_str(&format!(" ({} objects)", count.to_string().bright_cyan()));
+    }
+
+    if result.writable == Some(true) {
+        line.push_str(&format!(" [{}]", "WRITABLE".bright_red().bold()));
+    }
+    if result.acl_public == Some(true) {
+        line.push_str(&format!(" [{}]", "ACL READABLE".bright_red()));
+    }
+    if result.policy_public == Some(true) {
+        line.push_str(&format!(" [{}]", "POLICY READABLE".bright_red()));
+    }
 
-    for file in files.iter().take(5) {
+    println!("{}", line);
+
+    for file in result.files.iter().take(5) {
         println!("  └── {}", file.bright_cyan());
     }
 }
 
+/// Print one response at -vv, with why it was hidden if it was and its
+/// headers at -vvv. Written to stderr in a single call so lines from
+/// concurrent requests don't interleave.
+pub fn print_response(
+    target: &str,
+    status: u16,
+    size: usize,
+    headers: &[(String, String)],
+    hidden: Option<&str>,
+) {
+    if redirected() {
+        return;
+    }
+    let mut text = format!(
+        "{} {} (Status: {}) [Size: {}]",
+        "[RESP]".bright_black(),
+        target,
+        color_status(status),
+        size
+    );
+    if let Some(reason) = hidden {
+        text.push_str(&format!(
+            " {}",
+            format!("hidden: {}", reason).bright_black()
+        ));
+    }
+    for (name, value) in headers {
+        text.push_str(&format!("\n    {}: {}", name.bright_black(), value));
+    }
+    eprintln!("{}", text);
+}
+
 /// Print error message
 pub fn print_error(msg: &str, verbose: bool) {
-    if verbose {
+    if verbose && !to_dashboard(|| format!("[ERROR] {}", msg)) {
         eprintln!("{} {}", "[ERROR]".bright_red(), msg);
     }
 }
 
+/// Print a filter derived by auto-calibration
+pub fn print_calibration(msg: &str) {
+    eprintln!("{} {}", "[CAL]".bright_cyan(), msg);
+}
+
+/// Print where --adaptive left the concurrency, a hint for --threads next
+/// time
+pub fn print_concurrency(concurrency: &Concurrency, quiet: bool) {
+    if let (Some(limit), false) = (concurrency.adaptive_limit(), quiet) {
+        println!(
+            "[{}] Adaptive concurrency ended at {} threads",
+            "*".bright_cyan(),
+            limit.to_string().bright_white()
+        );
+    }
+}
+
+/// Print a CORS misconfiguration found with --check-cors
+pub fn print_cors_finding(target: &str, origin: &str) {
+    if to_dashboard(|| format!("[CORS] {} trusts {} with credentials", target, origin)) {
+        return;
+    }
+    println!(
+        "{} {} trusts {} with credentials",
+        "[CORS]".bright_red().bold(),
+        target,
+        origin
+    );
+}
+
 /// Print warning message
 pub fn print_warning(msg: &str) {
+    if to_dashboard(|| format!("[WARN] {}", msg)) {
+        return;
+    }
     eprintln!("{} {}", "[WARN]".bright_yellow(), msg);
 }
+
+/// Send warnings and errors to a dashboard and drop other console output
+/// while it owns the terminal; `None` prints to the terminal again
+pub fn redirect_console(feed: Option<Sender<DashboardEvent>>) {
+    *DASHBOARD.lock().unwrap() = feed;
+}
+
+/// Whether a dashboard owns the terminal
+fn redirected() -> bool {
+    DASHBOARD.lock().unwrap().is_some()
+}
+
+/// Hand a message to the dashboard if one owns the terminal
+fn to_dashboard(message: impl FnOnce() -> String) -> bool {
+    match *DASHBOARD.lock().unwrap() {
+        Some(ref feed) => {
+            let _ = feed.send(DashboardEvent::Notice(message()));
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_classes_are_colored_unless_disabled() {
+        // The override is global, so both settings are checked in one test
+        colored::control::set_override(true);
+        let colored: Vec<String> = [200, 301, 404, 503, 101]
+            .map(|status| color_status(status).to_string())
+            .into();
+        let line = vhost_result_line("dev.example.com", 403, 10);
+        colored::control::set_override(false);
+        let plain_status: Vec<String> = [200, 301, 404, 503, 101]
+            .map(|status| color_status(status).to_string())
+            .into();
+        let plain = [
+            dir_result_line(
+                "/admin",
+                301,
+                12,
+                Some("/admin/"),
+                Some(Duration::from_millis(7)),
+                true,
+                true,
+                "http://t/",
+            ),
+            vhost_result_line("dev.example.com", 403, 10),
+            fuzz_result_line("id=1", 500, 3, 1, 1, true),
+        ];
+        colored::control::unset_override();
+
+        assert_eq!(
+            colored,
+            [
+                "\x1b[92m200\x1b[0m",
+                "\x1b[96m301\x1b[0m",
+                "\x1b[93m404\x1b[0m",
+                "\x1b[91m503\x1b[0m",
+                "\x1b[37m101\x1b[0m",
+            ]
+        );
+        assert!(line.contains("(Status: \x1b[93m403\x1b[0m)"), "{:?}", line);
+
+        assert_eq!(plain_status, ["200", "301", "404", "503", "101"]);
+        assert_eq!(
+            plain,
+            [
+                "http://t/admin                 (Status: 301) [Size: 12] [Time: 7ms] [--> /admin/]",
+                "Found: dev.example.com (Status: 403) [Size: 10]",
+                "id=1                           [Status: 500, Size: 3, Words: 1, Lines: 1] [REFLECTED]",
+            ]
+        );
+        assert!(plain.iter().all(|line| !line.contains('\x1b')));
+    }
+}
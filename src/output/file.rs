@@ -1,19 +1,89 @@
-//! File output handlers (text and JSON)
+//! File output handlers (text, JSON, NDJSON, CSV, URL lists and HAR)
 
+use super::hooks::{HookConfig, Hooks};
+use super::progress::ScanStats;
+#[cfg(feature = "sqlite")]
+use super::sqlite::SqliteWriter;
+use super::tui::DashboardEvent;
+use crate::cli::{GlobalOpts, HttpOpts};
+use crate::error::{RbusterError, Result};
 use serde::Serialize;
 use std::path::Path;
-use std::sync::Arc;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
 use tokio::fs::File;
 use tokio::io::AsyncWriteExt;
 use tokio::sync::Mutex;
 
+/// Scan context written ahead of the results in JSON output
+#[derive(Serialize, Clone, Debug)]
+pub struct ScanMeta {
+    pub tool: &'static str,
+    pub version: &'static str,
+    pub mode: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    pub started_at: String,
+    pub wordlist: String,
+    /// HTTP method of the scan's requests, for HAR output
+    #[serde(skip)]
+    pub method: Option<String>,
+    /// Proxy of the scan's requests, also used for webhooks
+    #[serde(skip)]
+    pub proxy: Option<String>,
+    /// Whether TLS verification is off for the scan, and so for webhooks
+    #[serde(skip)]
+    pub insecure: bool,
+}
+
+impl ScanMeta {
+    /// Metadata for a scan starting now
+    pub fn new(mode: &'static str, target: Option<String>, opts: &GlobalOpts) -> Self {
+        let started_at = time::OffsetDateTime::now_utc()
+            .format(&time::format_description::well_known::Rfc3339)
+            .unwrap_or_default();
+        Self {
+            tool: env!("CARGO_PKG_NAME"),
+            version: env!("CARGO_PKG_VERSION"),
+            mode,
+            target,
+            started_at,
+            wordlist: opts.wordlist_display(),
+            method: None,
+            proxy: None,
+            insecure: false,
+        }
+    }
+
+    /// Take the proxy and TLS settings of an HTTP scan, for webhooks
+    pub fn with_http(self, http: &HttpOpts) -> Self {
+        Self {
+            proxy: http.proxy.first().cloned(),
+            insecure: http.insecure,
+            ..self
+        }
+    }
+}
+
 /// Result types for JSON output
 #[derive(Serialize, Clone)]
 pub struct DirResult {
+    /// Target base URL, set when scanning several targets
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    pub url: String,
     pub path: String,
     pub status: u16,
     pub size: usize,
     pub redirect: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    /// Whether the test Origin is trusted with credentials (--check-cors)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cors: Option<bool>,
 }
 
 #[derive(Serialize, Clone)]
@@ -21,6 +91,23 @@ pub struct DnsResultJson {
     pub subdomain: String,
     pub ips: Vec<String>,
     pub cnames: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub live: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_status: Option<u16>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub mx: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub txt: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub ns: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub soa: Vec<String>,
+    /// Service a dangling CNAME points at (--cname-only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub takeover: Option<String>,
 }
 
 #[derive(Serialize, Clone)]
@@ -37,6 +124,14 @@ pub struct FuzzResult {
     pub size: usize,
     pub words: usize,
     pub lines: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    /// Whether the parameter value came back in the body (--param-discovery)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reflected: Option<bool>,
+    /// Whether the test Origin is trusted with credentials (--check-cors)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cors: Option<bool>,
 }
 
 #[derive(Serialize, Clone)]
@@ -44,32 +139,655 @@ pub struct BucketResult {
     pub name: String,
     pub status: String,
     pub files: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub object_count: Option<usize>,
+    /// Whether an anonymous write succeeded, if it was attempted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub writable: Option<bool>,
+    /// Whether the bucket ACL could be read anonymously, if it was checked
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub acl_public: Option<bool>,
+    /// Whether the bucket policy could be read anonymously, if it was checked
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub policy_public: Option<bool>,
+}
+
+impl BucketResult {
+    /// Plain-text line for file output and hooks
+    pub fn line(&self) -> String {
+        let mut line = format!(
+            "{} [{}] files: {}",
+            self.name,
+            self.status,
+            self.object_count.unwrap_or(0)
+        );
+        if self.writable == Some(true) {
+            line.push_str(" [WRITABLE]");
+        }
+        if self.acl_public == Some(true) {
+            line.push_str(" [ACL READABLE]");
+        }
+        if self.policy_public == Some(true) {
+            line.push_str(" [POLICY READABLE]");
+        }
+        line
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct TftpResult {
+    pub filename: String,
+    pub readable: bool,
+    /// Whether a write request was accepted, if it was attempted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub writable: Option<bool>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct TcpResult {
+    pub host: String,
+    pub port: u16,
+    /// First bytes the service sent, with control characters escaped
+    pub banner: Option<String>,
+}
+
+impl TcpResult {
+    /// `host:port`, with IPv6 addresses in brackets
+    pub fn address(&self) -> String {
+        if self.host.contains(':') {
+            format!("[{}]:{}", self.host, self.port)
+        } else {
+            format!("{}:{}", self.host, self.port)
+        }
+    }
+}
+
+/// Stable, minimal representation of a result for diffable output
+pub trait Canonical {
+    /// Key results are sorted by
+    fn canonical_key(&self) -> String;
+    /// Single line written for the result
+    fn canonical_line(&self) -> String;
+}
+
+impl Canonical for DirResult {
+    fn canonical_key(&self) -> String {
+        // Paths alone are ambiguous across several targets
+        match self.target {
+            Some(_) => self.url.clone(),
+            None => self.path.clone(),
+        }
+    }
+    fn canonical_line(&self) -> String {
+        let mut line = format!("{} {} {}", self.status, self.size, self.canonical_key());
+        if self.cors == Some(true) {
+            line.push_str(" cors");
+        }
+        line
+    }
+}
+
+impl Canonical for DnsResultJson {
+    fn canonical_key(&self) -> String {
+        self.subdomain.clone()
+    }
+    fn canonical_line(&self) -> String {
+        let mut ips = self.ips.clone();
+        ips.sort();
+        match self.http_status {
+            Some(status) => format!("{} {} {}", self.subdomain, ips.join(","), status),
+            None => format!("{} {}", self.subdomain, ips.join(",")),
+        }
+    }
+}
+
+impl Canonical for VhostResult {
+    fn canonical_key(&self) -> String {
+        self.host.clone()
+    }
+    fn canonical_line(&self) -> String {
+        format!("{} {} {}", self.status, self.size, self.host)
+    }
+}
+
+impl Canonical for FuzzResult {
+    fn canonical_key(&self) -> String {
+        self.payload.clone()
+    }
+    fn canonical_line(&self) -> String {
+        let mut line = format!("{} {} {}", self.status, self.size, self.payload);
+        if self.reflected == Some(true) {
+            line.push_str(" reflected");
+        }
+        if self.cors == Some(true) {
+            line.push_str(" cors");
+        }
+        line
+    }
+}
+
+impl Canonical for BucketResult {
+    fn canonical_key(&self) -> String {
+        self.name.clone()
+    }
+    fn canonical_line(&self) -> String {
+        let mut line = format!("{} {}", self.status, self.name);
+        for (flag, name) in [
+            (self.writable, "writable"),
+            (self.acl_public, "acl-readable"),
+            (self.policy_public, "policy-readable"),
+        ] {
+            if flag == Some(true) {
+                line.push(' ');
+                line.push_str(name);
+            }
+        }
+        line
+    }
+}
+
+impl Canonical for TftpResult {
+    fn canonical_key(&self) -> String {
+        self.filename.clone()
+    }
+    fn canonical_line(&self) -> String {
+        match (self.readable, self.writable == Some(true)) {
+            (true, true) => format!("{} readable,writable", self.filename),
+            (false, true) => format!("{} writable", self.filename),
+            _ => self.filename.clone(),
+        }
+    }
+}
+
+impl Canonical for TcpResult {
+    fn canonical_key(&self) -> String {
+        // Zero-padded so ports sort numerically
+        format!("{}:{:05}", self.host, self.port)
+    }
+    fn canonical_line(&self) -> String {
+        match self.banner {
+            Some(ref banner) => format!("{} {}", self.address(), banner),
+            None => self.address(),
+        }
+    }
+}
+
+/// Flat row representation of a result for CSV output
+pub trait CsvRecord {
+    /// Header row for this result type
+    fn csv_header() -> &'static [&'static str];
+    /// Field values in header order
+    fn csv_record(&self) -> Vec<String>;
+}
+
+impl CsvRecord for DirResult {
+    fn csv_header() -> &'static [&'static str] {
+        &[
+            "path",
+            "status",
+            "size",
+            "redirect",
+            "url",
+            "time_ms",
+            "content_type",
+            "cors",
+        ]
+    }
+    fn csv_record(&self) -> Vec<String> {
+        vec![
+            self.path.clone(),
+            self.status.to_string(),
+            self.size.to_string(),
+            self.redirect.clone().unwrap_or_default(),
+            self.url.clone(),
+            self.time_ms.map(|t| t.to_string()).unwrap_or_default(),
+            self.content_type.clone().unwrap_or_default(),
+            self.cors.map(|c| c.to_string()).unwrap_or_default(),
+        ]
+    }
+}
+
+impl CsvRecord for DnsResultJson {
+    fn csv_header() -> &'static [&'static str] {
+        &[
+            "subdomain",
+            "ips",
+            "cnames",
+            "http_status",
+            "mx",
+            "txt",
+            "ns",
+            "soa",
+            "takeover",
+        ]
+    }
+    fn csv_record(&self) -> Vec<String> {
+        vec![
+            self.subdomain.clone(),
+            self.ips.join(" "),
+            self.cnames.join(" "),
+            self.http_status.map(|s| s.to_string()).unwrap_or_default(),
+            self.mx.join(" "),
+            self.txt.join(" "),
+            self.ns.join(" "),
+            self.soa.join(" "),
+            self.takeover.clone().unwrap_or_default(),
+        ]
+    }
+}
+
+impl CsvRecord for VhostResult {
+    fn csv_header() -> &'static [&'static str] {
+        &["host", "status", "size"]
+    }
+    fn csv_record(&self) -> Vec<String> {
+        vec![
+            self.host.clone(),
+            self.status.to_string(),
+            self.size.to_string(),
+        ]
+    }
+}
+
+impl CsvRecord for FuzzResult {
+    fn csv_header() -> &'static [&'static str] {
+        &[
+            "payload",
+            "status",
+            "size",
+            "words",
+            "lines",
+            "content_type",
+            "reflected",
+            "cors",
+        ]
+    }
+    fn csv_record(&self) -> Vec<String> {
+        vec![
+            self.payload.clone(),
+            self.status.to_string(),
+            self.size.to_string(),
+            self.words.to_string(),
+            self.lines.to_string(),
+            self.content_type.clone().unwrap_or_default(),
+            self.reflected.map(|r| r.to_string()).unwrap_or_default(),
+            self.cors.map(|c| c.to_string()).unwrap_or_default(),
+        ]
+    }
+}
+
+impl CsvRecord for BucketResult {
+    fn csv_header() -> &'static [&'static str] {
+        &[
+            "name",
+            "status",
+            "object_count",
+            "writable",
+            "acl_public",
+            "policy_public",
+            "files",
+        ]
+    }
+    fn csv_record(&self) -> Vec<String> {
+        vec![
+            self.name.clone(),
+            self.status.clone(),
+            self.object_count.map(|c| c.to_string()).unwrap_or_default(),
+            self.writable.map(|w| w.to_string()).unwrap_or_default(),
+            self.acl_public.map(|a| a.to_string()).unwrap_or_default(),
+            self.policy_public
+                .map(|p| p.to_string())
+                .unwrap_or_default(),
+            self.files.join(" "),
+        ]
+    }
+}
+
+impl CsvRecord for TftpResult {
+    fn csv_header() -> &'static [&'static str] {
+        &["filename", "readable", "writable"]
+    }
+    fn csv_record(&self) -> Vec<String> {
+        vec![
+            self.filename.clone(),
+            self.readable.to_string(),
+            self.writable.map(|w| w.to_string()).unwrap_or_default(),
+        ]
+    }
+}
+
+impl CsvRecord for TcpResult {
+    fn csv_header() -> &'static [&'static str] {
+        &["host", "port", "banner"]
+    }
+    fn csv_record(&self) -> Vec<String> {
+        vec![
+            self.host.clone(),
+            self.port.to_string(),
+            self.banner.clone().unwrap_or_default(),
+        ]
+    }
+}
+
+/// Table of a result type for SQLite output, with the CSV columns
+#[cfg_attr(not(feature = "sqlite"), allow(dead_code))]
+pub trait SqlRecord: CsvRecord {
+    /// Table the results are inserted into
+    fn sql_table() -> &'static str;
+    /// Columns stored as integers (numbers and flags); the rest are text
+    fn sql_integer_columns() -> &'static [&'static str];
+}
+
+impl SqlRecord for DirResult {
+    fn sql_table() -> &'static str {
+        "dir_results"
+    }
+    fn sql_integer_columns() -> &'static [&'static str] {
+        &["status", "size", "time_ms", "cors"]
+    }
+}
+
+impl SqlRecord for DnsResultJson {
+    fn sql_table() -> &'static str {
+        "dns_results"
+    }
+    fn sql_integer_columns() -> &'static [&'static str] {
+        &["http_status"]
+    }
+}
+
+impl SqlRecord for VhostResult {
+    fn sql_table() -> &'static str {
+        "vhost_results"
+    }
+    fn sql_integer_columns() -> &'static [&'static str] {
+        &["status", "size"]
+    }
+}
+
+impl SqlRecord for FuzzResult {
+    fn sql_table() -> &'static str {
+        "fuzz_results"
+    }
+    fn sql_integer_columns() -> &'static [&'static str] {
+        &["status", "size", "words", "lines", "reflected", "cors"]
+    }
+}
+
+impl SqlRecord for BucketResult {
+    fn sql_table() -> &'static str {
+        "bucket_results"
+    }
+    fn sql_integer_columns() -> &'static [&'static str] {
+        &["object_count", "writable", "acl_public", "policy_public"]
+    }
+}
+
+impl SqlRecord for TftpResult {
+    fn sql_table() -> &'static str {
+        "tftp_results"
+    }
+    fn sql_integer_columns() -> &'static [&'static str] {
+        &["readable", "writable"]
+    }
+}
+
+impl SqlRecord for TcpResult {
+    fn sql_table() -> &'static str {
+        "tcp_results"
+    }
+    fn sql_integer_columns() -> &'static [&'static str] {
+        &["port"]
+    }
+}
+
+/// `IP hostname` lines of a result for hosts file output
+pub trait HostsRecord {
+    /// One line per address; results without addresses write nothing
+    fn hosts_lines(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+impl HostsRecord for DnsResultJson {
+    fn hosts_lines(&self) -> Vec<String> {
+        self.ips
+            .iter()
+            .map(|ip| format!("{} {}", ip, self.subdomain))
+            .collect()
+    }
+}
+
+impl HostsRecord for DirResult {}
+impl HostsRecord for VhostResult {}
+impl HostsRecord for FuzzResult {}
+impl HostsRecord for BucketResult {}
+impl HostsRecord for TftpResult {}
+impl HostsRecord for TcpResult {}
+
+/// Request and response of a result found at a URL
+pub struct UrlHit<'a> {
+    pub url: &'a str,
+    pub status: u16,
+    pub size: usize,
+    pub time_ms: Option<u64>,
+    pub content_type: Option<&'a str>,
+    pub redirect: Option<&'a str>,
+}
+
+/// URL of a result for URL list and HAR output
+pub trait UrlRecord {
+    /// Results without a URL write nothing
+    fn url_hit(&self) -> Option<UrlHit<'_>> {
+        None
+    }
+}
+
+impl UrlRecord for DirResult {
+    fn url_hit(&self) -> Option<UrlHit<'_>> {
+        Some(UrlHit {
+            url: &self.url,
+            status: self.status,
+            size: self.size,
+            time_ms: self.time_ms,
+            content_type: self.content_type.as_deref(),
+            redirect: self.redirect.as_deref(),
+        })
+    }
+}
+
+impl UrlRecord for DnsResultJson {}
+impl UrlRecord for VhostResult {}
+impl UrlRecord for FuzzResult {}
+impl UrlRecord for BucketResult {}
+impl UrlRecord for TftpResult {}
+impl UrlRecord for TcpResult {}
+
+/// HAR entry with the metadata robuster keeps; headers, cookies and
+/// bodies are left empty
+fn har_entry(hit: &UrlHit, method: &str) -> serde_json::Value {
+    let time = hit.time_ms.unwrap_or(0);
+    let started = time::OffsetDateTime::now_utc() - time::Duration::milliseconds(time as i64);
+    serde_json::json!({
+        "startedDateTime": started
+            .format(&time::format_description::well_known::Rfc3339)
+            .unwrap_or_default(),
+        "time": time,
+        "request": {
+            "method": method,
+            "url": hit.url,
+            "httpVersion": "HTTP/1.1",
+            "cookies": [],
+            "headers": [],
+            "queryString": [],
+            "headersSize": -1,
+            "bodySize": -1,
+        },
+        "response": {
+            "status": hit.status,
+            "statusText": "",
+            "httpVersion": "HTTP/1.1",
+            "cookies": [],
+            "headers": [],
+            "content": {
+                "size": hit.size,
+                "mimeType": hit.content_type.unwrap_or_default(),
+            },
+            "redirectURL": hit.redirect.unwrap_or_default(),
+            "headersSize": -1,
+            "bodySize": hit.size,
+        },
+        "cache": {},
+        "timings": { "send": 0, "wait": time, "receive": 0 },
+    })
+}
+
+/// A result that can be written in every output format
+pub trait ResultRecord: Serialize + Canonical + SqlRecord + HostsRecord + UrlRecord {}
+
+impl<T: Serialize + Canonical + SqlRecord + HostsRecord + UrlRecord> ResultRecord for T {}
+
+/// Output file format
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+    /// One compact JSON object per line, flushed as it is written
+    Ndjson,
+    Csv,
+    /// Sorted `STATUS SIZE PATH`-style lines, written on finalize
+    Canonical,
+    /// `IP hostname` lines for /etc/hosts, one per address (dns mode)
+    Hosts,
+    /// SQLite database with a table per result type
+    Sqlite,
+    /// One found URL per line, for importing into Burp or ZAP (dir mode)
+    Urls,
+    /// HTTP Archive of the found requests, written on finalize (dir mode)
+    Har,
+}
+
+impl OutputFormat {
+    /// Pick the format from flags, falling back to the file extension
+    pub fn detect(path: &Path, canonical: bool, ndjson: bool, hosts: bool, urls: bool) -> Self {
+        if canonical {
+            OutputFormat::Canonical
+        } else if ndjson {
+            OutputFormat::Ndjson
+        } else if hosts {
+            OutputFormat::Hosts
+        } else if urls {
+            OutputFormat::Urls
+        } else {
+            match path.extension().and_then(|ext| ext.to_str()) {
+                Some("json") => OutputFormat::Json,
+                Some("ndjson") | Some("jsonl") => OutputFormat::Ndjson,
+                Some("csv") => OutputFormat::Csv,
+                Some("hosts") => OutputFormat::Hosts,
+                Some("db") | Some("sqlite") => OutputFormat::Sqlite,
+                Some("har") => OutputFormat::Har,
+                _ => OutputFormat::Text,
+            }
+        }
+    }
 }
 
 /// File writer with buffering
 pub struct FileWriter {
     file: Mutex<File>,
-    json_mode: bool,
+    format: OutputFormat,
+    /// JSON results are wrapped in an object with metadata and stats
+    wrapped: bool,
     first_entry: Mutex<bool>,
+    canonical_lines: Mutex<Vec<(String, String)>>,
+    /// HTTP method recorded in HAR entries
+    method: String,
+    har_entries: Mutex<Vec<serde_json::Value>>,
 }
 
 impl FileWriter {
-    pub async fn new(path: &Path) -> std::io::Result<Self> {
-        let json_mode = path.extension().map(|ext| ext == "json").unwrap_or(false);
+    /// Open the output file. JSON output is wrapped in an object starting with
+    /// `meta` when given, or written as a bare array otherwise.
+    pub async fn new(
+        path: &Path,
+        format: OutputFormat,
+        meta: Option<&ScanMeta>,
+    ) -> std::io::Result<Self> {
+        // Create missing parent directories
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            tokio::fs::create_dir_all(parent).await?;
+        }
 
         let mut file = File::create(path).await?;
 
-        if json_mode {
-            file.write_all(b"[\n").await?;
+        let wrapped = format == OutputFormat::Json && meta.is_some();
+        match meta {
+            Some(meta) if wrapped => {
+                // Open the object, leaving it unclosed for the results array
+                let header = serde_json::to_string_pretty(meta).map_err(std::io::Error::other)?;
+                let header = header.trim_end().trim_end_matches('}').trim_end();
+                file.write_all(header.as_bytes()).await?;
+                file.write_all(b",\n  \"results\": [\n").await?;
+            }
+            _ if format == OutputFormat::Json => file.write_all(b"[\n").await?,
+            _ => {}
         }
 
         Ok(Self {
             file: Mutex::new(file),
-            json_mode,
+            format,
+            wrapped,
             first_entry: Mutex::new(true),
+            canonical_lines: Mutex::new(Vec::new()),
+            method: "GET".to_string(),
+            har_entries: Mutex::new(Vec::new()),
         })
     }
 
+    /// Record `method` in HAR entries instead of GET
+    pub fn with_method(mut self, method: Option<&str>) -> Self {
+        if let Some(method) = method {
+            self.method = method.to_string();
+        }
+        self
+    }
+
+    /// Write a result in the configured format, using `line` for text output
+    pub async fn write_result<T: ResultRecord>(&self, item: &T, line: &str) -> std::io::Result<()> {
+        match self.format {
+            OutputFormat::Text => self.write_line(line).await,
+            OutputFormat::Json => self.write_json(item).await,
+            OutputFormat::Ndjson => self.write_ndjson(item).await,
+            OutputFormat::Csv => self.write_csv(item).await,
+            OutputFormat::Hosts => {
+                for line in item.hosts_lines() {
+                    self.write_line(&line).await?;
+                }
+                Ok(())
+            }
+            OutputFormat::Urls => match item.url_hit() {
+                Some(hit) => self.write_line(hit.url).await,
+                None => Ok(()),
+            },
+            OutputFormat::Har => {
+                if let Some(hit) = item.url_hit() {
+                    let entry = har_entry(&hit, &self.method);
+                    self.har_entries.lock().await.push(entry);
+                }
+                Ok(())
+            }
+            // Databases are written by SqliteWriter instead
+            OutputFormat::Sqlite => Ok(()),
+            OutputFormat::Canonical => {
+                self.canonical_lines
+                    .lock()
+                    .await
+                    .push((item.canonical_key(), item.canonical_line()));
+                Ok(())
+            }
+        }
+    }
+
     pub async fn write_line(&self, line: &str) -> std::io::Result<()> {
         let mut file = self.file.lock().await;
         file.write_all(line.as_bytes()).await?;
@@ -86,49 +804,324 @@ impl FileWriter {
         }
         *first = false;
 
-        let json = serde_json::to_string_pretty(item).map_err(std::io::Error::other)?;
+        let mut json = serde_json::to_string_pretty(item).map_err(std::io::Error::other)?;
+        if self.wrapped {
+            // Indent to the level of the results array
+            json = format!("    {}", json.replace('\n', "\n    "));
+        }
         file.write_all(json.as_bytes()).await?;
 
         Ok(())
     }
 
-    pub async fn finalize(&self) -> std::io::Result<()> {
-        if self.json_mode {
-            let mut file = self.file.lock().await;
-            file.write_all(b"\n]\n").await?;
+    /// Write a single-line JSON object and flush it immediately
+    pub async fn write_ndjson<T: Serialize>(&self, item: &T) -> std::io::Result<()> {
+        let mut json = serde_json::to_vec(item).map_err(std::io::Error::other)?;
+        json.push(b'\n');
+
+        let mut file = self.file.lock().await;
+        file.write_all(&json).await?;
+        file.flush().await
+    }
+
+    /// Write a CSV record, preceded by the header row on first use
+    pub async fn write_csv<T: CsvRecord>(&self, item: &T) -> std::io::Result<()> {
+        let mut file = self.file.lock().await;
+        let mut first = self.first_entry.lock().await;
+
+        let mut writer = csv::WriterBuilder::new()
+            .has_headers(false)
+            .from_writer(Vec::new());
+        if *first {
+            writer.write_record(T::csv_header())?;
         }
-        Ok(())
+        writer.write_record(item.csv_record())?;
+        *first = false;
+
+        let data = writer.into_inner().map_err(|e| e.into_error())?;
+        file.write_all(&data).await
     }
 
-    pub fn is_json(&self) -> bool {
-        self.json_mode
+    /// Close the output, adding `stats` to wrapped JSON
+    pub async fn finalize(&self, stats: &ScanStats) -> std::io::Result<()> {
+        match self.format {
+            OutputFormat::Json if self.wrapped => {
+                let stats = serde_json::to_string_pretty(stats).map_err(std::io::Error::other)?;
+                let mut file = self.file.lock().await;
+                file.write_all(b"\n  ],\n  \"stats\": ").await?;
+                file.write_all(stats.replace('\n', "\n  ").as_bytes())
+                    .await?;
+                file.write_all(b"\n}\n").await?;
+            }
+            OutputFormat::Json => {
+                let mut file = self.file.lock().await;
+                file.write_all(b"\n]\n").await?;
+            }
+            OutputFormat::Canonical => {
+                let mut lines = std::mem::take(&mut *self.canonical_lines.lock().await);
+                lines.sort();
+                lines.dedup();
+                for (_, line) in lines {
+                    self.write_line(&line).await?;
+                }
+            }
+            OutputFormat::Har => {
+                let entries = std::mem::take(&mut *self.har_entries.lock().await);
+                let har = serde_json::json!({
+                    "log": {
+                        "version": "1.2",
+                        "creator": {
+                            "name": env!("CARGO_PKG_NAME"),
+                            "version": env!("CARGO_PKG_VERSION"),
+                        },
+                        "entries": entries,
+                    }
+                });
+                let mut har = serde_json::to_vec_pretty(&har).map_err(std::io::Error::other)?;
+                har.push(b'\n');
+                self.file.lock().await.write_all(&har).await?;
+            }
+            OutputFormat::Text
+            | OutputFormat::Ndjson
+            | OutputFormat::Csv
+            | OutputFormat::Hosts
+            | OutputFormat::Sqlite
+            | OutputFormat::Urls => {}
+        }
+        self.file.lock().await.flush().await?;
+        Ok(())
     }
 }
 
 /// Output handler that can write to both console and file
 pub struct OutputHandler {
     file_writer: Option<Arc<FileWriter>>,
+    #[cfg(feature = "sqlite")]
+    sqlite: Option<SqliteWriter>,
+    hooks: Mutex<Option<Hooks>>,
+    dashboard: OnceLock<Sender<DashboardEvent>>,
 }
 
 impl OutputHandler {
-    pub async fn new(output_path: Option<&Path>) -> std::io::Result<Self> {
-        let file_writer = if let Some(path) = output_path {
-            Some(Arc::new(FileWriter::new(path).await?))
+    pub async fn new(opts: &GlobalOpts, meta: ScanMeta) -> Result<Self> {
+        let format = opts.output.as_deref().map(|path| {
+            OutputFormat::detect(path, opts.canonical, opts.ndjson, opts.hosts, opts.urls)
+        });
+        let cannot_open = |path: &Path, e: &dyn std::fmt::Display| {
+            RbusterError::ConfigError(format!(
+                "Cannot open output file '{}': {}",
+                path.display(),
+                e
+            ))
+        };
+
+        #[cfg(feature = "sqlite")]
+        let sqlite = match opts.output {
+            Some(ref path) if format == Some(OutputFormat::Sqlite) => {
+                Some(SqliteWriter::new(path, &meta).map_err(|e| cannot_open(path, &e))?)
+            }
+            _ => None,
+        };
+        #[cfg(not(feature = "sqlite"))]
+        if format == Some(OutputFormat::Sqlite) {
+            return Err(RbusterError::ConfigError(
+                "SQLite output needs robuster built with the sqlite feature".to_string(),
+            ));
+        }
+
+        let file_writer = match (&opts.output, format) {
+            (Some(path), Some(format)) if format != OutputFormat::Sqlite => {
+                if format == OutputFormat::Hosts && meta.mode != "dns" {
+                    return Err(RbusterError::ConfigError(
+                        "Hosts file output is only available in dns mode".to_string(),
+                    ));
+                }
+                if matches!(format, OutputFormat::Urls | OutputFormat::Har) && meta.mode != "dir" {
+                    return Err(RbusterError::ConfigError(
+                        "URL list and HAR output are only available in dir mode".to_string(),
+                    ));
+                }
+                let method = meta.method.as_deref();
+                let meta = (!opts.json_array).then_some(&meta);
+                let writer = FileWriter::new(path, format, meta)
+                    .await
+                    .map_err(|e| cannot_open(path, &e))?
+                    .with_method(method);
+                Some(Arc::new(writer))
+            }
+            _ => None,
+        };
+
+        let hooks = if opts.on_found_cmd.is_some() || opts.webhook.is_some() {
+            Some(Hooks::new(HookConfig {
+                command: opts.on_found_cmd.clone(),
+                webhook: opts.webhook.clone(),
+                webhook_template: opts.webhook_template.clone(),
+                interval: Duration::from_millis(opts.hook_interval),
+                proxy: meta.proxy.clone(),
+                insecure: meta.insecure,
+            }))
         } else {
             None
         };
 
-        Ok(Self { file_writer })
+        Ok(Self {
+            file_writer,
+            #[cfg(feature = "sqlite")]
+            sqlite,
+            hooks: Mutex::new(hooks),
+            dashboard: OnceLock::new(),
+        })
+    }
+
+    /// Also send result lines to a dashboard
+    pub fn attach_dashboard(&self, feed: Sender<DashboardEvent>) {
+        let _ = self.dashboard.set(feed);
     }
 
-    pub fn file_writer(&self) -> Option<Arc<FileWriter>> {
-        self.file_writer.clone()
+    /// Record a found result: write it to the output file, show it on the
+    /// dashboard and fire hooks
+    pub async fn record<T: ResultRecord>(&self, item: &T, line: &str) {
+        if let Some(ref writer) = self.file_writer {
+            let _ = writer.write_result(item, line).await;
+        }
+        #[cfg(feature = "sqlite")]
+        if let Some(ref sqlite) = self.sqlite {
+            sqlite.write_result(item);
+        }
+        if let Some(feed) = self.dashboard.get() {
+            let _ = feed.send(DashboardEvent::Found(line.to_string()));
+        }
+        if let Some(ref hooks) = *self.hooks.lock().await {
+            hooks.fire(item, line);
+        }
     }
 
-    pub async fn finalize(&self) -> std::io::Result<()> {
+    pub async fn finalize(&self, stats: &ScanStats) -> std::io::Result<()> {
         if let Some(ref writer) = self.file_writer {
-            writer.finalize().await?;
+            writer.finalize(stats).await?;
+        }
+        #[cfg(feature = "sqlite")]
+        if let Some(ref sqlite) = self.sqlite {
+            sqlite
+                .finalize(stats)
+                .await
+                .map_err(std::io::Error::other)?;
+        }
+        if let Some(hooks) = self.hooks.lock().await.take() {
+            hooks.finish().await;
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::random_label;
+    use std::collections::BTreeMap;
+
+    fn dns_result(subdomain: &str, ips: &[&str], cnames: &[&str]) -> DnsResultJson {
+        DnsResultJson {
+            subdomain: subdomain.to_string(),
+            ips: ips.iter().map(|ip| ip.to_string()).collect(),
+            cnames: cnames.iter().map(|c| c.to_string()).collect(),
+            live: None,
+            http_url: None,
+            http_status: None,
+            mx: Vec::new(),
+            txt: Vec::new(),
+            ns: Vec::new(),
+            soa: Vec::new(),
+            takeover: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn ndjson_lines_parse_on_their_own() {
+        let path = std::env::temp_dir().join(format!("rbuster-{}.jsonl", random_label(12)));
+        let format = OutputFormat::detect(&path, false, false, false, false);
+        assert_eq!(format, OutputFormat::Ndjson);
+
+        // Scan metadata would wrap JSON output, but not NDJSON
+        let meta = ScanMeta {
+            tool: "robuster",
+            version: "test",
+            mode: "dns",
+            target: None,
+            started_at: String::new(),
+            wordlist: "words.txt".to_string(),
+            method: None,
+            proxy: None,
+            insecure: false,
+        };
+        let writer = FileWriter::new(&path, format, Some(&meta)).await.unwrap();
+        let results = [
+            dns_result("www.example.com", &["192.0.2.1"], &[]),
+            dns_result("mail.example.com", &["192.0.2.2"], &["mx.example.net."]),
+        ];
+        for result in &results {
+            writer
+                .write_result(result, &result.subdomain)
+                .await
+                .unwrap();
+        }
+        // Lines are complete before the scan finishes
+        let partial = std::fs::read_to_string(&path).unwrap();
+        let stats = ScanStats {
+            requests: 2,
+            found: 2,
+            errors: 0,
+            duration_secs: 1.0,
+            status_codes: BTreeMap::new(),
+        };
+        writer.finalize(&stats).await.unwrap();
+        let written = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(partial, written);
+        let subdomains: Vec<String> = written
+            .lines()
+            .map(|line| {
+                let value: serde_json::Value = serde_json::from_str(line).unwrap();
+                value["subdomain"].as_str().unwrap().to_string()
+            })
+            .collect();
+        assert_eq!(subdomains, ["www.example.com", "mail.example.com"]);
+    }
+
+    #[tokio::test]
+    async fn hosts_output_has_a_line_per_address() {
+        let path = std::env::temp_dir().join(format!("rbuster-{}.hosts", random_label(12)));
+        let format = OutputFormat::detect(&path, false, false, false, false);
+        assert_eq!(format, OutputFormat::Hosts);
+
+        let writer = FileWriter::new(&path, format, None).await.unwrap();
+        let results = [
+            dns_result("www.example.com", &["192.0.2.1", "2001:db8::1"], &[]),
+            dns_result("cdn.example.com", &[], &["cdn.example.net."]),
+            dns_result("mail.example.com", &["192.0.2.2"], &["mx.example.net."]),
+        ];
+        for result in &results {
+            writer
+                .write_result(result, &result.subdomain)
+                .await
+                .unwrap();
+        }
+        let stats = ScanStats {
+            requests: 3,
+            found: 3,
+            errors: 0,
+            duration_secs: 1.0,
+            status_codes: BTreeMap::new(),
+        };
+        writer.finalize(&stats).await.unwrap();
+        let written = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            written,
+            "192.0.2.1 www.example.com\n2001:db8::1 www.example.com\n192.0.2.2 mail.example.com\n"
+        );
+    }
+}
@@ -0,0 +1,278 @@
+//! Per-result hooks (shell command and webhook)
+
+use super::print_warning;
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::process::Command;
+use tokio::sync::mpsc::{self, Sender};
+use tokio::task::JoinHandle;
+
+/// Longest wait for the webhook to answer one delivery
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Results waiting for the hooks; further results are dropped (and counted)
+/// until the queue drains
+const QUEUE_SIZE: usize = 1000;
+
+/// Hook configuration
+#[derive(Clone, Debug, Default)]
+pub struct HookConfig {
+    /// Shell command run per result, with `{field}` placeholders
+    pub command: Option<String>,
+    /// URL that receives each result as a JSON POST
+    pub webhook: Option<String>,
+    /// Webhook body with `{field}` placeholders, instead of the result JSON
+    pub webhook_template: Option<String>,
+    /// Minimum spacing between hook invocations
+    pub interval: Duration,
+    /// Proxy for webhook deliveries, as for the scan's own requests
+    pub proxy: Option<String>,
+    /// Skip TLS certificate verification for webhook deliveries
+    pub insecure: bool,
+}
+
+/// A found result queued for the hooks
+struct HookEvent {
+    fields: Value,
+    line: String,
+}
+
+/// Hooks run on a background task so slow commands or webhooks never stall
+/// the scan; events are queued and fired at most once per interval. Once the
+/// scan ends, the events still queued are fired without spacing.
+pub struct Hooks {
+    sender: Sender<HookEvent>,
+    worker: JoinHandle<()>,
+    finishing: Arc<AtomicBool>,
+    dropped: AtomicUsize,
+}
+
+impl Hooks {
+    pub fn new(config: HookConfig) -> Self {
+        let (sender, mut receiver) = mpsc::channel::<HookEvent>(QUEUE_SIZE);
+        let mut builder = reqwest::Client::builder()
+            .timeout(WEBHOOK_TIMEOUT)
+            .danger_accept_invalid_certs(config.insecure);
+        if let Some(ref proxy) = config.proxy {
+            match reqwest::Proxy::all(proxy) {
+                Ok(proxy) => builder = builder.proxy(proxy),
+                Err(e) => print_warning(&format!("Webhook proxy ignored: {}", e)),
+            }
+        }
+        let client = builder.build().unwrap_or_default();
+        let finishing = Arc::new(AtomicBool::new(false));
+        let draining = Arc::clone(&finishing);
+
+        let worker = tokio::spawn(async move {
+            let mut failures = 0usize;
+            while let Some(event) = receiver.recv().await {
+                if let Some(ref template) = config.command {
+                    let command = substitute(template, &event.fields, &event.line, shell_quote);
+                    let _ = Command::new("sh").arg("-c").arg(&command).status().await;
+                }
+
+                if let Some(ref url) = config.webhook {
+                    let body = match config.webhook_template {
+                        Some(ref template) => {
+                            substitute(template, &event.fields, &event.line, json_escape)
+                        }
+                        None => {
+                            let mut payload = event.fields;
+                            if let Value::Object(ref mut map) = payload {
+                                map.insert("text".to_string(), Value::String(event.line));
+                            }
+                            payload.to_string()
+                        }
+                    };
+                    let delivery = client
+                        .post(url)
+                        .header("Content-Type", "application/json")
+                        .body(body)
+                        .send()
+                        .await
+                        .and_then(|response| response.error_for_status());
+                    // Warn once; the total is reported when the scan ends
+                    if let Err(e) = delivery {
+                        if failures == 0 {
+                            print_warning(&format!("Webhook delivery failed: {}", e));
+                        }
+                        failures += 1;
+                    }
+                }
+
+                if !config.interval.is_zero() && !draining.load(Ordering::Relaxed) {
+                    tokio::time::sleep(config.interval).await;
+                }
+            }
+            if failures > 1 {
+                print_warning(&format!("{} webhook deliveries failed", failures));
+            }
+        });
+
+        Self {
+            sender,
+            worker,
+            finishing,
+            dropped: AtomicUsize::new(0),
+        }
+    }
+
+    /// Queue a result for the hooks, dropping it if the queue is full
+    pub fn fire<T: Serialize>(&self, item: &T, line: &str) {
+        if let Ok(fields) = serde_json::to_value(item) {
+            let event = HookEvent {
+                fields,
+                line: line.to_string(),
+            };
+            if self.sender.try_send(event).is_err() {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Fire the queued events without further spacing and wait for them
+    pub async fn finish(self) {
+        self.finishing.store(true, Ordering::Relaxed);
+        drop(self.sender);
+        let _ = self.worker.await;
+        let dropped = self.dropped.into_inner();
+        if dropped > 0 {
+            print_warning(&format!(
+                "{} results were not passed to hooks because they could not keep up",
+                dropped
+            ));
+        }
+    }
+}
+
+/// Replace `{field}` placeholders with result values passed through `quote`;
+/// `{line}` expands to the plain-text result line and unknown placeholders
+/// are kept as written. The template is read once, left to right, so braces
+/// inside a substituted value are never expanded again.
+fn substitute(template: &str, fields: &Value, line: &str, quote: fn(&str) -> String) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        let value = after
+            .find(['{', '}'])
+            .filter(|&close| after.as_bytes()[close] == b'}')
+            .and_then(|close| {
+                let text = match &after[..close] {
+                    "line" => Some(line.to_string()),
+                    key => fields.get(key).map(field_text),
+                };
+                text.map(|text| (text, close))
+            });
+        match value {
+            Some((text, close)) => {
+                out.push_str(&quote(&text));
+                rest = &after[close + 1..];
+            }
+            None => {
+                out.push('{');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+
+    out
+}
+
+/// Plain text of a result field
+fn field_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        Value::Array(items) => items
+            .iter()
+            .map(|v| v.as_str().map(str::to_string).unwrap_or(v.to_string()))
+            .collect::<Vec<_>>()
+            .join(","),
+        other => other.to_string(),
+    }
+}
+
+/// Quote a value for safe use as a single `sh` word
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/// Escape a value for use inside a JSON string, without the surrounding quotes
+fn json_escape(value: &str) -> String {
+    let quoted = Value::String(value.to_string()).to_string();
+    quoted[1..quoted.len() - 1].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::mock_server::{MockResponse, MockServer};
+    use serde_json::json;
+
+    #[test]
+    fn substitute_quotes_each_value_once() {
+        let fields = json!({"url": "http://t/{status}';id;'", "status": 200});
+        let command = substitute("echo {url} {status}", &fields, "", shell_quote);
+        assert_eq!(command, r"echo 'http://t/{status}'\'';id;'\''' '200'");
+    }
+
+    #[test]
+    fn substitute_keeps_unknown_placeholders() {
+        let fields = json!({"path": "/a"});
+        let command = substitute("{x} {path} {{path}} {line", &fields, "l", shell_quote);
+        assert_eq!(command, "{x} '/a' {'/a'} {line");
+    }
+
+    #[test]
+    fn substitute_expands_line() {
+        let command = substitute("say {line}", &json!({}), "/{path} found", shell_quote);
+        assert_eq!(command, "say '/{path} found'");
+    }
+
+    #[test]
+    fn webhook_template_escapes_values_once() {
+        let fields = json!({"url": "http://t/\"{status}", "status": 404});
+        let body = substitute(r#"{"text": "{url} {status}"}"#, &fields, "", json_escape);
+        assert_eq!(body, r#"{"text": "http://t/\"{status} 404"}"#);
+        let parsed: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(parsed["text"], "http://t/\"{status} 404");
+    }
+
+    #[tokio::test]
+    async fn webhook_receives_templated_results() {
+        let server = MockServer::start(|_| MockResponse::new(200, "")).await;
+        let hooks = Hooks::new(HookConfig {
+            webhook: Some(format!("{}/hook", server.url())),
+            webhook_template: Some(r#"{"content": "{line} ({status})"}"#.to_string()),
+            interval: Duration::from_secs(60),
+            ..Default::default()
+        });
+        hooks.fire(&json!({"status": 200}), "/admin");
+        hooks.fire(&json!({"status": 301}), "/login");
+        // Queued events are sent without the interval once the scan ends
+        tokio::time::timeout(Duration::from_secs(5), hooks.finish())
+            .await
+            .unwrap();
+
+        let bodies: Vec<String> = server
+            .requests()
+            .into_iter()
+            .filter(|r| r.method == "POST" && r.path == "/hook")
+            .map(|r| String::from_utf8(r.body).unwrap())
+            .collect();
+        assert_eq!(
+            bodies,
+            [
+                r#"{"content": "/admin (200)"}"#,
+                r#"{"content": "/login (301)"}"#
+            ]
+        );
+    }
+}
@@ -2,8 +2,15 @@
 
 pub mod console;
 pub mod file;
+pub mod hooks;
 pub mod progress;
+pub mod responses;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod tui;
 
 pub use console::*;
 pub use file::*;
 pub use progress::*;
+pub use responses::*;
+pub use tui::*;
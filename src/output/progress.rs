@@ -1,7 +1,12 @@
 //! Progress bar and status display
 
+use colored::*;
 use indicatif::{ProgressBar, ProgressStyle};
-use std::sync::Arc;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Instant;
 
 /// Create a styled progress bar
 pub fn create_progress_bar(total: u64, quiet: bool) -> Option<ProgressBar> {
@@ -12,7 +17,7 @@ pub fn create_progress_bar(total: u64, quiet: bool) -> Option<ProgressBar> {
     let pb = ProgressBar::new(total);
     pb.set_style(
         ProgressStyle::with_template(
-            "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({percent}%) {msg}"
+            "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({percent}%) {per_sec} ETA {eta} {msg}"
         )
         .unwrap()
         .progress_chars("█▓▒░ ")
@@ -35,12 +40,47 @@ pub fn create_spinner(message: &str) -> ProgressBar {
     pb
 }
 
+/// End-of-scan counters, as written to JSON output
+#[derive(Serialize, Clone, Debug)]
+pub struct ScanStats {
+    pub requests: usize,
+    pub found: usize,
+    pub errors: usize,
+    pub duration_secs: f64,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub status_codes: BTreeMap<u16, usize>,
+}
+
+/// Progress of one target in a multi-target scan
+pub struct TargetProgress {
+    pub name: String,
+    total: AtomicU64,
+    done: AtomicU64,
+}
+
+impl TargetProgress {
+    pub fn total(&self) -> u64 {
+        self.total.load(Ordering::Relaxed)
+    }
+
+    pub fn done(&self) -> u64 {
+        self.done.load(Ordering::Relaxed)
+    }
+}
+
 /// Progress tracker with atomic counters
 #[derive(Clone)]
 pub struct ProgressTracker {
     pub bar: Option<Arc<ProgressBar>>,
+    requests: Arc<std::sync::atomic::AtomicUsize>,
     found: Arc<std::sync::atomic::AtomicUsize>,
     errors: Arc<std::sync::atomic::AtomicUsize>,
+    statuses: Arc<Mutex<HashMap<u16, usize>>>,
+    /// Work done and queued, tracked apart from the bar for the dashboard
+    position: Arc<AtomicU64>,
+    length: Arc<AtomicU64>,
+    targets: Arc<OnceLock<Vec<TargetProgress>>>,
+    started: Instant,
 }
 
 impl ProgressTracker {
@@ -48,23 +88,95 @@ impl ProgressTracker {
         let bar = create_progress_bar(total, quiet).map(Arc::new);
         Self {
             bar,
+            requests: Arc::new(std::sync::atomic::AtomicUsize::new(0)),
             found: Arc::new(std::sync::atomic::AtomicUsize::new(0)),
             errors: Arc::new(std::sync::atomic::AtomicUsize::new(0)),
+            statuses: Arc::new(Mutex::new(HashMap::new())),
+            position: Arc::new(AtomicU64::new(0)),
+            length: Arc::new(AtomicU64::new(total)),
+            targets: Arc::new(OnceLock::new()),
+            started: Instant::now(),
         }
     }
 
     pub fn inc(&self) {
+        self.requests
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        self.position.fetch_add(1, Ordering::Relaxed);
         if let Some(ref bar) = self.bar {
             bar.inc(1);
         }
     }
 
+    /// Advance the bar for work done by an earlier run, without counting requests
+    pub fn inc_by(&self, n: u64) {
+        self.position.fetch_add(n, Ordering::Relaxed);
+        if let Some(ref bar) = self.bar {
+            bar.inc(n);
+        }
+    }
+
+    /// Grow the total when work is queued during the scan
+    pub fn inc_length(&self, n: u64) {
+        self.length.fetch_add(n, Ordering::Relaxed);
+        if let Some(ref bar) = self.bar {
+            bar.inc_length(n);
+        }
+    }
+
+    /// Work done so far, including work skipped from an earlier run, and
+    /// the total
+    pub fn position(&self) -> (u64, u64) {
+        (
+            self.position.load(Ordering::Relaxed),
+            self.length.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Also track progress per target, given as name, total and work
+    /// already done. Only the first call has an effect.
+    pub fn track_targets(&self, targets: Vec<(String, u64, u64)>) {
+        let _ = self.targets.set(
+            targets
+                .into_iter()
+                .map(|(name, total, done)| TargetProgress {
+                    name,
+                    total: AtomicU64::new(total),
+                    done: AtomicU64::new(done),
+                })
+                .collect(),
+        );
+    }
+
+    /// Count one finished request for target `t`
+    pub fn inc_target(&self, t: usize) {
+        if let Some(target) = self.targets.get().and_then(|targets| targets.get(t)) {
+            target.done.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Grow the total of target `t` when work is queued for it during the scan
+    pub fn inc_target_length(&self, t: usize, n: u64) {
+        if let Some(target) = self.targets.get().and_then(|targets| targets.get(t)) {
+            target.total.fetch_add(n, Ordering::Relaxed);
+        }
+    }
+
+    pub fn targets(&self) -> &[TargetProgress] {
+        self.targets.get().map_or(&[], Vec::as_slice)
+    }
+
     pub fn inc_found(&self) {
         self.found
             .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
         self.update_message();
     }
 
+    /// Count a shown result by its HTTP status code
+    pub fn record_status(&self, status: u16) {
+        *self.statuses.lock().unwrap().entry(status).or_insert(0) += 1;
+    }
+
     pub fn inc_error(&self) {
         self.errors
             .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
@@ -76,16 +188,76 @@ impl ProgressTracker {
         }
     }
 
-    #[allow(dead_code)]
+    pub fn request_count(&self) -> usize {
+        self.requests.load(std::sync::atomic::Ordering::Relaxed)
+    }
+
     pub fn found_count(&self) -> usize {
         self.found.load(std::sync::atomic::Ordering::Relaxed)
     }
 
-    #[allow(dead_code)]
     pub fn error_count(&self) -> usize {
         self.errors.load(std::sync::atomic::Ordering::Relaxed)
     }
 
+    /// Snapshot of the counters
+    pub fn stats(&self) -> ScanStats {
+        ScanStats {
+            requests: self.request_count(),
+            found: self.found_count(),
+            errors: self.error_count(),
+            duration_secs: self.started.elapsed().as_secs_f64(),
+            status_codes: self
+                .statuses
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(&status, &count)| (status, count))
+                .collect(),
+        }
+    }
+
+    /// Print end-of-scan statistics unless `quiet` is set
+    pub fn print_summary(&self, quiet: bool) {
+        if quiet {
+            return;
+        }
+
+        let stats = self.stats();
+        let rate = if stats.duration_secs > 0.0 {
+            stats.requests as f64 / stats.duration_secs
+        } else {
+            0.0
+        };
+
+        println!();
+        println!("{}", "═".repeat(60).bright_blue());
+        println!("{}", "Finished".bright_green().bold());
+        println!("{}", "═".repeat(60).bright_blue());
+        println!(
+            "[{}] Requests: {} | Found: {} | Errors: {} | Duration: {:.2}s | {:.1} req/s",
+            "*".bright_cyan(),
+            stats.requests.to_string().bright_white(),
+            stats.found.to_string().bright_green(),
+            stats.errors.to_string().bright_red(),
+            stats.duration_secs,
+            rate
+        );
+
+        if !stats.status_codes.is_empty() {
+            let breakdown: Vec<String> = stats
+                .status_codes
+                .iter()
+                .map(|(status, count)| format!("{}: {}", status, count))
+                .collect();
+            println!(
+                "[{}] Status codes: {}",
+                "*".bright_cyan(),
+                breakdown.join(", ")
+            );
+        }
+    }
+
     fn update_message(&self) {
         if let Some(ref bar) = self.bar {
             let found = self.found.load(std::sync::atomic::Ordering::Relaxed);
@@ -93,3 +265,49 @@ impl ProgressTracker {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats_break_down_shown_results_by_status() {
+        let progress = ProgressTracker::new(10, true);
+        for status in [200, 301, 200, 403, 200] {
+            progress.inc_found();
+            progress.record_status(status);
+        }
+        progress.inc_error();
+
+        let stats = progress.stats();
+        assert_eq!(stats.found, 5);
+        assert_eq!(stats.errors, 1);
+        assert_eq!(
+            stats.status_codes,
+            BTreeMap::from([(200, 3), (301, 1), (403, 1)])
+        );
+    }
+
+    #[test]
+    fn per_target_progress_ignores_unknown_indexes() {
+        let progress = ProgressTracker::new(8, true);
+        progress.track_targets(vec![
+            ("http://a.test/".to_string(), 5, 1),
+            ("http://b.test/".to_string(), 3, 0),
+        ]);
+        // Only the first call sets up the targets.
+        progress.track_targets(vec![("http://c.test/".to_string(), 9, 9)]);
+
+        progress.inc_target(0);
+        progress.inc_target(2);
+        progress.inc_target(usize::MAX);
+        progress.inc_target_length(1, 2);
+        progress.inc_target_length(2, 2);
+
+        let targets = progress.targets();
+        assert_eq!(targets.len(), 2);
+        assert_eq!(targets[0].name, "http://a.test/");
+        assert_eq!((targets[0].total(), targets[0].done()), (5, 2));
+        assert_eq!((targets[1].total(), targets[1].done()), (5, 0));
+    }
+}
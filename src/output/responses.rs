@@ -0,0 +1,81 @@
+//! Response bodies of shown results saved for offline triage (--save-responses)
+
+use crate::core::fnv1a;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Longest file name stem taken from a path or payload
+const MAX_STEM: usize = 80;
+
+/// Writes each shown response to its own file in a directory
+pub struct ResponseSaver {
+    dir: PathBuf,
+    /// Longest body written, in bytes
+    max_size: usize,
+    /// Write the status line and headers before the body
+    headers: bool,
+}
+
+impl ResponseSaver {
+    /// Create `dir` if needed
+    pub async fn new(dir: &Path, max_size: usize, headers: bool) -> io::Result<Self> {
+        tokio::fs::create_dir_all(dir).await?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            max_size,
+            headers,
+        })
+    }
+
+    /// Save a response under a file named after `name` (a path, URL or
+    /// payload) and return its path. Names that sanitize alike, such as
+    /// /a/b and /a_b, still get files of their own.
+    pub async fn save(
+        &self,
+        name: &str,
+        status: u16,
+        headers: &[(String, String)],
+        body: &[u8],
+    ) -> io::Result<PathBuf> {
+        let mut content = Vec::new();
+        if self.headers {
+            content.extend_from_slice(format!("HTTP {}\n", status).as_bytes());
+            for (header, value) in headers {
+                content.extend_from_slice(format!("{}: {}\n", header, value).as_bytes());
+            }
+            content.push(b'\n');
+        }
+        // Bytes as received, so binary and non-UTF-8 pages survive intact
+        content.extend_from_slice(&body[..body.len().min(self.max_size)]);
+
+        let path = self.dir.join(file_name(name));
+        tokio::fs::write(&path, content).await?;
+        Ok(path)
+    }
+}
+
+/// Safe file name for a response: the name's letters, digits, dots and
+/// dashes, then a 64-bit hash of the whole name so names that sanitize
+/// alike still differ
+fn file_name(name: &str) -> String {
+    let mut stem: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '.' | '-') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>()
+        .trim_matches(|c| c == '_' || c == '.')
+        .chars()
+        .take(MAX_STEM)
+        .collect();
+    if stem.is_empty() {
+        stem.push_str("root");
+    }
+
+    // A stable hash, so names are the same from run to run
+    format!("{}_{:016x}.txt", stem, fnv1a([name.as_bytes()]))
+}
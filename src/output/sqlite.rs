@@ -0,0 +1,321 @@
+//! SQLite output: a table per result type, filled as results are found
+
+use super::file::{ScanMeta, SqlRecord};
+use super::progress::ScanStats;
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, Result};
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+
+/// Results inserted before the open transaction is committed
+const COMMIT_ROWS: usize = 500;
+
+/// Longest a result waits in the open transaction
+const COMMIT_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Writes results into a SQLite database, e.g. for
+/// `SELECT * FROM dir_results WHERE status = 200`. Inserts run on a thread
+/// of their own, fed by a channel, so the scan never waits on the disk; they
+/// are batched in a transaction committed every few hundred rows or seconds.
+pub struct SqliteWriter {
+    commands: Mutex<Option<Sender<Command>>>,
+}
+
+/// Work for the writer thread
+enum Command {
+    Insert {
+        table: &'static str,
+        columns: &'static [&'static str],
+        integers: &'static [&'static str],
+        values: Vec<Value>,
+    },
+    /// Record the stats, commit and report the first error, if any
+    Finish(ScanStats, oneshot::Sender<Result<()>>),
+}
+
+struct Database {
+    conn: Connection,
+    /// Result tables created so far
+    tables: HashSet<&'static str>,
+    pending: usize,
+    last_commit: Instant,
+}
+
+impl SqliteWriter {
+    /// Create the database, replacing an existing file, and record `meta`
+    /// in the `scan` table
+    pub fn new(path: &Path, meta: &ScanMeta) -> Result<Self> {
+        // Create missing parent directories
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        let _ = std::fs::remove_file(path);
+
+        let conn = Connection::open(path)?;
+        conn.execute_batch(
+            "CREATE TABLE scan (
+                tool TEXT, version TEXT, mode TEXT, target TEXT, started_at TEXT,
+                wordlist TEXT, requests INTEGER, found INTEGER, errors INTEGER,
+                duration_secs REAL
+            )",
+        )?;
+        conn.execute(
+            "INSERT INTO scan (tool, version, mode, target, started_at, wordlist)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                meta.tool,
+                meta.version,
+                meta.mode,
+                meta.target,
+                meta.started_at,
+                meta.wordlist
+            ],
+        )?;
+        conn.execute_batch("BEGIN")?;
+
+        let db = Database {
+            conn,
+            tables: HashSet::new(),
+            pending: 0,
+            last_commit: Instant::now(),
+        };
+        let (commands, receiver) = mpsc::channel();
+        std::thread::spawn(move || db.run(receiver));
+
+        Ok(Self {
+            commands: Mutex::new(Some(commands)),
+        })
+    }
+
+    /// Queue a result for its table, which is created on first use
+    pub fn write_result<T: SqlRecord>(&self, item: &T) {
+        let columns = T::csv_header();
+        let integers = T::sql_integer_columns();
+
+        // Empty fields are unset options; flags are stored as 0 or 1
+        let values = columns
+            .iter()
+            .zip(item.csv_record())
+            .map(|(column, value)| match value.as_str() {
+                "" => Value::Null,
+                _ if !integers.contains(column) => Value::Text(value),
+                "true" => Value::Integer(1),
+                "false" => Value::Integer(0),
+                number => number
+                    .parse()
+                    .map_or_else(|_| Value::Text(value.clone()), Value::Integer),
+            })
+            .collect();
+        if let Some(ref commands) = *self.commands.lock().unwrap() {
+            let _ = commands.send(Command::Insert {
+                table: T::sql_table(),
+                columns,
+                integers,
+                values,
+            });
+        }
+    }
+
+    /// Record `stats` in the `scan` table once the queued results are in,
+    /// and commit. Fails with the first error the writer met.
+    pub async fn finalize(&self, stats: &ScanStats) -> Result<()> {
+        let Some(commands) = self.commands.lock().unwrap().take() else {
+            return Ok(());
+        };
+        let (reply, done) = oneshot::channel();
+        if commands
+            .send(Command::Finish(stats.clone(), reply))
+            .is_err()
+        {
+            return Ok(());
+        }
+        done.await.unwrap_or(Ok(()))
+    }
+}
+
+impl Database {
+    /// Apply commands until `Finish` or until the writer is dropped,
+    /// committing on time even while no results arrive
+    fn run(mut self, commands: Receiver<Command>) {
+        let mut failure = None;
+        loop {
+            let outcome = match commands.recv_timeout(COMMIT_INTERVAL) {
+                Ok(Command::Insert {
+                    table,
+                    columns,
+                    integers,
+                    values,
+                }) => self.insert(table, columns, integers, values),
+                Ok(Command::Finish(stats, reply)) => {
+                    let finished = self.finish(&stats);
+                    let _ = reply.send(failure.map_or(finished, Err));
+                    return;
+                }
+                Err(RecvTimeoutError::Timeout) => Ok(()),
+                Err(RecvTimeoutError::Disconnected) => {
+                    let _ = self.conn.execute_batch("COMMIT");
+                    return;
+                }
+            };
+            let outcome = outcome.and_then(|_| {
+                if self.pending > 0 && self.last_commit.elapsed() >= COMMIT_INTERVAL {
+                    self.commit()
+                } else {
+                    Ok(())
+                }
+            });
+            if let Err(e) = outcome {
+                failure.get_or_insert(e);
+            }
+        }
+    }
+
+    /// Insert a row, creating its table on first use
+    fn insert(
+        &mut self,
+        table: &'static str,
+        columns: &[&str],
+        integers: &[&str],
+        values: Vec<Value>,
+    ) -> Result<()> {
+        if self.tables.insert(table) {
+            let definitions: Vec<String> = columns
+                .iter()
+                .map(|column| {
+                    let kind = if integers.contains(column) {
+                        "INTEGER"
+                    } else {
+                        "TEXT"
+                    };
+                    format!("{} {}", column, kind)
+                })
+                .collect();
+            self.conn.execute_batch(&format!(
+                "CREATE TABLE {} ({})",
+                table,
+                definitions.join(", ")
+            ))?;
+        }
+
+        let placeholders = vec!["?"; columns.len()].join(", ");
+        self.conn
+            .prepare_cached(&format!(
+                "INSERT INTO {} ({}) VALUES ({})",
+                table,
+                columns.join(", "),
+                placeholders
+            ))?
+            .execute(params_from_iter(values))?;
+
+        self.pending += 1;
+        if self.pending >= COMMIT_ROWS {
+            self.commit()?;
+        }
+        Ok(())
+    }
+
+    /// Record `stats` in the `scan` table and commit the remaining results
+    fn finish(&mut self, stats: &ScanStats) -> Result<()> {
+        self.conn.execute(
+            "UPDATE scan SET requests = ?1, found = ?2, errors = ?3, duration_secs = ?4",
+            params![
+                stats.requests as i64,
+                stats.found as i64,
+                stats.errors as i64,
+                stats.duration_secs
+            ],
+        )?;
+        self.conn.execute_batch("COMMIT")?;
+        self.pending = 0;
+        Ok(())
+    }
+
+    /// Commit the open transaction and start the next one
+    fn commit(&mut self) -> Result<()> {
+        self.conn.execute_batch("COMMIT; BEGIN")?;
+        self.pending = 0;
+        self.last_commit = Instant::now();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::DirResult;
+    use std::collections::BTreeMap;
+
+    fn dir_result(path: &str, status: u16, size: usize) -> DirResult {
+        DirResult {
+            target: None,
+            url: format!("http://t{}", path),
+            path: path.to_string(),
+            status,
+            size,
+            redirect: None,
+            time_ms: Some(12),
+            content_type: None,
+            cors: Some(false),
+        }
+    }
+
+    #[tokio::test]
+    async fn results_are_read_back_after_finalize() {
+        let path =
+            std::env::temp_dir().join(format!("rbuster-{}.db", crate::core::random_label(12)));
+        let meta = ScanMeta {
+            tool: "robuster",
+            version: "test",
+            mode: "dir",
+            target: Some("http://t".to_string()),
+            started_at: String::new(),
+            wordlist: "words.txt".to_string(),
+            method: None,
+            proxy: None,
+            insecure: false,
+        };
+        let writer = SqliteWriter::new(&path, &meta).unwrap();
+        writer.write_result(&dir_result("/admin", 200, 512));
+        writer.write_result(&dir_result("/login", 302, 0));
+        writer.write_result(&dir_result("/backup", 200, 2048));
+        let stats = ScanStats {
+            requests: 3,
+            found: 3,
+            errors: 0,
+            duration_secs: 1.5,
+            status_codes: BTreeMap::new(),
+        };
+        writer.finalize(&stats).await.unwrap();
+
+        let conn = Connection::open(&path).unwrap();
+        let mut query = conn
+            .prepare("SELECT path, size, cors, redirect FROM dir_results WHERE status = 200 ORDER BY size")
+            .unwrap();
+        let rows: Vec<(String, i64, i64, Option<String>)> = query
+            .query_map([], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        let found: i64 = conn
+            .query_row("SELECT found FROM scan", [], |row| row.get(0))
+            .unwrap();
+        drop(query);
+        drop(conn);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            rows,
+            [
+                ("/admin".to_string(), 512, 0, None),
+                ("/backup".to_string(), 2048, 0, None)
+            ]
+        );
+        assert_eq!(found, 3);
+    }
+}
@@ -0,0 +1,266 @@
+//! Live terminal dashboard for long scans (--tui)
+
+use super::console::redirect_console;
+use super::progress::ProgressTracker;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Gauge, LineGauge, List, ListItem, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::VecDeque;
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// How long to wait for a key press between redraws
+const TICK: Duration = Duration::from_millis(200);
+
+/// Most running targets given a progress line of their own
+const MAX_TARGET_ROWS: usize = 10;
+
+/// Most recent finds and notices kept, more than a terminal shows at once
+const LOG_HISTORY: usize = 500;
+
+/// Something to show in the dashboard's lower pane
+pub enum DashboardEvent {
+    /// Result line of a shown response
+    Found(String),
+    /// Warning or error that would otherwise be printed
+    Notice(String),
+}
+
+/// Full-screen view of a running scan: counters, overall and per-target
+/// progress on top, recent finds below. Warnings and errors are shown in
+/// the finds pane while it owns the terminal.
+pub struct Dashboard {
+    feed: Sender<DashboardEvent>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<VecDeque<DashboardEvent>>>,
+}
+
+impl Dashboard {
+    /// Whether there is a terminal to draw on
+    pub fn available() -> bool {
+        std::io::stdout().is_terminal()
+    }
+
+    /// Take over the terminal and draw `progress` until `finish`. `cancel`
+    /// is called when the user quits, and should wind the scan down so it
+    /// reaches `finish` the usual way.
+    pub fn start(
+        mode: &str,
+        progress: ProgressTracker,
+        cancel: impl Fn() + Send + 'static,
+    ) -> std::io::Result<Self> {
+        let terminal = ratatui::try_init()?;
+        let (feed, events) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let title = format!(" robuster {} ", mode);
+        let thread = {
+            let stop = Arc::clone(&stop);
+            std::thread::spawn(move || run(terminal, &title, &progress, &events, &stop, cancel))
+        };
+        redirect_console(Some(feed.clone()));
+        Ok(Self {
+            feed,
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    /// Sender for result lines, e.g. for `OutputHandler::attach_dashboard`
+    pub fn feed(&self) -> Sender<DashboardEvent> {
+        self.feed.clone()
+    }
+
+    /// Restore the terminal, then print the most recent lines the dashboard
+    /// showed so they stay in the scrollback
+    pub fn finish(mut self) {
+        for event in self.close() {
+            match event {
+                DashboardEvent::Found(line) => println!("{}", line),
+                DashboardEvent::Notice(line) => eprintln!("{}", line),
+            }
+        }
+    }
+
+    fn close(&mut self) -> VecDeque<DashboardEvent> {
+        redirect_console(None);
+        self.stop.store(true, Ordering::Relaxed);
+        self.thread
+            .take()
+            .and_then(|thread| thread.join().ok())
+            .unwrap_or_default()
+    }
+}
+
+impl Drop for Dashboard {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+/// Redraw until stopped, keeping the latest events; q, Esc or Ctrl-C
+/// cancel the scan, which keeps being drawn while it winds down
+fn run(
+    mut terminal: DefaultTerminal,
+    title: &str,
+    progress: &ProgressTracker,
+    events: &Receiver<DashboardEvent>,
+    stop: &AtomicBool,
+    cancel: impl Fn(),
+) -> VecDeque<DashboardEvent> {
+    let mut log = VecDeque::with_capacity(LOG_HISTORY);
+    let mut stopping = false;
+    loop {
+        for event in events.try_iter() {
+            if log.len() == LOG_HISTORY {
+                log.pop_front();
+            }
+            log.push_back(event);
+        }
+        let _ = terminal.draw(|frame| draw(frame, title, progress, &log, stopping));
+        if stop.load(Ordering::Relaxed) {
+            break;
+        }
+        if quit_requested() && !stopping {
+            stopping = true;
+            cancel();
+        }
+    }
+    ratatui::restore();
+    log
+}
+
+/// Wait up to one tick for a key that quits
+fn quit_requested() -> bool {
+    if !event::poll(TICK).unwrap_or(false) {
+        return false;
+    }
+    match event::read() {
+        Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => {
+            matches!(key.code, KeyCode::Char('q') | KeyCode::Esc)
+                || (key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL))
+        }
+        _ => false,
+    }
+}
+
+fn draw(
+    frame: &mut Frame,
+    title: &str,
+    progress: &ProgressTracker,
+    log: &VecDeque<DashboardEvent>,
+    stopping: bool,
+) {
+    let stats = progress.stats();
+    let (position, length) = progress.position();
+    let targets = progress.targets();
+    let running: Vec<_> = targets
+        .iter()
+        .filter(|t| t.done() < t.total())
+        .take(MAX_TARGET_ROWS)
+        .collect();
+
+    // Counters, status codes and the overall gauge, then a line per target
+    let [top, bottom] = Layout::vertical([
+        Constraint::Length(5 + running.len() as u16),
+        Constraint::Min(3),
+    ])
+    .areas(frame.area());
+    let hint = if stopping {
+        " stopping... "
+    } else {
+        " q to quit "
+    };
+    let block = Block::bordered().title(title).title_bottom(hint);
+    let inner = block.inner(top);
+    frame.render_widget(block, top);
+    let rows = Layout::vertical(vec![Constraint::Length(1); 3 + running.len()]).split(inner);
+
+    let rate = if stats.duration_secs > 0.0 {
+        stats.requests as f64 / stats.duration_secs
+    } else {
+        0.0
+    };
+    let elapsed = stats.duration_secs as u64;
+    let mut counters = format!(
+        "Requests: {} | Found: {} | Errors: {} | {:.1} req/s | {:02}:{:02}:{:02}",
+        stats.requests,
+        stats.found,
+        stats.errors,
+        rate,
+        elapsed / 3600,
+        elapsed / 60 % 60,
+        elapsed % 60
+    );
+    if !targets.is_empty() {
+        let done = targets.iter().filter(|t| t.done() >= t.total()).count();
+        counters.push_str(&format!(" | Targets: {}/{}", done, targets.len()));
+    }
+    frame.render_widget(Paragraph::new(counters), rows[0]);
+
+    let breakdown: Vec<String> = stats
+        .status_codes
+        .iter()
+        .map(|(status, count)| format!("{}: {}", status, count))
+        .collect();
+    frame.render_widget(
+        Paragraph::new(format!("Status codes: {}", breakdown.join(", "))),
+        rows[1],
+    );
+
+    frame.render_widget(
+        Gauge::default()
+            .gauge_style(Style::default().fg(Color::Cyan))
+            .ratio(ratio(position, length))
+            .label(format!("{}/{}", position, length)),
+        rows[2],
+    );
+
+    for (target, row) in running.iter().zip(rows.iter().skip(3)) {
+        frame.render_widget(
+            LineGauge::default()
+                .filled_style(Style::default().fg(Color::Blue))
+                .ratio(ratio(target.done(), target.total()))
+                .label(format!(
+                    "{} {}/{}",
+                    target.name,
+                    target.done(),
+                    target.total()
+                )),
+            *row,
+        );
+    }
+
+    // Newest finds at the bottom
+    let visible = bottom.height.saturating_sub(2) as usize;
+    let items: Vec<ListItem> = log
+        .iter()
+        .skip(log.len().saturating_sub(visible))
+        .map(|event| match event {
+            DashboardEvent::Found(line) => ListItem::new(Line::raw(line.as_str())),
+            DashboardEvent::Notice(line) => ListItem::new(Line::styled(
+                line.as_str(),
+                Style::default().fg(Color::Yellow),
+            )),
+        })
+        .collect();
+    frame.render_widget(
+        List::new(items).block(Block::bordered().title(" Recent finds ")),
+        bottom,
+    );
+}
+
+/// Fraction done, within the 0..=1 a gauge accepts
+fn ratio(done: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        (done as f64 / total as f64).min(1.0)
+    }
+}